@@ -1,5 +1,11 @@
 use anyhow::Result;
-use std::{collections::HashSet, env, path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
 use screenpipe_core::Language;
 use screenpipe_db::DatabaseManager;
@@ -9,8 +15,12 @@ use crate::{
         device::{default_input_device, default_output_device},
         engine::AudioTranscriptionEngine,
     },
-    transcription::deepgram::CUSTOM_DEEPGRAM_API_TOKEN,
-    vad::{VadEngineEnum, VadSensitivity},
+    preprocessing::AudioPreprocessing,
+    transcription::{
+        backend::TranscriptionBackend, deepgram::CUSTOM_DEEPGRAM_API_TOKEN,
+        openai_compatible::OpenAiCompatibleConfig,
+    },
+    vad::{VadConfig, VadEngineEnum, VadSensitivity},
 };
 
 use crate::audio_manager::AudioManager;
@@ -25,6 +35,12 @@ pub struct AudioManagerOptions {
     pub enable_realtime: bool,
     pub audio_chunk_duration: Duration,
     pub vad_sensitivity: VadSensitivity,
+    /// Tuning of speech detection, the engine's defaults when not set
+    pub vad_config: Option<VadConfig>,
+    /// Preprocessing of input devices without their own in `device_preprocessing`
+    pub preprocessing: AudioPreprocessing,
+    /// Preprocessing by device name, like "MacBook Pro Microphone (input)"
+    pub device_preprocessing: HashMap<String, AudioPreprocessing>,
     pub health_check_grace_period: u64,
     pub enabled_devices: HashSet<String>,
     pub use_all_devices: bool,
@@ -32,6 +48,10 @@ pub struct AudioManagerOptions {
     pub deepgram_url: Option<String>,
     pub deepgram_websocket_url: Option<String>,
     pub output_path: Option<PathBuf>,
+    pub openai_compatible_config: Option<OpenAiCompatibleConfig>,
+    /// Replaces the engine picked with `transcription_engine`, which then only names the
+    /// transcriptions in the database
+    pub transcription_backend: Option<Arc<dyn TranscriptionBackend>>,
 }
 
 impl Default for AudioManagerOptions {
@@ -50,16 +70,29 @@ impl Default for AudioManagerOptions {
             enable_realtime: false,
             audio_chunk_duration: Duration::from_secs(30),
             vad_sensitivity: VadSensitivity::High,
+            vad_config: None,
+            preprocessing: AudioPreprocessing::default(),
+            device_preprocessing: HashMap::new(),
             health_check_grace_period: 15,
             enabled_devices,
             use_all_devices: false,
             db_path: None,
             deepgram_url,
             deepgram_websocket_url,
+            openai_compatible_config: None,
+            transcription_backend: None,
         }
     }
 }
 
+impl AudioManagerOptions {
+    /// Whether transcription runs a local whisper model, deepgram falls back to it
+    pub fn uses_whisper(&self) -> bool {
+        self.transcription_backend.is_none()
+            && *self.transcription_engine != AudioTranscriptionEngine::OpenAiCompatible
+    }
+}
+
 #[derive(Default)]
 pub struct AudioManagerBuilder {
     options: AudioManagerOptions,
@@ -112,6 +145,24 @@ impl AudioManagerBuilder {
         self
     }
 
+    pub fn vad_config(mut self, vad_config: Option<VadConfig>) -> Self {
+        self.options.vad_config = vad_config;
+        self
+    }
+
+    pub fn preprocessing(mut self, preprocessing: AudioPreprocessing) -> Self {
+        self.options.preprocessing = preprocessing;
+        self
+    }
+
+    pub fn device_preprocessing(
+        mut self,
+        device_preprocessing: HashMap<String, AudioPreprocessing>,
+    ) -> Self {
+        self.options.device_preprocessing = device_preprocessing;
+        self
+    }
+
     pub fn health_check_grace_period(mut self, health_check_grace_period: u64) -> Self {
         self.options.health_check_grace_period = health_check_grace_period;
         self
@@ -137,6 +188,22 @@ impl AudioManagerBuilder {
         self
     }
 
+    pub fn openai_compatible_config(
+        mut self,
+        openai_compatible_config: Option<OpenAiCompatibleConfig>,
+    ) -> Self {
+        self.options.openai_compatible_config = openai_compatible_config;
+        self
+    }
+
+    pub fn transcription_backend(
+        mut self,
+        transcription_backend: Arc<dyn TranscriptionBackend>,
+    ) -> Self {
+        self.options.transcription_backend = Some(transcription_backend);
+        self
+    }
+
     pub async fn build(&mut self, db: Arc<DatabaseManager>) -> Result<AudioManager> {
         self.validate_options()?;
         let options = &mut self.options;
@@ -166,6 +233,15 @@ impl AudioManagerBuilder {
             ));
         }
 
+        if self.options.transcription_engine == Arc::new(AudioTranscriptionEngine::OpenAiCompatible)
+            && self.options.openai_compatible_config.is_none()
+            && self.options.transcription_backend.is_none()
+        {
+            return Err(anyhow::anyhow!(
+                "An endpoint is required for the OpenAI compatible transcription engine"
+            ));
+        }
+
         if self.options.output_path.is_none() {
             return Err(anyhow::anyhow!("Output path is required for audio manager"));
         }
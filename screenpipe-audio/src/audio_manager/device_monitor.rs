@@ -48,6 +48,7 @@ pub async fn start_device_monitor(
                     };
 
                     if device_manager.is_running(&device)
+                        && !device.device_type.is_virtual()
                         && !currently_available_devices.contains(&device)
                     {
                         info!("Device {device_name} disconnected");
@@ -4,6 +4,7 @@ use std::{
     collections::HashSet,
     path::PathBuf,
     sync::{atomic::Ordering, Arc},
+    time::Duration,
 };
 use tokio::{
     join,
@@ -11,23 +12,24 @@ use tokio::{
     task::JoinHandle,
 };
 use tracing::{error, info, warn};
-use whisper_rs::WhisperContext;
 
 use screenpipe_db::DatabaseManager;
 
 use super::{start_device_monitor, stop_device_monitor, AudioManagerOptions};
 use crate::{
     core::{
-        device::{parse_audio_device, AudioDevice},
+        device::{parse_audio_device, AudioDevice, DeviceType},
         record_and_transcribe,
     },
     device::device_manager::DeviceManager,
+    preprocessing::{feed_echo_reference, AudioPreprocessor},
     segmentation::segmentation_manager::SegmentationManager,
     transcription::{
+        backend::{create_transcription_backend, TranscriptionBackend},
         deepgram::streaming::stream_transcription_deepgram,
         handle_new_transcript,
         stt::process_audio_input,
-        whisper::model::{create_whisper_context_parameters, download_whisper_model},
+        whisper::model::download_whisper_model,
     },
     vad::{silero::SileroVad, webrtc::WebRtcVad, VadEngine, VadEngineEnum},
     AudioInput, TranscriptionResult,
@@ -50,6 +52,7 @@ pub struct AudioManager {
     status: Arc<RwLock<AudioManagerStatus>>,
     db: Arc<DatabaseManager>,
     vad_engine: Arc<Mutex<Box<dyn VadEngine + Send>>>,
+    preprocessor: Arc<AudioPreprocessor>,
     recording_handles: Arc<RecordingHandlesMap>,
     recording_sender: Arc<crossbeam::channel::Sender<AudioInput>>,
     recording_receiver: Arc<crossbeam::channel::Receiver<AudioInput>>,
@@ -57,7 +60,8 @@ pub struct AudioManager {
     transcription_sender: Arc<crossbeam::channel::Sender<TranscriptionResult>>,
     transcription_receiver_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
     recording_receiver_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
-    stt_model_path: PathBuf,
+    /// Not downloaded when transcription doesn't run whisper locally
+    stt_model_path: Option<PathBuf>,
 }
 
 impl AudioManager {
@@ -65,16 +69,32 @@ impl AudioManager {
         let device_manager = DeviceManager::new().await?;
         let segmentation_manager = Arc::new(SegmentationManager::new().await?);
         let status = RwLock::new(AudioManagerStatus::Stopped);
-        let vad_engine: Arc<Mutex<Box<dyn VadEngine + Send>>> = match options.vad_engine {
-            VadEngineEnum::Silero => Arc::new(Mutex::new(Box::new(SileroVad::new().await?))),
-            VadEngineEnum::WebRtc => Arc::new(Mutex::new(Box::new(WebRtcVad::new()))),
+        let mut vad_engine: Box<dyn VadEngine + Send> = match options.vad_engine {
+            VadEngineEnum::Silero => Box::new(SileroVad::new().await?),
+            VadEngineEnum::WebRtc => Box::new(WebRtcVad::new()),
         };
+        if let Some(vad_config) = options.vad_config {
+            vad_engine.set_config(vad_config);
+        }
+        let vad_engine = Arc::new(Mutex::new(vad_engine));
+        // echo references cover a chunk with its overlap and the time it waits in the queue
+        let preprocessor = Arc::new(AudioPreprocessor::new(
+            options.preprocessing.clone(),
+            options.device_preprocessing.clone(),
+            options.audio_chunk_duration * 2 + Duration::from_secs(30),
+        ));
 
         let (recording_sender, recording_receiver) = crossbeam::channel::bounded(1000);
         let (transcription_sender, transcription_receiver) = crossbeam::channel::bounded(1000);
 
         let recording_handles = DashMap::new();
-        let stt_model_path = download_whisper_model(options.transcription_engine.clone())?;
+        let stt_model_path = if options.uses_whisper() {
+            Some(download_whisper_model(
+                options.transcription_engine.clone(),
+            )?)
+        } else {
+            None
+        };
 
         whisper_rs::install_logging_hooks();
 
@@ -85,6 +105,7 @@ impl AudioManager {
             status: Arc::new(status),
             db,
             vad_engine,
+            preprocessor,
             recording_sender: Arc::new(recording_sender),
             recording_receiver: Arc::new(recording_receiver),
             transcription_receiver: Arc::new(transcription_receiver),
@@ -195,6 +216,10 @@ impl AudioManager {
         Ok(())
     }
 
+    pub async fn options(&self) -> AudioManagerOptions {
+        self.options.read().await.clone()
+    }
+
     pub async fn status(&self) -> AudioManagerStatus {
         self.status.read().await.clone()
     }
@@ -244,6 +269,15 @@ impl AudioManager {
         let deepgram_api_key = options.deepgram_api_key.clone();
         let realtime_enabled = options.enable_realtime;
         let device_clone = device.clone();
+        let echo_reference_handle = (device.device_type == DeviceType::Output
+            && self.preprocessor.uses_echo_reference())
+        .then(|| {
+            tokio::spawn(feed_echo_reference(
+                stream.clone(),
+                self.preprocessor.clone(),
+                is_running.clone(),
+            ))
+        });
 
         let recording_handle = tokio::spawn(async move {
             let record_and_transcribe_handle = tokio::spawn(record_and_transcribe(
@@ -269,6 +303,9 @@ impl AudioManager {
             } else {
                 (record_and_transcribe_handle.await, Ok(Ok(())))
             };
+            if let Some(handle) = echo_reference_handle {
+                handle.abort();
+            }
 
             if record_result.is_err() || realtime_result.is_err() {
                 let mut e = anyhow!("record_device failed");
@@ -308,21 +345,21 @@ impl AudioManager {
         let options = self.options.read().await;
         let output_path = options.output_path.clone();
         let languages = options.languages.clone();
-        let deepgram_api_key = options.deepgram_api_key.clone();
-        let audio_transcription_engine = options.transcription_engine.clone();
+        let transcription_backend = self.create_transcription_backend(&options)?;
         let vad_engine = self.vad_engine.clone();
+        let preprocessor = self.preprocessor.clone();
         let whisper_receiver = self.recording_receiver.clone();
-        let context_param = create_whisper_context_parameters(audio_transcription_engine.clone())?;
-
-        let quantized_path = self.stt_model_path.clone();
-        let whisper_context = Arc::new(
-            WhisperContext::new_with_params(&quantized_path.to_string_lossy(), context_param)
-                .expect("failed to load model"),
-        );
 
         Ok(tokio::spawn(async move {
             while let Ok(audio) = whisper_receiver.recv() {
                 info!("Received audio from device: {:?}", audio.device.name);
+                let audio = match preprocessor.process(audio.clone()) {
+                    Ok(audio) => audio,
+                    Err(e) => {
+                        error!("Error preprocessing audio: {:?}", e);
+                        audio
+                    }
+                };
                 if let Err(e) = process_audio_input(
                     audio.clone(),
                     vad_engine.clone(),
@@ -330,11 +367,9 @@ impl AudioManager {
                     embedding_manager.clone(),
                     embedding_extractor.clone(),
                     &output_path.clone().unwrap(),
-                    audio_transcription_engine.clone(),
-                    deepgram_api_key.clone(),
+                    transcription_backend.clone(),
                     languages.clone(),
                     &transcription_sender.clone(),
-                    whisper_context.clone(),
                 )
                 .await
                 {
@@ -344,6 +379,21 @@ impl AudioManager {
         }))
     }
 
+    fn create_transcription_backend(
+        &self,
+        options: &AudioManagerOptions,
+    ) -> Result<Arc<dyn TranscriptionBackend>> {
+        if let Some(backend) = &options.transcription_backend {
+            return Ok(backend.clone());
+        }
+        create_transcription_backend(
+            options.transcription_engine.clone(),
+            self.stt_model_path.as_deref(),
+            options.deepgram_api_key.clone(),
+            options.openai_compatible_config.clone(),
+        )
+    }
+
     async fn start_transcription_receiver_handler(&self) -> Result<JoinHandle<()>> {
         let transcription_receiver = self.transcription_receiver.clone();
         let db = self.db.clone();
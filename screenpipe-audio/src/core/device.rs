@@ -5,6 +5,8 @@ use cpal::traits::{DeviceTrait, HostTrait};
 use oasgen::OaSchema;
 use serde::{Deserialize, Serialize};
 
+use super::virtual_device::VirtualSource;
+
 #[derive(OaSchema, Clone, Debug)]
 pub struct DeviceControl {
     pub is_running: bool,
@@ -15,6 +17,27 @@ pub struct DeviceControl {
 pub enum DeviceType {
     Input,
     Output,
+    /// Virtual devices, see [`crate::core::virtual_device`]
+    File,
+    Fifo,
+    Tcp,
+}
+
+impl DeviceType {
+    /// Reads PCM from a file, a named pipe or a TCP connection instead of a sound card
+    pub fn is_virtual(&self) -> bool {
+        matches!(self, DeviceType::File | DeviceType::Fifo | DeviceType::Tcp)
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            DeviceType::Input => "input",
+            DeviceType::Output => "output",
+            DeviceType::File => "file",
+            DeviceType::Fifo => "fifo",
+            DeviceType::Tcp => "tcp",
+        }
+    }
 }
 
 impl From<screenpipe_db::DeviceType> for DeviceType {
@@ -26,21 +49,21 @@ impl From<screenpipe_db::DeviceType> for DeviceType {
     }
 }
 
+/// Virtual devices are stored as input devices, their name tells where the audio came from
 impl From<DeviceType> for screenpipe_db::DeviceType {
     fn from(device_type: DeviceType) -> Self {
         match device_type {
-            DeviceType::Input => screenpipe_db::DeviceType::Input,
             DeviceType::Output => screenpipe_db::DeviceType::Output,
+            DeviceType::Input | DeviceType::File | DeviceType::Fifo | DeviceType::Tcp => {
+                screenpipe_db::DeviceType::Input
+            }
         }
     }
 }
 
 impl From<DeviceType> for Arc<screenpipe_db::DeviceType> {
     fn from(device_type: DeviceType) -> Self {
-        Arc::new(match device_type {
-            DeviceType::Input => screenpipe_db::DeviceType::Input,
-            DeviceType::Output => screenpipe_db::DeviceType::Output,
-        })
+        Arc::new(device_type.into())
     }
 }
 
@@ -78,28 +101,36 @@ impl AudioDevice {
                 name.trim_end_matches("(output)").trim().to_string(),
                 DeviceType::Output,
             ),
+            n if n.ends_with("(file)") => (
+                name.trim_end_matches("(file)").trim().to_string(),
+                DeviceType::File,
+            ),
+            n if n.ends_with("(fifo)") => (
+                name.trim_end_matches("(fifo)").trim().to_string(),
+                DeviceType::Fifo,
+            ),
+            n if n.ends_with("(tcp)") => (
+                name.trim_end_matches("(tcp)").trim().to_string(),
+                DeviceType::Tcp,
+            ),
             _ => {
                 return Err(anyhow!(
-                    "Device type (input/output) not specified in the name"
+                    "Device type (input/output/file/fifo/tcp) not specified in the name"
                 ))
             }
         };
 
+        if device_type.is_virtual() {
+            VirtualSource::parse(&name)?;
+        }
+
         Ok(AudioDevice::new(name, device_type))
     }
 }
 
 impl fmt::Display for AudioDevice {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{} ({})",
-            self.name,
-            match self.device_type {
-                DeviceType::Input => "input",
-                DeviceType::Output => "output",
-            }
-        )
+        write!(f, "{} ({})", self.name, self.device_type.suffix())
     }
 }
 
@@ -161,6 +192,9 @@ async fn get_screen_capture_host() -> Result<cpal::Host> {
 pub async fn get_cpal_device_and_config(
     audio_device: &AudioDevice,
 ) -> Result<(cpal::Device, cpal::SupportedStreamConfig)> {
+    if audio_device.device_type.is_virtual() {
+        return Err(anyhow!("{} is not a sound card", audio_device));
+    }
     let host = cpal::default_host();
     let is_output_device = audio_device.device_type == DeviceType::Output;
     let is_display = audio_device.to_string().contains("Display");
@@ -173,13 +207,13 @@ pub async fn get_cpal_device_and_config(
 
     let cpal_audio_device = if audio_device.to_string() == "default" {
         match audio_device.device_type {
-            DeviceType::Input => host.default_input_device(),
             DeviceType::Output => host.default_output_device(),
+            _ => host.default_input_device(),
         }
     } else {
         let mut devices = match audio_device.device_type {
-            DeviceType::Input => host.input_devices()?,
             DeviceType::Output => host.output_devices()?,
+            _ => host.input_devices()?,
         };
 
         #[cfg(target_os = "macos")]
@@ -198,21 +232,25 @@ pub async fn get_cpal_device_and_config(
         let configs = cpal_audio_device.supported_output_configs()?;
         let best_config = configs
             .max_by(|a, b| {
-                a.max_sample_rate().0.cmp(&b.max_sample_rate().0)
+                a.max_sample_rate()
+                    .0
+                    .cmp(&b.max_sample_rate().0)
                     .then(a.channels().cmp(&b.channels()))
             })
             .ok_or_else(|| anyhow!("No supported output configurations found"))?;
-        
+
         best_config.with_sample_rate(best_config.max_sample_rate())
     } else {
         let configs = cpal_audio_device.supported_input_configs()?;
         let best_config = configs
             .max_by(|a, b| {
-                a.max_sample_rate().0.cmp(&b.max_sample_rate().0)
+                a.max_sample_rate()
+                    .0
+                    .cmp(&b.max_sample_rate().0)
                     .then(a.channels().cmp(&b.channels()))
             })
             .ok_or_else(|| anyhow!("No supported input configurations found"))?;
-        
+
         best_config.with_sample_rate(best_config.max_sample_rate())
     };
 
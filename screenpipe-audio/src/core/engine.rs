@@ -1,4 +1,4 @@
-use std::fmt;
+use std::{fmt, str::FromStr};
 
 #[derive(Clone, Debug, PartialEq, Default)]
 pub enum AudioTranscriptionEngine {
@@ -10,6 +10,8 @@ pub enum AudioTranscriptionEngine {
     WhisperLargeV3TurboQuantized,
     WhisperLargeV3,
     WhisperLargeV3Quantized,
+    /// Any server implementing OpenAI's `/v1/audio/transcriptions`
+    OpenAiCompatible,
 }
 
 impl fmt::Display for AudioTranscriptionEngine {
@@ -26,6 +28,46 @@ impl fmt::Display for AudioTranscriptionEngine {
             AudioTranscriptionEngine::WhisperLargeV3TurboQuantized => {
                 write!(f, "WhisperLargeV3TurboQuantized")
             }
+            AudioTranscriptionEngine::OpenAiCompatible => write!(f, "OpenAiCompatible"),
+        }
+    }
+}
+
+impl FromStr for AudioTranscriptionEngine {
+    type Err = String;
+
+    /// Parses the names engines are stored under in the database
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Deepgram" => Ok(AudioTranscriptionEngine::Deepgram),
+            "WhisperTiny" => Ok(AudioTranscriptionEngine::WhisperTiny),
+            "WhisperTinyQuantized" => Ok(AudioTranscriptionEngine::WhisperTinyQuantized),
+            "WhisperLargeV3" => Ok(AudioTranscriptionEngine::WhisperLargeV3),
+            "WhisperLargeV3Quantized" => Ok(AudioTranscriptionEngine::WhisperLargeV3Quantized),
+            "WhisperLargeV3Turbo" => Ok(AudioTranscriptionEngine::WhisperLargeV3Turbo),
+            "WhisperLargeV3TurboQuantized" => {
+                Ok(AudioTranscriptionEngine::WhisperLargeV3TurboQuantized)
+            }
+            "OpenAiCompatible" => Ok(AudioTranscriptionEngine::OpenAiCompatible),
+            _ => Err(format!("unknown transcription engine: {}", s)),
+        }
+    }
+}
+
+impl AudioTranscriptionEngine {
+    /// Rough transcription quality, higher is better. Decides which version of a
+    /// re-transcribed segment is kept for search.
+    pub fn quality(&self) -> u8 {
+        match self {
+            AudioTranscriptionEngine::WhisperTinyQuantized => 1,
+            AudioTranscriptionEngine::WhisperTiny => 2,
+            AudioTranscriptionEngine::WhisperLargeV3TurboQuantized => 3,
+            AudioTranscriptionEngine::WhisperLargeV3Quantized => 3,
+            AudioTranscriptionEngine::WhisperLargeV3Turbo => 4,
+            AudioTranscriptionEngine::WhisperLargeV3 => 5,
+            // servers of the openai api usually run a large model
+            AudioTranscriptionEngine::OpenAiCompatible => 5,
+            AudioTranscriptionEngine::Deepgram => 6,
         }
     }
 }
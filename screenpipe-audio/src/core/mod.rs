@@ -2,6 +2,7 @@ pub mod device;
 pub mod engine;
 mod run_record_and_transcribe;
 pub mod stream;
+pub mod virtual_device;
 use crate::transcription::deepgram::streaming::stream_transcription_deepgram;
 use crate::AudioInput;
 use anyhow::Result;
@@ -39,15 +39,18 @@ pub async fn run_record_and_transcribe(
         && !audio_stream.is_disconnected.load(Ordering::Relaxed)
     {
         while collected_audio.len() < max_samples && is_running.load(Ordering::Relaxed) {
-            match receiver.recv().await {
-                Ok(chunk) => {
+            match tokio::time::timeout(Duration::from_millis(500), receiver.recv()).await {
+                Ok(Ok(chunk)) => {
                     collected_audio.extend(chunk);
                     update_device_capture_time(&device_name);
                 }
-                Err(e) => {
+                Ok(Err(e)) => {
                     error!("error receiving audio data: {}", e);
                     return Err(anyhow!("Audio stream error: {}", e));
                 }
+                // a file device stops at the end of the file, what it sent is still transcribed
+                Err(_) if audio_stream.is_disconnected() => break,
+                Err(_) => {}
             }
         }
 
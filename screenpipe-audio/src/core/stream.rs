@@ -11,6 +11,7 @@ use tracing::{error, warn};
 use crate::utils::audio::audio_to_mono;
 
 use super::device::{get_cpal_device_and_config, AudioDevice};
+use super::virtual_device::{open_virtual_device, read_virtual_device};
 
 #[derive(Clone)]
 pub struct AudioStream {
@@ -31,6 +32,10 @@ impl AudioStream {
         device: Arc<AudioDevice>,
         is_running: Arc<AtomicBool>,
     ) -> Result<Self> {
+        if device.device_type.is_virtual() {
+            return Self::from_virtual_device(device).await;
+        }
+
         let (tx, _) = broadcast::channel::<Vec<f32>>(1000);
         let tx_clone = tx.clone();
         let (cpal_audio_device, config) = get_cpal_device_and_config(&device).await?;
@@ -62,6 +67,44 @@ impl AudioStream {
         })
     }
 
+    /// Streams a file, named pipe or TCP connection, see [`super::virtual_device`]
+    async fn from_virtual_device(device: Arc<AudioDevice>) -> Result<Self> {
+        let (tx, _) = broadcast::channel::<Vec<f32>>(1000);
+        let (input, format) = open_virtual_device(&device).await?;
+        let is_disconnected = Arc::new(AtomicBool::new(false));
+        let (stream_control_tx, stream_control_rx) = mpsc::channel();
+
+        let reader = tokio::spawn(read_virtual_device(
+            device.clone(),
+            input,
+            format,
+            tx.clone(),
+            is_disconnected.clone(),
+        ));
+        // the reader is async, this thread only waits to stop it like the one holding a cpal stream
+        let stream_thread = tokio::task::spawn_blocking(move || {
+            let control = stream_control_rx.recv();
+            reader.abort();
+            if let Ok(StreamControl::Stop(response)) = control {
+                response.send(()).ok();
+            }
+        });
+
+        Ok(AudioStream {
+            device,
+            device_config: cpal::SupportedStreamConfig::new(
+                format.channels,
+                cpal::SampleRate(format.sample_rate),
+                cpal::SupportedBufferSize::Unknown,
+                cpal::SampleFormat::F32,
+            ),
+            transmitter: Arc::new(tx),
+            stream_control: stream_control_tx,
+            stream_thread: Some(Arc::new(tokio::sync::Mutex::new(Some(stream_thread)))),
+            is_disconnected,
+        })
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn spawn_audio_thread(
         device: cpal::Device,
@@ -0,0 +1,304 @@
+//! Devices reading PCM from a file, a named pipe or a TCP connection instead of a sound card,
+//! to run the pipeline without audio hardware and ingest audio from external recorders.
+//!
+//! The device name is where the audio comes from, optionally followed by its format, e.g.
+//! `/tmp/meeting.wav (file)`, `/tmp/recorder.pcm?rate=48000&channels=2 (fifo)` or
+//! `127.0.0.1:4000?format=f32le (tcp)`. Raw PCM defaults to 16kHz mono `s16le`, WAV files use
+//! the format of their header.
+
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+use crate::utils::audio::audio_to_mono;
+
+use super::device::{AudioDevice, DeviceType};
+
+/// Audio sent to the pipeline at a time, like the callbacks of a sound card
+const BLOCK_DURATION: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleEncoding {
+    S16Le,
+    F32Le,
+}
+
+impl SampleEncoding {
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            SampleEncoding::S16Le => 2,
+            SampleEncoding::F32Le => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PcmFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub encoding: SampleEncoding,
+}
+
+impl Default for PcmFormat {
+    fn default() -> Self {
+        PcmFormat {
+            sample_rate: 16000,
+            channels: 1,
+            encoding: SampleEncoding::S16Le,
+        }
+    }
+}
+
+impl PcmFormat {
+    fn frame_size(&self) -> usize {
+        self.encoding.bytes_per_sample() * self.channels as usize
+    }
+
+    /// Interleaved samples of whole frames, the bytes of an incomplete frame are left out
+    pub fn decode(&self, bytes: &[u8]) -> Vec<f32> {
+        let bytes = &bytes[..bytes.len() - bytes.len() % self.frame_size()];
+        match self.encoding {
+            SampleEncoding::S16Le => bytes
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+                .collect(),
+            SampleEncoding::F32Le => bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        }
+    }
+}
+
+/// Where a virtual device reads from and the format of its audio
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualSource {
+    /// Path of the file or named pipe, or address the TCP device listens on
+    pub location: String,
+    pub format: PcmFormat,
+}
+
+impl VirtualSource {
+    /// Parses `location[?rate=HZ&channels=N&format=s16le|f32le]`
+    pub fn parse(name: &str) -> Result<Self> {
+        let (location, params) = name.split_once('?').unwrap_or((name, ""));
+        if location.trim().is_empty() {
+            return Err(anyhow!("virtual device {} has no location", name));
+        }
+
+        let mut format = PcmFormat::default();
+        for param in params.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = param
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected key=value, got {}", param))?;
+            match key {
+                "rate" => format.sample_rate = value.parse()?,
+                "channels" => format.channels = value.parse()?,
+                "format" => {
+                    format.encoding = match value {
+                        "s16le" => SampleEncoding::S16Le,
+                        "f32le" => SampleEncoding::F32Le,
+                        _ => return Err(anyhow!("unsupported sample format: {}", value)),
+                    }
+                }
+                _ => return Err(anyhow!("unknown virtual device option: {}", key)),
+            }
+        }
+        if format.sample_rate == 0 || format.channels == 0 {
+            return Err(anyhow!("sample rate and channels must be positive"));
+        }
+
+        Ok(VirtualSource {
+            location: location.trim().to_string(),
+            format,
+        })
+    }
+}
+
+/// An opened virtual device, ready to be read by [`read_virtual_device`]
+pub(crate) enum VirtualInput {
+    File(tokio::io::Take<tokio::fs::File>),
+    #[cfg(unix)]
+    Fifo(tokio::net::unix::pipe::Receiver),
+    Tcp(tokio::net::TcpListener),
+}
+
+/// Opens the device, returning it with the format of its audio
+pub(crate) async fn open_virtual_device(device: &AudioDevice) -> Result<(VirtualInput, PcmFormat)> {
+    let source = VirtualSource::parse(&device.name)?;
+    match device.device_type {
+        DeviceType::File => {
+            let mut file = tokio::fs::File::open(&source.location)
+                .await
+                .map_err(|e| anyhow!("failed to open {}: {}", source.location, e))?;
+            let is_wav = Path::new(&source.location)
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+            let (format, size) = if is_wav {
+                read_wav_header(&mut file).await?
+            } else {
+                (source.format, u64::MAX)
+            };
+            Ok((VirtualInput::File(file.take(size)), format))
+        }
+        #[cfg(unix)]
+        DeviceType::Fifo => {
+            let receiver = tokio::net::unix::pipe::OpenOptions::new()
+                .open_receiver(&source.location)
+                .map_err(|e| anyhow!("failed to open {}: {}", source.location, e))?;
+            Ok((VirtualInput::Fifo(receiver), source.format))
+        }
+        #[cfg(not(unix))]
+        DeviceType::Fifo => Err(anyhow!("named pipes are only supported on unix")),
+        DeviceType::Tcp => {
+            let listener = tokio::net::TcpListener::bind(&source.location)
+                .await
+                .map_err(|e| anyhow!("failed to listen on {}: {}", source.location, e))?;
+            Ok((VirtualInput::Tcp(listener), source.format))
+        }
+        DeviceType::Input | DeviceType::Output => {
+            Err(anyhow!("{} is not a virtual device", device))
+        }
+    }
+}
+
+/// Sends the audio of the device as mono blocks until it ends, for files, or the task is
+/// aborted. Files are read at the pace they were recorded, named pipes and TCP connections at
+/// the pace of the writer, who can reconnect after closing them.
+pub(crate) async fn read_virtual_device(
+    device: Arc<AudioDevice>,
+    input: VirtualInput,
+    format: PcmFormat,
+    tx: broadcast::Sender<Vec<f32>>,
+    is_disconnected: Arc<AtomicBool>,
+) {
+    let result = match input {
+        VirtualInput::File(mut file) => {
+            let result = forward_pcm(&mut file, format, &tx, true).await;
+            info!("reached the end of {}", device);
+            result
+        }
+        #[cfg(unix)]
+        VirtualInput::Fifo(mut receiver) => loop {
+            if let Err(e) = forward_pcm(&mut receiver, format, &tx, false).await {
+                break Err(e);
+            }
+            // no writer, wait for the next one
+            tokio::time::sleep(BLOCK_DURATION).await;
+        },
+        VirtualInput::Tcp(listener) => loop {
+            match listener.accept().await {
+                Ok((mut stream, address)) => {
+                    info!("{} connected to {}", address, device);
+                    if let Err(e) = forward_pcm(&mut stream, format, &tx, false).await {
+                        warn!("connection of {} to {} failed: {}", address, device, e);
+                    }
+                    debug!("{} disconnected from {}", address, device);
+                }
+                Err(e) => break Err(e),
+            }
+        },
+    };
+
+    if let Err(e) = result {
+        warn!("failed to read {}: {}", device, e);
+    }
+    is_disconnected.store(true, Ordering::Relaxed);
+}
+
+/// Forwards PCM until the end of the reader, at real time pace when `paced`
+async fn forward_pcm<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    format: PcmFormat,
+    tx: &broadcast::Sender<Vec<f32>>,
+    paced: bool,
+) -> std::io::Result<()> {
+    let block_frames = (format.sample_rate as f64 * BLOCK_DURATION.as_secs_f64()) as usize;
+    let block_size = block_frames.max(1) * format.frame_size();
+    let mut interval = tokio::time::interval(BLOCK_DURATION);
+    let mut pending = Vec::with_capacity(block_size * 2);
+    let mut buffer = vec![0u8; block_size];
+
+    loop {
+        if paced {
+            interval.tick().await;
+            // a paced block is always whole, a short read only happens at the end
+            pending.clear();
+            let mut filled = 0;
+            while filled < block_size {
+                match reader.read(&mut buffer[filled..]).await? {
+                    0 => break,
+                    read => filled += read,
+                }
+            }
+            if filled == 0 {
+                return Ok(());
+            }
+            pending.extend_from_slice(&buffer[..filled]);
+        } else {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            pending.extend_from_slice(&buffer[..read]);
+        }
+
+        let samples = format.decode(&pending);
+        pending.drain(..samples.len() * format.encoding.bytes_per_sample());
+        if !samples.is_empty() {
+            let _ = tx.send(audio_to_mono(&samples, format.channels));
+        }
+    }
+}
+
+/// Reads the header of a WAV file up to its samples, which must be 16 bit integers or 32 bit
+/// floats. Returns their format and size in bytes.
+async fn read_wav_header<R: AsyncRead + Unpin>(reader: &mut R) -> Result<(PcmFormat, u64)> {
+    let mut riff = [0u8; 12];
+    reader.read_exact(&mut riff).await?;
+    if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+        return Err(anyhow!("not a wav file"));
+    }
+
+    let mut format = None;
+    loop {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).await?;
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        if &header[..4] == b"data" {
+            let format = format
+                .filter(|f: &PcmFormat| f.sample_rate > 0 && f.channels > 0)
+                .ok_or_else(|| anyhow!("wav file has no valid format chunk"))?;
+            return Ok((format, size as u64));
+        }
+
+        // chunks are padded to an even size
+        let mut body = vec![0u8; size + size % 2];
+        reader.read_exact(&mut body).await?;
+        if &header[..4] == b"fmt " {
+            if size < 16 {
+                return Err(anyhow!("invalid wav format chunk"));
+            }
+            let tag = u16::from_le_bytes([body[0], body[1]]);
+            let channels = u16::from_le_bytes([body[2], body[3]]);
+            let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+            let bits = u16::from_le_bytes([body[14], body[15]]);
+            // 0xFFFE is the extensible format, whose sub format is read from the bits
+            let encoding = match (tag, bits) {
+                (1 | 0xFFFE, 16) => SampleEncoding::S16Le,
+                (3 | 0xFFFE, 32) => SampleEncoding::F32Le,
+                _ => return Err(anyhow!("unsupported wav format {} ({} bits)", tag, bits)),
+            };
+            format = Some(PcmFormat {
+                sample_rate,
+                channels,
+                encoding,
+            });
+        }
+    }
+}
@@ -28,7 +28,8 @@ impl DeviceManager {
     }
 
     pub async fn start_device(&self, device: &AudioDevice) -> Result<()> {
-        if !self.devices().await.contains(device) {
+        // virtual devices aren't listed, opening them tells whether they exist
+        if !device.device_type.is_virtual() && !self.devices().await.contains(device) {
             return Err(anyhow!("device {device} not found"));
         }
 
@@ -1,4 +1,5 @@
 pub mod core;
+pub mod preprocessing;
 mod utils;
 pub mod vad;
 pub use transcription::stt::stt;
@@ -7,6 +8,7 @@ pub mod speaker;
 pub mod transcription;
 pub use utils::audio::pcm_decode;
 pub use utils::audio::resample;
+pub use utils::ffmpeg::decode_audio_file;
 pub mod audio_manager;
 mod device;
 mod segmentation;
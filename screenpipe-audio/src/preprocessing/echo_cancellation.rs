@@ -0,0 +1,154 @@
+use realfft::num_complex::Complex32;
+use realfft::RealFftPlanner;
+
+/// Filter taps of the echo path, 32ms at 16kHz
+const TAPS: usize = 512;
+/// Taps before the strongest echo, for the parts of the echo path the alignment puts ahead of it
+const PRE_TAPS: usize = 64;
+/// Step size of the adaptive filter
+const STEP_SIZE: f32 = 0.2;
+/// Correlation under which the reference isn't heard in the recording
+const MIN_ECHO_CORRELATION: f32 = 0.1;
+/// Adaptation stops while the residual is this much louder than the estimated echo, the
+/// microphone then picks up someone speaking over the echo
+const DOUBLE_TALK_RATIO: f32 = 4.0;
+
+/// Where the reference played by the speakers is heard in a recording
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EchoAlignment {
+    /// Sample of the reference heard at the first sample of the recording
+    pub offset: isize,
+    /// Normalized correlation of the recording and the reference at the offset
+    pub correlation: f32,
+    /// Level of the echo relative to the reference
+    pub gain: f32,
+}
+
+/// Finds the reference in the recording, `None` when it isn't heard. Both at the same sample
+/// rate, the reference may start before and end after the recording.
+pub fn find_echo(signal: &[f32], reference: &[f32]) -> Option<EchoAlignment> {
+    if signal.is_empty() || reference.is_empty() {
+        return None;
+    }
+    let signal_energy: f64 = signal.iter().map(|&s| (s as f64).powi(2)).sum();
+    if signal_energy <= f64::EPSILON {
+        return None;
+    }
+
+    // the reference padded with silence so at least half of the recording overlaps it
+    let padding = signal.len() / 2;
+    let mut padded = vec![0.0f32; padding];
+    padded.extend_from_slice(reference);
+    padded.resize(padded.len() + padding, 0.0);
+
+    let correlation = cross_correlation(signal, &padded);
+    let window_energy = window_energies(&padded, signal.len());
+
+    let (lag, correlation, energy) = correlation
+        .iter()
+        .zip(window_energy)
+        .enumerate()
+        .filter(|(_, (_, energy))| *energy > f64::EPSILON)
+        .map(|(lag, (&c, energy))| (lag, c as f64 / (signal_energy * energy).sqrt(), energy))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    if (correlation as f32) < MIN_ECHO_CORRELATION {
+        return None;
+    }
+    Some(EchoAlignment {
+        offset: lag as isize - padding as isize,
+        correlation: correlation as f32,
+        gain: (correlation * (signal_energy / energy).sqrt()) as f32,
+    })
+}
+
+/// Removes the echo of the reference from the recording with an adaptive filter
+pub fn cancel_echo(signal: &[f32], reference: &[f32], alignment: &EchoAlignment) -> Vec<f32> {
+    // window[j] of sample n is reference[n + offset - (TAPS - 1 - PRE_TAPS) + j], the strongest
+    // echo is at j = TAPS - 1 - PRE_TAPS
+    let base = alignment.offset - (TAPS - 1 - PRE_TAPS) as isize;
+    let aligned: Vec<f32> = (0..signal.len() + TAPS)
+        .map(|i| {
+            usize::try_from(base + i as isize)
+                .ok()
+                .and_then(|index| reference.get(index))
+                .copied()
+                .unwrap_or(0.0)
+        })
+        .collect();
+
+    let mut weights = vec![0.0f32; TAPS];
+    weights[TAPS - 1 - PRE_TAPS] = alignment.gain;
+    let mut power: f32 = aligned[..TAPS].iter().map(|x| x * x).sum();
+    let mut echo_level = 0.0f32;
+    let mut residual_level = 0.0f32;
+
+    let mut output = Vec::with_capacity(signal.len());
+    for (n, &sample) in signal.iter().enumerate() {
+        let window = &aligned[n..n + TAPS];
+        let echo: f32 = weights.iter().zip(window).map(|(w, x)| w * x).sum();
+        let residual = sample - echo;
+        output.push(residual);
+
+        echo_level = 0.99 * echo_level + 0.01 * echo * echo;
+        residual_level = 0.99 * residual_level + 0.01 * residual * residual;
+        if power > f32::EPSILON && residual_level <= DOUBLE_TALK_RATIO * echo_level {
+            let step = STEP_SIZE * residual / (power + 1e-6);
+            for (w, x) in weights.iter_mut().zip(window) {
+                *w += step * x;
+            }
+        }
+        power = (power + aligned[n + TAPS].powi(2) - aligned[n].powi(2)).max(0.0);
+    }
+    output
+}
+
+/// `c[lag] = sum(signal[n] * reference[n + lag])` for every lag keeping the signal within the
+/// reference
+fn cross_correlation(signal: &[f32], reference: &[f32]) -> Vec<f32> {
+    let size = (signal.len() + reference.len()).next_power_of_two();
+    let mut planner = RealFftPlanner::<f32>::new();
+    let forward = planner.plan_fft_forward(size);
+    let inverse = planner.plan_fft_inverse(size);
+
+    let mut a = signal.to_vec();
+    a.resize(size, 0.0);
+    let mut b = reference.to_vec();
+    b.resize(size, 0.0);
+    let mut spectrum_a = forward.make_output_vec();
+    let mut spectrum_b = forward.make_output_vec();
+    // sizes always match the plan
+    forward.process(&mut a, &mut spectrum_a).unwrap();
+    forward.process(&mut b, &mut spectrum_b).unwrap();
+
+    let mut product: Vec<Complex32> = spectrum_a
+        .iter()
+        .zip(&spectrum_b)
+        .map(|(a, b)| a.conj() * b)
+        .collect();
+    // the first and last bins of a real signal have no imaginary part
+    product[0].im = 0.0;
+    if let Some(last) = product.last_mut() {
+        last.im = 0.0;
+    }
+    let mut correlation = inverse.make_output_vec();
+    inverse.process(&mut product, &mut correlation).unwrap();
+
+    correlation.truncate(reference.len() - signal.len() + 1);
+    for c in correlation.iter_mut() {
+        *c /= size as f32;
+    }
+    correlation
+}
+
+/// Energy of every window of `len` samples
+fn window_energies(samples: &[f32], len: usize) -> Vec<f64> {
+    let mut prefix = Vec::with_capacity(samples.len() + 1);
+    prefix.push(0.0f64);
+    for &s in samples {
+        prefix.push(prefix[prefix.len() - 1] + (s as f64).powi(2));
+    }
+    (0..=samples.len() - len)
+        .map(|start| prefix[start + len] - prefix[start])
+        .collect()
+}
@@ -0,0 +1,211 @@
+//! Optional clean-up of recorded audio before VAD and transcription.
+//!
+//! Each device can run acoustic echo cancellation, noise suppression and automatic gain control,
+//! in that order. Echo cancellation removes what the speakers played from a microphone, using
+//! the live audio of the recorded output devices as reference, so the other side of a call on
+//! speakers isn't transcribed twice.
+
+pub mod echo_cancellation;
+pub mod noise_suppression;
+
+use anyhow::Result;
+use dashmap::DashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::debug;
+
+use crate::core::device::{AudioDevice, DeviceType};
+use crate::core::stream::AudioStream;
+use crate::transcription::stt::SAMPLE_RATE;
+use crate::utils::audio::{normalize_v2, resample};
+use crate::AudioInput;
+
+use echo_cancellation::{cancel_echo, find_echo};
+use noise_suppression::suppress_noise;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AudioPreprocessing {
+    pub noise_suppression: bool,
+    /// Brings quiet and loud recordings to the same level
+    pub auto_gain: bool,
+    /// Only applies to input devices
+    pub echo_cancellation: bool,
+    /// Output device whose audio is removed, the one heard best when not set
+    pub echo_reference: Option<String>,
+}
+
+impl AudioPreprocessing {
+    pub fn is_enabled(&self) -> bool {
+        self.noise_suppression || self.auto_gain || self.echo_cancellation
+    }
+}
+
+/// Recent audio of an output device
+struct EchoReference {
+    samples: VecDeque<f32>,
+    sample_rate: u32,
+}
+
+pub struct AudioPreprocessor {
+    default: AudioPreprocessing,
+    devices: HashMap<String, AudioPreprocessing>,
+    references: DashMap<String, EchoReference>,
+    reference_duration: Duration,
+}
+
+impl AudioPreprocessor {
+    /// `default` applies to input devices without settings in `devices`. References keep
+    /// `reference_duration` of audio, which must cover a chunk and the time it waits to be
+    /// transcribed.
+    pub fn new(
+        default: AudioPreprocessing,
+        devices: HashMap<String, AudioPreprocessing>,
+        reference_duration: Duration,
+    ) -> Self {
+        Self {
+            default,
+            devices,
+            references: DashMap::new(),
+            reference_duration,
+        }
+    }
+
+    pub fn settings(&self, device: &AudioDevice) -> AudioPreprocessing {
+        if let Some(settings) = self.devices.get(&device.to_string()) {
+            return settings.clone();
+        }
+        match device.device_type {
+            DeviceType::Output => AudioPreprocessing::default(),
+            _ => self.default.clone(),
+        }
+    }
+
+    /// Whether the audio of output devices needs to be kept for echo cancellation
+    pub fn uses_echo_reference(&self) -> bool {
+        self.default.echo_cancellation || self.devices.values().any(|s| s.echo_cancellation)
+    }
+
+    pub fn push_reference(&self, device: &AudioDevice, samples: &[f32], sample_rate: u32) {
+        let capacity = (self.reference_duration.as_secs_f64() * sample_rate as f64) as usize;
+        let mut reference = self
+            .references
+            .entry(device.to_string())
+            .or_insert_with(|| EchoReference {
+                samples: VecDeque::with_capacity(capacity),
+                sample_rate,
+            });
+        if reference.sample_rate != sample_rate {
+            reference.samples.clear();
+            reference.sample_rate = sample_rate;
+        }
+        reference.samples.extend(samples);
+        let excess = reference.samples.len().saturating_sub(capacity);
+        reference.samples.drain(..excess);
+    }
+
+    pub fn remove_reference(&self, device: &AudioDevice) {
+        self.references.remove(&device.to_string());
+    }
+
+    /// Runs the device's preprocessing, the result is resampled to 16kHz when any runs
+    pub fn process(&self, audio: AudioInput) -> Result<AudioInput> {
+        let settings = self.settings(&audio.device);
+        if !settings.is_enabled() {
+            return Ok(audio);
+        }
+
+        let mut data = if audio.sample_rate != SAMPLE_RATE {
+            resample(audio.data.as_ref(), audio.sample_rate, SAMPLE_RATE)?
+        } else {
+            audio.data.as_ref().to_vec()
+        };
+
+        if settings.echo_cancellation && audio.device.device_type == DeviceType::Input {
+            data = self.cancel_echo(&audio.device, &settings, data)?;
+        }
+        if settings.noise_suppression {
+            data = suppress_noise(&data)?;
+        }
+        if settings.auto_gain {
+            data = normalize_v2(&data);
+        }
+
+        Ok(AudioInput {
+            data: Arc::new(data),
+            sample_rate: SAMPLE_RATE,
+            ..audio
+        })
+    }
+
+    /// Removes the echo of the reference heard best in the recording
+    fn cancel_echo(
+        &self,
+        device: &AudioDevice,
+        settings: &AudioPreprocessing,
+        data: Vec<f32>,
+    ) -> Result<Vec<f32>> {
+        let references: Vec<(String, Vec<f32>, u32)> = self
+            .references
+            .iter()
+            .filter(|r| !r.samples.is_empty())
+            .filter(|r| {
+                settings
+                    .echo_reference
+                    .as_ref()
+                    .is_none_or(|name| r.key() == name)
+            })
+            .map(|r| {
+                let samples: Vec<f32> = r.samples.iter().copied().collect();
+                (r.key().clone(), samples, r.sample_rate)
+            })
+            .collect();
+
+        let mut best = None;
+        for (name, samples, sample_rate) in references {
+            let samples = if sample_rate != SAMPLE_RATE {
+                resample(&samples, sample_rate, SAMPLE_RATE)?
+            } else {
+                samples
+            };
+            let Some(alignment) = find_echo(&data, &samples) else {
+                continue;
+            };
+            match &best {
+                Some((_, _, best)) if best.correlation >= alignment.correlation => {}
+                _ => best = Some((name, samples, alignment)),
+            }
+        }
+
+        match best {
+            Some((name, reference, alignment)) => {
+                debug!(
+                    "cancelling echo of {} in {} (correlation {:.2})",
+                    name, device, alignment.correlation
+                );
+                Ok(cancel_echo(&data, &reference, &alignment))
+            }
+            None => Ok(data),
+        }
+    }
+}
+
+/// Keeps the live audio of an output device as echo reference while it's recorded
+pub async fn feed_echo_reference(
+    stream: Arc<AudioStream>,
+    preprocessor: Arc<AudioPreprocessor>,
+    is_running: Arc<AtomicBool>,
+) {
+    let mut receiver = stream.subscribe().await;
+    let sample_rate = stream.device_config.sample_rate().0;
+    while is_running.load(Ordering::Relaxed) {
+        match receiver.recv().await {
+            Ok(chunk) => preprocessor.push_reference(&stream.device, &chunk, sample_rate),
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        }
+    }
+    preprocessor.remove_reference(&stream.device);
+}
@@ -0,0 +1,55 @@
+use anyhow::Result;
+
+use crate::utils::audio::{average_noise_spectrum, spectral_subtraction};
+
+/// Frames of 100ms at 16kHz, half overlapping
+const FRAME_SIZE: usize = 1600;
+const HOP_SIZE: usize = FRAME_SIZE / 2;
+/// Share of the quietest frames taken as the noise floor
+const NOISE_FRAMES_SHARE: f32 = 0.1;
+/// Noise removed relative to the estimate, leaves less residual noise at the cost of quiet speech
+const OVER_SUBTRACTION: f32 = 3.0;
+
+/// Removes steady background noise like fans and air conditioning with spectral subtraction.
+/// The noise is estimated from the quietest frames of the audio.
+pub fn suppress_noise(audio: &[f32]) -> Result<Vec<f32>> {
+    if audio.is_empty() {
+        return Ok(Vec::new());
+    }
+    // periodic hann windows overlapping by half add up to one
+    let window: Vec<f32> = (0..FRAME_SIZE)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FRAME_SIZE as f32).cos())
+        .collect();
+
+    let mut padded = vec![0.0f32; HOP_SIZE];
+    padded.extend_from_slice(audio);
+    padded.resize(padded.len() + FRAME_SIZE, 0.0);
+
+    let frames: Vec<Vec<f32>> = (0..audio.len() + HOP_SIZE)
+        .step_by(HOP_SIZE)
+        .map(|start| {
+            padded[start..start + FRAME_SIZE]
+                .iter()
+                .zip(&window)
+                .map(|(s, w)| s * w)
+                .collect()
+        })
+        .collect();
+
+    let mut powers: Vec<f32> = frames.iter().map(|f| average_noise_spectrum(f)).collect();
+    powers.sort_by(f32::total_cmp);
+    let quietest = ((powers.len() as f32 * NOISE_FRAMES_SHARE).ceil() as usize).max(1);
+    let noise = OVER_SUBTRACTION * powers[..quietest].iter().sum::<f32>() / quietest as f32;
+
+    let mut output = vec![0.0f32; padded.len()];
+    for (index, frame) in frames.iter().enumerate() {
+        let start = index * HOP_SIZE;
+        for (out, sample) in output[start..]
+            .iter_mut()
+            .zip(spectral_subtraction(frame, noise)?)
+        {
+            *out += sample;
+        }
+    }
+    Ok(output[HOP_SIZE..HOP_SIZE + audio.len()].to_vec())
+}
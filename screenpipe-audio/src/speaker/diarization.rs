@@ -0,0 +1,318 @@
+//! Offline diarization of a whole conversation.
+//!
+//! While recording, every segment is matched to a speaker on its own, which is noisy for short
+//! segments. This re-segments each audio chunk of a time window, clusters the voices of all
+//! segments together and rewrites the speaker of the transcriptions, so one voice keeps one
+//! speaker through the conversation.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use screenpipe_core::encryption::open_media;
+use screenpipe_db::{normalize_embedding, DatabaseManager, DiarizationTranscription};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn};
+
+use crate::transcription::stt::SAMPLE_RATE;
+use crate::utils::audio::{normalize_v2, pcm_decode, resample};
+
+use super::{
+    embedding::EmbeddingExtractor,
+    embedding_manager::EmbeddingManager,
+    models::{get_or_download_model, PyannoteModel},
+    segment::get_segments,
+};
+
+/// Clusters whose segments are further apart than this, as average cosine distance, are
+/// different voices. Same bar as matching a segment to a speaker while recording.
+pub const CLUSTER_MAX_DISTANCE: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiarizationProgress {
+    pub chunks_total: usize,
+    pub chunks_processed: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DiarizationSummary {
+    pub chunks: usize,
+    /// Chunks that could not be read, e.g. removed by retention, their transcriptions are kept
+    pub chunks_failed: usize,
+    pub segments: usize,
+    /// Speaker of each voice found in the window
+    pub speakers: Vec<i64>,
+    pub transcriptions_updated: u64,
+}
+
+/// A voice segment of an audio chunk, offsets in seconds from the start of the chunk
+struct VoiceSegment {
+    audio_chunk_id: i64,
+    start: f64,
+    end: f64,
+    embedding: Vec<f32>,
+}
+
+/// Diarizes the transcriptions recorded between `start` and `end`, optionally only those of
+/// one device. Voices that match a known speaker keep it, others get a new speaker.
+pub async fn diarize_window(
+    db: &DatabaseManager,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    device: Option<&str>,
+    progress: impl Fn(DiarizationProgress),
+) -> Result<DiarizationSummary> {
+    let transcriptions = db
+        .get_transcriptions_for_diarization(start, end, device)
+        .await?;
+    let mut chunks: BTreeMap<i64, (String, Vec<DiarizationTranscription>)> = BTreeMap::new();
+    for transcription in transcriptions {
+        chunks
+            .entry(transcription.audio_chunk_id)
+            .or_insert_with(|| (transcription.file_path.clone(), Vec::new()))
+            .1
+            .push(transcription);
+    }
+
+    let mut summary = DiarizationSummary {
+        chunks: chunks.len(),
+        ..Default::default()
+    };
+    progress(DiarizationProgress {
+        chunks_total: chunks.len(),
+        chunks_processed: 0,
+    });
+    if chunks.is_empty() {
+        return Ok(summary);
+    }
+
+    let segmentation_model_path = get_or_download_model(PyannoteModel::Segmentation).await?;
+    let embedding_model_path = get_or_download_model(PyannoteModel::Embedding).await?;
+    let embedding_extractor = Arc::new(Mutex::new(EmbeddingExtractor::new(&embedding_model_path)?));
+
+    let mut segments = Vec::new();
+    for (processed, (&audio_chunk_id, (file_path, _))) in chunks.iter().enumerate() {
+        match segment_chunk(
+            file_path,
+            segmentation_model_path.clone(),
+            embedding_extractor.clone(),
+        )
+        .await
+        {
+            Ok(chunk_segments) => {
+                debug!("found {} segments in {}", chunk_segments.len(), file_path);
+                segments.extend(chunk_segments.into_iter().filter_map(
+                    |(start, end, embedding)| {
+                        Some(VoiceSegment {
+                            audio_chunk_id,
+                            start,
+                            end,
+                            embedding: normalize_embedding(&embedding)?,
+                        })
+                    },
+                ));
+            }
+            Err(e) => {
+                warn!("skipping {} in diarization: {}", file_path, e);
+                summary.chunks_failed += 1;
+            }
+        }
+        progress(DiarizationProgress {
+            chunks_total: chunks.len(),
+            chunks_processed: processed + 1,
+        });
+    }
+    summary.segments = segments.len();
+
+    let embeddings: Vec<Vec<f32>> = segments.iter().map(|s| s.embedding.clone()).collect();
+    // quadratic in the segments of the window, kept off the runtime threads
+    let clusters =
+        tokio::task::spawn_blocking(move || cluster_embeddings(&embeddings, CLUSTER_MAX_DISTANCE))
+            .await?;
+    let cluster_count = clusters.iter().max().map_or(0, |max| max + 1);
+
+    for cluster in 0..cluster_count {
+        let members: Vec<&[f32]> = segments
+            .iter()
+            .zip(&clusters)
+            .filter(|(_, c)| **c == cluster)
+            .map(|(s, _)| s.embedding.as_slice())
+            .collect();
+        let centroid = mean(&members);
+        let speaker = match db.get_speaker_from_embedding(&centroid).await? {
+            Some(speaker) => speaker,
+            None => db.insert_speaker(&centroid).await?,
+        };
+        summary.speakers.push(speaker.id);
+    }
+
+    let assignments: Vec<(i64, i64)> = chunks
+        .values()
+        .flat_map(|(_, transcriptions)| transcriptions)
+        .filter_map(|transcription| {
+            let cluster = transcription_cluster(transcription, &segments, &clusters)?;
+            Some((transcription.id, summary.speakers[cluster]))
+        })
+        .collect();
+    summary.transcriptions_updated = db.assign_transcription_speakers(&assignments).await?;
+
+    info!(
+        "diarized {} chunks from {} to {}: {} segments, {} speakers, {} transcriptions updated",
+        summary.chunks,
+        start,
+        end,
+        summary.segments,
+        summary.speakers.len(),
+        summary.transcriptions_updated
+    );
+    Ok(summary)
+}
+
+/// Runs segmentation over a whole chunk, returning (start, end, embedding) of each segment.
+async fn segment_chunk(
+    file_path: &str,
+    segmentation_model_path: PathBuf,
+    embedding_extractor: Arc<Mutex<EmbeddingExtractor>>,
+) -> Result<Vec<(f64, f64, Vec<f32>)>> {
+    let media = open_media(file_path).await?;
+    let path = media.path().to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let (samples, sample_rate) = pcm_decode(&path)?;
+        let samples = if sample_rate != SAMPLE_RATE {
+            resample(&samples, sample_rate, SAMPLE_RATE)?
+        } else {
+            samples
+        };
+
+        get_segments(
+            &normalize_v2(&samples),
+            SAMPLE_RATE,
+            &segmentation_model_path,
+            embedding_extractor,
+            EmbeddingManager::new(usize::MAX),
+        )?
+        .map(|segment| segment.map(|s| (s.start, s.end, s.embedding)))
+        .collect()
+    })
+    .await?
+}
+
+/// Cluster of the segments that overlap the transcription the most
+fn transcription_cluster(
+    transcription: &DiarizationTranscription,
+    segments: &[VoiceSegment],
+    clusters: &[usize],
+) -> Option<usize> {
+    let (start, end) = (transcription.start_time?, transcription.end_time?);
+    let mut overlaps: BTreeMap<usize, f64> = BTreeMap::new();
+    for (segment, &cluster) in segments.iter().zip(clusters) {
+        if segment.audio_chunk_id != transcription.audio_chunk_id {
+            continue;
+        }
+        let overlap = end.min(segment.end) - start.max(segment.start);
+        if overlap > 0.0 {
+            *overlaps.entry(cluster).or_default() += overlap;
+        }
+    }
+    overlaps
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(cluster, _)| cluster)
+}
+
+/// Agglomerative clustering of normalized embeddings with average linkage on cosine distance,
+/// merging until every pair of clusters is at least `max_distance` apart.
+///
+/// Returns the cluster of each embedding, numbered in order of first appearance. Uses the
+/// nearest neighbor chain so an hour long meeting stays quadratic.
+pub fn cluster_embeddings(embeddings: &[Vec<f32>], max_distance: f32) -> Vec<usize> {
+    let n = embeddings.len();
+    let mut distances = vec![0f32; n * n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let similarity: f32 = embeddings[i]
+                .iter()
+                .zip(&embeddings[j])
+                .map(|(a, b)| a * b)
+                .sum();
+            distances[i * n + j] = 1.0 - similarity;
+            distances[j * n + i] = 1.0 - similarity;
+        }
+    }
+
+    // a cluster is kept at the index of its first embedding
+    let mut members: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+    // clusters that may still merge
+    let mut open = vec![true; n];
+    let mut chain: Vec<usize> = Vec::new();
+
+    loop {
+        if chain.is_empty() {
+            match open.iter().position(|&o| o) {
+                Some(i) => chain.push(i),
+                None => break,
+            }
+        }
+        let a = chain[chain.len() - 1];
+        let previous = chain.len().checked_sub(2).map(|i| chain[i]);
+
+        // ties go to the previous link so the chain can't cycle
+        let mut nearest = previous.map(|p| (p, distances[a * n + p]));
+        for k in (0..n).filter(|&k| open[k] && k != a) {
+            let distance = distances[a * n + k];
+            if nearest.is_none_or(|(_, d)| distance < d) {
+                nearest = Some((k, distance));
+            }
+        }
+
+        match nearest {
+            Some((b, distance)) if distance < max_distance => {
+                if Some(b) != previous {
+                    chain.push(b);
+                    continue;
+                }
+                chain.truncate(chain.len() - 2);
+
+                let (keep, merged) = (a.min(b), a.max(b));
+                let (keep_size, merged_size) =
+                    (members[keep].len() as f32, members[merged].len() as f32);
+                for k in (0..n).filter(|&k| open[k] && k != keep && k != merged) {
+                    let distance = (distances[keep * n + k] * keep_size
+                        + distances[merged * n + k] * merged_size)
+                        / (keep_size + merged_size);
+                    distances[keep * n + k] = distance;
+                    distances[k * n + keep] = distance;
+                }
+                let moved = std::mem::take(&mut members[merged]);
+                members[keep].extend(moved);
+                open[merged] = false;
+            }
+            // distances only grow as clusters merge, so nothing in the chain, whose links get
+            // closer towards the end, can merge anymore
+            _ => {
+                for i in chain.drain(..) {
+                    open[i] = false;
+                }
+            }
+        }
+    }
+
+    let mut labels = vec![0; n];
+    for (label, cluster) in members.iter().filter(|m| !m.is_empty()).enumerate() {
+        for &i in cluster {
+            labels[i] = label;
+        }
+    }
+    labels
+}
+
+fn mean(embeddings: &[&[f32]]) -> Vec<f32> {
+    let mut sum = vec![0f32; embeddings.first().map_or(0, |e| e.len())];
+    for embedding in embeddings {
+        for (total, v) in sum.iter_mut().zip(embedding.iter()) {
+            *total += v;
+        }
+    }
+    sum.iter().map(|v| v / embeddings.len() as f32).collect()
+}
@@ -1,11 +1,14 @@
 use anyhow::{bail, Result};
 use ndarray::Array1;
+use screenpipe_db::{normalize_embedding, MAX_CENTROID_WEIGHT};
 use std::collections::HashMap;
 
+/// Speakers heard in the current session, each as the mean of its normalized segment embeddings
 #[derive(Debug, Clone)]
 pub struct EmbeddingManager {
     max_speakers: usize,
     speakers: HashMap<usize, Array1<f32>>,
+    sample_counts: HashMap<usize, usize>,
     next_speaker_id: usize,
 }
 
@@ -14,6 +17,7 @@ impl EmbeddingManager {
         Self {
             max_speakers,
             speakers: HashMap::new(),
+            sample_counts: HashMap::new(),
             next_speaker_id: 1,
         }
     }
@@ -25,7 +29,7 @@ impl EmbeddingManager {
         dot_product / (norm_a * norm_b)
     }
 
-    /// Search or create speaker
+    /// Search or create speaker, moving the centroid of the matched speaker towards `embedding`
     pub fn search_speaker(&mut self, embedding: Vec<f32>, threshold: f32) -> Option<usize> {
         let embedding_array = Array1::from_vec(embedding);
         let mut best_speaker_id = None;
@@ -40,7 +44,10 @@ impl EmbeddingManager {
         }
 
         match best_speaker_id {
-            Some(id) => Some(id),
+            Some(id) => {
+                self.update_centroid(id, &embedding_array);
+                Some(id)
+            }
             None if self.speakers.len() < self.max_speakers => {
                 Some(self.add_speaker(embedding_array))
             }
@@ -68,11 +75,33 @@ impl EmbeddingManager {
 
     fn add_speaker(&mut self, embedding: Array1<f32>) -> usize {
         let speaker_id = self.next_speaker_id;
-        self.speakers.insert(speaker_id, embedding);
+        self.speakers.insert(speaker_id, Self::normalize(embedding));
+        self.sample_counts.insert(speaker_id, 1);
         self.next_speaker_id += 1;
         speaker_id
     }
 
+    fn update_centroid(&mut self, speaker_id: usize, embedding: &Array1<f32>) {
+        let (Some(centroid), Some(count)) = (
+            self.speakers.get_mut(&speaker_id),
+            self.sample_counts.get_mut(&speaker_id),
+        ) else {
+            return;
+        };
+        let weight = (*count).min(MAX_CENTROID_WEIGHT as usize) as f32;
+        let embedding = Self::normalize(embedding.clone());
+        *centroid = (&*centroid * weight + embedding) / (weight + 1.0);
+        *count += 1;
+    }
+
+    /// See [`normalize_embedding`], all zero embeddings are kept as is
+    fn normalize(embedding: Array1<f32>) -> Array1<f32> {
+        match embedding.as_slice().and_then(normalize_embedding) {
+            Some(normalized) => Array1::from(normalized),
+            None => embedding,
+        }
+    }
+
     #[allow(unused)]
     pub fn get_all_speakers(&self) -> &HashMap<usize, Array1<f32>> {
         &self.speakers
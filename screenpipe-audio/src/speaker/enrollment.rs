@@ -0,0 +1,58 @@
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+use crate::transcription::stt::SAMPLE_RATE;
+use crate::utils::audio::{normalize_v2, pcm_decode, resample};
+
+use super::{
+    embedding::EmbeddingExtractor,
+    models::{get_or_download_model, PyannoteModel},
+};
+
+/// Length of the windows a voice sample is cut into, each one gives an embedding
+const WINDOW_SECS: usize = 3;
+/// Shortest trailing window worth embedding
+const MIN_WINDOW_SECS: usize = 1;
+/// Windows quieter than this, as rms before normalization, are silence and skipped
+const MIN_WINDOW_RMS: f32 = 0.005;
+
+/// Computes voice embeddings of a recording of one person speaking, for
+/// `DatabaseManager::enroll_speaker`. Any format the recorder can decode works, a few
+/// seconds of speech is enough and more gives a steadier voice print.
+pub async fn compute_enrollment_embeddings(path: &Path) -> Result<Vec<Vec<f32>>> {
+    let embedding_model_path = get_or_download_model(PyannoteModel::Embedding).await?;
+    let path = path.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let (samples, sample_rate) = pcm_decode(&path)?;
+        let samples = if sample_rate != SAMPLE_RATE {
+            resample(&samples, sample_rate, SAMPLE_RATE)?
+        } else {
+            samples
+        };
+
+        let mut extractor = EmbeddingExtractor::new(&embedding_model_path)?;
+        let window = WINDOW_SECS * SAMPLE_RATE as usize;
+        let mut embeddings = Vec::new();
+        for chunk in samples.chunks(window) {
+            if chunk.len() < MIN_WINDOW_SECS * SAMPLE_RATE as usize || rms(chunk) < MIN_WINDOW_RMS {
+                continue;
+            }
+            embeddings.push(extractor.compute(&normalize_v2(chunk))?.collect());
+        }
+
+        if embeddings.is_empty() {
+            return Err(anyhow!(
+                "no speech found in {:?}, enrollment needs at least {} second of audio",
+                path,
+                MIN_WINDOW_SECS
+            ));
+        }
+        Ok(embeddings)
+    })
+    .await?
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
@@ -1,3 +1,4 @@
+pub mod diarization;
 pub mod embedding;
 
 use std::path::Path;
@@ -14,6 +15,7 @@ pub fn create_session<P: AsRef<Path>>(path: P) -> Result<Session> {
     Ok(session)
 }
 pub mod embedding_manager;
+pub mod enrollment;
 pub mod models;
 mod prepare_segments;
 pub use prepare_segments::prepare_segments;
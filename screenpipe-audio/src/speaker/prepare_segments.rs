@@ -1,7 +1,7 @@
 use super::segment::get_segments;
 use crate::{
     utils::audio::{average_noise_spectrum, normalize_v2, spectral_subtraction},
-    vad::VadEngine,
+    vad::{regions::smooth_speech_frames, VadEngine},
 };
 use anyhow::Result;
 use std::{path::PathBuf, sync::Arc, sync::Mutex as StdMutex};
@@ -28,19 +28,17 @@ pub async fn prepare_segments(
 
     let mut noise = 0.;
     let mut audio_frames = Vec::new();
-    let mut total_frames = 0;
-    let mut speech_frame_count = 0;
+    let mut speech_frames = Vec::new();
 
     for chunk in audio_data.chunks(frame_size) {
-        total_frames += 1;
-
+        let mut is_speech = false;
         let mut new_chunk = chunk.to_vec();
         let status = vad_engine.lock().await.audio_type(chunk);
         match status {
             Ok(VadStatus::Speech) => {
                 if let Ok(processed_audio) = spectral_subtraction(chunk, noise) {
                     new_chunk = processed_audio;
-                    speech_frame_count += 1;
+                    is_speech = true;
                 }
             }
             Ok(VadStatus::Unknown) => {
@@ -48,11 +46,18 @@ pub async fn prepare_segments(
             }
             _ => {}
         }
+        speech_frames.push(is_speech);
         audio_frames.extend(new_chunk);
     }
 
+    let vad_config = vad_engine.lock().await.config();
+    let total_frames = speech_frames.len();
+    let speech_frame_count = smooth_speech_frames(&speech_frames, &vad_config)
+        .into_iter()
+        .filter(|&is_speech| is_speech)
+        .count();
     let speech_ratio = speech_frame_count as f32 / total_frames as f32;
-    let min_speech_ratio = vad_engine.lock().await.get_min_speech_ratio();
+    let min_speech_ratio = vad_config.min_speech_ratio;
 
     info!(
         "device: {}, speech ratio: {}, min_speech_ratio: {}, audio_frames: {}, speech_frames: {}",
@@ -0,0 +1,145 @@
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use screenpipe_core::Language;
+use screenpipe_db::TranscriptionWord;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::error;
+use whisper_rs::WhisperContext;
+
+use crate::core::engine::AudioTranscriptionEngine;
+
+use super::deepgram::batch::transcribe_with_deepgram;
+use super::openai_compatible::{OpenAiCompatibleBackend, OpenAiCompatibleConfig};
+use super::whisper::batch::process_with_whisper;
+use super::whisper::model::{create_whisper_context_parameters, download_whisper_model};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcription {
+    pub text: String,
+    /// Word timings in seconds from the start of the transcribed audio, empty when the engine
+    /// doesn't report them
+    pub words: Vec<TranscriptionWord>,
+}
+
+/// A speech to text engine.
+///
+/// The built-in engines are picked with [`crate::core::engine::AudioTranscriptionEngine`],
+/// others can be plugged in with
+/// [`crate::audio_manager::AudioManagerBuilder::transcription_backend`].
+pub trait TranscriptionBackend: Send + Sync {
+    /// Transcribes a segment of mono audio
+    fn transcribe<'a>(
+        &'a self,
+        audio: &'a [f32],
+        sample_rate: u32,
+        device: &'a str,
+        languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<Transcription>>;
+}
+
+/// Local whisper.cpp model
+pub struct WhisperBackend {
+    context: Arc<WhisperContext>,
+}
+
+impl WhisperBackend {
+    pub fn new(context: Arc<WhisperContext>) -> Self {
+        Self { context }
+    }
+}
+
+impl TranscriptionBackend for WhisperBackend {
+    fn transcribe<'a>(
+        &'a self,
+        audio: &'a [f32],
+        _sample_rate: u32,
+        _device: &'a str,
+        languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<Transcription>> {
+        Box::pin(process_with_whisper(
+            audio,
+            languages.to_vec(),
+            self.context.clone(),
+        ))
+    }
+}
+
+/// Deepgram's batch api, falling back to local whisper when a request fails
+pub struct DeepgramBackend {
+    api_key: String,
+    fallback: WhisperBackend,
+}
+
+impl DeepgramBackend {
+    pub fn new(api_key: String, fallback: WhisperBackend) -> Self {
+        Self { api_key, fallback }
+    }
+}
+
+impl TranscriptionBackend for DeepgramBackend {
+    fn transcribe<'a>(
+        &'a self,
+        audio: &'a [f32],
+        sample_rate: u32,
+        device: &'a str,
+        languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<Transcription>> {
+        Box::pin(async move {
+            match transcribe_with_deepgram(
+                &self.api_key,
+                audio,
+                device,
+                sample_rate,
+                languages.to_vec(),
+            )
+            .await
+            {
+                Ok(transcription) => Ok(transcription),
+                Err(e) => {
+                    error!(
+                        "device: {}, deepgram transcription failed, falling back to Whisper: {:?}",
+                        device, e
+                    );
+                    self.fallback
+                        .transcribe(audio, sample_rate, device, languages)
+                        .await
+                }
+            }
+        })
+    }
+}
+
+/// Creates the backend of a built-in engine. The whisper model the engine needs is downloaded
+/// unless `whisper_model_path` is given, so this can block for a while.
+pub fn create_transcription_backend(
+    engine: Arc<AudioTranscriptionEngine>,
+    whisper_model_path: Option<&Path>,
+    deepgram_api_key: Option<String>,
+    openai_compatible_config: Option<OpenAiCompatibleConfig>,
+) -> Result<Arc<dyn TranscriptionBackend>> {
+    if *engine == AudioTranscriptionEngine::OpenAiCompatible {
+        let config = openai_compatible_config
+            .ok_or_else(|| anyhow!("openai compatible transcription needs an endpoint"))?;
+        return Ok(Arc::new(OpenAiCompatibleBackend::new(config)?));
+    }
+
+    let model_path = match whisper_model_path {
+        Some(path) => path.to_path_buf(),
+        None => download_whisper_model(engine.clone())?,
+    };
+    let context_param = create_whisper_context_parameters(engine.clone())?;
+    let whisper = WhisperBackend::new(Arc::new(WhisperContext::new_with_params(
+        &model_path.to_string_lossy(),
+        context_param,
+    )?));
+
+    let backend: Arc<dyn TranscriptionBackend> = match *engine {
+        AudioTranscriptionEngine::Deepgram => Arc::new(DeepgramBackend::new(
+            deepgram_api_key.unwrap_or_default(),
+            whisper,
+        )),
+        _ => Arc::new(whisper),
+    };
+    Ok(backend)
+}
@@ -2,10 +2,12 @@ use anyhow::Result;
 use hound::{WavSpec, WavWriter};
 use reqwest::{Client, Response};
 use screenpipe_core::Language;
+use screenpipe_db::TranscriptionWord;
 use serde_json::Value;
 use std::io::Cursor;
 use tracing::{debug, error, info};
 
+use crate::transcription::backend::Transcription;
 use crate::transcription::deepgram::{CUSTOM_DEEPGRAM_API_TOKEN, DEEPGRAM_API_URL};
 
 pub async fn transcribe_with_deepgram(
@@ -14,7 +16,7 @@ pub async fn transcribe_with_deepgram(
     device: &str,
     sample_rate: u32,
     languages: Vec<Language>,
-) -> Result<String> {
+) -> Result<Transcription> {
     debug!("starting deepgram transcription");
 
     // Use token from env var
@@ -113,7 +115,7 @@ async fn get_deepgram_response(
 async fn handle_deepgram_response(
     response: Result<Response, reqwest::Error>,
     device: &str,
-) -> Result<String> {
+) -> Result<Transcription> {
     match response {
         Ok(resp) => {
             debug!("received response from deepgram api");
@@ -142,7 +144,10 @@ async fn handle_deepgram_response(
                         );
                     }
 
-                    Ok(transcription.to_string())
+                    Ok(Transcription {
+                        text: transcription.to_string(),
+                        words: deepgram_words(&result),
+                    })
                 }
                 Err(e) => {
                     error!("Failed to parse JSON response: {:?}", e);
@@ -159,3 +164,26 @@ async fn handle_deepgram_response(
         }
     }
 }
+
+/// Words of the first alternative of a deepgram response, with punctuation when smart format
+/// added it
+pub fn deepgram_words(result: &Value) -> Vec<TranscriptionWord> {
+    let Some(words) = result["results"]["channels"][0]["alternatives"][0]["words"].as_array()
+    else {
+        return Vec::new();
+    };
+    words
+        .iter()
+        .filter_map(|word| {
+            let text = word["punctuated_word"]
+                .as_str()
+                .or_else(|| word["word"].as_str())?;
+            Some(TranscriptionWord {
+                text: text.to_string(),
+                start: word["start"].as_f64()?,
+                end: word["end"].as_f64()?,
+                confidence: word["confidence"].as_f64().map(|c| c as f32),
+            })
+        })
+        .collect()
+}
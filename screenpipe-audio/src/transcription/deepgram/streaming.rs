@@ -298,7 +298,7 @@ async fn handle_transcription(result: StreamResponse, device: Arc<AudioDevice>)
             );
             let res = channel.alternatives.first().unwrap();
             let text = res.transcript.clone();
-            let is_input = device.device_type == DeviceType::Input;
+            let is_input = device.device_type != DeviceType::Output;
 
             let speaker = res
                 .words
@@ -354,7 +354,7 @@ async fn handle_transcription(result: StreamResponse, device: Arc<AudioDevice>)
                                             None
                                         };
                                         
-                                        let is_input = device.device_type == DeviceType::Input;
+                                        let is_input = device.device_type != DeviceType::Output;
                                         
                                         let _ = send_event(
                                             "transcription",
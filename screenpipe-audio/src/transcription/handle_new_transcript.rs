@@ -40,6 +40,13 @@ pub async fn handle_new_transcript(
                 if current_transcript.is_some()
                     && current != current_transcript.clone().unwrap_or_default()
                 {
+                    let cut = current_transcript
+                        .as_deref()
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .count()
+                        .saturating_sub(current.split_whitespace().count());
+                    transcription.drop_leading_words(cut);
                     current_transcript = Some(current);
                 }
             }
@@ -0,0 +1,238 @@
+//! Import of audio recorded outside screenpipe.
+//!
+//! Phone calls, voice memos and other recordings go through the same VAD, segmentation, speaker
+//! matching and transcription as live audio. A file is split into chunks of the live chunk
+//! duration, each stored in the data directory and placed at the time it was recorded, so the
+//! import shows up in search and the timeline like live audio.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use screenpipe_core::Language;
+use screenpipe_db::DatabaseManager;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::core::device::AudioDevice;
+use crate::core::engine::AudioTranscriptionEngine;
+use crate::segmentation::segmentation_manager::SegmentationManager;
+use crate::speaker::prepare_segments;
+use crate::utils::ffmpeg::{decode_audio_file, sanitize_device_name, write_audio_to_file};
+use crate::vad::{silero::SileroVad, webrtc::WebRtcVad, VadConfig, VadEngine, VadEngineEnum};
+
+use super::backend::TranscriptionBackend;
+use super::process_transcription_result;
+use super::stt::{run_stt, SAMPLE_RATE};
+
+/// Cosine distance under which a transcription goes to one of several hinted speakers. Same bar
+/// as re-attributing past transcriptions to named speakers.
+pub const SPEAKER_HINT_MAX_DISTANCE: f64 = 0.4;
+
+/// How an imported file was recorded
+#[derive(Debug, Clone)]
+pub struct AudioImport {
+    /// When the recording started
+    pub timestamp: DateTime<Utc>,
+    pub device: AudioDevice,
+    /// Names of the people heard in the recording, see
+    /// [`DatabaseManager::apply_speaker_hints`]
+    pub speakers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AudioImportSummary {
+    /// Length of the recording in seconds
+    pub duration: f64,
+    pub chunks: usize,
+    /// Chunks skipped by VAD, like silent chunks of live audio
+    pub chunks_without_speech: usize,
+    pub transcriptions: usize,
+    /// Transcriptions moved from the speaker matched while transcribing to a hinted one
+    pub transcriptions_hinted: u64,
+}
+
+/// Runs audio files through the live transcription pipeline.
+pub struct AudioImporter {
+    db: Arc<DatabaseManager>,
+    transcription_engine: Arc<AudioTranscriptionEngine>,
+    transcription_backend: Arc<dyn TranscriptionBackend>,
+    vad_engine: Arc<Mutex<Box<dyn VadEngine + Send>>>,
+    segmentation_manager: SegmentationManager,
+    languages: Vec<Language>,
+    chunk_duration: Duration,
+    output_path: PathBuf,
+}
+
+impl AudioImporter {
+    /// Loads the VAD and speaker models. Chunks are written to `output_path`, the data
+    /// directory of live audio.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new(
+        db: Arc<DatabaseManager>,
+        transcription_engine: Arc<AudioTranscriptionEngine>,
+        transcription_backend: Arc<dyn TranscriptionBackend>,
+        vad_engine: VadEngineEnum,
+        vad_config: VadConfig,
+        languages: Vec<Language>,
+        chunk_duration: Duration,
+        output_path: PathBuf,
+    ) -> Result<Self> {
+        let mut vad_engine: Box<dyn VadEngine + Send> = match vad_engine {
+            VadEngineEnum::Silero => Box::new(SileroVad::new().await?),
+            VadEngineEnum::WebRtc => Box::new(WebRtcVad::new()),
+        };
+        vad_engine.set_config(vad_config);
+
+        Ok(Self {
+            db,
+            transcription_engine,
+            transcription_backend,
+            vad_engine: Arc::new(Mutex::new(vad_engine)),
+            segmentation_manager: SegmentationManager::new().await?,
+            languages,
+            chunk_duration,
+            output_path,
+        })
+    }
+
+    /// Transcribes an audio file in any format ffmpeg reads and stores it as recorded by
+    /// `import.device` at `import.timestamp`.
+    pub async fn import_file(
+        &self,
+        path: &Path,
+        import: &AudioImport,
+    ) -> Result<AudioImportSummary> {
+        let samples = {
+            let path = path.to_path_buf();
+            tokio::task::spawn_blocking(move || decode_audio_file(&path, SAMPLE_RATE)).await??
+        };
+        let chunk_len = ((self.chunk_duration.as_secs_f64() * SAMPLE_RATE as f64) as usize).max(1);
+
+        let mut summary = AudioImportSummary {
+            duration: samples.len() as f64 / SAMPLE_RATE as f64,
+            ..Default::default()
+        };
+        for (index, chunk) in samples.chunks(chunk_len).enumerate() {
+            let offset = (index * chunk_len) as f64 / SAMPLE_RATE as f64;
+            let timestamp =
+                import.timestamp + chrono::Duration::milliseconds((offset * 1000.0) as i64);
+
+            summary.chunks += 1;
+            match self.import_chunk(chunk, timestamp, import).await? {
+                Some((transcriptions, hinted)) => {
+                    summary.transcriptions += transcriptions;
+                    summary.transcriptions_hinted += hinted;
+                }
+                None => summary.chunks_without_speech += 1,
+            }
+        }
+
+        info!(
+            "imported {} ({:.0}s) as {}: {} chunks, {} transcriptions",
+            path.display(),
+            summary.duration,
+            import.device,
+            summary.chunks,
+            summary.transcriptions
+        );
+        Ok(summary)
+    }
+
+    /// Returns the number of transcriptions and of those moved to a hinted speaker, or
+    /// `None` when the chunk has too little speech
+    async fn import_chunk(
+        &self,
+        samples: &[f32],
+        timestamp: DateTime<Utc>,
+        import: &AudioImport,
+    ) -> Result<Option<(usize, u64)>> {
+        let device_name = import.device.to_string();
+        let (mut segments, speech_ratio_ok) = prepare_segments(
+            samples,
+            self.vad_engine.clone(),
+            &self.segmentation_manager.segmentation_model_path,
+            self.segmentation_manager.embedding_manager.clone(),
+            self.segmentation_manager.embedding_extractor.clone(),
+            &device_name,
+        )
+        .await?;
+        if !speech_ratio_ok {
+            return Ok(None);
+        }
+
+        let file_path = chunk_file_path(&self.output_path, &device_name, timestamp);
+        write_audio_to_file(samples, SAMPLE_RATE, &file_path, false)?;
+        let file_path = file_path.to_string_lossy().to_string();
+
+        let device = Arc::new(import.device.clone());
+        let mut transcriptions = 0;
+        let mut audio_chunk_id = None;
+        while let Some(segment) = segments.recv().await {
+            let result = run_stt(
+                segment,
+                device.clone(),
+                self.transcription_backend.clone(),
+                self.languages.clone(),
+                file_path.clone(),
+                timestamp.timestamp() as u64,
+            )
+            .await?;
+            if result
+                .transcription
+                .as_deref()
+                .is_none_or(|text| text.trim().is_empty())
+            {
+                continue;
+            }
+
+            if let Some(id) = process_transcription_result(
+                &self.db,
+                result,
+                self.transcription_engine.clone(),
+                None,
+                None,
+            )
+            .await?
+            {
+                audio_chunk_id = Some(id);
+                transcriptions += 1;
+            }
+        }
+
+        let Some(audio_chunk_id) = audio_chunk_id else {
+            // nothing was said, the chunk isn't referenced by the database
+            if let Err(e) = std::fs::remove_file(&file_path) {
+                warn!("failed to remove {}: {}", file_path, e);
+            }
+            return Ok(Some((0, 0)));
+        };
+        self.db
+            .set_audio_chunk_timestamp(audio_chunk_id, timestamp)
+            .await?;
+        let hinted = self
+            .db
+            .apply_speaker_hints(audio_chunk_id, &import.speakers, SPEAKER_HINT_MAX_DISTANCE)
+            .await?;
+        Ok(Some((transcriptions, hinted)))
+    }
+}
+
+/// Path of a chunk named like live chunks after the time it was recorded, numbered when a
+/// chunk of the same device and second exists
+fn chunk_file_path(output_path: &Path, device: &str, timestamp: DateTime<Utc>) -> PathBuf {
+    let name = format!(
+        "{}_{}",
+        sanitize_device_name(device),
+        timestamp.format("%Y-%m-%d_%H-%M-%S")
+    );
+    let mut path = output_path.join(format!("{}.mp4", name));
+    let mut n = 1;
+    while path.exists() {
+        path = output_path.join(format!("{}_{}.mp4", name, n));
+        n += 1;
+    }
+    path
+}
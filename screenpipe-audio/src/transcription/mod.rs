@@ -2,7 +2,11 @@ use std::sync::Arc;
 
 use crate::core::device::AudioDevice;
 
+pub mod backend;
 pub mod deepgram;
+pub mod import;
+pub mod openai_compatible;
+pub mod retranscribe;
 pub mod stt;
 pub mod whisper;
 
@@ -19,6 +23,7 @@ mod text_utils;
 mod transcription_result;
 
 pub use transcription_result::process_transcription_result;
+pub use transcription_result::remove_pii_from_words;
 pub use transcription_result::TranscriptionResult;
 mod handle_new_transcript;
 pub use handle_new_transcript::handle_new_transcript;
@@ -0,0 +1,134 @@
+//! Transcription through any server implementing OpenAI's `/v1/audio/transcriptions`, e.g. a
+//! self-hosted whisper.cpp server or faster-whisper-server.
+
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use hound::{WavSpec, WavWriter};
+use reqwest::multipart::{Form, Part};
+use reqwest::Client;
+use screenpipe_core::Language;
+use serde::Deserialize;
+use std::io::Cursor;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use super::backend::{Transcription, TranscriptionBackend};
+
+#[derive(Debug, Clone)]
+pub struct OpenAiCompatibleConfig {
+    /// Full url of the endpoint, e.g. http://localhost:8080/v1/audio/transcriptions
+    pub endpoint: String,
+    /// Sent as a bearer token when set
+    pub api_key: Option<String>,
+    pub model: String,
+    pub timeout: Duration,
+}
+
+impl OpenAiCompatibleConfig {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            api_key: None,
+            model: "whisper-1".to_string(),
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TranscriptionResponse {
+    text: String,
+}
+
+pub struct OpenAiCompatibleBackend {
+    config: OpenAiCompatibleConfig,
+    client: Client,
+}
+
+impl OpenAiCompatibleBackend {
+    pub fn new(config: OpenAiCompatibleConfig) -> Result<Self> {
+        let client = Client::builder().timeout(config.timeout).build()?;
+        Ok(Self { config, client })
+    }
+
+    async fn request(
+        &self,
+        audio: &[f32],
+        sample_rate: u32,
+        device: &str,
+        languages: &[Language],
+    ) -> Result<Transcription> {
+        let wav = Part::bytes(encode_wav(audio, sample_rate)?)
+            .file_name("audio.wav")
+            .mime_str("audio/wav")?;
+        let mut form = Form::new()
+            .part("file", wav)
+            .text("model", self.config.model.clone())
+            .text("response_format", "json");
+        // the api takes a single language, let the server detect it otherwise
+        if let [language] = languages {
+            form = form.text("language", language.as_lang_code());
+        }
+
+        let mut request = self.client.post(&self.config.endpoint).multipart(form);
+        if let Some(api_key) = &self.config.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        debug!("sending audio of {} to {}", device, self.config.endpoint);
+        let response = request.send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!(
+                "{} returned {}: {}",
+                self.config.endpoint,
+                status,
+                body
+            ));
+        }
+        let transcription = response.json::<TranscriptionResponse>().await?.text;
+        let transcription = transcription.trim().to_string();
+
+        info!(
+            "device: {}, transcription successful. length: {} characters",
+            device,
+            transcription.len()
+        );
+        Ok(Transcription {
+            text: transcription,
+            words: Vec::new(),
+        })
+    }
+}
+
+impl TranscriptionBackend for OpenAiCompatibleBackend {
+    fn transcribe<'a>(
+        &'a self,
+        audio: &'a [f32],
+        sample_rate: u32,
+        device: &'a str,
+        languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<Transcription>> {
+        Box::pin(self.request(audio, sample_rate, device, languages))
+    }
+}
+
+/// 16 bit pcm wav, the one format every implementation of the api reads
+fn encode_wav(audio: &[f32], sample_rate: u32) -> Result<Vec<u8>> {
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = WavWriter::new(&mut cursor, spec)?;
+        for &sample in audio {
+            writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+        }
+        writer.finalize()?;
+    }
+    Ok(cursor.into_inner())
+}
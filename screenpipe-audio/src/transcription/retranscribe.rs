@@ -0,0 +1,196 @@
+//! Re-transcription of recorded audio with another engine.
+//!
+//! Audio chunks are kept on disk, so segments transcribed with a small model while recording
+//! can be run through a better engine later. Each engine's text is stored as a version of the
+//! transcription and search returns the best one, see [`AudioTranscriptionEngine::quality`].
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use screenpipe_core::{encryption::open_media, pii_removal, Language};
+use screenpipe_db::{DatabaseManager, RetranscriptionTarget, TranscriptionWord};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::core::engine::AudioTranscriptionEngine;
+use crate::utils::audio::{pcm_decode, resample};
+
+use super::backend::TranscriptionBackend;
+use super::remove_pii_from_words;
+use super::stt::{stt, SAMPLE_RATE};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetranscriptionProgress {
+    pub chunks_total: usize,
+    pub chunks_processed: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetranscriptionSummary {
+    pub chunks: usize,
+    /// Chunks that could not be read, e.g. removed by retention
+    pub chunks_failed: usize,
+    pub transcriptions: usize,
+    pub transcriptions_failed: usize,
+    /// Transcriptions whose searched version is now the one of the new engine
+    pub transcriptions_replaced: usize,
+}
+
+/// Transcribes the segments recorded between `start` and `end` again with `engine`, optionally
+/// only those of one device. The new text replaces the searched one unless an engine of
+/// higher quality transcribed the segment before.
+#[allow(clippy::too_many_arguments)]
+pub async fn retranscribe_window(
+    db: &DatabaseManager,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    device: Option<&str>,
+    engine: &AudioTranscriptionEngine,
+    backend: Arc<dyn TranscriptionBackend>,
+    languages: Vec<Language>,
+    progress: impl Fn(RetranscriptionProgress),
+) -> Result<RetranscriptionSummary> {
+    let targets = db
+        .get_transcriptions_for_retranscription(start, end, device)
+        .await?;
+    let mut chunks: BTreeMap<i64, (String, Vec<RetranscriptionTarget>)> = BTreeMap::new();
+    for target in targets {
+        chunks
+            .entry(target.audio_chunk_id)
+            .or_insert_with(|| (target.file_path.clone(), Vec::new()))
+            .1
+            .push(target);
+    }
+
+    let mut summary = RetranscriptionSummary {
+        chunks: chunks.len(),
+        ..Default::default()
+    };
+    progress(RetranscriptionProgress {
+        chunks_total: chunks.len(),
+        chunks_processed: 0,
+    });
+
+    let engine_name = engine.to_string();
+    for (processed, (file_path, targets)) in chunks.values().enumerate() {
+        match read_chunk(file_path).await {
+            Ok(samples) => {
+                for target in targets {
+                    match retranscribe_segment(
+                        db,
+                        target,
+                        &samples,
+                        engine,
+                        &engine_name,
+                        backend.clone(),
+                        languages.clone(),
+                    )
+                    .await
+                    {
+                        Ok(replaced) => {
+                            summary.transcriptions += 1;
+                            if replaced {
+                                summary.transcriptions_replaced += 1;
+                            }
+                        }
+                        Err(e) => {
+                            warn!("failed to re-transcribe transcription {}: {}", target.id, e);
+                            summary.transcriptions_failed += 1;
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("skipping {} in re-transcription: {}", file_path, e);
+                summary.chunks_failed += 1;
+            }
+        }
+        progress(RetranscriptionProgress {
+            chunks_total: chunks.len(),
+            chunks_processed: processed + 1,
+        });
+    }
+
+    info!(
+        "re-transcribed {} chunks from {} to {} with {}: {} transcriptions, {} replaced, {} failed",
+        summary.chunks,
+        start,
+        end,
+        engine_name,
+        summary.transcriptions,
+        summary.transcriptions_replaced,
+        summary.transcriptions_failed
+    );
+    Ok(summary)
+}
+
+/// Stores the new version of one transcription, returns whether search now returns it
+async fn retranscribe_segment(
+    db: &DatabaseManager,
+    target: &RetranscriptionTarget,
+    samples: &[f32],
+    engine: &AudioTranscriptionEngine,
+    engine_name: &str,
+    backend: Arc<dyn TranscriptionBackend>,
+    languages: Vec<Language>,
+) -> Result<bool> {
+    // transcriptions without offsets cover the whole chunk
+    let offset = target.start_time.unwrap_or(0.0);
+    let first = ((offset * SAMPLE_RATE as f64) as usize).min(samples.len());
+    let last = target
+        .end_time
+        .map_or(samples.len(), |end| (end * SAMPLE_RATE as f64) as usize)
+        .clamp(first, samples.len());
+
+    let transcription = stt(
+        &samples[first..last],
+        SAMPLE_RATE,
+        &target.file_path,
+        backend,
+        languages,
+    )
+    .await?;
+
+    let mut text = transcription.text;
+    let mut words: Vec<TranscriptionWord> = transcription
+        .words
+        .into_iter()
+        .map(|word| TranscriptionWord {
+            start: word.start + offset,
+            end: word.end + offset,
+            ..word
+        })
+        .collect();
+    if pii_removal::is_enabled() {
+        text = pii_removal::remove_pii(&text);
+        words = remove_pii_from_words(words);
+    }
+
+    // names of engines that no longer exist rank below every current one
+    let current_quality = target
+        .transcription_engine
+        .parse::<AudioTranscriptionEngine>()
+        .map_or(0, |current| current.quality());
+    let make_current = !text.trim().is_empty() && engine.quality() >= current_quality;
+
+    db.add_audio_transcription_version(target.id, &text, engine_name, &words, make_current)
+        .await?;
+    Ok(make_current)
+}
+
+/// Decodes a recorded chunk as mono at [`SAMPLE_RATE`]
+async fn read_chunk(file_path: &str) -> Result<Vec<f32>> {
+    let media = open_media(file_path).await?;
+    let path = media.path().to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let (samples, sample_rate) = pcm_decode(&path)?;
+        if sample_rate != SAMPLE_RATE {
+            resample(&samples, sample_rate, SAMPLE_RATE)
+        } else {
+            Ok(samples)
+        }
+    })
+    .await?
+}
@@ -1,11 +1,9 @@
 use crate::core::device::AudioDevice;
-use crate::core::engine::AudioTranscriptionEngine;
 use crate::speaker::embedding::EmbeddingExtractor;
 use crate::speaker::embedding_manager::EmbeddingManager;
 use crate::speaker::prepare_segments;
 use crate::speaker::segment::SpeechSegment;
-use crate::transcription::deepgram::batch::transcribe_with_deepgram;
-use crate::transcription::whisper::batch::process_with_whisper;
+use crate::transcription::backend::{Transcription, TranscriptionBackend};
 use crate::utils::audio::resample;
 use crate::utils::ffmpeg::{get_new_file_path, write_audio_to_file};
 use crate::vad::VadEngine;
@@ -13,6 +11,7 @@ use anyhow::Result;
 #[cfg(target_os = "macos")]
 use objc::rc::autoreleasepool;
 use screenpipe_core::Language;
+use screenpipe_db::TranscriptionWord;
 use std::path::PathBuf;
 use std::{
     sync::Arc,
@@ -21,22 +20,18 @@ use std::{
 };
 use tokio::sync::Mutex;
 use tracing::error;
-use whisper_rs::WhisperContext;
 
 use crate::{AudioInput, TranscriptionResult};
 
 pub const SAMPLE_RATE: u32 = 16000;
 
-#[allow(clippy::too_many_arguments)]
 pub async fn stt_sync(
     audio: &[f32],
     sample_rate: u32,
     device: &str,
-    audio_transcription_engine: Arc<AudioTranscriptionEngine>,
-    deepgram_api_key: Option<String>,
+    transcription_backend: Arc<dyn TranscriptionBackend>,
     languages: Vec<Language>,
-    whisper_context: Arc<WhisperContext>,
-) -> Result<String> {
+) -> Result<Transcription> {
     let audio = audio.to_vec();
 
     let device = device.to_string();
@@ -45,48 +40,22 @@ pub async fn stt_sync(
         &audio,
         sample_rate,
         &device,
-        audio_transcription_engine,
-        deepgram_api_key,
+        transcription_backend,
         languages,
-        whisper_context,
     )
     .await
 }
 
-#[allow(clippy::too_many_arguments)]
 pub async fn stt(
     audio: &[f32],
     sample_rate: u32,
     device: &str,
-    audio_transcription_engine: Arc<AudioTranscriptionEngine>,
-    deepgram_api_key: Option<String>,
+    transcription_backend: Arc<dyn TranscriptionBackend>,
     languages: Vec<Language>,
-    whisper_context: Arc<WhisperContext>,
-) -> Result<String> {
-    let transcription: Result<String> =
-        if audio_transcription_engine == AudioTranscriptionEngine::Deepgram.into() {
-            // Deepgram implementation
-            let api_key = deepgram_api_key.unwrap_or_default();
-
-            match transcribe_with_deepgram(&api_key, audio, device, sample_rate, languages.clone())
-                .await
-            {
-                Ok(transcription) => Ok(transcription),
-                Err(e) => {
-                    error!(
-                        "device: {}, deepgram transcription failed, falling back to Whisper: {:?}",
-                        device, e
-                    );
-                    // Fallback to Whisper
-                    process_with_whisper(audio, languages.clone(), whisper_context).await
-                }
-            }
-        } else {
-            // Existing Whisper implementation
-            process_with_whisper(audio, languages, whisper_context).await
-        };
-
-    transcription
+) -> Result<Transcription> {
+    transcription_backend
+        .transcribe(audio, sample_rate, device, &languages)
+        .await
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -97,11 +66,9 @@ pub async fn process_audio_input(
     embedding_manager: EmbeddingManager,
     embedding_extractor: Arc<StdMutex<EmbeddingExtractor>>,
     output_path: &PathBuf,
-    audio_transcription_engine: Arc<AudioTranscriptionEngine>,
-    deepgram_api_key: Option<String>,
+    transcription_backend: Arc<dyn TranscriptionBackend>,
     languages: Vec<Language>,
     output_sender: &crossbeam::channel::Sender<TranscriptionResult>,
-    whisper_context: Arc<WhisperContext>,
 ) -> Result<()> {
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -155,12 +122,10 @@ pub async fn process_audio_input(
                     run_stt(
                         segment,
                         audio.device.clone(),
-                        audio_transcription_engine.clone(),
-                        deepgram_api_key.clone(),
+                        transcription_backend.clone(),
                         languages.clone(),
                         path,
                         timestamp,
-                        whisper_context.clone(),
                     )
                 })
                 .await?
@@ -173,12 +138,10 @@ pub async fn process_audio_input(
             run_stt(
                 segment,
                 audio.device.clone(),
-                audio_transcription_engine.clone(),
-                deepgram_api_key.clone(),
+                transcription_backend.clone(),
                 languages.clone(),
                 path,
                 timestamp,
-                whisper_context.clone(),
             )
             .await?
         };
@@ -191,16 +154,13 @@ pub async fn process_audio_input(
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
 pub async fn run_stt(
     segment: SpeechSegment,
     device: Arc<AudioDevice>,
-    audio_transcription_engine: Arc<AudioTranscriptionEngine>,
-    deepgram_api_key: Option<String>,
+    transcription_backend: Arc<dyn TranscriptionBackend>,
     languages: Vec<Language>,
     path: String,
     timestamp: u64,
-    whisper_context: Arc<WhisperContext>,
 ) -> Result<TranscriptionResult> {
     let audio = segment.samples.clone();
     let sample_rate = segment.sample_rate;
@@ -208,10 +168,8 @@ pub async fn run_stt(
         &audio,
         sample_rate,
         &device.to_string(),
-        audio_transcription_engine.clone(),
-        deepgram_api_key.clone(),
+        transcription_backend,
         languages.clone(),
-        whisper_context,
     )
     .await
     {
@@ -222,7 +180,17 @@ pub async fn run_stt(
                 channels: 1,
                 device: device.clone(),
             },
-            transcription: Some(transcription),
+            transcription: Some(transcription.text),
+            // relative to the chunk file, like the segment
+            words: transcription
+                .words
+                .into_iter()
+                .map(|word| TranscriptionWord {
+                    start: word.start + segment.start,
+                    end: word.end + segment.start,
+                    ..word
+                })
+                .collect(),
             path,
             timestamp,
             error: None,
@@ -240,6 +208,7 @@ pub async fn run_stt(
                     device: device.clone(),
                 },
                 transcription: None,
+                words: Vec::new(),
                 path,
                 timestamp,
                 error: Some(e.to_string()),
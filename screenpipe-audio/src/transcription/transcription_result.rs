@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
-use screenpipe_db::{DatabaseManager, Speaker};
+use screenpipe_core::pii_removal;
+use screenpipe_db::{DatabaseManager, Speaker, TranscriptionWord};
 use tracing::{debug, error, info};
 
 use crate::core::engine::AudioTranscriptionEngine;
@@ -13,6 +14,8 @@ pub struct TranscriptionResult {
     pub input: AudioInput,
     pub speaker_embedding: Vec<f32>,
     pub transcription: Option<String>,
+    /// Timings of the words of the transcription, in seconds from the start of the chunk
+    pub words: Vec<TranscriptionWord>,
     pub timestamp: u64,
     pub error: Option<String>,
     pub start_time: f64,
@@ -42,13 +45,76 @@ impl TranscriptionResult {
 
         None
     }
+
+    /// Drops the timings of words cut from the start of the transcription, as
+    /// [`Self::cleanup_overlap`] does
+    pub fn drop_leading_words(&mut self, count: usize) {
+        self.words.drain(..count.min(self.words.len()));
+    }
+}
+
+/// Redacts pii from the timings of words. Pii is looked for in the words joined, like in the
+/// transcription, since engines often split it over several words, e.g. an email spelled out.
+/// The words of each match become a single word with the rule name in brackets, spanning their
+/// timings.
+pub fn remove_pii_from_words(words: Vec<TranscriptionWord>) -> Vec<TranscriptionWord> {
+    let mut joined = String::new();
+    let mut ranges = Vec::with_capacity(words.len());
+    for word in &words {
+        if !joined.is_empty() {
+            joined.push(' ');
+        }
+        ranges.push(joined.len()..joined.len() + word.text.len());
+        joined.push_str(&word.text);
+    }
+
+    let mut found = pii_removal::find_pii(&joined).into_iter().peekable();
+    let mut redacted = Vec::with_capacity(words.len());
+    let mut i = 0;
+    while i < words.len() {
+        if found
+            .peek()
+            .is_none_or(|(pii, _)| pii.start >= ranges[i].end)
+        {
+            redacted.push(words[i].clone());
+            i += 1;
+            continue;
+        }
+
+        // the words overlapping this match and any match overlapping those words
+        let first = i;
+        let mut text = String::new();
+        let mut cursor = ranges[first].start;
+        while let Some((pii, name)) = found.next_if(|(pii, _)| pii.start < ranges[i].end) {
+            text.push_str(&joined[cursor..pii.start.max(cursor)]);
+            text.push_str(&format!("[{}]", name));
+            cursor = cursor.max(pii.end);
+            while i + 1 < words.len() && ranges[i + 1].start < cursor {
+                i += 1;
+            }
+        }
+        text.push_str(&joined[cursor.min(ranges[i].end)..ranges[i].end]);
+
+        let matched = &words[first..=i];
+        redacted.push(TranscriptionWord {
+            text,
+            start: matched[0].start,
+            end: matched[matched.len() - 1].end,
+            confidence: matched
+                .iter()
+                .filter_map(|word| word.confidence)
+                .reduce(f32::min),
+        });
+        i += 1;
+    }
+    redacted
 }
 
 pub async fn process_transcription_result(
     db: &DatabaseManager,
     result: TranscriptionResult,
     audio_transcription_engine: Arc<AudioTranscriptionEngine>,
-    previous_transcript: Option<String>,
+    mut previous_transcript: Option<String>,
     previous_transcript_id: Option<i64>,
 ) -> Result<Option<i64>, anyhow::Error> {
     if result.error.is_some() || result.transcription.is_none() {
@@ -63,7 +129,13 @@ pub async fn process_transcription_result(
 
     info!("Detected speaker: {:?}", speaker);
 
-    let transcription = result.transcription.unwrap();
+    let mut transcription = result.transcription.unwrap();
+    let mut words = result.words;
+    if pii_removal::is_enabled() {
+        transcription = pii_removal::remove_pii(&transcription);
+        previous_transcript = previous_transcript.map(|text| pii_removal::remove_pii(&text));
+        words = remove_pii_from_words(words);
+    }
     let transcription_engine = audio_transcription_engine.to_string();
     let mut chunk_id: Option<i64> = None;
 
@@ -91,40 +163,50 @@ pub async fn process_transcription_result(
                 return Ok(Some(audio_chunk_id));
             }
 
-            if let Err(e) = db
-                .insert_audio_transcription(
+            match db
+                .insert_audio_transcription_with_words(
                     audio_chunk_id,
                     &transcription,
                     0,
                     &transcription_engine,
                     &screenpipe_db::AudioDevice {
                         name: result.input.device.name.clone(),
-                        device_type: match result.input.device.device_type {
-                            crate::core::device::DeviceType::Input => {
-                                screenpipe_db::DeviceType::Input
-                            }
-                            crate::core::device::DeviceType::Output => {
-                                screenpipe_db::DeviceType::Output
-                            }
-                        },
+                        device_type: result.input.device.device_type.clone().into(),
                     },
                     Some(speaker.id),
                     Some(result.start_time),
                     Some(result.end_time),
+                    &words,
                 )
                 .await
             {
-                error!(
-                    "Failed to insert audio transcription for device {}: {}",
-                    result.input.device, e
-                );
-                return Ok(Some(audio_chunk_id));
-            } else {
-                debug!(
-                    "Inserted audio transcription for chunk {} from device {} using {}",
-                    audio_chunk_id, result.input.device, transcription_engine
-                );
-                chunk_id = Some(audio_chunk_id);
+                Err(e) => {
+                    error!(
+                        "Failed to insert audio transcription for device {}: {}",
+                        result.input.device, e
+                    );
+                    return Ok(Some(audio_chunk_id));
+                }
+                Ok(transcription_id) => {
+                    debug!(
+                        "Inserted audio transcription for chunk {} from device {} using {}",
+                        audio_chunk_id, result.input.device, transcription_engine
+                    );
+                    // kept so the transcription can be re-attributed once speakers are named
+                    if let Err(e) = db
+                        .insert_audio_transcription_speaker_embedding(
+                            transcription_id,
+                            &result.speaker_embedding,
+                        )
+                        .await
+                    {
+                        error!(
+                            "Failed to store speaker embedding of transcription {}: {}",
+                            transcription_id, e
+                        );
+                    }
+                    chunk_id = Some(audio_chunk_id);
+                }
             }
         }
         Err(e) => error!(
@@ -141,6 +223,7 @@ async fn get_or_create_speaker_from_embedding(
 ) -> Result<Speaker, anyhow::Error> {
     let speaker = db.get_speaker_from_embedding(embedding).await?;
     if let Some(speaker) = speaker {
+        db.update_speaker_centroid(speaker.id, embedding).await?;
         Ok(speaker)
     } else {
         let speaker = db.insert_speaker(embedding).await?;
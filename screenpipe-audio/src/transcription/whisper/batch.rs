@@ -1,17 +1,30 @@
 use super::detect_language;
+use crate::transcription::backend::Transcription;
 use anyhow::Result;
 use screenpipe_core::Language;
+use screenpipe_db::TranscriptionWord;
 use std::sync::Arc;
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext};
+
+/// A text token of a whisper segment, times in centiseconds as whisper reports them
+#[derive(Debug, Clone)]
+pub struct WhisperToken {
+    /// A character can be split over several tokens, so this may not be valid utf-8 on its own
+    pub bytes: Vec<u8>,
+    pub t0: i64,
+    pub t1: i64,
+    pub probability: f32,
+}
+
 /// Processes audio data using the Whisper model to generate transcriptions.
 ///
 /// # Returns
-/// A string containing the processed transcript
+/// The processed transcript with the timing of each word
 pub async fn process_with_whisper(
     audio: &[f32],
     languages: Vec<Language>,
     whisper_context: Arc<WhisperContext>,
-) -> Result<String> {
+) -> Result<Transcription> {
     let mut whisper_state = whisper_context
         .create_state()
         .expect("failed to create key");
@@ -51,6 +64,8 @@ pub async fn process_with_whisper(
         .expect("failed to get number of segments");
 
     let mut transcript = String::new();
+    let mut tokens = Vec::new();
+    let eot = whisper_context.token_eot();
 
     for i in 0..num_segments {
         // Get the transcribed text and timestamps for the current segment.
@@ -59,7 +74,56 @@ pub async fn process_with_whisper(
             .expect("failed to get segment");
 
         transcript.push_str(&segment);
+
+        for j in 0..whisper_state.full_n_tokens(i)? {
+            let data = whisper_state.full_get_token_data(i, j)?;
+            // timestamp and other special tokens come after end of text in the vocabulary
+            if data.id >= eot {
+                continue;
+            }
+            tokens.push(WhisperToken {
+                bytes: whisper_state.full_get_token_bytes(i, j)?,
+                t0: data.t0,
+                t1: data.t1,
+                probability: data.p,
+            });
+        }
+    }
+
+    Ok(Transcription {
+        text: transcript,
+        words: tokens_to_words(&tokens),
+    })
+}
+
+/// Joins whisper tokens into words, a token starting with a space starts a new word. The
+/// confidence of a word is the mean probability of its tokens.
+pub fn tokens_to_words(tokens: &[WhisperToken]) -> Vec<TranscriptionWord> {
+    let mut groups: Vec<Vec<&WhisperToken>> = Vec::new();
+    for token in tokens {
+        match groups.last_mut() {
+            Some(group) if !token.bytes.starts_with(b" ") => group.push(token),
+            _ => groups.push(vec![token]),
+        }
     }
 
-    Ok(transcript)
+    groups
+        .into_iter()
+        .filter_map(|group| {
+            let bytes: Vec<u8> = group.iter().flat_map(|t| t.bytes.clone()).collect();
+            let text = String::from_utf8_lossy(&bytes).trim().to_string();
+            if text.is_empty() {
+                return None;
+            }
+            let (first, last) = (group.first()?, group.last()?);
+            Some(TranscriptionWord {
+                text,
+                start: first.t0 as f64 / 100.0,
+                end: last.t1 as f64 / 100.0,
+                confidence: Some(
+                    group.iter().map(|t| t.probability).sum::<f32>() / group.len() as f32,
+                ),
+            })
+        })
+        .collect()
 }
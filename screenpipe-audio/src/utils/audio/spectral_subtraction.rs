@@ -2,6 +2,8 @@ use anyhow::Result;
 use realfft::num_complex::{Complex32, ComplexFloat};
 use realfft::RealFftPlanner;
 
+/// Removes noise of power `d` per sample, as measured by [`average_noise_spectrum`], from a
+/// frame of up to 1600 samples
 pub fn spectral_subtraction(audio: &[f32], d: f32) -> Result<Vec<f32>> {
     let mut real_planner = RealFftPlanner::<f32>::new();
     let window_size = 1600; // 16k sample rate - 100ms
@@ -16,6 +18,9 @@ pub fn spectral_subtraction(audio: &[f32], d: f32) -> Result<Vec<f32>> {
     let mut indata = padded_audio;
     r2c.process(&mut indata, &mut y)?;
 
+    // noise of power d per sample has power d * window_size in every bin
+    let d = d * window_size as f32;
+
     let mut processed_audio = y
         .iter()
         .map(|&x| {
@@ -41,6 +46,12 @@ pub fn spectral_subtraction(audio: &[f32], d: f32) -> Result<Vec<f32>> {
 
     c2r.process(&mut processed_audio, &mut outdata)?;
 
+    // the inverse transform isn't normalized
+    outdata.truncate(audio.len());
+    for sample in outdata.iter_mut() {
+        *sample /= window_size as f32;
+    }
+
     Ok(outdata)
 }
 
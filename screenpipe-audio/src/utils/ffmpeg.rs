@@ -1,7 +1,7 @@
 use anyhow::Result;
 use chrono::Utc;
 use tracing::debug;
-use screenpipe_core::find_ffmpeg_path;
+use screenpipe_core::{encryption, find_ffmpeg_path};
 use std::io::Write;
 use std::path::PathBuf;
 use std::{
@@ -78,9 +78,49 @@ fn encode_single_audio(
     Ok(())
 }
 
+/// Decodes an audio file in any format ffmpeg reads, e.g. mp3, m4a or opus, to mono samples at
+/// `sample_rate`.
+pub fn decode_audio_file(path: &Path, sample_rate: u32) -> Result<Vec<f32>> {
+    let ffmpeg_path = find_ffmpeg_path().ok_or_else(|| anyhow::anyhow!("ffmpeg not found"))?;
+    let output = Command::new(ffmpeg_path)
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args([
+            "-f",
+            "f32le",
+            "-ac",
+            "1",
+            "-ar",
+            &sample_rate.to_string(),
+            "pipe:1",
+        ])
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "ffmpeg failed to decode {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(output
+        .stdout
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect())
+}
+
+/// Device name usable in a file name, device names like `Microphone (USB: 2)` have characters
+/// windows doesn't allow in paths
+pub fn sanitize_device_name(device: &str) -> String {
+    device.replace(['/', '\\', ':', '?', '*'], "_")
+}
+
 pub fn get_new_file_path(device: &str, output_path: &PathBuf) -> String {
     let new_file_name = Utc::now().format("%Y-%m-%d_%H-%M-%S").to_string();
-    let sanitized_device_name = device.replace(['/', '\\'], "_");
+    let sanitized_device_name = sanitize_device_name(device);
     PathBuf::from(output_path)
         .join(format!("{}_{}.mp4", sanitized_device_name, new_file_name))
         .to_str()
@@ -102,6 +142,7 @@ pub fn write_audio_to_file(
             1,
             &PathBuf::from(path),
         )?;
+        encryption::encrypt_file_if_enabled(path)?;
     }
     Ok(())
 }
@@ -1,3 +1,4 @@
+pub mod regions;
 pub mod silero;
 pub mod webrtc;
 
@@ -6,6 +7,7 @@ use lazy_static::lazy_static;
 use silero::SileroVad;
 use std::path::PathBuf;
 use std::sync::Once;
+use std::time::Duration;
 use tokio::sync::Mutex;
 use vad_rs::VadStatus;
 use webrtc::WebRtcVad;
@@ -26,6 +28,50 @@ impl VadSensitivity {
             VadSensitivity::High => 0.2,    // 20% of frames must be speech
         }
     }
+
+    /// Speech probability a frame needs to count as speech
+    pub fn speech_threshold(&self) -> f32 {
+        match self {
+            VadSensitivity::Low => 0.7,
+            VadSensitivity::Medium => SPEECH_THRESHOLD,
+            VadSensitivity::High => 0.3,
+        }
+    }
+}
+
+/// Tuning of speech detection, the sensitivity levels are presets of it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VadConfig {
+    /// Speech probability above which a frame is speech
+    pub speech_threshold: f32,
+    /// Speech probability below which a frame is silence. Frames between the two thresholds
+    /// continue the speech or silence before them.
+    pub silence_threshold: f32,
+    /// Silence after speech still counted as speech, so pauses between words don't split it
+    pub hangover: Duration,
+    /// Speech shorter than this, like a door closing or a cough, is counted as silence
+    pub min_speech_duration: Duration,
+    /// Share of frames that must be speech for a chunk to be transcribed
+    pub min_speech_ratio: f32,
+}
+
+impl From<VadSensitivity> for VadConfig {
+    fn from(sensitivity: VadSensitivity) -> Self {
+        let speech_threshold = sensitivity.speech_threshold();
+        VadConfig {
+            speech_threshold,
+            silence_threshold: SILENCE_THRESHOLD.min(speech_threshold),
+            hangover: Duration::ZERO,
+            min_speech_duration: Duration::ZERO,
+            min_speech_ratio: sensitivity.min_speech_ratio(),
+        }
+    }
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        VadSensitivity::default().into()
+    }
 }
 
 #[derive(Clone)]
@@ -38,7 +84,14 @@ pub trait VadEngine: Send {
     fn is_voice_segment(&mut self, audio_chunk: &[f32]) -> anyhow::Result<bool>;
     fn set_sensitivity(&mut self, sensitivity: VadSensitivity);
     fn audio_type(&mut self, audio_chunk: &[f32]) -> anyhow::Result<VadStatus>;
-    fn get_min_speech_ratio(&self) -> f32;
+    /// Probability that a frame of 100ms is speech. Engines without a model answer 0 or 1.
+    fn speech_probability(&mut self, audio_chunk: &[f32]) -> anyhow::Result<f32>;
+    fn set_config(&mut self, config: VadConfig);
+    fn config(&self) -> VadConfig;
+
+    fn get_min_speech_ratio(&self) -> f32 {
+        self.config().min_speech_ratio
+    }
 }
 
 const FRAME_HISTORY: usize = 10; // Number of frames to consider for decision
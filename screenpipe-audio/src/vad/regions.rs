@@ -0,0 +1,118 @@
+//! Speech regions of a recording from per-frame speech probabilities, used to look at what VAD
+//! hears and tune [`VadConfig`] for a room.
+
+use serde::Serialize;
+use std::time::Duration;
+
+use super::{VadConfig, VadEngine};
+
+/// Samples in a VAD frame, 100ms at 16kHz like the frames of the live pipeline
+pub const FRAME_SIZE: usize = 1600;
+pub const FRAME_DURATION: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct VadFrame {
+    /// Offset in the recording in seconds
+    pub start: f64,
+    pub probability: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SpeechRegion {
+    /// Offsets in the recording in seconds
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Speech probability of every frame of 16kHz mono audio
+pub fn speech_probabilities(
+    engine: &mut dyn VadEngine,
+    audio: &[f32],
+) -> anyhow::Result<Vec<VadFrame>> {
+    audio
+        .chunks(FRAME_SIZE)
+        .enumerate()
+        .map(|(index, frame)| {
+            Ok(VadFrame {
+                start: index as f64 * FRAME_DURATION.as_secs_f64(),
+                probability: engine.speech_probability(frame)?,
+            })
+        })
+        .collect()
+}
+
+/// Speech or silence of every frame, frames between the two thresholds keep the state of the
+/// frame before them
+pub fn speech_frames(frames: &[VadFrame], config: &VadConfig) -> Vec<bool> {
+    let mut speech = false;
+    frames
+        .iter()
+        .map(|frame| {
+            if frame.probability > config.speech_threshold {
+                speech = true;
+            } else if frame.probability < config.silence_threshold {
+                speech = false;
+            }
+            speech
+        })
+        .collect()
+}
+
+/// Drops speech shorter than the minimum speech duration, then extends what's left by the
+/// hangover
+pub fn smooth_speech_frames(speech: &[bool], config: &VadConfig) -> Vec<bool> {
+    let min_speech_frames = frames_in(config.min_speech_duration);
+    let hangover_frames = frames_in(config.hangover);
+
+    let mut smoothed = vec![false; speech.len()];
+    let mut start = 0;
+    while start < speech.len() {
+        if !speech[start] {
+            start += 1;
+            continue;
+        }
+        let end = speech[start..]
+            .iter()
+            .position(|&s| !s)
+            .map_or(speech.len(), |len| start + len);
+        if end - start >= min_speech_frames {
+            let hangover_end = (end + hangover_frames).min(speech.len());
+            smoothed[start..hangover_end].fill(true);
+        }
+        start = end;
+    }
+    smoothed
+}
+
+/// Speech regions of a recording, in order
+pub fn speech_regions(frames: &[VadFrame], config: &VadConfig) -> Vec<SpeechRegion> {
+    let speech = smooth_speech_frames(&speech_frames(frames, config), config);
+    let frame_duration = FRAME_DURATION.as_secs_f64();
+
+    let mut regions = Vec::new();
+    let mut region_start = None;
+    for (index, &is_speech) in speech.iter().enumerate() {
+        match (is_speech, region_start) {
+            (true, None) => region_start = Some(frames[index].start),
+            (false, Some(start)) => {
+                regions.push(SpeechRegion {
+                    start,
+                    end: frames[index].start,
+                });
+                region_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let (Some(start), Some(last)) = (region_start, frames.last()) {
+        regions.push(SpeechRegion {
+            start,
+            end: last.start + frame_duration,
+        });
+    }
+    regions
+}
+
+fn frames_in(duration: Duration) -> usize {
+    (duration.as_secs_f64() / FRAME_DURATION.as_secs_f64()).ceil() as usize
+}
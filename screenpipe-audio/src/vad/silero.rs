@@ -8,14 +8,13 @@ use vad_rs::{Vad, VadStatus};
 use crate::vad::FRAME_HISTORY;
 
 use super::{
-    VadEngine, VadSensitivity, DOWNLOAD_ONCE, MODEL_PATH, SILENCE_THRESHOLD,
-    SPEECH_FRAME_THRESHOLD, SPEECH_THRESHOLD,
+    VadConfig, VadEngine, VadSensitivity, DOWNLOAD_ONCE, MODEL_PATH, SPEECH_FRAME_THRESHOLD,
 };
 
 pub struct SileroVad {
     vad: Vad,
     prob_history: VecDeque<f32>,
-    sensitivity: VadSensitivity,
+    config: VadConfig,
 }
 
 impl SileroVad {
@@ -31,7 +30,7 @@ impl SileroVad {
         Ok(Self {
             vad,
             prob_history: VecDeque::with_capacity(FRAME_HISTORY),
-            sensitivity: VadSensitivity::Medium,
+            config: VadSensitivity::Medium.into(),
         })
     }
 
@@ -99,12 +98,12 @@ impl SileroVad {
         let speech_frames = self
             .prob_history
             .iter()
-            .filter(|&&p| p > SPEECH_THRESHOLD)
+            .filter(|&&p| p > self.config.speech_threshold)
             .count();
         let silence_frames = self
             .prob_history
             .iter()
-            .filter(|&&p| p < SILENCE_THRESHOLD)
+            .filter(|&&p| p < self.config.silence_threshold)
             .count();
 
         if speech_frames >= SPEECH_FRAME_THRESHOLD {
@@ -117,20 +116,12 @@ impl SileroVad {
     }
 
     fn get_threshold(&self) -> f32 {
-        match self.sensitivity {
-            VadSensitivity::Low => 0.7,
-            VadSensitivity::Medium => 0.5,
-            VadSensitivity::High => 0.3,
-        }
+        self.config.speech_threshold
     }
-}
 
-impl VadEngine for SileroVad {
-    fn is_voice_segment(&mut self, audio_chunk: &[f32]) -> anyhow::Result<bool> {
+    fn compute_prob(&mut self, audio_chunk: &[f32]) -> anyhow::Result<f32> {
         const CHUNK_SIZE: usize = 1600; // 100 milliseconds
 
-        let threshold = self.get_threshold();
-
         let mut chunk_data: Vec<f32> = audio_chunk.to_vec();
         chunk_data.resize(CHUNK_SIZE, 0.0);
 
@@ -138,28 +129,25 @@ impl VadEngine for SileroVad {
             debug!("SileroVad Error computing VAD: {}", e);
             anyhow::anyhow!("Vad compute error: {}", e)
         })?;
+        Ok(result.prob)
+    }
+}
 
-        let status = self.update_status(result.prob);
+impl VadEngine for SileroVad {
+    fn is_voice_segment(&mut self, audio_chunk: &[f32]) -> anyhow::Result<bool> {
+        let threshold = self.get_threshold();
+        let prob = self.compute_prob(audio_chunk)?;
+        let status = self.update_status(prob);
 
-        Ok(status == VadStatus::Speech && result.prob > threshold)
+        Ok(status == VadStatus::Speech && prob > threshold)
     }
 
     fn audio_type(&mut self, audio_chunk: &[f32]) -> anyhow::Result<VadStatus> {
-        const CHUNK_SIZE: usize = 1600; // 100 milliseconds
-
         let threshold = self.get_threshold();
+        let prob = self.compute_prob(audio_chunk)?;
+        let status = self.update_status(prob);
 
-        let mut chunk_data: Vec<f32> = audio_chunk.to_vec();
-        chunk_data.resize(CHUNK_SIZE, 0.0);
-
-        let result = self.vad.compute(&chunk_data).map_err(|e| {
-            debug!("SileroVad Error computing VAD: {}", e);
-            anyhow::anyhow!("Vad compute error: {}", e)
-        })?;
-
-        let status = self.update_status(result.prob);
-
-        if status == VadStatus::Speech && result.prob > threshold {
+        if status == VadStatus::Speech && prob > threshold {
             return Ok(VadStatus::Speech);
         }
 
@@ -170,11 +158,19 @@ impl VadEngine for SileroVad {
         }
     }
 
+    fn speech_probability(&mut self, audio_chunk: &[f32]) -> anyhow::Result<f32> {
+        self.compute_prob(audio_chunk)
+    }
+
     fn set_sensitivity(&mut self, sensitivity: VadSensitivity) {
-        self.sensitivity = sensitivity;
+        self.config = sensitivity.into();
+    }
+
+    fn set_config(&mut self, config: VadConfig) {
+        self.config = config;
     }
 
-    fn get_min_speech_ratio(&self) -> f32 {
-        self.sensitivity.min_speech_ratio()
+    fn config(&self) -> VadConfig {
+        self.config
     }
 }
@@ -1,12 +1,13 @@
 use anyhow;
 use vad_rs::VadStatus;
 
-use super::{VadEngine, VadSensitivity};
+use super::{VadConfig, VadEngine, VadSensitivity};
 
 #[derive(Default)]
 pub struct WebRtcVad {
     vad: webrtc_vad::Vad,
     sensitivity: VadSensitivity,
+    config: VadConfig,
 }
 
 impl WebRtcVad {
@@ -15,6 +16,7 @@ impl WebRtcVad {
         Self {
             vad,
             sensitivity: VadSensitivity::Medium,
+            config: VadSensitivity::Medium.into(),
         }
     }
 }
@@ -62,11 +64,25 @@ impl VadEngine for WebRtcVad {
         Ok(VadStatus::Speech)
     }
 
+    fn speech_probability(&mut self, audio_chunk: &[f32]) -> anyhow::Result<f32> {
+        Ok(if self.is_voice_segment(audio_chunk)? {
+            1.0
+        } else {
+            0.0
+        })
+    }
+
     fn set_sensitivity(&mut self, sensitivity: VadSensitivity) {
         self.sensitivity = sensitivity;
+        self.config = sensitivity.into();
+    }
+
+    // webrtc decides on its own, the aggressiveness stays that of the sensitivity
+    fn set_config(&mut self, config: VadConfig) {
+        self.config = config;
     }
 
-    fn get_min_speech_ratio(&self) -> f32 {
-        self.sensitivity.min_speech_ratio()
+    fn config(&self) -> VadConfig {
+        self.config
     }
 }
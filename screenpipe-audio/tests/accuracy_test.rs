@@ -4,6 +4,7 @@ use screenpipe_audio::core::engine::AudioTranscriptionEngine;
 use screenpipe_audio::speaker::embedding::EmbeddingExtractor;
 use screenpipe_audio::speaker::embedding_manager::EmbeddingManager;
 use screenpipe_audio::speaker::prepare_segments;
+use screenpipe_audio::transcription::backend::WhisperBackend;
 use screenpipe_audio::transcription::stt::SAMPLE_RATE;
 use screenpipe_audio::transcription::whisper::model::{
     create_whisper_context_parameters, download_whisper_model,
@@ -136,15 +137,13 @@ async fn test_transcription_accuracy() {
                     &segment.samples,
                     audio_input.sample_rate,
                     &audio_input.device.to_string(),
-                    Arc::new(AudioTranscriptionEngine::WhisperLargeV3Turbo),
-                    None,
+                    Arc::new(WhisperBackend::new(whisper_context.clone())),
                     vec![Language::English],
-                    whisper_context.clone(),
                 )
                 .await
                 .unwrap();
 
-                transcription.push_str(&transcript);
+                transcription.push_str(&transcript.text);
             }
 
             let distance = levenshtein(expected_transcription, &transcription.to_lowercase());
@@ -12,6 +12,7 @@ mod tests {
     use screenpipe_audio::speaker::embedding::EmbeddingExtractor;
     use screenpipe_audio::speaker::embedding_manager::EmbeddingManager;
     use screenpipe_audio::speaker::prepare_segments;
+    use screenpipe_audio::transcription::backend::WhisperBackend;
     use screenpipe_audio::transcription::whisper::model::{
         create_whisper_context_parameters, download_whisper_model,
     };
@@ -275,15 +276,13 @@ mod tests {
                 &segment.samples,
                 audio_input.sample_rate,
                 &audio_input.device.to_string(),
-                Arc::new(AudioTranscriptionEngine::WhisperLargeV3Turbo),
-                None,
+                Arc::new(WhisperBackend::new(whisper_context.clone())),
                 vec![Language::Arabic],
-                whisper_context.clone(),
             )
             .await
             .unwrap();
 
-            transcription_result.push_str(&transcript);
+            transcription_result.push_str(&transcript.text);
             transcription_result.push('\n');
         }
 
@@ -376,15 +375,13 @@ mod tests {
                 &segment.samples,
                 audio_input.sample_rate,
                 &audio_input.device.to_string(),
-                Arc::new(AudioTranscriptionEngine::WhisperLargeV3Turbo),
-                None,
+                Arc::new(WhisperBackend::new(whisper_context.clone())),
                 vec![Language::English],
-                whisper_context.clone(),
             )
             .await
             .unwrap();
 
-            transcription.push_str(&transcript);
+            transcription.push_str(&transcript.text);
         }
 
         let elapsed_time = start_time.elapsed();
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use futures::future::BoxFuture;
+use screenpipe_audio::core::device::{AudioDevice, DeviceType};
+use screenpipe_audio::core::engine::AudioTranscriptionEngine;
+use screenpipe_audio::transcription::backend::{Transcription, TranscriptionBackend};
+use screenpipe_audio::transcription::import::{AudioImport, AudioImporter};
+use screenpipe_audio::vad::{VadEngineEnum, VadSensitivity};
+use screenpipe_core::Language;
+use screenpipe_db::DatabaseManager;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Answers every segment with the same text
+struct FixedBackend;
+
+impl TranscriptionBackend for FixedBackend {
+    fn transcribe<'a>(
+        &'a self,
+        _audio: &'a [f32],
+        _sample_rate: u32,
+        _device: &'a str,
+        _languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<Transcription>> {
+        Box::pin(async {
+            Ok(Transcription {
+                text: "imported voice memo".to_string(),
+                words: Vec::new(),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+#[ignore] // downloads the segmentation models and needs ffmpeg
+async fn test_import_audio_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Arc::new(
+        DatabaseManager::new(&dir.path().join("db.sqlite").to_string_lossy())
+            .await
+            .unwrap(),
+    );
+    let importer = AudioImporter::new(
+        db.clone(),
+        Arc::new(AudioTranscriptionEngine::WhisperLargeV3Turbo),
+        Arc::new(FixedBackend),
+        VadEngineEnum::Silero,
+        VadSensitivity::Low.into(),
+        vec![Language::English],
+        Duration::from_secs(30),
+        dir.path().to_path_buf(),
+    )
+    .await
+    .unwrap();
+
+    let recorded_at = Utc.with_ymd_and_hms(2024, 5, 1, 9, 30, 0).unwrap();
+    let import = AudioImport {
+        timestamp: recorded_at,
+        device: AudioDevice::new("phone".to_string(), DeviceType::Input),
+        speakers: vec!["kapil".to_string()],
+    };
+    let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("test_data")
+        .join("poetic_kapil_gupta.wav");
+    let summary = importer.import_file(&file, &import).await.unwrap();
+    assert!(summary.transcriptions > 0);
+    assert!(summary.transcriptions_hinted > 0);
+
+    let results = db
+        .search_audio(
+            "imported",
+            100,
+            0,
+            Some(recorded_at),
+            Some(recorded_at + chrono::Duration::seconds(summary.duration.ceil() as i64)),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(!results.is_empty());
+    for result in results {
+        assert_eq!(result.device_name, "phone");
+        assert_eq!(result.speaker.unwrap().name, "kapil");
+        assert!(PathBuf::from(result.file_path).starts_with(dir.path()));
+    }
+}
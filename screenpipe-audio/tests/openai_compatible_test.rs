@@ -0,0 +1,119 @@
+use screenpipe_audio::transcription::backend::TranscriptionBackend;
+use screenpipe_audio::transcription::openai_compatible::{
+    OpenAiCompatibleBackend, OpenAiCompatibleConfig,
+};
+use screenpipe_core::Language;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// Answers one request with `status` and `body`, the handle returns the raw request
+async fn mock_server(status: &'static str, body: &'static str) -> (String, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!(
+        "http://{}/v1/audio/transcriptions",
+        listener.local_addr().unwrap()
+    );
+
+    let handle = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 8192];
+        loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            if n == 0 || is_complete(&request) {
+                break;
+            }
+        }
+
+        let response = format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8_lossy(&request).to_string()
+    });
+
+    (url, handle)
+}
+
+fn is_complete(request: &[u8]) -> bool {
+    let Some(header_end) = request.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return false;
+    };
+    let headers = String::from_utf8_lossy(&request[..header_end]).to_lowercase();
+    if headers.contains("transfer-encoding: chunked") {
+        return request.ends_with(b"0\r\n\r\n");
+    }
+    let content_length = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|length| length.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    request.len() >= header_end + 4 + content_length
+}
+
+fn tone() -> Vec<f32> {
+    (0..16000)
+        .map(|i| (i as f32 * 440.0 * 2.0 * std::f32::consts::PI / 16000.0).sin() * 0.5)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_openai_compatible_transcription() {
+    let (url, server) = mock_server("200 OK", r#"{"text":" hello from the mock server\n"}"#).await;
+    let backend = OpenAiCompatibleBackend::new(OpenAiCompatibleConfig {
+        api_key: Some("sk-test".to_string()),
+        model: "whisper-large-v3".to_string(),
+        ..OpenAiCompatibleConfig::new(url)
+    })
+    .unwrap();
+
+    let transcription = backend
+        .transcribe(&tone(), 16000, "test (input)", &[Language::English])
+        .await
+        .unwrap();
+    assert_eq!(transcription.text, "hello from the mock server");
+    assert!(transcription.words.is_empty());
+
+    let request = server.await.unwrap();
+    assert!(request.starts_with("POST /v1/audio/transcriptions"));
+    assert!(request
+        .to_lowercase()
+        .contains("authorization: bearer sk-test"));
+    assert!(request.contains("filename=\"audio.wav\""));
+    assert!(request.contains("RIFF"));
+    assert!(request.contains("name=\"model\"\r\n\r\nwhisper-large-v3"));
+    assert!(request.contains("name=\"language\"\r\n\r\nen"));
+}
+
+#[tokio::test]
+async fn test_openai_compatible_transcription_error() {
+    let (url, server) = mock_server(
+        "500 Internal Server Error",
+        r#"{"error":"model not loaded"}"#,
+    )
+    .await;
+    let backend = OpenAiCompatibleBackend::new(OpenAiCompatibleConfig::new(url)).unwrap();
+
+    // several languages are left to the server to detect
+    let error = backend
+        .transcribe(
+            &tone(),
+            16000,
+            "test (input)",
+            &[Language::English, Language::French],
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("500"));
+    assert!(error.contains("model not loaded"));
+
+    let request = server.await.unwrap();
+    assert!(!request.to_lowercase().contains("authorization"));
+    assert!(!request.contains("name=\"language\""));
+}
@@ -0,0 +1,97 @@
+use screenpipe_audio::preprocessing::echo_cancellation::{cancel_echo, find_echo};
+use screenpipe_audio::preprocessing::noise_suppression::suppress_noise;
+
+const SAMPLE_RATE: f32 = 16000.0;
+
+/// Deterministic white noise in [-amplitude, amplitude]
+fn noise(len: usize, amplitude: f32, seed: u64) -> Vec<f32> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((state >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0) * amplitude
+        })
+        .collect()
+}
+
+fn sine(len: usize, frequency: f32, amplitude: f32) -> Vec<f32> {
+    (0..len)
+        .map(|n| {
+            (2.0 * std::f32::consts::PI * frequency * n as f32 / SAMPLE_RATE).sin() * amplitude
+        })
+        .collect()
+}
+
+fn energy(samples: &[f32]) -> f32 {
+    samples.iter().map(|s| s * s).sum()
+}
+
+#[test]
+fn test_echo_cancellation_removes_played_audio() {
+    // what the speakers played, the microphone hears it 15ms later with some reverb
+    let reference = noise(48000, 0.5, 1);
+    let near_end = sine(32000, 300.0, 0.05);
+    let delay = 240;
+    let start = 8000;
+    let recording: Vec<f32> = (0..32000)
+        .map(|n| {
+            let played = start + n - delay;
+            near_end[n] + 0.3 * reference[played] + 0.1 * reference[played - 40]
+        })
+        .collect();
+
+    let alignment = find_echo(&recording, &reference).unwrap();
+    assert_eq!(alignment.offset, (start - delay) as isize);
+    assert!(alignment.correlation > 0.5);
+
+    let cleaned = cancel_echo(&recording, &reference, &alignment);
+    assert_eq!(cleaned.len(), recording.len());
+    // once the filter converged, what's left is the near end
+    let echo: Vec<f32> = recording[16000..]
+        .iter()
+        .zip(&near_end[16000..])
+        .map(|(r, n)| r - n)
+        .collect();
+    let residual: Vec<f32> = cleaned[16000..]
+        .iter()
+        .zip(&near_end[16000..])
+        .map(|(c, n)| c - n)
+        .collect();
+    assert!(energy(&residual) < energy(&echo) / 20.0);
+}
+
+#[test]
+fn test_echo_cancellation_ignores_unrelated_reference() {
+    let reference = noise(48000, 0.5, 1);
+    let recording = noise(32000, 0.5, 2);
+
+    assert!(find_echo(&recording, &reference).is_none());
+    assert!(find_echo(&recording, &[]).is_none());
+}
+
+#[test]
+fn test_noise_suppression_keeps_speech_and_lowers_noise() {
+    let background = noise(48000, 0.05, 3);
+    let tone = sine(16000, 440.0, 0.5);
+    let audio: Vec<f32> = background
+        .iter()
+        .enumerate()
+        .map(|(n, b)| {
+            b + if (16000..32000).contains(&n) {
+                tone[n - 16000]
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    let cleaned = suppress_noise(&audio).unwrap();
+    assert_eq!(cleaned.len(), audio.len());
+    // the background alone is at least 10dB quieter
+    assert!(energy(&cleaned[..14000]) < energy(&background[..14000]) / 10.0);
+    // the tone keeps most of its energy
+    let kept = energy(&cleaned[18000..30000]) / energy(&audio[18000..30000]);
+    assert!(kept > 0.7 && kept < 1.1, "kept {}", kept);
+}
@@ -1,5 +1,6 @@
 mod tests {
     use log::LevelFilter;
+    use screenpipe_audio::speaker::diarization::{cluster_embeddings, CLUSTER_MAX_DISTANCE};
     use screenpipe_audio::speaker::embedding::EmbeddingExtractor;
     use screenpipe_audio::speaker::embedding_manager::EmbeddingManager;
     use screenpipe_audio::speaker::segment::get_segments;
@@ -94,4 +95,60 @@ mod tests {
         }
         assert_eq!(obama_count, 2);
     }
+
+    /// A voice embedding pointing mostly along `axis`
+    fn voice(axis: usize, noise: f32) -> Vec<f32> {
+        let mut embedding = vec![noise; 512];
+        embedding[axis] = 1.0;
+        embedding
+    }
+
+    #[test]
+    fn test_embedding_manager_updates_centroids() {
+        let mut embedding_manager = EmbeddingManager::new(usize::MAX);
+
+        assert_eq!(
+            embedding_manager.search_speaker(voice(0, 0.0), 0.5),
+            Some(1)
+        );
+        assert_eq!(
+            embedding_manager.search_speaker(voice(1, 0.0), 0.5),
+            Some(2)
+        );
+        let first = embedding_manager.get_all_speakers()[&1].clone();
+
+        // a matched segment moves the centroid of its speaker, without creating a new one
+        assert_eq!(
+            embedding_manager.search_speaker(voice(0, 0.04), 0.5),
+            Some(1)
+        );
+        assert_eq!(embedding_manager.get_all_speakers().len(), 2);
+        assert_ne!(embedding_manager.get_all_speakers()[&1], first);
+        assert_eq!(
+            embedding_manager
+                .get_best_speaker_match(voice(1, 0.02))
+                .unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_cluster_embeddings_groups_voices() {
+        let embeddings: Vec<Vec<f32>> = [(0, 0.0), (1, 0.0), (0, 0.01), (2, 0.0), (1, 0.02)]
+            .into_iter()
+            .map(|(axis, noise)| {
+                let embedding = voice(axis, noise);
+                let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+                embedding.iter().map(|v| v / norm).collect()
+            })
+            .collect();
+
+        assert_eq!(
+            cluster_embeddings(&embeddings, CLUSTER_MAX_DISTANCE),
+            vec![0, 1, 0, 2, 1]
+        );
+        // nothing is close enough to merge
+        assert_eq!(cluster_embeddings(&embeddings, 0.0), vec![0, 1, 2, 3, 4]);
+        assert!(cluster_embeddings(&[], CLUSTER_MAX_DISTANCE).is_empty());
+    }
 }
@@ -0,0 +1,111 @@
+use screenpipe_audio::transcription::deepgram::batch::deepgram_words;
+use screenpipe_audio::transcription::remove_pii_from_words;
+use screenpipe_audio::transcription::whisper::batch::{tokens_to_words, WhisperToken};
+use screenpipe_db::TranscriptionWord;
+use serde_json::json;
+
+fn token(text: &[u8], t0: i64, t1: i64, probability: f32) -> WhisperToken {
+    WhisperToken {
+        bytes: text.to_vec(),
+        t0,
+        t1,
+        probability,
+    }
+}
+
+#[test]
+fn test_whisper_tokens_to_words() {
+    let cafe = " café".as_bytes();
+    let tokens = vec![
+        token(b" Hello", 0, 40, 0.9),
+        token(b",", 40, 45, 0.7),
+        token(b" wor", 50, 70, 0.6),
+        token(b"ld", 70, 90, 1.0),
+        // "é" split over two tokens
+        token(&cafe[..4], 100, 110, 0.5),
+        token(&cafe[4..], 110, 120, 0.5),
+        token(b" ", 120, 125, 0.1),
+    ];
+
+    let words = tokens_to_words(&tokens);
+    let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+    assert_eq!(texts, vec!["Hello,", "world", "café"]);
+    assert_eq!((words[0].start, words[0].end), (0.0, 0.45));
+    assert_eq!((words[1].start, words[1].end), (0.5, 0.9));
+    assert!((words[1].confidence.unwrap() - 0.8).abs() < 1e-6);
+    assert_eq!((words[2].start, words[2].end), (1.0, 1.2));
+}
+
+#[test]
+fn test_deepgram_words() {
+    let response = json!({
+        "results": {
+            "channels": [{
+                "alternatives": [{
+                    "transcript": "hello world",
+                    "words": [
+                        {"word": "hello", "punctuated_word": "Hello", "start": 0.08, "end": 0.4, "confidence": 0.99},
+                        {"word": "world", "start": 0.48, "end": 0.9}
+                    ]
+                }]
+            }]
+        }
+    });
+
+    assert_eq!(
+        deepgram_words(&response),
+        vec![
+            TranscriptionWord {
+                text: "Hello".to_string(),
+                start: 0.08,
+                end: 0.4,
+                confidence: Some(0.99),
+            },
+            TranscriptionWord {
+                text: "world".to_string(),
+                start: 0.48,
+                end: 0.9,
+                confidence: None,
+            },
+        ]
+    );
+    assert!(deepgram_words(&json!({"err_code": "INVALID_AUTH"})).is_empty());
+}
+
+#[test]
+fn test_remove_pii_from_words() {
+    let word = |text: &str, start: f64, confidence: Option<f32>| TranscriptionWord {
+        text: text.to_string(),
+        start,
+        end: start + 0.5,
+        confidence,
+    };
+    // the phone number is split over several words, none of them is pii alone
+    let words = vec![
+        word("call", 0.0, Some(0.9)),
+        word("+1", 1.0, Some(0.8)),
+        word("415", 2.0, None),
+        word("555", 3.0, Some(0.6)),
+        word("0132,", 4.0, Some(0.7)),
+        word("mail", 5.0, None),
+        word("<test@example.com>", 6.0, None),
+        word("thanks", 7.0, None),
+    ];
+
+    assert_eq!(
+        remove_pii_from_words(words.clone()),
+        vec![
+            words[0].clone(),
+            TranscriptionWord {
+                text: "[PHONE],".to_string(),
+                start: 1.0,
+                end: 4.5,
+                confidence: Some(0.6),
+            },
+            words[5].clone(),
+            word("<[EMAIL]>", 6.0, None),
+            words[7].clone(),
+        ]
+    );
+    assert!(remove_pii_from_words(vec![]).is_empty());
+}
@@ -0,0 +1,68 @@
+use screenpipe_audio::vad::regions::{speech_regions, SpeechRegion, VadFrame, FRAME_DURATION};
+use screenpipe_audio::vad::{VadConfig, VadSensitivity};
+use std::time::Duration;
+
+fn frames(probabilities: &[f32]) -> Vec<VadFrame> {
+    probabilities
+        .iter()
+        .enumerate()
+        .map(|(index, &probability)| VadFrame {
+            start: index as f64 * FRAME_DURATION.as_secs_f64(),
+            probability,
+        })
+        .collect()
+}
+
+fn rounded(regions: Vec<SpeechRegion>) -> Vec<(f64, f64)> {
+    regions
+        .into_iter()
+        .map(|r| {
+            (
+                (r.start * 10.0).round() / 10.0,
+                (r.end * 10.0).round() / 10.0,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_speech_regions_hysteresis() {
+    let config = VadConfig::from(VadSensitivity::Medium);
+    // 0.4 is between the silence and speech thresholds and continues what came before
+    let frames = frames(&[0.1, 0.9, 0.4, 0.8, 0.2, 0.4, 0.1, 0.6]);
+
+    assert_eq!(
+        rounded(speech_regions(&frames, &config)),
+        vec![(0.1, 0.4), (0.7, 0.8)]
+    );
+}
+
+#[test]
+fn test_speech_regions_hangover_and_min_speech() {
+    let config = VadConfig {
+        hangover: Duration::from_millis(200),
+        min_speech_duration: Duration::from_millis(200),
+        ..VadConfig::from(VadSensitivity::Medium)
+    };
+    // a 100ms click, two words with a 200ms pause, then a trailing click
+    let frames = frames(&[
+        0.9, 0.0, 0.0, 0.0, 0.9, 0.9, 0.0, 0.0, 0.9, 0.9, 0.9, 0.0, 0.0, 0.0, 0.0, 0.9,
+    ]);
+
+    assert_eq!(rounded(speech_regions(&frames, &config)), vec![(0.4, 1.3)]);
+}
+
+#[test]
+fn test_default_config_keeps_sensitivity_presets() {
+    for sensitivity in [
+        VadSensitivity::Low,
+        VadSensitivity::Medium,
+        VadSensitivity::High,
+    ] {
+        let config = VadConfig::from(sensitivity);
+        assert_eq!(config.min_speech_ratio, sensitivity.min_speech_ratio());
+        assert!(config.silence_threshold <= config.speech_threshold);
+        assert_eq!(config.hangover, Duration::ZERO);
+    }
+    assert_eq!(VadConfig::default().speech_threshold, 0.5);
+}
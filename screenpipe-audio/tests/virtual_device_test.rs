@@ -0,0 +1,95 @@
+use screenpipe_audio::core::device::{parse_audio_device, DeviceType};
+use screenpipe_audio::core::record_and_transcribe;
+use screenpipe_audio::core::stream::AudioStream;
+use screenpipe_audio::core::virtual_device::{PcmFormat, SampleEncoding, VirtualSource};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_parse_virtual_devices() {
+    let device = parse_audio_device("/tmp/meeting.wav (file)").unwrap();
+    assert_eq!(device.device_type, DeviceType::File);
+    assert_eq!(device.name, "/tmp/meeting.wav");
+    assert_eq!(device.to_string(), "/tmp/meeting.wav (file)");
+
+    let device =
+        parse_audio_device("127.0.0.1:4000?rate=48000&channels=2&format=f32le (tcp)").unwrap();
+    assert_eq!(device.device_type, DeviceType::Tcp);
+    assert!(device.device_type.is_virtual());
+    assert_eq!(
+        VirtualSource::parse(&device.name).unwrap(),
+        VirtualSource {
+            location: "127.0.0.1:4000".to_string(),
+            format: PcmFormat {
+                sample_rate: 48000,
+                channels: 2,
+                encoding: SampleEncoding::F32Le,
+            },
+        }
+    );
+
+    let device = parse_audio_device("/tmp/recorder.pcm (fifo)").unwrap();
+    assert_eq!(
+        VirtualSource::parse(&device.name).unwrap().format,
+        PcmFormat::default()
+    );
+    assert!(parse_audio_device("/tmp/recorder.pcm?format=mp3 (fifo)").is_err());
+    assert!(parse_audio_device("/tmp/recorder.pcm?rate=0 (fifo)").is_err());
+}
+
+#[tokio::test]
+async fn test_file_device_records_until_end_of_file() {
+    let path = std::env::temp_dir().join("screenpipe_virtual_device_test.wav");
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 16000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    // two seconds of a 440Hz tone
+    for n in 0..32000 {
+        let sample = (2.0 * std::f32::consts::PI * 440.0 * n as f32 / 16000.0).sin() * 8000.0;
+        writer.write_sample(sample as i16).unwrap();
+        writer.write_sample(sample as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let device = parse_audio_device(&format!("{} (file)", path.display())).unwrap();
+    let is_running = Arc::new(AtomicBool::new(true));
+    let stream = Arc::new(
+        AudioStream::from_device(Arc::new(device), is_running.clone())
+            .await
+            .unwrap(),
+    );
+    assert_eq!(stream.device_config.sample_rate().0, 16000);
+
+    let (sender, receiver) = crossbeam::channel::bounded(10);
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        record_and_transcribe(
+            stream.clone(),
+            Duration::from_secs(30),
+            Arc::new(sender),
+            is_running,
+        ),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    // the end of the file ends the chunk early
+    assert!(stream.is_disconnected());
+    let chunks: Vec<_> = receiver.try_iter().collect();
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].data.len(), 32000);
+    assert_eq!(chunks[0].sample_rate, 16000);
+    let peak = chunks[0]
+        .data
+        .iter()
+        .fold(0.0f32, |peak, s| peak.max(s.abs()));
+    assert!((peak - 8000.0 / 32768.0).abs() < 0.01);
+
+    let _ = std::fs::remove_file(path);
+}
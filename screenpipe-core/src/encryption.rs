@@ -0,0 +1,418 @@
+//! Opt-in encryption at rest for recorded media.
+//!
+//! A single 256 bit master key is supplied through a keyfile or the `SCREENPIPE_ENCRYPTION_KEY`
+//! environment variable, both holding 64 hex characters. Separate keys for the SQLCipher
+//! database and for media files are derived from it with HKDF-SHA256.
+//!
+//! Media files are encrypted with XChaCha20-Poly1305 in fixed size segments, following the
+//! STREAM construction: every segment is authenticated on its own and the final segment is
+//! marked, so truncation and reordering are detected. Files start with [`MAGIC`], which lets
+//! readers handle plaintext recordings made before encryption was enabled.
+
+use anyhow::{anyhow, Context, Result};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha2::Sha256;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use tempfile::NamedTempFile;
+use tracing::debug;
+
+pub const KEY_ENV_VAR: &str = "SCREENPIPE_ENCRYPTION_KEY";
+
+/// Header of every encrypted file, followed by the nonce prefix
+pub const MAGIC: &[u8; 8] = b"SPENC\x00\x00\x01";
+
+const KEY_LEN: usize = 32;
+const NONCE_PREFIX_LEN: usize = 16;
+const TAG_LEN: usize = 16;
+const SEGMENT_LEN: usize = 64 * 1024;
+const HEADER_LEN: usize = MAGIC.len() + NONCE_PREFIX_LEN;
+const DECRYPTED_MEDIA_DIR: &str = "screenpipe-decrypted";
+
+static MEDIA_CIPHER: OnceLock<MediaCipher> = OnceLock::new();
+
+/// The master key. Only derived keys ever leave this type.
+#[derive(Clone)]
+pub struct EncryptionKey([u8; KEY_LEN]);
+
+impl EncryptionKey {
+    pub fn from_hex(hex_key: &str) -> Result<Self> {
+        let bytes = hex::decode(hex_key.trim()).context("encryption key is not valid hex")?;
+        let key: [u8; KEY_LEN] = bytes
+            .try_into()
+            .map_err(|_| anyhow!("encryption key must be {} bytes", KEY_LEN))?;
+        Ok(Self(key))
+    }
+
+    pub fn from_keyfile(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read keyfile {}", path.display()))?;
+        Self::from_hex(&contents)
+    }
+
+    /// Reads the key from `keyfile` when given, otherwise from [`KEY_ENV_VAR`].
+    /// Returns `None` when neither is set, which leaves encryption disabled.
+    ///
+    /// [`KEY_ENV_VAR`] is removed from the environment, so processes started later, like
+    /// pipes, don't inherit the key.
+    pub fn load(keyfile: Option<&Path>) -> Result<Option<Self>> {
+        let env_key = std::env::var(KEY_ENV_VAR);
+        std::env::remove_var(KEY_ENV_VAR);
+
+        if let Some(path) = keyfile {
+            return Self::from_keyfile(path).map(Some);
+        }
+        match env_key {
+            Ok(hex_key) if !hex_key.trim().is_empty() => Self::from_hex(&hex_key).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Hex encoded raw key for SQLCipher's `PRAGMA key`
+    pub fn database_key(&self) -> String {
+        hex::encode(self.derive(b"screenpipe database"))
+    }
+
+    fn derive(&self, info: &[u8]) -> [u8; KEY_LEN] {
+        let mut okm = [0u8; KEY_LEN];
+        Hkdf::<Sha256>::new(None, &self.0)
+            .expand(info, &mut okm)
+            .expect("32 bytes is a valid hkdf output length");
+        okm
+    }
+}
+
+/// Authenticated encryption of media files and cached frames
+pub struct MediaCipher {
+    cipher: XChaCha20Poly1305,
+}
+
+impl MediaCipher {
+    pub fn new(key: &EncryptionKey) -> Self {
+        let media_key = key.derive(b"screenpipe media");
+        Self {
+            cipher: XChaCha20Poly1305::new(Key::from_slice(&media_key)),
+        }
+    }
+
+    pub fn encrypt<R: Read, W: Write>(&self, mut reader: R, mut writer: W) -> Result<()> {
+        let mut prefix = [0u8; NONCE_PREFIX_LEN];
+        rand::thread_rng().fill_bytes(&mut prefix);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&prefix)?;
+
+        let mut segment = vec![0u8; SEGMENT_LEN];
+        let mut len = read_full(&mut reader, &mut segment)?;
+        let mut counter = 0u64;
+        loop {
+            let mut next = vec![0u8; SEGMENT_LEN];
+            let next_len = if len == SEGMENT_LEN {
+                read_full(&mut reader, &mut next)?
+            } else {
+                0
+            };
+            let last = next_len == 0;
+
+            let ciphertext = self
+                .cipher
+                .encrypt(
+                    &segment_nonce(&prefix, counter),
+                    Payload {
+                        msg: &segment[..len],
+                        aad: &[last as u8],
+                    },
+                )
+                .map_err(|_| anyhow!("failed to encrypt segment {}", counter))?;
+            writer.write_all(&ciphertext)?;
+
+            if last {
+                break;
+            }
+            segment = next;
+            len = next_len;
+            counter += 1;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn decrypt<R: Read, W: Write>(&self, mut reader: R, mut writer: W) -> Result<()> {
+        let mut header = [0u8; HEADER_LEN];
+        if read_full(&mut reader, &mut header)? != HEADER_LEN || &header[..MAGIC.len()] != MAGIC {
+            return Err(anyhow!("not an encrypted screenpipe file"));
+        }
+        let prefix: [u8; NONCE_PREFIX_LEN] = header[MAGIC.len()..].try_into()?;
+
+        let mut segment = vec![0u8; SEGMENT_LEN + TAG_LEN];
+        let mut len = read_full(&mut reader, &mut segment)?;
+        let mut counter = 0u64;
+        loop {
+            let mut next = vec![0u8; SEGMENT_LEN + TAG_LEN];
+            let next_len = if len == segment.len() {
+                read_full(&mut reader, &mut next)?
+            } else {
+                0
+            };
+            let last = next_len == 0;
+
+            let plaintext = self
+                .cipher
+                .decrypt(
+                    &segment_nonce(&prefix, counter),
+                    Payload {
+                        msg: &segment[..len],
+                        aad: &[last as u8],
+                    },
+                )
+                .map_err(|_| {
+                    anyhow!("encrypted file is corrupted, truncated or uses a different key")
+                })?;
+            writer.write_all(&plaintext)?;
+
+            if last {
+                break;
+            }
+            segment = next;
+            len = next_len;
+            counter += 1;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn encrypt_bytes(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(plaintext.len() + HEADER_LEN + TAG_LEN);
+        self.encrypt(plaintext, &mut out)?;
+        Ok(out)
+    }
+
+    pub fn decrypt_bytes(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(data.len());
+        self.decrypt(data, &mut out)?;
+        Ok(out)
+    }
+
+    /// Replaces a plaintext file with its encrypted form. The file is rewritten next to the
+    /// original and renamed over it, so a crash never leaves a half encrypted file behind.
+    pub fn encrypt_file_in_place(&self, path: &Path) -> Result<()> {
+        if is_encrypted_file(path)? {
+            return Ok(());
+        }
+        let partial = partial_path(path);
+        let result = (|| -> Result<()> {
+            let reader = BufReader::new(File::open(path)?);
+            let writer = BufWriter::new(File::create(&partial)?);
+            self.encrypt(reader, writer)
+        })();
+        match result {
+            Ok(()) => {
+                std::fs::rename(&partial, path)?;
+                debug!("encrypted {}", path.display());
+                Ok(())
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&partial);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Enables media encryption for the rest of the process
+pub fn init(key: &EncryptionKey) -> Result<()> {
+    MEDIA_CIPHER
+        .set(MediaCipher::new(key))
+        .map_err(|_| anyhow!("encryption is already initialized"))
+}
+
+pub fn is_enabled() -> bool {
+    MEDIA_CIPHER.get().is_some()
+}
+
+fn media_cipher() -> Result<&'static MediaCipher> {
+    MEDIA_CIPHER
+        .get()
+        .ok_or_else(|| anyhow!("file is encrypted but no encryption key was provided"))
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn is_encrypted_file(path: &Path) -> Result<bool> {
+    let mut magic = [0u8; MAGIC.len()];
+    let len = read_full(&mut File::open(path)?, &mut magic)?;
+    Ok(is_encrypted(&magic[..len]))
+}
+
+/// Encrypts `data` when encryption is enabled, otherwise returns it unchanged
+pub fn encrypt_if_enabled(data: &[u8]) -> Result<Vec<u8>> {
+    match MEDIA_CIPHER.get() {
+        Some(cipher) => cipher.encrypt_bytes(data),
+        None => Ok(data.to_vec()),
+    }
+}
+
+/// Decrypts `data` when it is encrypted, otherwise returns it unchanged
+pub fn decrypt_if_encrypted(data: &[u8]) -> Result<Vec<u8>> {
+    if is_encrypted(data) {
+        media_cipher()?.decrypt_bytes(data)
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+/// Size of a file of `plaintext_len` bytes once encrypted
+pub fn encrypted_len(plaintext_len: u64) -> u64 {
+    let segments = plaintext_len.div_ceil(SEGMENT_LEN as u64).max(1);
+    HEADER_LEN as u64 + plaintext_len + segments * TAG_LEN as u64
+}
+
+/// Encrypts a finished recording when encryption is enabled
+pub fn encrypt_file_if_enabled(path: &Path) -> Result<()> {
+    match MEDIA_CIPHER.get() {
+        Some(cipher) => cipher.encrypt_file_in_place(path),
+        None => Ok(()),
+    }
+}
+
+/// A media file ready to be handed to ffmpeg
+pub struct MediaFile {
+    path: PathBuf,
+    // removed on drop, leftovers of a crash by remove_leftover_decrypted_media
+    decrypted: Option<NamedTempFile>,
+}
+
+impl MediaFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn path_str(&self) -> &str {
+        self.path.to_str().unwrap_or_default()
+    }
+
+    pub fn is_decrypted(&self) -> bool {
+        self.decrypted.is_some()
+    }
+}
+
+/// Where recordings are decrypted for ffmpeg. In memory on linux, so plaintext never reaches
+/// the disk there.
+fn decrypted_media_dir() -> PathBuf {
+    let shm = Path::new("/dev/shm");
+    let base = if cfg!(target_os = "linux") && shm.is_dir() {
+        shm.to_path_buf()
+    } else {
+        std::env::temp_dir()
+    };
+    base.join(DECRYPTED_MEDIA_DIR)
+}
+
+fn create_decrypted_media_dir() -> Result<PathBuf> {
+    let dir = decrypted_media_dir();
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(&dir)?;
+    Ok(dir)
+}
+
+/// Removes recordings decrypted before `started_at` that were never cleaned up, like when
+/// screenpipe was killed while ffmpeg read one. Returns how many were removed.
+pub fn remove_leftover_decrypted_media(started_at: SystemTime) -> Result<usize> {
+    let entries = match std::fs::read_dir(decrypted_media_dir()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let is_old = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified < started_at);
+        if is_old && std::fs::remove_file(entry.path()).is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Opens a recording for reading. Plaintext files are used in place, encrypted files are
+/// decrypted to a temporary file only readable by the current user that lives as long as the
+/// returned [`MediaFile`].
+pub async fn open_media(path: impl AsRef<Path>) -> Result<MediaFile> {
+    let path = path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || open_media_blocking(&path)).await?
+}
+
+/// [`open_media`] for callers already off the async runtime
+pub fn open_media_blocking(path: &Path) -> Result<MediaFile> {
+    if !is_encrypted_file(path)? {
+        return Ok(MediaFile {
+            path: path.to_path_buf(),
+            decrypted: None,
+        });
+    }
+
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let temp = tempfile::Builder::new()
+        .prefix("screenpipe_")
+        .suffix(&extension)
+        .tempfile_in(create_decrypted_media_dir()?)?;
+    media_cipher()?.decrypt(
+        BufReader::new(File::open(path)?),
+        BufWriter::new(temp.as_file()),
+    )?;
+
+    Ok(MediaFile {
+        path: temp.path().to_path_buf(),
+        decrypted: Some(temp),
+    })
+}
+
+fn segment_nonce(prefix: &[u8; NONCE_PREFIX_LEN], counter: u64) -> XNonce {
+    let mut nonce = [0u8; NONCE_PREFIX_LEN + 8];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..].copy_from_slice(&counter.to_be_bytes());
+    XNonce::clone_from_slice(&nonce)
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    path.with_file_name(name)
+}
+
+/// Fills `buf` unless the reader ends first, returning the number of bytes read
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
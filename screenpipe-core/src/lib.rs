@@ -22,6 +22,7 @@ pub mod llama;
 pub use llama::*;
 pub mod pipes;
 pub use pipes::*;
+pub mod encryption;
 mod language;
 #[cfg(feature = "security")]
 pub mod pii_removal;
@@ -1,40 +1,945 @@
+//! Linux backend built on AT-SPI2, talking to the accessibility bus over D-Bus.
+//!
+//! The operator API is synchronous while `atspi` is async, so every call is driven to completion
+//! on a dedicated runtime. This keeps the engine usable from inside the server's tokio handlers.
+//! Synthesized mouse and keyboard input goes through the registry's `DeviceEventController`,
+//! which only works on X11 sessions.
+
 use crate::operator::element::UIElementImpl;
 use crate::operator::platforms::AccessibilityEngine;
 use crate::operator::ClickResult;
 use crate::operator::{AutomationError, Locator, Selector, UIElement, UIElementAttributes};
+use atspi::connection::set_session_accessibility;
+use atspi::proxy::accessible::AccessibleProxy;
+use atspi::proxy::device_event_controller::{DeviceEventControllerProxy, KeySynthType};
+use atspi::proxy::proxy_ext::ProxyExt;
+use atspi::zbus::{self, proxy::CacheProperties, zvariant::OwnedObjectPath};
+use atspi::{AccessibilityConnection, CoordType, ObjectRef, State, StateSet};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Debug;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tracing::debug;
+
+const REGISTRY_DEST: &str = "org.a11y.atspi.Registry";
+const REGISTRY_ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+const NULL_PATH: &str = "/org/a11y/atspi/null";
+
+/// Hard limits so a misbehaving application can't make a search walk forever
+const MAX_TREE_DEPTH: usize = 64;
+const MAX_VISITED_ELEMENTS: usize = 20_000;
+
+/// X11 modifier masks understood by `KeySynthType::Lockmodifiers`
+const SHIFT_MASK: u32 = 1 << 0;
+const CONTROL_MASK: u32 = 1 << 2;
+const ALT_MASK: u32 = 1 << 3;
+const SUPER_MASK: u32 = 1 << 6;
+
+/// Action names that mean "activate this element", in order of preference
+const CLICK_ACTIONS: &[&str] = &["click", "press", "activate", "jump", "open", "toggle"];
+
+fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("operator-atspi")
+            .enable_all()
+            .build()
+            .expect("failed to create at-spi runtime")
+    })
+}
+
+/// Runs an at-spi future to completion. When called from inside another tokio runtime the
+/// future is driven from a scoped thread, since nesting `block_on` would panic.
+fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    if tokio::runtime::Handle::try_current().is_ok() {
+        std::thread::scope(|s| {
+            s.spawn(|| runtime().block_on(future))
+                .join()
+                .expect("at-spi worker thread panicked")
+        })
+    } else {
+        runtime().block_on(future)
+    }
+}
+
+fn platform_error(e: impl std::fmt::Display) -> AutomationError {
+    AutomationError::PlatformError(e.to_string())
+}
+
+/// A reference to a remote accessible object on the accessibility bus
+#[derive(Clone)]
+struct AtspiObject {
+    conn: zbus::Connection,
+    bus: String,
+    path: OwnedObjectPath,
+}
+
+impl AtspiObject {
+    fn root(conn: &zbus::Connection) -> Self {
+        Self {
+            conn: conn.clone(),
+            bus: REGISTRY_DEST.to_string(),
+            path: OwnedObjectPath::try_from(REGISTRY_ROOT_PATH).expect("valid registry path"),
+        }
+    }
+
+    fn from_ref(conn: &zbus::Connection, object: ObjectRef) -> Option<Self> {
+        if object.path.as_str() == NULL_PATH {
+            return None;
+        }
+        Some(Self {
+            conn: conn.clone(),
+            bus: object.name.to_string(),
+            path: object.path,
+        })
+    }
+
+    fn key(&self) -> (String, String) {
+        (self.bus.clone(), self.path.to_string())
+    }
+
+    async fn accessible(&self) -> Result<AccessibleProxy<'static>, AutomationError> {
+        AccessibleProxy::builder(&self.conn)
+            .destination(self.bus.clone())
+            .map_err(platform_error)?
+            .path(self.path.clone())
+            .map_err(platform_error)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+            .map_err(platform_error)
+    }
+
+    async fn children(&self) -> Result<Vec<AtspiObject>, AutomationError> {
+        let accessible = self.accessible().await?;
+        let refs = match accessible.get_children().await {
+            Ok(refs) => refs,
+            Err(e) => {
+                // some toolkits only implement the index based accessors
+                debug!(
+                    "GetChildren failed ({}), falling back to GetChildAtIndex",
+                    e
+                );
+                let count = accessible.child_count().await.map_err(platform_error)?;
+                let mut refs = Vec::with_capacity(count.max(0) as usize);
+                for index in 0..count {
+                    if let Ok(child) = accessible.get_child_at_index(index).await {
+                        refs.push(child);
+                    }
+                }
+                refs
+            }
+        };
+        Ok(refs
+            .into_iter()
+            .filter_map(|r| AtspiObject::from_ref(&self.conn, r))
+            .collect())
+    }
+
+    async fn parent(&self) -> Result<Option<AtspiObject>, AutomationError> {
+        let parent = self
+            .accessible()
+            .await?
+            .parent()
+            .await
+            .map_err(platform_error)?;
+        Ok(AtspiObject::from_ref(&self.conn, parent))
+    }
+
+    async fn role_name(&self) -> String {
+        match self.accessible().await {
+            Ok(accessible) => accessible.get_role_name().await.unwrap_or_default(),
+            Err(_) => String::new(),
+        }
+    }
+
+    async fn name(&self) -> String {
+        match self.accessible().await {
+            Ok(accessible) => accessible.name().await.unwrap_or_default(),
+            Err(_) => String::new(),
+        }
+    }
+
+    async fn states(&self) -> StateSet {
+        match self.accessible().await {
+            Ok(accessible) => accessible
+                .get_state()
+                .await
+                .unwrap_or_else(|_| StateSet::empty()),
+            Err(_) => StateSet::empty(),
+        }
+    }
+
+    async fn object_attributes(&self) -> HashMap<String, String> {
+        match self.accessible().await {
+            Ok(accessible) => accessible.get_attributes().await.unwrap_or_default(),
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    /// Toolkit provided id: the `AccessibleId` property when set, else the `id` object attribute
+    async fn element_id(&self) -> Option<String> {
+        let accessible = self.accessible().await.ok()?;
+        if let Ok(id) = accessible.accessible_id().await {
+            if !id.is_empty() {
+                return Some(id);
+            }
+        }
+        accessible
+            .get_attributes()
+            .await
+            .ok()
+            .and_then(|attrs| attrs.get("id").cloned())
+            .filter(|id| !id.is_empty())
+    }
+
+    /// Full contents of the Text interface, if the element implements it
+    async fn text_contents(&self) -> Option<String> {
+        let accessible = self.accessible().await.ok()?;
+        let mut proxies = accessible.proxies().await.ok()?;
+        let text = proxies.text().ok()?;
+        let count = text.character_count().await.ok()?;
+        text.get_text(0, count).await.ok().filter(|t| !t.is_empty())
+    }
+
+    async fn current_value(&self) -> Option<f64> {
+        let accessible = self.accessible().await.ok()?;
+        let mut proxies = accessible.proxies().await.ok()?;
+        let value = proxies.value().ok()?;
+        value.current_value().await.ok()
+    }
+
+    async fn extents(&self) -> Result<(i32, i32, i32, i32), AutomationError> {
+        let accessible = self.accessible().await?;
+        let mut proxies = accessible.proxies().await.map_err(platform_error)?;
+        let component = proxies.component().map_err(platform_error)?;
+        component
+            .get_extents(CoordType::Screen)
+            .await
+            .map_err(platform_error)
+    }
+
+    async fn center(&self) -> Result<(i32, i32), AutomationError> {
+        let (x, y, width, height) = self.extents().await?;
+        if width <= 0 || height <= 0 {
+            return Err(AutomationError::PlatformError(
+                "element has no on-screen extents".to_string(),
+            ));
+        }
+        Ok((x + width / 2, y + height / 2))
+    }
+
+    async fn grab_focus(&self) -> Result<(), AutomationError> {
+        let accessible = self.accessible().await?;
+        let mut proxies = accessible.proxies().await.map_err(platform_error)?;
+        let component = proxies.component().map_err(platform_error)?;
+        if component.grab_focus().await.map_err(platform_error)? {
+            Ok(())
+        } else {
+            Err(AutomationError::PlatformError(
+                "element refused to take focus".to_string(),
+            ))
+        }
+    }
+
+    async fn actions(&self) -> Vec<String> {
+        let Ok(accessible) = self.accessible().await else {
+            return Vec::new();
+        };
+        let Ok(mut proxies) = accessible.proxies().await else {
+            return Vec::new();
+        };
+        let Ok(action) = proxies.action() else {
+            return Vec::new();
+        };
+        action
+            .get_actions()
+            .await
+            .map(|actions| actions.into_iter().map(|a| a.name).collect())
+            .unwrap_or_default()
+    }
+
+    async fn do_action(&self, index: i32) -> Result<bool, AutomationError> {
+        let accessible = self.accessible().await?;
+        let mut proxies = accessible.proxies().await.map_err(platform_error)?;
+        let action = proxies.action().map_err(platform_error)?;
+        action.do_action(index).await.map_err(platform_error)
+    }
+
+    async fn device_event_controller(
+        &self,
+    ) -> Result<DeviceEventControllerProxy<'static>, AutomationError> {
+        DeviceEventControllerProxy::builder(&self.conn)
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+            .map_err(platform_error)
+    }
+
+    async fn mouse_event(&self, x: i32, y: i32, event: &str) -> Result<(), AutomationError> {
+        self.device_event_controller()
+            .await?
+            .generate_mouse_event(x, y, event)
+            .await
+            .map_err(platform_error)
+    }
+
+    async fn keyboard_event(
+        &self,
+        keycode: i32,
+        keystring: &str,
+        synth_type: KeySynthType,
+    ) -> Result<(), AutomationError> {
+        self.device_event_controller()
+            .await?
+            .generate_keyboard_event(keycode, keystring, synth_type)
+            .await
+            .map_err(platform_error)
+    }
+}
+
+/// Depth-first pre-order cursor over the elements below a root (the root itself is not yielded).
+/// Children of the last yielded element are expanded lazily so callers can prune subtrees.
+struct TreeWalker {
+    stack: Vec<(AtspiObject, usize)>,
+    visited: HashSet<(String, String)>,
+    expand: Option<(AtspiObject, usize)>,
+    max_depth: usize,
+}
+
+impl TreeWalker {
+    fn new(root: &AtspiObject, max_depth: usize) -> Self {
+        let mut visited = HashSet::new();
+        visited.insert(root.key());
+        Self {
+            stack: Vec::new(),
+            visited,
+            expand: Some((root.clone(), 0)),
+            max_depth,
+        }
+    }
 
-pub struct LinuxEngine;
+    /// Don't descend into the element returned by the last call to `next`
+    fn skip_children(&mut self) {
+        self.expand = None;
+    }
+
+    async fn next(&mut self) -> Option<(AtspiObject, usize)> {
+        if let Some((element, depth)) = self.expand.take() {
+            if depth < self.max_depth {
+                match element.children().await {
+                    Ok(children) => self
+                        .stack
+                        .extend(children.into_iter().rev().map(|c| (c, depth + 1))),
+                    Err(e) => debug!(
+                        "failed to list children of {}: {}",
+                        element.path.as_str(),
+                        e
+                    ),
+                }
+            }
+        }
+
+        loop {
+            let (element, depth) = self.stack.pop()?;
+            if !self.visited.insert(element.key()) {
+                continue;
+            }
+            if self.visited.len() > MAX_VISITED_ELEMENTS {
+                debug!("tree walk stopped after {} elements", MAX_VISITED_ELEMENTS);
+                return None;
+            }
+            self.expand = Some((element.clone(), depth));
+            return Some((element, depth));
+        }
+    }
+}
+
+/// Lowercases and strips separators so "push button", "push_button" and "AXPushButton" compare equal
+fn normalize_role(role: &str) -> String {
+    let role = role.strip_prefix("AX").unwrap_or(role);
+    role.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Maps the generic roles used across platforms to normalized AT-SPI role names
+fn map_generic_role_to_atspi_roles(role: &str) -> Vec<String> {
+    let normalized = normalize_role(role);
+    let roles: &[&str] = match normalized.as_str() {
+        "window" => &["frame", "window", "dialog", "alert", "filechooser"],
+        "dialog" => &["dialog", "alert", "filechooser"],
+        "button" => &["pushbutton", "togglebutton", "button", "pushbuttonmenu"],
+        "checkbox" => &["checkbox", "checkmenuitem"],
+        "radiobutton" | "radio" => &["radiobutton", "radiomenuitem"],
+        "menu" => &["menu"],
+        "menuitem" => &["menuitem", "checkmenuitem", "radiomenuitem"],
+        "menubar" => &["menubar"],
+        "textfield" | "input" | "edit" | "textbox" | "textarea" | "searchfield" => {
+            &["entry", "text", "passwordtext", "editbar", "spinbutton"]
+        }
+        "text" | "statictext" | "label" => &["label", "statictext", "text", "paragraph"],
+        "link" | "hyperlink" => &["link"],
+        "list" => &["list", "listbox"],
+        "listitem" => &["listitem"],
+        "combobox" | "popupbutton" => &["combobox"],
+        "tab" | "tabitem" => &["pagetab"],
+        "tablist" | "tabgroup" => &["pagetablist"],
+        "image" => &["image", "icon"],
+        "table" => &["table", "treetable"],
+        "cell" => &["tablecell"],
+        "row" => &["tablerow"],
+        "tree" | "outline" => &["tree", "treetable"],
+        "treeitem" => &["treeitem"],
+        "toolbar" => &["toolbar"],
+        "statusbar" => &["statusbar"],
+        "scrollbar" => &["scrollbar"],
+        "slider" => &["slider"],
+        "progressbar" | "progressindicator" => &["progressbar"],
+        "document" | "webarea" => &["documentweb", "documentframe", "documenttext"],
+        "group" | "pane" => &["panel", "grouping", "section", "filler"],
+        "application" => &["application"],
+        _ => &[],
+    };
+
+    let mut result: Vec<String> = roles.iter().map(|r| r.to_string()).collect();
+    if !result.contains(&normalized) {
+        result.push(normalized);
+    }
+    result
+}
+
+fn role_matches(selector_role: &str, element_role: &str) -> bool {
+    let element_role = normalize_role(element_role);
+    map_generic_role_to_atspi_roles(selector_role).contains(&element_role)
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+/// One step of a `Selector::Path`, e.g. `frame`, `push button[2]` or `*[@name='OK']`
+#[derive(Debug, Clone, PartialEq)]
+struct PathStep {
+    /// Match any descendant instead of only direct children (`//`)
+    descendant: bool,
+    /// `None` matches any role (`*`)
+    role: Option<String>,
+    name: Option<String>,
+    /// 1-based position among the matches of this step
+    index: Option<usize>,
+}
+
+fn parse_path(path: &str) -> Result<Vec<PathStep>, AutomationError> {
+    let invalid =
+        |msg: &str| AutomationError::InvalidArgument(format!("{} in path '{}'", msg, path));
+
+    let trimmed = path.trim();
+    let body = trimmed
+        .strip_prefix('/')
+        .ok_or_else(|| invalid("path must start with '/'"))?;
+
+    let mut steps = Vec::new();
+    let mut descendant = false;
+    for segment in body.split('/') {
+        let segment = segment.trim();
+        if segment.is_empty() {
+            // "//" makes the next step match any descendant
+            descendant = true;
+            continue;
+        }
+
+        let (role_part, mut predicates) = match segment.find('[') {
+            Some(pos) => (&segment[..pos], &segment[pos..]),
+            None => (segment, ""),
+        };
+
+        let mut step = PathStep {
+            descendant,
+            role: match role_part.trim() {
+                "*" | "" => None,
+                role => Some(role.to_string()),
+            },
+            name: None,
+            index: None,
+        };
+
+        while !predicates.is_empty() {
+            let end = predicates
+                .find(']')
+                .ok_or_else(|| invalid("unclosed '['"))?;
+            let predicate = predicates[1..end].trim();
+            if let Ok(index) = predicate.parse::<usize>() {
+                if index == 0 {
+                    return Err(invalid("path indices start at 1"));
+                }
+                step.index = Some(index);
+            } else if let Some(value) = predicate.strip_prefix("@name=") {
+                step.name = Some(value.trim_matches(|c| c == '\'' || c == '"').to_string());
+            } else {
+                return Err(invalid(&format!("unsupported predicate '{}'", predicate)));
+            }
+            predicates = predicates[end + 1..].trim_start();
+        }
+
+        steps.push(step);
+        descendant = false;
+    }
+
+    if steps.is_empty() {
+        return Err(invalid("empty path"));
+    }
+    Ok(steps)
+}
+
+/// Parses combinations like "ctrl+shift+t" or "{Enter}" into an X keysym and a modifier mask
+fn parse_key_combination(key_combo: &str) -> Result<(u32, u32), AutomationError> {
+    let combo = key_combo
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}');
+    let parts: Vec<String> = combo.split('+').map(|s| s.trim().to_lowercase()).collect();
+
+    let key = match parts.last() {
+        Some(key) if !key.is_empty() => key,
+        _ => {
+            return Err(AutomationError::InvalidArgument(
+                "Empty key combination".to_string(),
+            ))
+        }
+    };
+
+    let mut modifiers = 0;
+    for modifier in &parts[..parts.len() - 1] {
+        modifiers |= match modifier.as_str() {
+            "shift" => SHIFT_MASK,
+            "ctrl" | "control" => CONTROL_MASK,
+            "alt" | "option" => ALT_MASK,
+            "super" | "meta" | "cmd" | "command" | "win" => SUPER_MASK,
+            _ => {
+                return Err(AutomationError::InvalidArgument(format!(
+                    "Unknown modifier: {}",
+                    modifier
+                )))
+            }
+        };
+    }
+
+    Ok((keysym_for_key(key)?, modifiers))
+}
+
+fn keysym_for_key(key: &str) -> Result<u32, AutomationError> {
+    let keysym = match key {
+        "enter" | "return" => 0xff0d,
+        "tab" => 0xff09,
+        "escape" | "esc" => 0xff1b,
+        "backspace" => 0xff08,
+        "delete" | "del" => 0xffff,
+        "insert" => 0xff63,
+        "home" => 0xff50,
+        "end" => 0xff57,
+        "pageup" => 0xff55,
+        "pagedown" => 0xff56,
+        "left" => 0xff51,
+        "up" => 0xff52,
+        "right" => 0xff53,
+        "down" => 0xff54,
+        "space" => 0x20,
+        _ => {
+            if let Some(n) = key.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+                if (1..=24).contains(&n) {
+                    return Ok(0xffbe + n - 1);
+                }
+            }
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                // latin-1 characters map directly, everything else uses the unicode keysym range
+                (Some(c), None) if (c as u32) < 0x100 => c as u32,
+                (Some(c), None) => 0x0100_0000 | c as u32,
+                _ => {
+                    return Err(AutomationError::InvalidArgument(format!(
+                        "Unknown key: {}",
+                        key
+                    )))
+                }
+            }
+        }
+    };
+    Ok(keysym)
+}
+
+/// Checks an element against a non-structural selector
+async fn element_matches(element: &AtspiObject, selector: &Selector) -> bool {
+    match selector {
+        Selector::Role { role, name } => {
+            if !role_matches(role, &element.role_name().await) {
+                return false;
+            }
+            match name {
+                Some(name) => contains_ignore_case(&element.name().await, name),
+                None => true,
+            }
+        }
+        Selector::Id(id) => element.element_id().await.as_deref() == Some(id.as_str()),
+        Selector::Name(name) => contains_ignore_case(&element.name().await, name),
+        Selector::Text(text) => {
+            if contains_ignore_case(&element.name().await, text) {
+                return true;
+            }
+            element
+                .text_contents()
+                .await
+                .is_some_and(|contents| contains_ignore_case(&contents, text))
+        }
+        Selector::Attributes(attributes) => attributes_match(element, attributes).await,
+        Selector::Path(_) | Selector::Filter(_) | Selector::Chain(_) => false,
+    }
+}
+
+/// `role`, `name`, `description` and `id` are matched against the element itself,
+/// any other key against the toolkit's object attributes
+async fn attributes_match(element: &AtspiObject, attributes: &BTreeMap<String, String>) -> bool {
+    let mut object_attributes: Option<HashMap<String, String>> = None;
+    for (key, expected) in attributes {
+        let matched = match key.to_lowercase().as_str() {
+            "role" => role_matches(expected, &element.role_name().await),
+            "name" | "label" => element.name().await.eq_ignore_ascii_case(expected),
+            "description" => match element.accessible().await {
+                Ok(accessible) => accessible
+                    .description()
+                    .await
+                    .unwrap_or_default()
+                    .eq_ignore_ascii_case(expected),
+                Err(_) => false,
+            },
+            "id" => element.element_id().await.as_deref() == Some(expected.as_str()),
+            _ => {
+                if object_attributes.is_none() {
+                    object_attributes = Some(element.object_attributes().await);
+                }
+                object_attributes
+                    .as_ref()
+                    .and_then(|attrs| attrs.get(key))
+                    .is_some_and(|value| value == expected)
+            }
+        };
+        if !matched {
+            return false;
+        }
+    }
+    true
+}
+
+/// Finds elements below `root` matching `selector`, stopping at the first match if `first_only`
+fn find_matching<'a>(
+    root: &'a AtspiObject,
+    selector: &'a Selector,
+    first_only: bool,
+) -> std::pin::Pin<Box<dyn Future<Output = Result<Vec<AtspiObject>, AutomationError>> + Send + 'a>>
+{
+    Box::pin(async move {
+        match selector {
+            Selector::Filter(_) => Err(AutomationError::UnsupportedOperation(
+                "Filter selector is not supported on linux".to_string(),
+            )),
+            Selector::Chain(selectors) => {
+                if selectors.is_empty() {
+                    return Err(AutomationError::InvalidArgument(
+                        "Chain selector must not be empty".to_string(),
+                    ));
+                }
+                let mut scopes = vec![root.clone()];
+                for (i, selector) in selectors.iter().enumerate() {
+                    let last = i == selectors.len() - 1;
+                    let mut next = Vec::new();
+                    let mut seen = HashSet::new();
+                    for scope in &scopes {
+                        for found in find_matching(scope, selector, first_only && last).await? {
+                            if seen.insert(found.key()) {
+                                next.push(found);
+                            }
+                        }
+                        if first_only && last && !next.is_empty() {
+                            break;
+                        }
+                    }
+                    scopes = next;
+                    if scopes.is_empty() {
+                        break;
+                    }
+                }
+                Ok(scopes)
+            }
+            Selector::Path(path) => {
+                let steps = parse_path(path)?;
+                let mut scopes = vec![root.clone()];
+                for step in &steps {
+                    let mut next = Vec::new();
+                    let mut seen = HashSet::new();
+                    for scope in &scopes {
+                        for found in path_step_matches(scope, step).await {
+                            if seen.insert(found.key()) {
+                                next.push(found);
+                            }
+                        }
+                    }
+                    scopes = next;
+                    if scopes.is_empty() {
+                        break;
+                    }
+                }
+                if first_only {
+                    scopes.truncate(1);
+                }
+                Ok(scopes)
+            }
+            _ => {
+                let mut found = Vec::new();
+                let mut walker = TreeWalker::new(root, MAX_TREE_DEPTH);
+                while let Some((element, _)) = walker.next().await {
+                    if element_matches(&element, selector).await {
+                        found.push(element);
+                        if first_only {
+                            break;
+                        }
+                    }
+                }
+                Ok(found)
+            }
+        }
+    })
+}
+
+async fn path_step_matches(scope: &AtspiObject, step: &PathStep) -> Vec<AtspiObject> {
+    let candidates = if step.descendant {
+        let mut all = Vec::new();
+        let mut walker = TreeWalker::new(scope, MAX_TREE_DEPTH);
+        while let Some((element, _)) = walker.next().await {
+            all.push(element);
+        }
+        all
+    } else {
+        scope.children().await.unwrap_or_default()
+    };
+
+    let mut matches = Vec::new();
+    for candidate in candidates {
+        if let Some(role) = &step.role {
+            if !role_matches(role, &candidate.role_name().await) {
+                continue;
+            }
+        }
+        if let Some(name) = &step.name {
+            if candidate.name().await != *name {
+                continue;
+            }
+        }
+        matches.push(candidate);
+    }
+
+    match step.index {
+        Some(index) => matches.into_iter().nth(index - 1).into_iter().collect(),
+        None => matches,
+    }
+}
+
+pub struct LinuxEngine {
+    conn: zbus::Connection,
+    use_background_apps: bool,
+    activate_app: bool,
+}
 
 impl LinuxEngine {
     pub fn new(use_background_apps: bool, activate_app: bool) -> Result<Self, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
+        let conn = block_on(async {
+            // applications only expose their tree once session accessibility is turned on
+            if let Err(e) = set_session_accessibility(true).await {
+                debug!("failed to enable session accessibility: {}", e);
+            }
+            AccessibilityConnection::new()
+                .await
+                .map(|a11y| a11y.connection().clone())
+        })
+        .map_err(|e| {
+            AutomationError::PlatformError(format!(
+                "failed to connect to the at-spi accessibility bus: {}",
+                e
+            ))
+        })?;
+
+        Ok(Self::with_connection(
+            conn,
+            use_background_apps,
+            activate_app,
         ))
     }
+
+    fn with_connection(
+        conn: zbus::Connection,
+        use_background_apps: bool,
+        activate_app: bool,
+    ) -> Self {
+        Self {
+            conn,
+            use_background_apps,
+            activate_app,
+        }
+    }
+
+    fn wrap(&self, object: AtspiObject) -> UIElement {
+        UIElement::new(Box::new(LinuxUIElement {
+            object,
+            use_background_apps: self.use_background_apps,
+            activate_app: self.activate_app,
+        }))
+    }
+
+    fn root_object(&self, root: Option<&UIElement>) -> AtspiObject {
+        root.and_then(|el| el.as_any().downcast_ref::<LinuxUIElement>())
+            .map(|el| el.object.clone())
+            .unwrap_or_else(|| AtspiObject::root(&self.conn))
+    }
+
+    async fn applications(&self) -> Result<Vec<AtspiObject>, AutomationError> {
+        let apps = AtspiObject::root(&self.conn).children().await?;
+        if self.use_background_apps {
+            return Ok(apps);
+        }
+
+        // without background apps only keep applications that have a window on screen
+        let mut visible = Vec::new();
+        for app in apps {
+            for window in app.children().await.unwrap_or_default() {
+                if window.states().await.contains(State::Showing) {
+                    visible.push(app);
+                    break;
+                }
+            }
+        }
+        Ok(visible)
+    }
+
+    async fn application_by_name(&self, name: &str) -> Result<AtspiObject, AutomationError> {
+        let apps = AtspiObject::root(&self.conn).children().await?;
+        let mut partial = None;
+        for app in apps {
+            let app_name = app.name().await;
+            if app_name.eq_ignore_ascii_case(name) {
+                return Ok(app);
+            }
+            if partial.is_none() && !name.is_empty() && contains_ignore_case(&app_name, name) {
+                partial = Some(app);
+            }
+        }
+        partial.ok_or_else(|| {
+            AutomationError::ElementNotFound(format!("Application '{}' not found", name))
+        })
+    }
+
+    /// Application owning the currently active window
+    async fn active_application(&self) -> Result<AtspiObject, AutomationError> {
+        for app in AtspiObject::root(&self.conn).children().await? {
+            for window in app.children().await.unwrap_or_default() {
+                if window.states().await.contains(State::Active) {
+                    return Ok(app);
+                }
+            }
+        }
+        Err(AutomationError::ElementNotFound(
+            "No active application found".to_string(),
+        ))
+    }
+
+    async fn focused_element(&self) -> Result<AtspiObject, AutomationError> {
+        let apps = AtspiObject::root(&self.conn).children().await?;
+
+        // look inside active windows first, they almost always hold the focus
+        let mut windows = Vec::new();
+        for app in &apps {
+            for window in app.children().await.unwrap_or_default() {
+                let states = window.states().await;
+                if states.contains(State::Showing) {
+                    windows.push((states.contains(State::Active), window));
+                }
+            }
+        }
+        windows.sort_by_key(|(active, _)| !*active);
+
+        for (_, window) in windows {
+            let mut walker = TreeWalker::new(&window, MAX_TREE_DEPTH);
+            while let Some((element, _)) = walker.next().await {
+                let states = element.states().await;
+                if states.contains(State::Focused) {
+                    return Ok(element);
+                }
+                if !states.contains(State::Showing) {
+                    walker.skip_children();
+                }
+            }
+        }
+
+        Err(AutomationError::ElementNotFound(
+            "No focused element found".to_string(),
+        ))
+    }
+
+    /// Polls the registry until an application with this name shows up
+    async fn wait_for_application(&self, name: &str) -> Result<AtspiObject, AutomationError> {
+        let start = std::time::Instant::now();
+        loop {
+            match self.application_by_name(name).await {
+                Ok(app) => return Ok(app),
+                Err(e) if start.elapsed() > Duration::from_secs(10) => return Err(e),
+                Err(_) => tokio::time::sleep(Duration::from_millis(200)).await,
+            }
+        }
+    }
+}
+
+/// Skips empty strings and labels repeated by the child that renders them
+fn push_text(texts: &mut Vec<String>, text: String) {
+    let text = text.trim();
+    if !text.is_empty() && texts.last().map(String::as_str) != Some(text) {
+        texts.push(text.to_string());
+    }
+}
+
+fn spawn_detached(program: &str, args: &[&str]) -> std::io::Result<()> {
+    std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map(|_| ())
 }
 
 impl AccessibilityEngine for LinuxEngine {
     fn get_root_element(&self) -> UIElement {
-        panic!("Linux implementation is not yet available")
+        self.wrap(AtspiObject::root(&self.conn))
     }
 
     fn get_focused_element(&self) -> Result<UIElement, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        block_on(self.focused_element()).map(|e| self.wrap(e))
     }
 
     fn get_applications(&self) -> Result<Vec<UIElement>, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        block_on(self.applications()).map(|apps| apps.into_iter().map(|a| self.wrap(a)).collect())
     }
 
-    fn get_application_by_name(&self, _name: &str) -> Result<UIElement, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn get_application_by_name(&self, name: &str) -> Result<UIElement, AutomationError> {
+        debug!("searching application from name: {}", name);
+        block_on(self.application_by_name(name)).map(|app| self.wrap(app))
     }
 
     fn find_element(
@@ -42,179 +947,469 @@ impl AccessibilityEngine for LinuxEngine {
         selector: &Selector,
         root: Option<&UIElement>,
     ) -> Result<UIElement, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let root = self.root_object(root);
+        let found = block_on(find_matching(&root, selector, true))?;
+        found
+            .into_iter()
+            .next()
+            .map(|e| self.wrap(e))
+            .ok_or_else(|| {
+                AutomationError::ElementNotFound(format!(
+                    "No element found for selector: {:?}",
+                    selector
+                ))
+            })
     }
 
     fn find_elements(
         &self,
-        _selector: &Selector,
-        _root: Option<&UIElement>,
+        selector: &Selector,
+        root: Option<&UIElement>,
     ) -> Result<Vec<UIElement>, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let root = self.root_object(root);
+        let found = block_on(find_matching(&root, selector, false))?;
+        Ok(found.into_iter().map(|e| self.wrap(e)).collect())
     }
 
-    fn open_application(&self, _app_name: &str) -> Result<UIElement, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn open_application(&self, app_name: &str) -> Result<UIElement, AutomationError> {
+        // try the executable first, then the desktop entry
+        spawn_detached(app_name, &[])
+            .or_else(|_| spawn_detached("gtk-launch", &[app_name]))
+            .map_err(|e| {
+                AutomationError::PlatformError(format!(
+                    "Failed to open application '{}': {}",
+                    app_name, e
+                ))
+            })?;
+
+        block_on(self.wait_for_application(app_name)).map(|app| self.wrap(app))
     }
 
-    fn open_url(&self, _url: &str, _browser: Option<&str>) -> Result<UIElement, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn open_url(&self, url: &str, browser: Option<&str>) -> Result<UIElement, AutomationError> {
+        match browser {
+            Some(browser) => spawn_detached(browser, &[url]),
+            None => spawn_detached("xdg-open", &[url]),
+        }
+        .map_err(|e| AutomationError::PlatformError(format!("Failed to open URL: {}", e)))?;
+
+        std::thread::sleep(Duration::from_millis(500));
+
+        let app = match browser {
+            Some(browser) => block_on(self.wait_for_application(browser)),
+            None => block_on(self.active_application()),
+        }?;
+        Ok(self.wrap(app))
     }
 }
 
-// Placeholder LinuxUIElement that implements UIElementImpl
-pub struct LinuxUIElement;
+pub struct LinuxUIElement {
+    object: AtspiObject,
+    use_background_apps: bool,
+    activate_app: bool,
+}
 
 impl Debug for LinuxUIElement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("LinuxUIElement").finish()
+        f.debug_struct("LinuxUIElement")
+            .field("bus", &self.object.bus)
+            .field("path", &self.object.path.as_str())
+            .finish()
+    }
+}
+
+impl LinuxUIElement {
+    fn wrap(&self, object: AtspiObject) -> UIElement {
+        UIElement::new(Box::new(LinuxUIElement {
+            object,
+            use_background_apps: self.use_background_apps,
+            activate_app: self.activate_app,
+        }))
+    }
+
+    /// Clicks the center of the element with a synthesized mouse event
+    fn mouse_click(&self, event: &str) -> Result<(i32, i32), AutomationError> {
+        block_on(async {
+            let (x, y) = self.object.center().await?;
+            self.object.mouse_event(x, y, "abs").await?;
+            self.object.mouse_event(x, y, event).await?;
+            Ok((x, y))
+        })
     }
 }
 
 impl UIElementImpl for LinuxUIElement {
     fn object_id(&self) -> usize {
-        0
+        let mut hasher = DefaultHasher::new();
+        self.object.key().hash(&mut hasher);
+        hasher.finish() as usize
     }
 
     fn id(&self) -> Option<String> {
-        None
+        block_on(self.object.element_id())
     }
 
     fn role(&self) -> String {
-        "".to_string()
+        block_on(self.object.role_name())
     }
 
     fn attributes(&self) -> UIElementAttributes {
-        UIElementAttributes {
-            role: "".to_string(),
-            label: None,
-            value: None,
-            description: None,
-            properties: std::collections::HashMap::new(),
-        }
+        block_on(async {
+            let accessible = self.object.accessible().await.ok();
+            let (name, description) = match &accessible {
+                Some(accessible) => (
+                    accessible.name().await.unwrap_or_default(),
+                    accessible.description().await.unwrap_or_default(),
+                ),
+                None => (String::new(), String::new()),
+            };
+
+            let value = match self.object.text_contents().await {
+                Some(text) => Some(text),
+                None => self.object.current_value().await.map(|v| v.to_string()),
+            };
+
+            let mut properties = HashMap::new();
+            for (key, value) in self.object.object_attributes().await {
+                properties.insert(key, Some(serde_json::Value::String(value)));
+            }
+            let states: Vec<serde_json::Value> = self
+                .object
+                .states()
+                .await
+                .iter()
+                .map(|s| serde_json::Value::String(s.to_static_str().to_string()))
+                .collect();
+            properties.insert("states".to_string(), Some(serde_json::Value::Array(states)));
+            if let Some(id) = self.object.element_id().await {
+                properties.insert("id".to_string(), Some(serde_json::Value::String(id)));
+            }
+
+            UIElementAttributes {
+                role: self.object.role_name().await,
+                label: Some(name).filter(|n| !n.is_empty()),
+                value,
+                description: Some(description).filter(|d| !d.is_empty()),
+                properties,
+            }
+        })
     }
 
     fn children(&self) -> Result<Vec<UIElement>, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let children = block_on(self.object.children())?;
+        Ok(children.into_iter().map(|c| self.wrap(c)).collect())
     }
 
     fn parent(&self) -> Result<Option<UIElement>, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let parent = block_on(self.object.parent())?;
+        Ok(parent.map(|p| self.wrap(p)))
     }
 
     fn bounds(&self) -> Result<(f64, f64, f64, f64), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let (x, y, width, height) = block_on(self.object.extents())?;
+        Ok((x as f64, y as f64, width as f64, height as f64))
     }
 
     fn click(&self) -> Result<ClickResult, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        // prefer the element's own action, it works even when the window is covered
+        let performed = block_on(async {
+            let actions = self.object.actions().await;
+            for wanted in CLICK_ACTIONS {
+                if let Some(index) = actions.iter().position(|a| a.eq_ignore_ascii_case(wanted)) {
+                    if self.object.do_action(index as i32).await.unwrap_or(false) {
+                        return Some(actions[index].clone());
+                    }
+                }
+            }
+            None
+        });
+
+        if let Some(action) = performed {
+            return Ok(ClickResult {
+                method: "AT-SPI Action".to_string(),
+                coordinates: None,
+                details: format!("performed '{}' action", action),
+            });
+        }
+
+        debug!("no click action available, falling back to a mouse event");
+        let (x, y) = self.mouse_click("b1c")?;
+        Ok(ClickResult {
+            method: "Mouse Event (Fallback)".to_string(),
+            coordinates: Some((x as f64, y as f64)),
+            details: "Clicked the center of the element's extents".to_string(),
+        })
     }
 
     fn double_click(&self) -> Result<ClickResult, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let (x, y) = self.mouse_click("b1d")?;
+        Ok(ClickResult {
+            method: "Double Click".to_string(),
+            coordinates: Some((x as f64, y as f64)),
+            details: "Double clicked the center of the element's extents".to_string(),
+        })
     }
 
     fn right_click(&self) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        self.mouse_click("b3c").map(|_| ())
     }
 
     fn hover(&self) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        block_on(async {
+            let (x, y) = self.object.center().await?;
+            self.object.mouse_event(x, y, "abs").await
+        })
     }
 
     fn focus(&self) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        block_on(self.object.grab_focus())
     }
 
-    fn type_text(&self, _text: &str) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn type_text(&self, text: &str) -> Result<(), AutomationError> {
+        let inserted = block_on(async {
+            let accessible = self.object.accessible().await.ok()?;
+            let mut proxies = accessible.proxies().await.ok()?;
+            let position = match proxies.text() {
+                Ok(text_proxy) => text_proxy.caret_offset().await.unwrap_or(-1),
+                Err(_) => -1,
+            };
+            let editable = proxies.editable_text().ok()?;
+            let position = if position < 0 { i32::MAX } else { position };
+            editable
+                .insert_text(position, text, text.chars().count() as i32)
+                .await
+                .ok()
+        });
+
+        if inserted == Some(true) {
+            return Ok(());
+        }
+
+        // not editable through at-spi, type it as keyboard input instead
+        debug!("element is not editable, typing text as keyboard input");
+        if let Err(e) = self.focus() {
+            debug!("failed to focus element before typing: {}", e);
+        }
+        block_on(self.object.keyboard_event(0, text, KeySynthType::String))
     }
 
-    fn press_key(&self, _key: &str) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn press_key(&self, key_combo: &str) -> Result<(), AutomationError> {
+        debug!("pressing key combination: {}", key_combo);
+        let (keysym, modifiers) = parse_key_combination(key_combo)?;
+
+        if let Err(e) = self.focus() {
+            debug!("failed to focus element before key press: {}", e);
+        }
+
+        block_on(async {
+            if modifiers != 0 {
+                self.object
+                    .keyboard_event(modifiers as i32, "", KeySynthType::Lockmodifiers)
+                    .await?;
+            }
+            let result = self
+                .object
+                .keyboard_event(keysym as i32, "", KeySynthType::Sym)
+                .await;
+            if modifiers != 0 {
+                self.object
+                    .keyboard_event(modifiers as i32, "", KeySynthType::Unlockmodifiers)
+                    .await?;
+            }
+            result
+        })
     }
 
     fn get_text(&self, max_depth: usize) -> Result<String, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let texts = block_on(async {
+            let mut texts: Vec<String> = Vec::new();
+            push_text(&mut texts, self.object.name().await);
+            if let Some(contents) = self.object.text_contents().await {
+                push_text(&mut texts, contents);
+            }
+
+            let mut walker = TreeWalker::new(&self.object, max_depth);
+            while let Some((element, _)) = walker.next().await {
+                push_text(&mut texts, element.name().await);
+                if let Some(contents) = element.text_contents().await {
+                    push_text(&mut texts, contents);
+                }
+            }
+            texts
+        });
+
+        Ok(texts.join(" "))
     }
 
-    fn set_value(&self, _value: &str) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn set_value(&self, value: &str) -> Result<(), AutomationError> {
+        block_on(async {
+            let accessible = self.object.accessible().await?;
+            let mut proxies = accessible.proxies().await.map_err(platform_error)?;
+
+            if let Ok(editable) = proxies.editable_text() {
+                return if editable
+                    .set_text_contents(value)
+                    .await
+                    .map_err(platform_error)?
+                {
+                    Ok(())
+                } else {
+                    Err(AutomationError::PlatformError(
+                        "element rejected the new text".to_string(),
+                    ))
+                };
+            }
+
+            if let Ok(value_proxy) = proxies.value() {
+                let number = value.trim().parse::<f64>().map_err(|_| {
+                    AutomationError::InvalidArgument(format!(
+                        "'{}' is not a number, the element only accepts numeric values",
+                        value
+                    ))
+                })?;
+                return value_proxy
+                    .set_current_value(number)
+                    .await
+                    .map_err(platform_error);
+            }
+
+            Err(AutomationError::UnsupportedOperation(
+                "element does not implement EditableText or Value".to_string(),
+            ))
+        })
     }
 
     fn is_enabled(&self) -> Result<bool, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        Ok(block_on(self.object.states()).contains(State::Enabled))
     }
 
     fn is_visible(&self) -> Result<bool, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let states = block_on(self.object.states());
+        Ok(states.contains(State::Showing) && states.contains(State::Visible))
     }
 
     fn is_focused(&self) -> Result<bool, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        Ok(block_on(self.object.states()).contains(State::Focused))
     }
 
-    fn perform_action(&self, _action: &str) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn perform_action(&self, action: &str) -> Result<(), AutomationError> {
+        match action {
+            "focus" => return self.focus(),
+            "click" => return self.click().map(|_| ()),
+            "double_click" => return self.double_click().map(|_| ()),
+            "right_click" => return self.right_click(),
+            _ => {}
+        }
+
+        block_on(async {
+            let actions = self.object.actions().await;
+            let index = actions
+                .iter()
+                .position(|a| a.eq_ignore_ascii_case(action))
+                .ok_or_else(|| {
+                    AutomationError::UnsupportedOperation(format!(
+                        "action '{}' not supported, available actions: {:?}",
+                        action, actions
+                    ))
+                })?;
+            if self.object.do_action(index as i32).await? {
+                Ok(())
+            } else {
+                Err(AutomationError::PlatformError(format!(
+                    "action '{}' failed",
+                    action
+                )))
+            }
+        })
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
 
-    fn create_locator(&self, _selector: Selector) -> Result<Locator, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn create_locator(&self, selector: Selector) -> Result<Locator, AutomationError> {
+        let engine = LinuxEngine::with_connection(
+            self.object.conn.clone(),
+            self.use_background_apps,
+            self.activate_app,
+        );
+        Ok(Locator::new(Arc::new(engine), selector).within(self.wrap(self.object.clone())))
     }
 
     fn clone_box(&self) -> Box<dyn UIElementImpl> {
-        Box::new(LinuxUIElement)
+        Box::new(LinuxUIElement {
+            object: self.object.clone(),
+            use_background_apps: self.use_background_apps,
+            activate_app: self.activate_app,
+        })
     }
 
-    fn scroll(&self, _direction: &str, _amount: f64) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn scroll(&self, direction: &str, amount: f64) -> Result<(), AutomationError> {
+        // buttons 4-7 are the X11 wheel buttons
+        let event = match direction {
+            "up" => "b4c",
+            "down" => "b5c",
+            "left" => "b6c",
+            "right" => "b7c",
+            _ => {
+                return Err(AutomationError::InvalidArgument(format!(
+                    "Invalid scroll direction: {}",
+                    direction
+                )))
+            }
+        };
+
+        block_on(async {
+            let (x, y) = self.object.center().await?;
+            self.object.mouse_event(x, y, "abs").await?;
+            for _ in 0..(amount.abs().round() as usize).max(1) {
+                self.object.mouse_event(x, y, event).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_matching() {
+        assert!(role_matches("button", "push button"));
+        assert!(role_matches("AXButton", "toggle button"));
+        assert!(role_matches("textfield", "entry"));
+        assert!(role_matches("window", "frame"));
+        assert!(role_matches("push_button", "push button"));
+        assert!(!role_matches("button", "label"));
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let steps = parse_path("/frame//push button[@name='OK'][2]").unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].role.as_deref(), Some("frame"));
+        assert!(!steps[0].descendant);
+        assert!(steps[1].descendant);
+        assert_eq!(steps[1].role.as_deref(), Some("push button"));
+        assert_eq!(steps[1].name.as_deref(), Some("OK"));
+        assert_eq!(steps[1].index, Some(2));
+
+        assert_eq!(parse_path("/*").unwrap()[0].role, None);
+        assert!(parse_path("frame").is_err());
+        assert!(parse_path("/frame[0]").is_err());
+        assert!(parse_path("/frame[@title='x']").is_err());
+    }
+
+    #[test]
+    fn test_parse_key_combination() {
+        assert_eq!(parse_key_combination("Enter").unwrap(), (0xff0d, 0));
+        assert_eq!(parse_key_combination("{Tab}").unwrap(), (0xff09, 0));
+        assert_eq!(
+            parse_key_combination("ctrl+shift+t").unwrap(),
+            ('t' as u32, CONTROL_MASK | SHIFT_MASK)
+        );
+        assert_eq!(parse_key_combination("alt+f4").unwrap(), (0xffc1, ALT_MASK));
+        assert!(parse_key_combination("hyper+a").is_err());
+        assert!(parse_key_combination("").is_err());
     }
 }
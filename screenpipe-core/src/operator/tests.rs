@@ -305,4 +305,133 @@ mod tests {
             // println!("children: {:?}", children.len());
         }
     }
+
+    /// These tests drive a real GTK window, run them inside a throwaway session:
+    /// `dbus-run-session -- xvfb-run cargo test -p screenpipe-core linux_tests -- --ignored`
+    /// (requires python3-gi, at-spi2-core and an X server)
+    #[cfg(target_os = "linux")]
+    mod linux_tests {
+        use crate::operator::{Desktop, Selector};
+        use std::process::{Child, Command};
+        use std::time::{Duration, Instant};
+
+        const TEST_APP: &str = r#"
+import gi
+gi.require_version("Gtk", "3.0")
+from gi.repository import Gtk, GLib
+GLib.set_prgname("operator-test-app")
+win = Gtk.Window(title="operator test window")
+box = Gtk.Box(orientation=Gtk.Orientation.VERTICAL)
+label = Gtk.Label(label="waiting")
+entry = Gtk.Entry()
+button = Gtk.Button(label="Press me")
+button.connect("clicked", lambda _: label.set_text("clicked"))
+for w in (label, entry, button):
+    box.add(w)
+win.add(box)
+win.connect("destroy", Gtk.main_quit)
+win.show_all()
+Gtk.main()
+"#;
+
+        struct TestApp(Child);
+
+        impl Drop for TestApp {
+            fn drop(&mut self) {
+                let _ = self.0.kill();
+            }
+        }
+
+        fn start_test_app(desktop: &Desktop) -> TestApp {
+            let child = Command::new("python3")
+                .args(["-c", TEST_APP])
+                .spawn()
+                .expect("failed to start gtk test app");
+            let app = TestApp(child);
+
+            let start = Instant::now();
+            while desktop.application("operator-test-app").is_err() {
+                assert!(
+                    start.elapsed() < Duration::from_secs(10),
+                    "test app never registered on the accessibility bus"
+                );
+                std::thread::sleep(Duration::from_millis(200));
+            }
+            app
+        }
+
+        #[test]
+        #[ignore]
+        fn test_find_elements_with_every_selector() {
+            let desktop = Desktop::new(true, false).unwrap();
+            let _app = start_test_app(&desktop);
+            let app = desktop.application("operator-test-app").unwrap();
+
+            let button = app.locator("button").unwrap().first().unwrap().unwrap();
+            assert_eq!(button.attributes().label.as_deref(), Some("Press me"));
+
+            let by_role_and_name = app
+                .locator(Selector::Role {
+                    role: "button".to_string(),
+                    name: Some("press".to_string()),
+                })
+                .unwrap()
+                .first()
+                .unwrap()
+                .unwrap();
+            assert_eq!(by_role_and_name, button);
+
+            let by_name = app.locator(Selector::Name("Press me".to_string())).unwrap();
+            assert_eq!(by_name.first().unwrap().unwrap(), button);
+
+            let by_text = app.locator(Selector::Text("waiting".to_string())).unwrap();
+            assert_eq!(by_text.first().unwrap().unwrap().role(), "label");
+
+            let by_path = app
+                .locator(Selector::Path("/frame//push button".to_string()))
+                .unwrap();
+            assert_eq!(by_path.first().unwrap().unwrap(), button);
+
+            let by_attributes = app
+                .locator(Selector::Attributes(
+                    [("role".to_string(), "textfield".to_string())]
+                        .into_iter()
+                        .collect(),
+                ))
+                .unwrap();
+            assert_eq!(by_attributes.all().unwrap().len(), 1);
+
+            let chained = app
+                .locator("window")
+                .unwrap()
+                .locator(Selector::Name("Press me".to_string()));
+            assert_eq!(chained.first().unwrap().unwrap(), button);
+
+            assert!(app.locator(Selector::Filter(0)).unwrap().first().is_err());
+        }
+
+        #[test]
+        #[ignore]
+        fn test_click_type_and_get_text() {
+            let desktop = Desktop::new(true, false).unwrap();
+            let _app = start_test_app(&desktop);
+            let app = desktop.application("operator-test-app").unwrap();
+
+            let button = app.locator("button").unwrap().first().unwrap().unwrap();
+            let result = button.click().unwrap();
+            assert_eq!(result.method, "AT-SPI Action");
+
+            let entry = app.locator("textfield").unwrap().first().unwrap().unwrap();
+            entry.type_text("hello").unwrap();
+            assert_eq!(entry.attributes().value.as_deref(), Some("hello"));
+
+            entry.focus().unwrap();
+            entry.press_key("ctrl+a").unwrap();
+
+            let window = app.locator("window").unwrap().first().unwrap().unwrap();
+            let text = window.text(10).unwrap();
+            assert!(text.contains("clicked"), "unexpected text: {}", text);
+            assert!(text.contains("hello"), "unexpected text: {}", text);
+        }
+    }
 }
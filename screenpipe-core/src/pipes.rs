@@ -23,6 +23,7 @@ use serde_json::json;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 
+use crate::encryption::KEY_ENV_VAR;
 use crate::pick_unused_port;
 use once_cell::sync::Lazy;
 
@@ -366,6 +367,15 @@ async fn spawn_watchdog(parent_pid: u32, child_pid: u32) -> Result<tokio::proces
 pub async fn run_pipe(
     pipe: &str,
     screenpipe_dir: PathBuf,
+) -> Result<(tokio::process::Child, PipeState)> {
+    run_pipe_with_env(pipe, screenpipe_dir, Vec::new()).await
+}
+
+/// Like [`run_pipe`], with extra environment variables for the pipe process, e.g. its api token
+pub async fn run_pipe_with_env(
+    pipe: &str,
+    screenpipe_dir: PathBuf,
+    extra_env: Vec<(String, String)>,
 ) -> Result<(tokio::process::Child, PipeState)> {
     let bun_path = find_bun_path().ok_or_else(|| {
         let err = anyhow::anyhow!("bun not found");
@@ -422,6 +432,7 @@ pub async fn run_pipe(
         "PIPE_DIR".to_string(),
         pipe_dir.to_str().unwrap().to_string(),
     ));
+    env_vars.extend(extra_env);
 
     if is_nextjs {
         debug!(
@@ -530,6 +541,7 @@ pub async fn run_pipe(
                 .current_dir(&pipe_dir)
                 .env("NPM_CONFIG_REGISTRY", "https://registry.npmjs.org")
                 .env("BUN_CONFIG_REGISTRY", "https://registry.npmjs.org")
+                .env_remove(KEY_ENV_VAR)
                 .output()
                 .await?;
 
@@ -611,6 +623,7 @@ pub async fn run_pipe(
             .arg(port.to_string())
             .current_dir(&pipe_dir)
             .envs(env_vars)
+            .env_remove(KEY_ENV_VAR)
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped());
 
@@ -654,6 +667,7 @@ pub async fn run_pipe(
         .arg("--bun")
         .arg(&main_module)
         .envs(env_vars)
+        .env_remove(KEY_ENV_VAR)
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
         .spawn()?;
@@ -784,6 +798,7 @@ async fn retry_install(bun_path: &Path, dest_dir: &Path, max_retries: u32) -> Re
         let mut install_child = Command::new(bun_path)
             .arg("i")
             .current_dir(dest_dir)
+            .env_remove(KEY_ENV_VAR)
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
             .spawn()?;
@@ -1579,6 +1594,7 @@ async fn try_build_nextjs(pipe_dir: &Path, bun_path: &Path) -> Result<BuildStatu
         .arg("--bun")
         .arg("build")
         .current_dir(pipe_dir)
+        .env_remove(KEY_ENV_VAR)
         .output()
         .await?;
 
@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use screenpipe_core::encryption::{
+        self, encrypted_len, is_encrypted_file, open_media, EncryptionKey, MediaCipher,
+        KEY_ENV_VAR, MAGIC,
+    };
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    fn cipher() -> MediaCipher {
+        MediaCipher::new(&EncryptionKey::from_hex(KEY).unwrap())
+    }
+
+    #[test]
+    fn test_round_trip_across_segment_boundaries() {
+        let cipher = cipher();
+        for len in [0, 1, 64 * 1024 - 1, 64 * 1024, 64 * 1024 + 1, 200_000] {
+            let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let encrypted = cipher.encrypt_bytes(&plaintext).unwrap();
+            assert!(encrypted.starts_with(MAGIC));
+            assert_eq!(encrypted.len() as u64, encrypted_len(len as u64));
+            assert_eq!(cipher.decrypt_bytes(&encrypted).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn test_tampering_and_truncation_are_rejected() {
+        let cipher = cipher();
+        let plaintext = vec![7u8; 150_000];
+        let encrypted = cipher.encrypt_bytes(&plaintext).unwrap();
+
+        let mut tampered = encrypted.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(cipher.decrypt_bytes(&tampered).is_err());
+
+        // dropping the final segment leaves a stream that still ends on a segment boundary
+        let header_and_two_segments = 24 + 2 * (64 * 1024 + 16);
+        assert!(cipher
+            .decrypt_bytes(&encrypted[..header_and_two_segments])
+            .is_err());
+
+        let other = MediaCipher::new(&EncryptionKey::from_hex(&KEY.replace("00", "ff")).unwrap());
+        assert!(other.decrypt_bytes(&encrypted).is_err());
+    }
+
+    #[test]
+    fn test_encrypt_file_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("monitor_1_2025-01-01_00-00-00.mp4");
+        std::fs::write(&path, b"not really an mp4").unwrap();
+
+        let cipher = cipher();
+        cipher.encrypt_file_in_place(&path).unwrap();
+        assert!(is_encrypted_file(&path).unwrap());
+        let encrypted = std::fs::read(&path).unwrap();
+
+        // already encrypted files are left alone
+        cipher.encrypt_file_in_place(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), encrypted);
+        assert_eq!(
+            cipher.decrypt_bytes(&encrypted).unwrap(),
+            b"not really an mp4"
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_key_parsing() {
+        assert!(EncryptionKey::from_hex("abcd").is_err());
+        assert!(EncryptionKey::from_hex("zz").is_err());
+        let key = EncryptionKey::from_hex(&format!("{}\n", KEY)).unwrap();
+        assert_eq!(key.database_key().len(), 64);
+        assert_ne!(key.database_key(), KEY);
+    }
+
+    #[tokio::test]
+    async fn test_decrypted_media_is_removed() {
+        let _ = encryption::init(&EncryptionKey::from_hex(KEY).unwrap());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("monitor_1.mp4");
+        std::fs::write(&path, cipher().encrypt_bytes(b"not really an mp4").unwrap()).unwrap();
+
+        let media = open_media(&path).await.unwrap();
+        assert!(media.is_decrypted());
+        assert_eq!(std::fs::read(media.path()).unwrap(), b"not really an mp4");
+        let decrypted = media.path().to_path_buf();
+        drop(media);
+        assert!(!decrypted.exists());
+
+        // left behind by a run that was killed
+        let leftover = decrypted.with_file_name("screenpipe_leftover.mp4");
+        std::fs::write(&leftover, b"plaintext").unwrap();
+        let removed = encryption::remove_leftover_decrypted_media(
+            std::time::SystemTime::now() + std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+        assert!(removed >= 1);
+        assert!(!leftover.exists());
+    }
+
+    #[test]
+    fn test_key_is_removed_from_the_environment() {
+        std::env::set_var(KEY_ENV_VAR, KEY);
+        let key = EncryptionKey::load(None).unwrap().unwrap();
+        assert_eq!(
+            key.database_key(),
+            EncryptionKey::from_hex(KEY).unwrap().database_key()
+        );
+        // not inherited by pipes started later
+        assert!(std::env::var(KEY_ENV_VAR).is_err());
+        assert!(EncryptionKey::load(None).unwrap().is_none());
+    }
+}
@@ -0,0 +1,446 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Sqlite, Transaction};
+use std::collections::HashMap;
+
+use crate::speaker_db::{add_to_centroid, embedding_from_bytes};
+use crate::{
+    ArchiveAudioChunk, ArchiveAudioTranscription, ArchiveData, ArchiveFrame, ArchiveImportStats,
+    ArchiveOcrText, ArchiveOcrTextEmbedding, ArchiveSpeaker, ArchiveSpeakerEmbedding, ArchiveTag,
+    ArchiveTagLink, ArchiveVideoChunk, DatabaseManager,
+};
+
+const FRAMES_IN_RANGE: &str = "SELECT id FROM frames WHERE timestamp >= ?1 AND timestamp <= ?2";
+
+const AUDIO_CHUNKS_IN_RANGE: &str = r#"
+    SELECT audio_chunk_id FROM audio_transcriptions WHERE timestamp >= ?1 AND timestamp <= ?2
+    UNION
+    SELECT id FROM audio_chunks WHERE timestamp >= ?1 AND timestamp <= ?2
+"#;
+
+const SPEAKERS_IN_RANGE: &str = r#"
+    SELECT DISTINCT speaker_id FROM audio_transcriptions
+    WHERE timestamp >= ?1 AND timestamp <= ?2 AND speaker_id IS NOT NULL
+"#;
+
+impl DatabaseManager {
+    /// Collects every row recorded between `start` and `end` for an archive export.
+    ///
+    /// Video chunks are included whole when at least one of their frames is in range, audio
+    /// chunks when they or one of their transcriptions are.
+    pub async fn export_archive_data(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<ArchiveData, sqlx::Error> {
+        let video_chunks = sqlx::query_as::<_, ArchiveVideoChunk>(
+            "SELECT id, file_path, device_name FROM video_chunks
+             WHERE id IN (SELECT video_chunk_id FROM frames WHERE timestamp >= ?1 AND timestamp <= ?2)
+             ORDER BY id",
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let frames = sqlx::query_as::<_, ArchiveFrame>(
+            "SELECT id, video_chunk_id, offset_index, timestamp, name, device_name, browser_url, app_name, window_name, focused
+             FROM frames WHERE timestamp >= ?1 AND timestamp <= ?2
+             ORDER BY id",
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let ocr_text = sqlx::query_as::<_, ArchiveOcrText>(&format!(
+            "SELECT frame_id, text, text_json, app_name, ocr_engine, window_name, focused
+             FROM ocr_text WHERE frame_id IN ({FRAMES_IN_RANGE})"
+        ))
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let ocr_text_embeddings = sqlx::query_as::<_, ArchiveOcrTextEmbedding>(&format!(
+            "SELECT frame_id, embedding FROM ocr_text_embeddings WHERE frame_id IN ({FRAMES_IN_RANGE})"
+        ))
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let audio_chunks = sqlx::query_as::<_, ArchiveAudioChunk>(&format!(
+            "SELECT id, file_path, timestamp FROM audio_chunks WHERE id IN ({AUDIO_CHUNKS_IN_RANGE}) ORDER BY id"
+        ))
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let audio_transcriptions = sqlx::query_as::<_, ArchiveAudioTranscription>(
+            "SELECT id, audio_chunk_id, offset_index, timestamp, transcription, device, is_input_device,
+                    speaker_id, transcription_engine, start_time, end_time, words_json
+             FROM audio_transcriptions WHERE timestamp >= ?1 AND timestamp <= ?2
+             ORDER BY id",
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let speakers = sqlx::query_as::<_, ArchiveSpeaker>(&format!(
+            "SELECT id, name, metadata, hallucination FROM speakers WHERE id IN ({SPEAKERS_IN_RANGE})"
+        ))
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let speaker_embeddings = sqlx::query_as::<_, ArchiveSpeakerEmbedding>(&format!(
+            "SELECT speaker_id, embedding FROM speaker_embeddings WHERE speaker_id IN ({SPEAKERS_IN_RANGE})"
+        ))
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let vision_tags = sqlx::query_as::<_, ArchiveTagLink>(&format!(
+            "SELECT vision_id as target_id, tag_id FROM vision_tags WHERE vision_id IN ({FRAMES_IN_RANGE})"
+        ))
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let audio_tags = sqlx::query_as::<_, ArchiveTagLink>(&format!(
+            "SELECT audio_chunk_id as target_id, tag_id FROM audio_tags WHERE audio_chunk_id IN ({AUDIO_CHUNKS_IN_RANGE})"
+        ))
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let tags = sqlx::query_as::<_, ArchiveTag>(&format!(
+            "SELECT id, name FROM tags WHERE id IN (
+                SELECT tag_id FROM vision_tags WHERE vision_id IN ({FRAMES_IN_RANGE})
+                UNION
+                SELECT tag_id FROM audio_tags WHERE audio_chunk_id IN ({AUDIO_CHUNKS_IN_RANGE})
+            )"
+        ))
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ArchiveData {
+            video_chunks,
+            frames,
+            ocr_text,
+            ocr_text_embeddings,
+            audio_chunks,
+            audio_transcriptions,
+            speakers,
+            speaker_embeddings,
+            tags,
+            vision_tags,
+            audio_tags,
+        })
+    }
+
+    /// Merges exported rows into this database in a single transaction.
+    ///
+    /// Ids are remapped to fresh ones. Rows that already exist are reused instead of duplicated:
+    /// chunks are matched on `file_path`, frames on chunk and offset, transcriptions on chunk,
+    /// offset and text, speakers on an identical embedding or name, and tags on name.
+    /// `file_path` values must already point at the location of the media on this machine.
+    pub async fn import_archive_data(
+        &self,
+        data: &ArchiveData,
+    ) -> Result<ArchiveImportStats, sqlx::Error> {
+        let mut stats = ArchiveImportStats::default();
+        let mut tx = self.pool.begin().await?;
+
+        // video chunks and frames
+        let mut video_chunk_ids = HashMap::new();
+        for chunk in &data.video_chunks {
+            let existing: Option<i64> =
+                sqlx::query_scalar("SELECT id FROM video_chunks WHERE file_path = ?1 LIMIT 1")
+                    .bind(&chunk.file_path)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            let id = match existing {
+                Some(id) => id,
+                None => {
+                    stats.video_chunks_imported += 1;
+                    sqlx::query("INSERT INTO video_chunks (file_path, device_name) VALUES (?1, ?2)")
+                        .bind(&chunk.file_path)
+                        .bind(&chunk.device_name)
+                        .execute(&mut *tx)
+                        .await?
+                        .last_insert_rowid()
+                }
+            };
+            video_chunk_ids.insert(chunk.id, id);
+        }
+
+        let mut frame_ids = HashMap::new();
+        // frames created by this import; rows hanging off existing frames are already present
+        let mut new_frame_ids = HashMap::new();
+        for frame in &data.frames {
+            let Some(&video_chunk_id) = video_chunk_ids.get(&frame.video_chunk_id) else {
+                continue;
+            };
+            let existing: Option<i64> = sqlx::query_scalar(
+                "SELECT id FROM frames WHERE video_chunk_id = ?1 AND offset_index = ?2 LIMIT 1",
+            )
+            .bind(video_chunk_id)
+            .bind(frame.offset_index)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let id = match existing {
+                Some(id) => {
+                    stats.frames_skipped += 1;
+                    id
+                }
+                None => {
+                    stats.frames_imported += 1;
+                    let id = sqlx::query(
+                        "INSERT INTO frames (video_chunk_id, offset_index, timestamp, name, device_name, browser_url, app_name, window_name, focused)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    )
+                    .bind(video_chunk_id)
+                    .bind(frame.offset_index)
+                    .bind(frame.timestamp)
+                    .bind(&frame.name)
+                    .bind(&frame.device_name)
+                    .bind(&frame.browser_url)
+                    .bind(&frame.app_name)
+                    .bind(&frame.window_name)
+                    .bind(frame.focused)
+                    .execute(&mut *tx)
+                    .await?
+                    .last_insert_rowid();
+                    new_frame_ids.insert(frame.id, id);
+                    id
+                }
+            };
+            frame_ids.insert(frame.id, id);
+        }
+
+        for ocr in &data.ocr_text {
+            let Some(&frame_id) = new_frame_ids.get(&ocr.frame_id) else {
+                continue;
+            };
+            sqlx::query(
+                "INSERT INTO ocr_text (frame_id, text, text_json, app_name, ocr_engine, window_name, focused, text_length)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )
+            .bind(frame_id)
+            .bind(&ocr.text)
+            .bind(&ocr.text_json)
+            .bind(&ocr.app_name)
+            .bind(&ocr.ocr_engine)
+            .bind(&ocr.window_name)
+            .bind(ocr.focused)
+            .bind(ocr.text.len() as i64)
+            .execute(&mut *tx)
+            .await?;
+            stats.ocr_text_imported += 1;
+        }
+
+        for embedding in &data.ocr_text_embeddings {
+            let Some(&frame_id) = new_frame_ids.get(&embedding.frame_id) else {
+                continue;
+            };
+            sqlx::query("INSERT INTO ocr_text_embeddings (frame_id, embedding) VALUES (?1, ?2)")
+                .bind(frame_id)
+                .bind(&embedding.embedding)
+                .execute(&mut *tx)
+                .await?;
+            stats.embeddings_imported += 1;
+        }
+
+        // speakers
+        let mut speaker_ids = HashMap::new();
+        for speaker in &data.speakers {
+            let embeddings: Vec<&ArchiveSpeakerEmbedding> = data
+                .speaker_embeddings
+                .iter()
+                .filter(|e| e.speaker_id == speaker.id)
+                .collect();
+            match find_matching_speaker(&mut tx, speaker, &embeddings).await? {
+                Some(id) => {
+                    stats.speakers_merged += 1;
+                    speaker_ids.insert(speaker.id, id);
+                }
+                None => {
+                    let id = sqlx::query(
+                        "INSERT INTO speakers (name, metadata, hallucination) VALUES (?1, ?2, ?3)",
+                    )
+                    .bind(&speaker.name)
+                    .bind(&speaker.metadata)
+                    .bind(speaker.hallucination.unwrap_or(false))
+                    .execute(&mut *tx)
+                    .await?
+                    .last_insert_rowid();
+                    for embedding in embeddings {
+                        sqlx::query(
+                            "INSERT INTO speaker_embeddings (embedding, speaker_id) VALUES (vec_f32(?1), ?2)",
+                        )
+                        .bind(&embedding.embedding)
+                        .bind(id)
+                        .execute(&mut *tx)
+                        .await?;
+                        let embedding: Vec<f32> =
+                            embedding_from_bytes(&embedding.embedding).collect();
+                        add_to_centroid(&mut tx, id, &embedding, 1).await?;
+                    }
+                    stats.speakers_imported += 1;
+                    speaker_ids.insert(speaker.id, id);
+                }
+            }
+        }
+
+        // audio chunks and transcriptions
+        let mut audio_chunk_ids = HashMap::new();
+        for chunk in &data.audio_chunks {
+            let existing: Option<i64> =
+                sqlx::query_scalar("SELECT id FROM audio_chunks WHERE file_path = ?1 LIMIT 1")
+                    .bind(&chunk.file_path)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            let id = match existing {
+                Some(id) => id,
+                None => {
+                    stats.audio_chunks_imported += 1;
+                    sqlx::query("INSERT INTO audio_chunks (file_path, timestamp) VALUES (?1, ?2)")
+                        .bind(&chunk.file_path)
+                        .bind(chunk.timestamp)
+                        .execute(&mut *tx)
+                        .await?
+                        .last_insert_rowid()
+                }
+            };
+            audio_chunk_ids.insert(chunk.id, id);
+        }
+
+        for transcription in &data.audio_transcriptions {
+            let Some(&audio_chunk_id) = audio_chunk_ids.get(&transcription.audio_chunk_id) else {
+                continue;
+            };
+            let exists: Option<i64> = sqlx::query_scalar(
+                "SELECT id FROM audio_transcriptions
+                 WHERE audio_chunk_id = ?1 AND offset_index = ?2 AND transcription = ?3 LIMIT 1",
+            )
+            .bind(audio_chunk_id)
+            .bind(transcription.offset_index)
+            .bind(&transcription.transcription)
+            .fetch_optional(&mut *tx)
+            .await?;
+            if exists.is_some() {
+                stats.audio_transcriptions_skipped += 1;
+                continue;
+            }
+
+            let speaker_id = transcription
+                .speaker_id
+                .and_then(|id| speaker_ids.get(&id).copied());
+            sqlx::query(
+                "INSERT INTO audio_transcriptions (audio_chunk_id, offset_index, timestamp, transcription, device, is_input_device, speaker_id, transcription_engine, start_time, end_time, text_length, words_json)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )
+            .bind(audio_chunk_id)
+            .bind(transcription.offset_index)
+            .bind(transcription.timestamp)
+            .bind(&transcription.transcription)
+            .bind(&transcription.device)
+            .bind(transcription.is_input_device)
+            .bind(speaker_id)
+            .bind(&transcription.transcription_engine)
+            .bind(transcription.start_time)
+            .bind(transcription.end_time)
+            .bind(transcription.transcription.len() as i64)
+            .bind(&transcription.words_json)
+            .execute(&mut *tx)
+            .await?;
+            stats.audio_transcriptions_imported += 1;
+        }
+
+        // tags
+        let mut tag_ids = HashMap::new();
+        for tag in &data.tags {
+            stats.tags_imported += sqlx::query("INSERT OR IGNORE INTO tags (name) VALUES (?1)")
+                .bind(&tag.name)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            let id: i64 = sqlx::query_scalar("SELECT id FROM tags WHERE name = ?1")
+                .bind(&tag.name)
+                .fetch_one(&mut *tx)
+                .await?;
+            tag_ids.insert(tag.id, id);
+        }
+
+        for link in &data.vision_tags {
+            if let (Some(frame_id), Some(tag_id)) =
+                (frame_ids.get(&link.target_id), tag_ids.get(&link.tag_id))
+            {
+                sqlx::query(
+                    "INSERT OR IGNORE INTO vision_tags (vision_id, tag_id) VALUES (?1, ?2)",
+                )
+                .bind(frame_id)
+                .bind(tag_id)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        for link in &data.audio_tags {
+            if let (Some(chunk_id), Some(tag_id)) = (
+                audio_chunk_ids.get(&link.target_id),
+                tag_ids.get(&link.tag_id),
+            ) {
+                sqlx::query(
+                    "INSERT OR IGNORE INTO audio_tags (audio_chunk_id, tag_id) VALUES (?1, ?2)",
+                )
+                .bind(chunk_id)
+                .bind(tag_id)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(stats)
+    }
+}
+
+/// Finds a local speaker sharing one of the exported embeddings, falling back to a speaker with
+/// the same non-empty name.
+async fn find_matching_speaker(
+    tx: &mut Transaction<'_, Sqlite>,
+    speaker: &ArchiveSpeaker,
+    embeddings: &[&ArchiveSpeakerEmbedding],
+) -> Result<Option<i64>, sqlx::Error> {
+    for embedding in embeddings {
+        let existing: Option<i64> = sqlx::query_scalar(
+            "SELECT speaker_id FROM speaker_embeddings WHERE embedding = ?1 AND speaker_id IS NOT NULL LIMIT 1",
+        )
+        .bind(&embedding.embedding)
+        .fetch_optional(&mut **tx)
+        .await?;
+        if existing.is_some() {
+            return Ok(existing);
+        }
+    }
+
+    match speaker.name.as_deref() {
+        Some(name) if !name.is_empty() => {
+            sqlx::query_scalar("SELECT id FROM speakers WHERE name = ?1 LIMIT 1")
+                .bind(name)
+                .fetch_optional(&mut **tx)
+                .await
+        }
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,192 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{AudioDuplicate, DatabaseManager, TranscriptionWord};
+
+/// Transcriptions with fewer words are too common to tell a duplicate from a coincidence
+const MIN_DUPLICATE_WORDS: usize = 3;
+/// A transcription with this many times the words of the other has speech the other lacks
+const LONGER_RATIO: f64 = 1.5;
+
+#[derive(sqlx::FromRow)]
+struct DedupRow {
+    id: i64,
+    transcription: String,
+    is_input_device: bool,
+    words_json: Option<String>,
+    timestamp: DateTime<Utc>,
+    duplicate_of: Option<i64>,
+}
+
+struct Candidate {
+    id: i64,
+    words: Vec<String>,
+    is_input_device: bool,
+    confidence: Option<f64>,
+}
+
+impl From<DedupRow> for Candidate {
+    fn from(row: DedupRow) -> Self {
+        let confidences: Vec<f64> = TranscriptionWord::from_json(row.words_json.as_deref())
+            .iter()
+            .filter_map(|word| word.confidence.map(f64::from))
+            .collect();
+        Candidate {
+            id: row.id,
+            words: normalized_words(&row.transcription),
+            is_input_device: row.is_input_device,
+            confidence: (!confidences.is_empty())
+                .then(|| confidences.iter().sum::<f64>() / confidences.len() as f64),
+        }
+    }
+}
+
+impl DatabaseManager {
+    /// Id of the last audio transcription, 0 when there is none
+    pub async fn get_latest_audio_transcription_id(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM audio_transcriptions")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    pub async fn get_audio_transcription_ids_after(
+        &self,
+        after_id: i64,
+        limit: u32,
+    ) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar("SELECT id FROM audio_transcriptions WHERE id > ?1 ORDER BY id LIMIT ?2")
+            .bind(after_id)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Looks for the same speech transcribed from another device within `max_time_gap`, like a
+    /// call heard both from the output device and through the microphone.
+    ///
+    /// Transcriptions are duplicates when at least `min_similarity` of the words of the shorter
+    /// one are found in order in the other. Of the two, the one with clearly more words is kept,
+    /// then the one with the higher word confidence, then the output device's, which hears the
+    /// other side of a call directly. The other is marked as a duplicate and left out of search.
+    pub async fn deduplicate_audio_transcription(
+        &self,
+        transcription_id: i64,
+        max_time_gap: Duration,
+        min_similarity: f64,
+    ) -> Result<Option<AudioDuplicate>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let row: Option<DedupRow> = sqlx::query_as(
+            "SELECT id, transcription, is_input_device, words_json, timestamp, duplicate_of
+             FROM audio_transcriptions WHERE id = ?1",
+        )
+        .bind(transcription_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(row) = row.filter(|row| row.duplicate_of.is_none()) else {
+            return Ok(None);
+        };
+
+        let others: Vec<DedupRow> = sqlx::query_as(
+            "SELECT id, transcription, is_input_device, words_json, timestamp, duplicate_of
+             FROM audio_transcriptions
+             WHERE id != ?1
+               AND device != (SELECT device FROM audio_transcriptions WHERE id = ?1)
+               AND duplicate_of IS NULL
+               AND timestamp >= ?2 AND timestamp <= ?3",
+        )
+        .bind(transcription_id)
+        .bind(row.timestamp - max_time_gap)
+        .bind(row.timestamp + max_time_gap)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let transcription = Candidate::from(row);
+        if transcription.words.len() < MIN_DUPLICATE_WORDS {
+            return Ok(None);
+        }
+        let best = others
+            .into_iter()
+            .map(Candidate::from)
+            .filter(|other| other.words.len() >= MIN_DUPLICATE_WORDS)
+            .map(|other| (word_similarity(&transcription.words, &other.words), other))
+            .filter(|(similarity, _)| *similarity >= min_similarity)
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        let Some((similarity, other)) = best else {
+            return Ok(None);
+        };
+
+        let (kept, duplicate) = if keeps_first(&transcription, &other) {
+            (transcription.id, other.id)
+        } else {
+            (other.id, transcription.id)
+        };
+
+        sqlx::query(
+            "UPDATE audio_transcriptions SET duplicate_of = ?1 WHERE id = ?2 OR duplicate_of = ?2",
+        )
+        .bind(kept)
+        .bind(duplicate)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(Some(AudioDuplicate {
+            kept_id: kept,
+            duplicate_id: duplicate,
+            similarity,
+        }))
+    }
+}
+
+fn normalized_words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Longest common subsequence of the words over the length of the shorter transcription, so a
+/// transcription contained in a longer one is a full match
+fn word_similarity(a: &[String], b: &[String]) -> f64 {
+    let shorter = a.len().min(b.len());
+    if shorter == 0 {
+        return 0.0;
+    }
+    let mut previous = vec![0usize; b.len() + 1];
+    let mut current = vec![0usize; b.len() + 1];
+    for word in a {
+        for (j, other) in b.iter().enumerate() {
+            current[j + 1] = if word == other {
+                previous[j] + 1
+            } else {
+                previous[j + 1].max(current[j])
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()] as f64 / shorter as f64
+}
+
+fn keeps_first(a: &Candidate, b: &Candidate) -> bool {
+    let (a_words, b_words) = (a.words.len() as f64, b.words.len() as f64);
+    if a_words >= b_words * LONGER_RATIO {
+        return true;
+    }
+    if b_words >= a_words * LONGER_RATIO {
+        return false;
+    }
+    if let (Some(a_confidence), Some(b_confidence)) = (a.confidence, b.confidence) {
+        if a_confidence != b_confidence {
+            return a_confidence > b_confidence;
+        }
+    }
+    if a.is_input_device != b.is_input_device {
+        return !a.is_input_device;
+    }
+    a.id < b.id
+}
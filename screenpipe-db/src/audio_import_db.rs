@@ -0,0 +1,141 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::speaker_db::{add_to_centroid, embedding_from_bytes};
+use crate::DatabaseManager;
+
+impl DatabaseManager {
+    /// Moves an imported audio chunk to the time it was recorded at. Its transcriptions are
+    /// placed at their offset in the chunk, so search and the timeline show them like
+    /// transcriptions of live audio.
+    pub async fn set_audio_chunk_timestamp(
+        &self,
+        audio_chunk_id: i64,
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE audio_chunks SET timestamp = ?1 WHERE id = ?2")
+            .bind(timestamp)
+            .bind(audio_chunk_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let transcriptions: Vec<(i64, Option<f64>)> = sqlx::query_as(
+            "SELECT id, start_time FROM audio_transcriptions WHERE audio_chunk_id = ?1",
+        )
+        .bind(audio_chunk_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        for (id, start_time) in transcriptions {
+            let offset = Duration::milliseconds((start_time.unwrap_or(0.0) * 1000.0) as i64);
+            sqlx::query("UPDATE audio_transcriptions SET timestamp = ?1 WHERE id = ?2")
+                .bind(timestamp + offset)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await
+    }
+
+    /// Attributes the transcriptions of an imported chunk to the people the recording is known
+    /// to contain, creating speakers for names not seen before.
+    ///
+    /// With a single name every transcription is that person's and their voice is added to the
+    /// speaker's centroid. With several names each transcription goes to the closest of their
+    /// centroids within `max_distance`, the others keep the speaker found while transcribing.
+    /// Returns how many transcriptions changed speaker.
+    pub async fn apply_speaker_hints(
+        &self,
+        audio_chunk_id: i64,
+        names: &[String],
+        max_distance: f64,
+    ) -> Result<u64, sqlx::Error> {
+        if names.is_empty() {
+            return Ok(0);
+        }
+        let mut tx = self.pool.begin().await?;
+
+        let mut speaker_ids = Vec::with_capacity(names.len());
+        for name in names {
+            let existing: Option<i64> = sqlx::query_scalar(
+                "SELECT id FROM speakers WHERE name = ?1 AND hallucination = 0 ORDER BY id LIMIT 1",
+            )
+            .bind(name)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let speaker_id = match existing {
+                Some(id) => id,
+                None => sqlx::query("INSERT INTO speakers (name) VALUES (?1)")
+                    .bind(name)
+                    .execute(&mut *tx)
+                    .await?
+                    .last_insert_rowid(),
+            };
+            speaker_ids.push(speaker_id);
+        }
+
+        let transcriptions: Vec<(i64, Option<i64>, Option<Vec<u8>>)> = sqlx::query_as(
+            r#"
+            SELECT at.id, at.speaker_id, e.embedding
+            FROM audio_transcriptions at
+            LEFT JOIN audio_transcription_speaker_embeddings e
+                ON e.audio_transcription_id = at.id
+            WHERE at.audio_chunk_id = ?1
+            ORDER BY at.id
+            "#,
+        )
+        .bind(audio_chunk_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let candidates = serde_json::to_string(&speaker_ids).unwrap_or_else(|_| "[]".to_string());
+        let mut updated = 0;
+        for (transcription_id, speaker_id, embedding) in transcriptions {
+            let hinted_speaker_id = if speaker_ids.len() == 1 {
+                if let Some(embedding) = &embedding {
+                    let embedding: Vec<f32> = embedding_from_bytes(embedding).collect();
+                    add_to_centroid(&mut tx, speaker_ids[0], &embedding, 1).await?;
+                }
+                Some(speaker_ids[0])
+            } else {
+                match &embedding {
+                    Some(embedding) => {
+                        sqlx::query_scalar(
+                            r#"
+                            SELECT speaker_id
+                            FROM speaker_centroids
+                            WHERE speaker_id IN (SELECT value FROM json_each(?1))
+                              AND vec_distance_cosine(embedding, ?2) < ?3
+                            ORDER BY vec_distance_cosine(embedding, ?2)
+                            LIMIT 1
+                            "#,
+                        )
+                        .bind(&candidates)
+                        .bind(embedding)
+                        .bind(max_distance)
+                        .fetch_optional(&mut *tx)
+                        .await?
+                    }
+                    None => None,
+                }
+            };
+
+            let Some(hinted_speaker_id) = hinted_speaker_id else {
+                continue;
+            };
+            if speaker_id == Some(hinted_speaker_id) {
+                continue;
+            }
+            sqlx::query("UPDATE audio_transcriptions SET speaker_id = ?1 WHERE id = ?2")
+                .bind(hinted_speaker_id)
+                .bind(transcription_id)
+                .execute(&mut *tx)
+                .await?;
+            updated += 1;
+        }
+
+        tx.commit().await?;
+        Ok(updated)
+    }
+}
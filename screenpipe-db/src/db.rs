@@ -3,12 +3,10 @@ use image::DynamicImage;
 use libsqlite3_sys::sqlite3_auto_extension;
 use sqlite_vec::sqlite3_vec_init;
 use sqlx::migrate::MigrateDatabase;
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
-use sqlx::Column;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
 use sqlx::Error as SqlxError;
 use sqlx::Row;
-use sqlx::TypeInfo;
-use sqlx::ValueRef;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, warn};
@@ -19,19 +17,30 @@ use zerocopy::AsBytes;
 
 use futures::future::try_join_all;
 
+use crate::speaker_db::{add_to_centroid, merge_centroids};
 use crate::{
     AudioChunksResponse, AudioDevice, AudioEntry, AudioResult, AudioResultRaw, ContentType,
     DeviceType, FrameData, FrameRow, OCREntry, OCRResult, OCRResultRaw, OcrEngine, OcrTextBlock,
     Order, SearchMatch, SearchResult, Speaker, TagContentType, TextBounds, TextPosition,
-    TimeSeriesChunk, UiContent, VideoMetadata,
+    TimeSeriesChunk, TranscriptionWord, UiContent, UiMonitoringText, VideoMetadata,
 };
 
 pub struct DatabaseManager {
     pub pool: SqlitePool,
+    /// Connections that can't write, used for queries coming from outside like /raw_sql
+    pub(crate) read_only_pool: SqlitePool,
 }
 
 impl DatabaseManager {
     pub async fn new(database_path: &str) -> Result<Self, sqlx::Error> {
+        Self::new_with_key(database_path, None).await
+    }
+
+    /// Like [`DatabaseManager::new`], encrypting the database with SQLCipher when `key` is set.
+    ///
+    /// `key` is a hex encoded 256 bit raw key, which spares SQLCipher its passphrase derivation
+    /// on every new connection. Requires building with the `sqlcipher` feature.
+    pub async fn new_with_key(database_path: &str, key: Option<&str>) -> Result<Self, sqlx::Error> {
         debug!(
             "Initializing DatabaseManager with database path: {}",
             database_path
@@ -51,13 +60,23 @@ impl DatabaseManager {
             sqlx::Sqlite::create_database(&connection_string).await?;
         }
 
+        let mut connect_options = SqliteConnectOptions::from_str(&connection_string)?;
+        if let Some(key) = key {
+            // sqlx runs the key pragma before anything else on every new connection
+            connect_options = connect_options.pragma("key", format!("\"x'{}'\"", key));
+        }
+
         let pool = SqlitePoolOptions::new()
             .max_connections(50)
             .min_connections(3) // Minimum number of idle connections
             .acquire_timeout(Duration::from_secs(10))
-            .connect(&connection_string)
+            .connect_with(connect_options.clone())
             .await?;
 
+        if key.is_some() {
+            Self::check_encryption(&pool).await?;
+        }
+
         // Enable WAL mode
         sqlx::query("PRAGMA journal_mode = WAL;")
             .execute(&pool)
@@ -73,12 +92,49 @@ impl DatabaseManager {
             .execute(&pool)
             .await?;
 
-        let db_manager = DatabaseManager { pool };
-
         // Run migrations after establishing the connection
-        Self::run_migrations(&db_manager.pool).await?;
+        Self::run_migrations(&pool).await?;
 
-        Ok(db_manager)
+        // opened once the schema exists, query_only makes sqlite refuse any write
+        let read_only_pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .acquire_timeout(Duration::from_secs(10))
+            .connect_with(connect_options.read_only(true).pragma("query_only", "ON"))
+            .await?;
+
+        Ok(DatabaseManager {
+            pool,
+            read_only_pool,
+        })
+    }
+
+    async fn check_encryption(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        // plain sqlite silently ignores the key pragma, never write unencrypted data by mistake
+        let cipher_version: Option<String> = sqlx::query_scalar("PRAGMA cipher_version")
+            .fetch_optional(pool)
+            .await?;
+        if cipher_version.is_none() {
+            return Err(sqlx::Error::Configuration(
+                "an encryption key was provided but screenpipe was built without the sqlcipher feature"
+                    .into(),
+            ));
+        }
+
+        // the key is only verified once a page is read
+        if let Err(e) = sqlx::query("SELECT COUNT(*) FROM sqlite_master")
+            .execute(pool)
+            .await
+        {
+            return Err(sqlx::Error::Configuration(
+                format!(
+                    "failed to open the encrypted database, the key is wrong or the database is not encrypted: {}",
+                    e
+                )
+                .into(),
+            ));
+        }
+
+        Ok(())
     }
 
     async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
@@ -143,12 +199,43 @@ impl DatabaseManager {
         start_time: Option<f64>,
         end_time: Option<f64>,
     ) -> Result<i64, sqlx::Error> {
+        self.insert_audio_transcription_with_words(
+            audio_chunk_id,
+            transcription,
+            offset_index,
+            transcription_engine,
+            device,
+            speaker_id,
+            start_time,
+            end_time,
+            &[],
+        )
+        .await
+    }
+
+    /// Like [`Self::insert_audio_transcription`], also storing the word timings, see
+    /// [`TranscriptionWord`]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn insert_audio_transcription_with_words(
+        &self,
+        audio_chunk_id: i64,
+        transcription: &str,
+        offset_index: i64,
+        transcription_engine: &str,
+        device: &AudioDevice,
+        speaker_id: Option<i64>,
+        start_time: Option<f64>,
+        end_time: Option<f64>,
+        words: &[TranscriptionWord],
+    ) -> Result<i64, sqlx::Error> {
+        let words_json = (!words.is_empty())
+            .then(|| serde_json::to_string(words).unwrap_or_else(|_| "[]".to_string()));
         let text_length = transcription.len() as i64;
         let mut tx = self.pool.begin().await?;
 
         // Insert the full transcription
         let id = sqlx::query(
-            "INSERT INTO audio_transcriptions (audio_chunk_id, transcription, offset_index, timestamp, transcription_engine, device, is_input_device, speaker_id, start_time, end_time, text_length) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            "INSERT INTO audio_transcriptions (audio_chunk_id, transcription, offset_index, timestamp, transcription_engine, device, is_input_device, speaker_id, start_time, end_time, text_length, words_json) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
         )
         .bind(audio_chunk_id)
         .bind(transcription)
@@ -161,6 +248,7 @@ impl DatabaseManager {
         .bind(start_time)
         .bind(end_time)
         .bind(text_length)
+        .bind(words_json)
         .execute(&mut *tx)
         .await?
         .last_insert_rowid();
@@ -171,21 +259,34 @@ impl DatabaseManager {
         Ok(id)
     }
 
+    /// Cuts the transcription of a chunk to `transcription`, the words it starts with, e.g. when
+    /// the next transcription overlaps it. The timings of the words cut are dropped too.
     pub async fn update_audio_transcription(
         &self,
         audio_chunk_id: i64,
         transcription: &str,
     ) -> Result<i64, sqlx::Error> {
         let text_length = transcription.len() as i64;
+        let word_count = transcription.split_whitespace().count() as i64;
         let mut tx = self.pool.begin().await?;
 
         // Insert the full transcription
         let affected = sqlx::query(
-            "UPDATE audio_transcriptions SET transcription = ?1, text_length = ?2 WHERE audio_chunk_id = ?3",
+            r#"
+            UPDATE audio_transcriptions
+            SET transcription = ?1,
+                text_length = ?2,
+                words_json = CASE WHEN words_json IS NULL THEN NULL ELSE (
+                    SELECT json_group_array(json(value))
+                    FROM (SELECT value FROM json_each(words_json) WHERE key < ?4 ORDER BY key)
+                ) END
+            WHERE audio_chunk_id = ?3
+            "#,
         )
         .bind(transcription)
         .bind(text_length)
         .bind(audio_chunk_id)
+        .bind(word_count)
         .execute(&mut *tx)
         .await?
         .rows_affected();
@@ -211,6 +312,7 @@ impl DatabaseManager {
         .bind(id)
         .execute(&mut *tx)
         .await?;
+        add_to_centroid(&mut tx, id, embedding, 1).await?;
         tx.commit().await?;
 
         Ok(Speaker {
@@ -256,9 +358,9 @@ impl DatabaseManager {
              FROM speakers
              WHERE id = (
                  SELECT speaker_id
-                 FROM speaker_embeddings
+                 FROM speaker_centroids
                  WHERE vec_distance_cosine(embedding, vec_f32(?1)) < ?2
-                 ORDER BY vec_distance_cosine(embedding, vec_f32(?1))
+                 ORDER BY vec_distance_cosine(embedding, vec_f32(?1)), speaker_id
                  LIMIT 1
              )",
         )
@@ -376,7 +478,7 @@ impl DatabaseManager {
             .bind(frame_id)
             .bind(text)
             .bind(text_json)
-            .bind(format!("{:?}", *ocr_engine))
+            .bind(ocr_engine.to_string())
             .bind(text_length)
             .execute(&mut *tx)
             .await?;
@@ -386,6 +488,33 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Replaces the ocr text of a frame, like when it's OCR'd again by another engine
+    pub async fn replace_ocr_text(
+        &self,
+        frame_id: i64,
+        text: &str,
+        text_json: &str,
+        ocr_engine: Arc<OcrEngine>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        // deleted and inserted again so the search index triggers run for empty texts too
+        sqlx::query("DELETE FROM ocr_text WHERE frame_id = ?1")
+            .bind(frame_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("INSERT INTO ocr_text (frame_id, text, text_json, ocr_engine, text_length) VALUES (?1, ?2, ?3, ?4, ?5)")
+            .bind(frame_id)
+            .bind(text)
+            .bind(text_json)
+            .bind(ocr_engine.to_string())
+            .bind(text.len() as i64)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn search(
         &self,
@@ -781,28 +910,7 @@ impl DatabaseManager {
             .fetch_all(&self.pool)
             .await?;
 
-        Ok(raw_results
-            .into_iter()
-            .map(|raw| OCRResult {
-                frame_id: raw.frame_id,
-                ocr_text: raw.ocr_text,
-                text_json: raw.text_json,
-                timestamp: raw.timestamp,
-                frame_name: raw.frame_name,
-                file_path: raw.file_path,
-                offset_index: raw.offset_index,
-                app_name: raw.app_name,
-                ocr_engine: raw.ocr_engine,
-                window_name: raw.window_name,
-                device_name: raw.device_name,
-                tags: raw
-                    .tags
-                    .map(|t| t.split(',').map(String::from).collect())
-                    .unwrap_or_default(),
-                browser_url: raw.browser_url,
-                focused: raw.focused,
-            })
-            .collect())
+        Ok(raw_results.into_iter().map(OCRResult::from).collect())
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -820,6 +928,7 @@ impl DatabaseManager {
         // base query for audio search
         let mut base_sql = String::from(
             "SELECT
+                audio_transcriptions.id as transcription_id,
                 audio_transcriptions.audio_chunk_id,
                 audio_transcriptions.transcription,
                 audio_transcriptions.timestamp,
@@ -831,7 +940,8 @@ impl DatabaseManager {
                 audio_transcriptions.is_input_device,
                 audio_transcriptions.speaker_id,
                 audio_transcriptions.start_time,
-                audio_transcriptions.end_time
+                audio_transcriptions.end_time,
+                audio_transcriptions.words_json
              FROM audio_transcriptions
              JOIN audio_chunks ON audio_transcriptions.audio_chunk_id = audio_chunks.id
              LEFT JOIN speakers ON audio_transcriptions.speaker_id = speakers.id
@@ -840,7 +950,7 @@ impl DatabaseManager {
         );
         // if query is provided, join the corresponding fts table
         if !query.is_empty() {
-            base_sql.push_str(" JOIN audio_transcriptions_fts ON audio_transcriptions_fts.audio_chunk_id = audio_transcriptions.audio_chunk_id");
+            base_sql.push_str(" JOIN audio_transcriptions_fts ON audio_transcriptions_fts.rowid = audio_transcriptions.id");
         }
 
         // build where clause conditions in order
@@ -861,6 +971,7 @@ impl DatabaseManager {
             conditions.push("COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) <= ?");
         }
         conditions.push("(speakers.id IS NULL OR speakers.hallucination = 0)");
+        conditions.push("audio_transcriptions.duplicate_of IS NULL");
         if speaker_ids.is_some() {
             conditions.push("(json_array_length(?) = 0 OR audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?)))");
         }
@@ -923,6 +1034,7 @@ impl DatabaseManager {
                 };
 
                 Ok::<AudioResult, sqlx::Error>(AudioResult {
+                    transcription_id: raw.transcription_id,
                     audio_chunk_id: raw.audio_chunk_id,
                     transcription: raw.transcription,
                     timestamp: raw.timestamp,
@@ -942,6 +1054,7 @@ impl DatabaseManager {
                     speaker,
                     start_time: raw.start_time,
                     end_time: raw.end_time,
+                    words: TranscriptionWord::from_json(raw.words_json.as_deref()),
                 })
             })
             .collect();
@@ -949,6 +1062,16 @@ impl DatabaseManager {
         Ok(try_join_all(futures).await?.into_iter().collect())
     }
 
+    pub async fn get_audio_transcription(
+        &self,
+        transcription_id: i64,
+    ) -> Result<Option<AudioResult>, sqlx::Error> {
+        Ok(self
+            .get_audio_results_by_ids(&[transcription_id])
+            .await?
+            .remove(&transcription_id))
+    }
+
     pub async fn get_frame(&self, frame_id: i64) -> Result<Option<(String, i64)>, sqlx::Error> {
         sqlx::query_as::<_, (String, i64)>(
             r#"
@@ -1142,11 +1265,12 @@ impl DatabaseManager {
                        AND (?4 IS NULL OR COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) >= ?4)
                        AND (?5 IS NULL OR COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) <= ?5)
                        AND (json_array_length(?6) = 0 OR audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?6)))
+                       AND audio_transcriptions.duplicate_of IS NULL
                 "#,
                 table = if query.is_empty() {
                     "audio_transcriptions"
                 } else {
-                    "audio_transcriptions_fts JOIN audio_transcriptions ON audio_transcriptions_fts.audio_chunk_id = audio_transcriptions.audio_chunk_id"
+                    "audio_transcriptions_fts JOIN audio_transcriptions ON audio_transcriptions_fts.rowid = audio_transcriptions.id"
                 },
                 match_condition = if query.is_empty() {
                     "1=1"
@@ -1423,43 +1547,6 @@ impl DatabaseManager {
         tx.commit().await?;
         Ok(())
     }
-    pub async fn execute_raw_sql(&self, query: &str) -> Result<serde_json::Value, sqlx::Error> {
-        let rows = sqlx::query(query).fetch_all(&self.pool).await?;
-
-        let result: Vec<serde_json::Map<String, serde_json::Value>> = rows
-            .iter()
-            .map(|row| {
-                let mut map = serde_json::Map::new();
-                for (i, column) in row.columns().iter().enumerate() {
-                    if let Ok(value) = row.try_get_raw(i) {
-                        let json_value = match value.type_info().name() {
-                            "TEXT" => {
-                                let s: String = row.try_get(i).unwrap_or_default();
-                                serde_json::Value::String(s)
-                            }
-                            "INTEGER" => {
-                                let i: i64 = row.try_get(i).unwrap_or_default();
-                                serde_json::Value::Number(i.into())
-                            }
-                            "REAL" => {
-                                let f: f64 = row.try_get(i).unwrap_or_default();
-                                serde_json::Value::Number(
-                                    serde_json::Number::from_f64(f).unwrap_or(0.into()),
-                                )
-                            }
-                            _ => serde_json::Value::Null,
-                        };
-                        map.insert(column.name().to_string(), json_value);
-                    }
-                }
-                map
-            })
-            .collect();
-
-        Ok(serde_json::Value::Array(
-            result.into_iter().map(serde_json::Value::Object).collect(),
-        ))
-    }
 
     pub async fn find_video_chunks(
         &self,
@@ -1686,6 +1773,62 @@ impl DatabaseManager {
         Ok(tags.into_iter().map(|t| t.0).collect())
     }
 
+    /// The most recently written ui monitoring row, as a cursor for
+    /// [`DatabaseManager::get_ui_monitoring_written_after`].
+    pub async fn get_latest_ui_monitoring_cursor(
+        &self,
+    ) -> Result<Option<(String, i64)>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT CAST(timestamp AS TEXT), id FROM ui_monitoring ORDER BY timestamp DESC, id DESC LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Ui monitoring rows inserted or updated after the `(timestamp, id)` cursor, oldest first.
+    /// The ui monitor rewrites `text_output` and `timestamp` of a window's row as it reads more
+    /// text, so ids alone miss updates.
+    pub async fn get_ui_monitoring_written_after(
+        &self,
+        timestamp: &str,
+        id: i64,
+        limit: i64,
+    ) -> Result<Vec<UiMonitoringText>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, app, text_output AS text, CAST(timestamp AS TEXT) AS timestamp
+            FROM ui_monitoring
+            WHERE CAST(timestamp AS TEXT) > ?1 OR (CAST(timestamp AS TEXT) = ?1 AND id > ?2)
+            ORDER BY timestamp, id
+            LIMIT ?3
+            "#,
+        )
+        .bind(timestamp)
+        .bind(id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Replaces the text of a ui monitoring row, unless the ui monitor changed it since
+    /// `previous_text` was read. Returns whether the row was updated.
+    pub async fn update_ui_monitoring_text(
+        &self,
+        id: i64,
+        previous_text: &str,
+        text: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE ui_monitoring SET text_output = ?1 WHERE id = ?2 AND text_output = ?3",
+        )
+        .bind(text)
+        .bind(id)
+        .bind(previous_text)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn get_audio_chunks_for_speaker(
         &self,
         speaker_id: i64,
@@ -1822,6 +1965,8 @@ impl DatabaseManager {
             .execute(&mut *tx)
             .await?;
 
+        merge_centroids(&mut tx, speaker_to_keep_id, speaker_to_merge_id).await?;
+
         // delete the speaker to merge
         sqlx::query("DELETE FROM speakers WHERE id = ?")
             .bind(speaker_to_merge_id)
@@ -1847,6 +1992,10 @@ impl DatabaseManager {
 
         // Array of (query, operation description) tuples
         let operations = [
+            (
+                "DELETE FROM audio_transcription_speaker_embeddings WHERE audio_transcription_id IN (SELECT id FROM audio_transcriptions WHERE speaker_id = ?)",
+                "transcription speaker embeddings",
+            ),
             (
                 "DELETE FROM audio_transcriptions WHERE speaker_id = ?",
                 "audio transcriptions",
@@ -1859,6 +2008,10 @@ impl DatabaseManager {
                 "DELETE FROM speaker_embeddings WHERE speaker_id = ?",
                 "speaker embeddings",
             ),
+            (
+                "DELETE FROM speaker_centroids WHERE speaker_id = ?",
+                "speaker centroid",
+            ),
             (
                 "DELETE FROM speakers WHERE id = ?",
                 "speaker",
@@ -2062,7 +2215,9 @@ impl DatabaseManager {
                 ocr_text.ocr_engine,
                 frames.window_name,
                 GROUP_CONCAT(tags.name, ',') as tags,
-                frames.browser_url
+                frames.browser_url,
+                frames.focused,
+                frames.device_name
             FROM embedding_matches
             JOIN ocr_text ON embedding_matches.frame_id = ocr_text.frame_id
             JOIN frames ON ocr_text.frame_id = frames.id
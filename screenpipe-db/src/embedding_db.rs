@@ -0,0 +1,249 @@
+use chrono::{DateTime, Utc};
+use zerocopy::AsBytes;
+
+use crate::hybrid_db::CandidateKind;
+use crate::{ContentType, DatabaseManager, PendingEmbedding, SearchResult, SemanticSearchResult};
+
+impl DatabaseManager {
+    pub async fn insert_audio_transcription_embedding(
+        &self,
+        audio_transcription_id: i64,
+        embedding: &[f32],
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO audio_transcription_embeddings (audio_transcription_id, embedding) VALUES (?1, ?2)",
+        )
+        .bind(audio_transcription_id)
+        .bind(embedding.as_bytes())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn insert_ui_monitoring_embedding(
+        &self,
+        ui_monitoring_id: i64,
+        embedding: &[f32],
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO ui_monitoring_embeddings (ui_monitoring_id, embedding) VALUES (?1, ?2)",
+        )
+        .bind(ui_monitoring_id)
+        .bind(embedding.as_bytes())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Transcriptions after `after_id` that have text but no embedding, oldest first.
+    pub async fn get_audio_transcriptions_without_embeddings(
+        &self,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<PendingEmbedding>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT audio_transcriptions.id, audio_transcriptions.transcription as text
+            FROM audio_transcriptions
+            LEFT JOIN audio_transcription_embeddings
+                ON audio_transcription_embeddings.audio_transcription_id = audio_transcriptions.id
+            WHERE audio_transcriptions.id > ?1
+                AND audio_transcription_embeddings.id IS NULL
+                AND TRIM(audio_transcriptions.transcription) != ''
+            ORDER BY audio_transcriptions.id
+            LIMIT ?2
+            "#,
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// UI monitoring rows after `after_id` that have text but no embedding, oldest first.
+    pub async fn get_ui_monitoring_without_embeddings(
+        &self,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<PendingEmbedding>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT ui_monitoring.id, ui_monitoring.text_output as text
+            FROM ui_monitoring
+            LEFT JOIN ui_monitoring_embeddings
+                ON ui_monitoring_embeddings.ui_monitoring_id = ui_monitoring.id
+            WHERE ui_monitoring.id > ?1
+                AND ui_monitoring_embeddings.id IS NULL
+                AND TRIM(ui_monitoring.text_output) != ''
+            ORDER BY ui_monitoring.id
+            LIMIT ?2
+            "#,
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Transcriptions whose text was replaced by a re-transcription in a version after
+    /// `after_version_id` and has no embedding, each with the id of that version, oldest first.
+    pub async fn get_retranscribed_without_embeddings(
+        &self,
+        after_version_id: i64,
+        limit: i64,
+    ) -> Result<Vec<(i64, PendingEmbedding)>, sqlx::Error> {
+        let rows: Vec<(i64, i64, String)> = sqlx::query_as(
+            r#"
+            SELECT v.id, audio_transcriptions.id, audio_transcriptions.transcription
+            FROM audio_transcription_versions v
+            JOIN audio_transcriptions ON audio_transcriptions.id = v.audio_transcription_id
+            LEFT JOIN audio_transcription_embeddings
+                ON audio_transcription_embeddings.audio_transcription_id = audio_transcriptions.id
+            WHERE v.id > ?1
+                AND v.transcription_engine = audio_transcriptions.transcription_engine
+                AND audio_transcription_embeddings.id IS NULL
+                AND TRIM(audio_transcriptions.transcription) != ''
+            ORDER BY v.id
+            LIMIT ?2
+            "#,
+        )
+        .bind(after_version_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(version_id, id, text)| (version_id, PendingEmbedding { id, text }))
+            .collect())
+    }
+
+    /// Number of transcriptions and UI monitoring rows still missing an embedding.
+    pub async fn count_text_without_embeddings(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM audio_transcriptions
+                 WHERE TRIM(transcription) != ''
+                   AND id NOT IN (SELECT audio_transcription_id FROM audio_transcription_embeddings))
+              + (SELECT COUNT(*) FROM ui_monitoring
+                 WHERE TRIM(text_output) != ''
+                   AND id NOT IN (SELECT ui_monitoring_id FROM ui_monitoring_embeddings))
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Highest audio transcription, UI monitoring and transcription version ids, 0 when the
+    /// tables are empty.
+    pub async fn get_latest_text_ids(&self) -> Result<(i64, i64, i64), sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT COALESCE(MAX(id), 0) FROM audio_transcriptions),
+                (SELECT COALESCE(MAX(id), 0) FROM ui_monitoring),
+                (SELECT COALESCE(MAX(id), 0) FROM audio_transcription_versions)
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Finds the OCR text, transcriptions and UI text closest to `embedding`, across the
+    /// modalities selected by `content_type`, ordered by cosine distance.
+    pub async fn search_similar_content(
+        &self,
+        embedding: &[f32],
+        content_type: ContentType,
+        limit: u32,
+        threshold: f32,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> Result<Vec<SemanticSearchResult>, sqlx::Error> {
+        let (search_ocr, search_audio, search_ui) = match content_type {
+            ContentType::All => (true, true, true),
+            ContentType::OCR => (true, false, false),
+            ContentType::Audio => (false, true, false),
+            ContentType::UI => (false, false, true),
+            ContentType::AudioAndUi => (false, true, true),
+            ContentType::OcrAndUi => (true, false, true),
+            ContentType::AudioAndOcr => (true, true, false),
+        };
+
+        let (ocr, audio, ui) = tokio::try_join!(
+            async {
+                if search_ocr {
+                    self.rank_ocr_vector(
+                        embedding, threshold, "", start_time, end_time, None, None, limit,
+                    )
+                    .await
+                } else {
+                    Ok(Vec::new())
+                }
+            },
+            async {
+                if search_audio {
+                    self.rank_audio_vector(
+                        embedding, threshold, start_time, end_time, None, None, None, limit,
+                    )
+                    .await
+                } else {
+                    Ok(Vec::new())
+                }
+            },
+            async {
+                if search_ui {
+                    self.rank_ui_vector(
+                        embedding, threshold, None, None, start_time, end_time, None, None, limit,
+                    )
+                    .await
+                } else {
+                    Ok(Vec::new())
+                }
+            },
+        )?;
+
+        let mut ranked: Vec<(CandidateKind, i64, f64)> = ocr
+            .into_iter()
+            .map(|c| (CandidateKind::Ocr, c.id, c.score))
+            .chain(
+                audio
+                    .into_iter()
+                    .map(|c| (CandidateKind::Audio, c.id, c.score)),
+            )
+            .chain(ui.into_iter().map(|c| (CandidateKind::Ui, c.id, c.score)))
+            .collect();
+        ranked.sort_by(|a, b| a.2.total_cmp(&b.2).then_with(|| b.1.cmp(&a.1)));
+        ranked.truncate(limit as usize);
+
+        let ids_of = |kind: CandidateKind| -> Vec<i64> {
+            ranked
+                .iter()
+                .filter(|(k, _, _)| *k == kind)
+                .map(|(_, id, _)| *id)
+                .collect()
+        };
+        let (ocr_ids, audio_ids, ui_ids) = (
+            ids_of(CandidateKind::Ocr),
+            ids_of(CandidateKind::Audio),
+            ids_of(CandidateKind::Ui),
+        );
+        let (mut ocr, mut audio, mut ui) = tokio::try_join!(
+            self.get_ocr_results_by_frame_ids(&ocr_ids),
+            self.get_audio_results_by_ids(&audio_ids),
+            self.get_ui_results_by_ids(&ui_ids),
+        )?;
+
+        Ok(ranked
+            .into_iter()
+            .filter_map(|(kind, id, distance)| {
+                let result = match kind {
+                    CandidateKind::Ocr => SearchResult::OCR(ocr.remove(&id)?),
+                    CandidateKind::Audio => SearchResult::Audio(audio.remove(&id)?),
+                    CandidateKind::Ui => SearchResult::UI(ui.remove(&id)?),
+                };
+                Some(SemanticSearchResult { result, distance })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,757 @@
+use chrono::{DateTime, Utc};
+use futures::future::try_join_all;
+use sqlx::FromRow;
+use std::collections::HashMap;
+use zerocopy::AsBytes;
+
+use crate::{
+    AudioResult, AudioResultRaw, ContentType, DatabaseManager, DeviceType, HybridScore,
+    HybridSearchResult, OCRResult, OCRResultRaw, SearchResult, TranscriptionWord, UiContent,
+};
+
+/// Constant of the reciprocal rank fusion formula `1 / (k + rank)`
+pub const DEFAULT_RRF_K: u32 = 60;
+
+/// Each ranked list fetches this many candidates per requested result
+const CANDIDATES_PER_RESULT: u32 = 4;
+const MIN_CANDIDATES: u32 = 50;
+
+#[derive(FromRow)]
+pub(crate) struct RankedCandidate {
+    pub id: i64,
+    pub score: f64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum CandidateKind {
+    Ocr,
+    Audio,
+    Ui,
+}
+
+impl DatabaseManager {
+    /// Blends full text and vector similarity results with reciprocal rank fusion.
+    ///
+    /// Every content type contributes a keyword list ranked by bm25 and, when `embedding` is
+    /// given, a list ranked by cosine distance to its stored embeddings. The lists are fused
+    /// with `1 / (rrf_k + rank)` and the filters behave like in [`DatabaseManager::search`].
+    ///
+    /// Returns the requested page together with the number of fused candidates.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_hybrid(
+        &self,
+        query: &str,
+        embedding: Option<&[f32]>,
+        content_type: ContentType,
+        limit: u32,
+        offset: u32,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<Vec<i64>>,
+        frame_name: Option<&str>,
+        browser_url: Option<&str>,
+        focused: Option<bool>,
+        threshold: f32,
+        rrf_k: u32,
+    ) -> Result<(Vec<HybridSearchResult>, usize), sqlx::Error> {
+        let candidates = (limit + offset)
+            .saturating_mul(CANDIDATES_PER_RESULT)
+            .max(MIN_CANDIDATES);
+
+        let (mut search_ocr, mut search_audio, mut search_ui) = match content_type {
+            ContentType::All => (true, true, true),
+            ContentType::OCR => (true, false, false),
+            ContentType::Audio => (false, true, false),
+            ContentType::UI => (false, false, true),
+            ContentType::AudioAndUi => (false, true, true),
+            ContentType::OcrAndUi => (true, false, true),
+            ContentType::AudioAndOcr => (true, true, false),
+        };
+        // same narrowing as the keyword search: frame level filters only exist on frames
+        if focused.is_some() || browser_url.is_some() {
+            (search_audio, search_ui) = (false, false);
+        }
+        if app_name.is_some() || window_name.is_some() || frame_name.is_some() {
+            search_audio = false;
+        }
+        if frame_name.is_some() {
+            search_ui = false;
+        }
+        if query.trim().is_empty() && embedding.is_none() {
+            (search_ocr, search_audio, search_ui) = (false, false, false);
+        }
+
+        let frame_query = frame_fts_query(app_name, window_name, browser_url, focused, frame_name);
+        let has_query = !query.trim().is_empty();
+
+        let (ocr_keyword, ocr_vector, audio_keyword, audio_vector, ui_keyword, ui_vector) = tokio::try_join!(
+            async {
+                if search_ocr && has_query {
+                    self.rank_ocr_keyword(
+                        query,
+                        &frame_query,
+                        start_time,
+                        end_time,
+                        min_length,
+                        max_length,
+                        candidates,
+                    )
+                    .await
+                } else {
+                    Ok(Vec::new())
+                }
+            },
+            async {
+                match embedding {
+                    Some(embedding) if search_ocr => {
+                        self.rank_ocr_vector(
+                            embedding,
+                            threshold,
+                            &frame_query,
+                            start_time,
+                            end_time,
+                            min_length,
+                            max_length,
+                            candidates,
+                        )
+                        .await
+                    }
+                    _ => Ok(Vec::new()),
+                }
+            },
+            async {
+                if search_audio && has_query {
+                    self.rank_audio_keyword(
+                        query,
+                        start_time,
+                        end_time,
+                        min_length,
+                        max_length,
+                        speaker_ids.as_deref(),
+                        candidates,
+                    )
+                    .await
+                } else {
+                    Ok(Vec::new())
+                }
+            },
+            async {
+                match embedding {
+                    Some(embedding) if search_audio => {
+                        self.rank_audio_vector(
+                            embedding,
+                            threshold,
+                            start_time,
+                            end_time,
+                            min_length,
+                            max_length,
+                            speaker_ids.as_deref(),
+                            candidates,
+                        )
+                        .await
+                    }
+                    _ => Ok(Vec::new()),
+                }
+            },
+            async {
+                if search_ui && has_query {
+                    self.rank_ui_keyword(
+                        query,
+                        app_name,
+                        window_name,
+                        start_time,
+                        end_time,
+                        min_length,
+                        max_length,
+                        candidates,
+                    )
+                    .await
+                } else {
+                    Ok(Vec::new())
+                }
+            },
+            async {
+                match embedding {
+                    Some(embedding) if search_ui => {
+                        self.rank_ui_vector(
+                            embedding,
+                            threshold,
+                            app_name,
+                            window_name,
+                            start_time,
+                            end_time,
+                            min_length,
+                            max_length,
+                            candidates,
+                        )
+                        .await
+                    }
+                    _ => Ok(Vec::new()),
+                }
+            },
+        )?;
+
+        let mut fused: HashMap<(CandidateKind, i64), HybridScore> = HashMap::new();
+        for (kind, list) in [
+            (CandidateKind::Ocr, ocr_keyword),
+            (CandidateKind::Audio, audio_keyword),
+            (CandidateKind::Ui, ui_keyword),
+        ] {
+            for (i, candidate) in list.into_iter().enumerate() {
+                let score = fused.entry((kind, candidate.id)).or_default();
+                score.keyword_rank = Some(i + 1);
+                score.keyword_score = Some(candidate.score);
+                score.rrf_score += rrf(rrf_k, i + 1);
+            }
+        }
+        for (kind, list) in [
+            (CandidateKind::Ocr, ocr_vector),
+            (CandidateKind::Audio, audio_vector),
+            (CandidateKind::Ui, ui_vector),
+        ] {
+            for (i, candidate) in list.into_iter().enumerate() {
+                let score = fused.entry((kind, candidate.id)).or_default();
+                score.vector_rank = Some(i + 1);
+                score.vector_distance = Some(candidate.score);
+                score.rrf_score += rrf(rrf_k, i + 1);
+            }
+        }
+
+        let mut ranked: Vec<((CandidateKind, i64), HybridScore)> = fused.into_iter().collect();
+        // ties are broken by id so pagination stays stable
+        ranked.sort_by(|(a_key, a), (b_key, b)| {
+            b.rrf_score
+                .total_cmp(&a.rrf_score)
+                .then_with(|| b_key.cmp(a_key))
+        });
+        let total = ranked.len();
+        let page: Vec<_> = ranked
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+
+        let ids_of = |kind: CandidateKind| -> Vec<i64> {
+            page.iter()
+                .filter(|((k, _), _)| *k == kind)
+                .map(|((_, id), _)| *id)
+                .collect()
+        };
+        let (ocr_ids, audio_ids, ui_ids) = (
+            ids_of(CandidateKind::Ocr),
+            ids_of(CandidateKind::Audio),
+            ids_of(CandidateKind::Ui),
+        );
+        let (mut ocr, mut audio, mut ui) = tokio::try_join!(
+            self.get_ocr_results_by_frame_ids(&ocr_ids),
+            self.get_audio_results_by_ids(&audio_ids),
+            self.get_ui_results_by_ids(&ui_ids),
+        )?;
+
+        let results = page
+            .into_iter()
+            .filter_map(|((kind, id), score)| {
+                let result = match kind {
+                    CandidateKind::Ocr => SearchResult::OCR(ocr.remove(&id)?),
+                    CandidateKind::Audio => SearchResult::Audio(audio.remove(&id)?),
+                    CandidateKind::Ui => SearchResult::UI(ui.remove(&id)?),
+                };
+                Some(HybridSearchResult { result, score })
+            })
+            .collect();
+
+        Ok((results, total))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn rank_ocr_keyword(
+        &self,
+        query: &str,
+        frame_query: &str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        candidates: u32,
+    ) -> Result<Vec<RankedCandidate>, sqlx::Error> {
+        let sql = format!(
+            r#"
+            WITH matches AS MATERIALIZED (
+                SELECT frame_id, bm25(ocr_text_fts) as score
+                FROM ocr_text_fts
+                WHERE ocr_text_fts MATCH ?1
+            )
+            SELECT ocr_text.frame_id as id, MIN(matches.score) as score
+            FROM matches
+            JOIN ocr_text ON ocr_text.frame_id = matches.frame_id
+            JOIN frames ON frames.id = ocr_text.frame_id
+            {frame_fts_join}
+            WHERE 1=1
+                {frame_fts_condition}
+                AND (?3 IS NULL OR frames.timestamp >= ?3)
+                AND (?4 IS NULL OR frames.timestamp <= ?4)
+                AND (?5 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) >= ?5)
+                AND (?6 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) <= ?6)
+            GROUP BY ocr_text.frame_id
+            ORDER BY score ASC, ocr_text.frame_id DESC
+            LIMIT ?7
+            "#,
+            frame_fts_join = frame_fts_join(frame_query),
+            frame_fts_condition = frame_fts_condition(frame_query),
+        );
+
+        sqlx::query_as(&sql)
+            .bind(query)
+            .bind(frame_query)
+            .bind(start_time)
+            .bind(end_time)
+            .bind(min_length.map(|l| l as i64))
+            .bind(max_length.map(|l| l as i64))
+            .bind(candidates)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn rank_ocr_vector(
+        &self,
+        embedding: &[f32],
+        threshold: f32,
+        frame_query: &str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        candidates: u32,
+    ) -> Result<Vec<RankedCandidate>, sqlx::Error> {
+        let sql = format!(
+            r#"
+            SELECT
+                ocr_text_embeddings.frame_id as id,
+                MIN(vec_distance_cosine(ocr_text_embeddings.embedding, vec_f32(?1))) as score
+            FROM ocr_text_embeddings
+            JOIN frames ON frames.id = ocr_text_embeddings.frame_id
+            JOIN ocr_text ON ocr_text.frame_id = frames.id
+            {frame_fts_join}
+            WHERE 1=1
+                {frame_fts_condition}
+                AND (?3 IS NULL OR frames.timestamp >= ?3)
+                AND (?4 IS NULL OR frames.timestamp <= ?4)
+                AND (?5 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) >= ?5)
+                AND (?6 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) <= ?6)
+            GROUP BY ocr_text_embeddings.frame_id
+            HAVING score < ?8
+            ORDER BY score ASC, ocr_text_embeddings.frame_id DESC
+            LIMIT ?7
+            "#,
+            frame_fts_join = frame_fts_join(frame_query),
+            frame_fts_condition = frame_fts_condition(frame_query),
+        );
+
+        sqlx::query_as(&sql)
+            .bind(embedding.as_bytes())
+            .bind(frame_query)
+            .bind(start_time)
+            .bind(end_time)
+            .bind(min_length.map(|l| l as i64))
+            .bind(max_length.map(|l| l as i64))
+            .bind(candidates)
+            .bind(threshold)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn rank_audio_vector(
+        &self,
+        embedding: &[f32],
+        threshold: f32,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<&[i64]>,
+        candidates: u32,
+    ) -> Result<Vec<RankedCandidate>, sqlx::Error> {
+        let speaker_ids_json = serde_json::to_string(speaker_ids.unwrap_or_default())
+            .unwrap_or_else(|_| "[]".to_string());
+
+        sqlx::query_as(
+            r#"
+            SELECT
+                audio_transcriptions.id as id,
+                vec_distance_cosine(audio_transcription_embeddings.embedding, vec_f32(?1)) as score
+            FROM audio_transcription_embeddings
+            JOIN audio_transcriptions
+                ON audio_transcriptions.id = audio_transcription_embeddings.audio_transcription_id
+            LEFT JOIN speakers ON audio_transcriptions.speaker_id = speakers.id
+            WHERE (?2 IS NULL OR audio_transcriptions.timestamp >= ?2)
+                AND (?3 IS NULL OR audio_transcriptions.timestamp <= ?3)
+                AND (?4 IS NULL OR COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) >= ?4)
+                AND (?5 IS NULL OR COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) <= ?5)
+                AND (speakers.id IS NULL OR speakers.hallucination = 0)
+                AND (json_array_length(?6) = 0 OR audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?6)))
+                AND audio_transcriptions.duplicate_of IS NULL
+                AND score < ?8
+            ORDER BY score ASC, audio_transcriptions.id DESC
+            LIMIT ?7
+            "#,
+        )
+        .bind(embedding.as_bytes())
+        .bind(start_time)
+        .bind(end_time)
+        .bind(min_length.map(|l| l as i64))
+        .bind(max_length.map(|l| l as i64))
+        .bind(speaker_ids_json)
+        .bind(candidates)
+        .bind(threshold)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn rank_ui_vector(
+        &self,
+        embedding: &[f32],
+        threshold: f32,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        candidates: u32,
+    ) -> Result<Vec<RankedCandidate>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                ui_monitoring.id as id,
+                vec_distance_cosine(ui_monitoring_embeddings.embedding, vec_f32(?1)) as score
+            FROM ui_monitoring_embeddings
+            JOIN ui_monitoring ON ui_monitoring.id = ui_monitoring_embeddings.ui_monitoring_id
+            WHERE (?2 IS NULL OR ui_monitoring.app LIKE '%' || ?2 || '%')
+                AND (?3 IS NULL OR ui_monitoring.window LIKE '%' || ?3 || '%')
+                AND (?4 IS NULL OR ui_monitoring.timestamp >= ?4)
+                AND (?5 IS NULL OR ui_monitoring.timestamp <= ?5)
+                AND (?6 IS NULL OR COALESCE(ui_monitoring.text_length, LENGTH(ui_monitoring.text_output)) >= ?6)
+                AND (?7 IS NULL OR COALESCE(ui_monitoring.text_length, LENGTH(ui_monitoring.text_output)) <= ?7)
+                AND score < ?9
+            ORDER BY score ASC, ui_monitoring.id DESC
+            LIMIT ?8
+            "#,
+        )
+        .bind(embedding.as_bytes())
+        .bind(app_name.filter(|a| !a.is_empty()))
+        .bind(window_name.filter(|w| !w.is_empty()))
+        .bind(start_time)
+        .bind(end_time)
+        .bind(min_length.map(|l| l as i64))
+        .bind(max_length.map(|l| l as i64))
+        .bind(candidates)
+        .bind(threshold)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn rank_audio_keyword(
+        &self,
+        query: &str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<&[i64]>,
+        candidates: u32,
+    ) -> Result<Vec<RankedCandidate>, sqlx::Error> {
+        let speaker_ids_json = serde_json::to_string(speaker_ids.unwrap_or_default())
+            .unwrap_or_else(|_| "[]".to_string());
+
+        sqlx::query_as(
+            r#"
+            WITH matches AS MATERIALIZED (
+                SELECT rowid, bm25(audio_transcriptions_fts) as score
+                FROM audio_transcriptions_fts
+                WHERE audio_transcriptions_fts MATCH ?1
+            )
+            SELECT audio_transcriptions.id as id, MIN(matches.score) as score
+            FROM matches
+            JOIN audio_transcriptions ON audio_transcriptions.id = matches.rowid
+            LEFT JOIN speakers ON audio_transcriptions.speaker_id = speakers.id
+            WHERE (?2 IS NULL OR audio_transcriptions.timestamp >= ?2)
+                AND (?3 IS NULL OR audio_transcriptions.timestamp <= ?3)
+                AND (?4 IS NULL OR COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) >= ?4)
+                AND (?5 IS NULL OR COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) <= ?5)
+                AND (speakers.id IS NULL OR speakers.hallucination = 0)
+                AND (json_array_length(?6) = 0 OR audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?6)))
+                AND audio_transcriptions.duplicate_of IS NULL
+            GROUP BY audio_transcriptions.id
+            ORDER BY score ASC, audio_transcriptions.id DESC
+            LIMIT ?7
+            "#,
+        )
+        .bind(query)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(min_length.map(|l| l as i64))
+        .bind(max_length.map(|l| l as i64))
+        .bind(speaker_ids_json)
+        .bind(candidates)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn rank_ui_keyword(
+        &self,
+        query: &str,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        candidates: u32,
+    ) -> Result<Vec<RankedCandidate>, sqlx::Error> {
+        let mut fts_parts = vec![query.to_owned()];
+        if let Some(app) = app_name.filter(|a| !a.is_empty()) {
+            fts_parts.push(format!("app:{}", app));
+        }
+        if let Some(window) = window_name.filter(|w| !w.is_empty()) {
+            fts_parts.push(format!("window:{}", window));
+        }
+
+        sqlx::query_as(
+            r#"
+            WITH matches AS MATERIALIZED (
+                SELECT ui_id, bm25(ui_monitoring_fts) as score
+                FROM ui_monitoring_fts
+                WHERE ui_monitoring_fts MATCH ?1
+            )
+            SELECT ui_monitoring.id as id, MIN(matches.score) as score
+            FROM matches
+            JOIN ui_monitoring ON matches.ui_id = ui_monitoring.id
+            WHERE (?2 IS NULL OR ui_monitoring.timestamp >= ?2)
+                AND (?3 IS NULL OR ui_monitoring.timestamp <= ?3)
+                AND (?4 IS NULL OR COALESCE(ui_monitoring.text_length, LENGTH(ui_monitoring.text_output)) >= ?4)
+                AND (?5 IS NULL OR COALESCE(ui_monitoring.text_length, LENGTH(ui_monitoring.text_output)) <= ?5)
+            GROUP BY ui_monitoring.id
+            ORDER BY score ASC, ui_monitoring.id DESC
+            LIMIT ?6
+            "#,
+        )
+        .bind(fts_parts.join(" "))
+        .bind(start_time)
+        .bind(end_time)
+        .bind(min_length.map(|l| l as i64))
+        .bind(max_length.map(|l| l as i64))
+        .bind(candidates)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub(crate) async fn get_ocr_results_by_frame_ids(
+        &self,
+        frame_ids: &[i64],
+    ) -> Result<HashMap<i64, OCRResult>, sqlx::Error> {
+        if frame_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let raw: Vec<OCRResultRaw> = sqlx::query_as(
+            r#"
+            SELECT
+                ocr_text.frame_id,
+                ocr_text.text as ocr_text,
+                ocr_text.text_json,
+                frames.timestamp,
+                frames.name as frame_name,
+                video_chunks.file_path,
+                frames.offset_index,
+                frames.app_name,
+                ocr_text.ocr_engine,
+                frames.window_name,
+                video_chunks.device_name,
+                GROUP_CONCAT(tags.name, ',') as tags,
+                frames.browser_url,
+                frames.focused
+            FROM frames
+            JOIN video_chunks ON frames.video_chunk_id = video_chunks.id
+            JOIN ocr_text ON frames.id = ocr_text.frame_id
+            LEFT JOIN vision_tags ON frames.id = vision_tags.vision_id
+            LEFT JOIN tags ON vision_tags.tag_id = tags.id
+            WHERE frames.id IN (SELECT value FROM json_each(?1))
+            GROUP BY frames.id
+            "#,
+        )
+        .bind(serde_json::to_string(frame_ids).unwrap_or_else(|_| "[]".to_string()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(raw
+            .into_iter()
+            .map(|raw| (raw.frame_id, OCRResult::from(raw)))
+            .collect())
+    }
+
+    pub(crate) async fn get_audio_results_by_ids(
+        &self,
+        ids: &[i64],
+    ) -> Result<HashMap<i64, AudioResult>, sqlx::Error> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows: Vec<AudioResultRaw> = sqlx::query_as(
+            r#"
+            SELECT
+                audio_transcriptions.id as transcription_id,
+                audio_transcriptions.audio_chunk_id,
+                audio_transcriptions.transcription,
+                audio_transcriptions.timestamp,
+                audio_chunks.file_path,
+                audio_transcriptions.offset_index,
+                audio_transcriptions.transcription_engine,
+                GROUP_CONCAT(tags.name, ',') as tags,
+                audio_transcriptions.device as device_name,
+                audio_transcriptions.is_input_device,
+                audio_transcriptions.speaker_id,
+                audio_transcriptions.start_time,
+                audio_transcriptions.end_time,
+                audio_transcriptions.words_json
+            FROM audio_transcriptions
+            JOIN audio_chunks ON audio_transcriptions.audio_chunk_id = audio_chunks.id
+            LEFT JOIN audio_tags ON audio_chunks.id = audio_tags.audio_chunk_id
+            LEFT JOIN tags ON audio_tags.tag_id = tags.id
+            WHERE audio_transcriptions.id IN (SELECT value FROM json_each(?1))
+            GROUP BY audio_transcriptions.id
+            "#,
+        )
+        .bind(serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let futures = rows.into_iter().map(|raw| async move {
+            let speaker = match raw.speaker_id {
+                Some(id) => self.get_speaker_by_id(id).await.ok(),
+                None => None,
+            };
+            Ok::<_, sqlx::Error>((
+                raw.transcription_id,
+                AudioResult {
+                    transcription_id: raw.transcription_id,
+                    audio_chunk_id: raw.audio_chunk_id,
+                    transcription: raw.transcription,
+                    timestamp: raw.timestamp,
+                    file_path: raw.file_path,
+                    offset_index: raw.offset_index,
+                    transcription_engine: raw.transcription_engine,
+                    tags: raw
+                        .tags
+                        .map(|s| s.split(',').map(|s| s.to_owned()).collect())
+                        .unwrap_or_default(),
+                    device_name: raw.device_name,
+                    device_type: if raw.is_input_device {
+                        DeviceType::Input
+                    } else {
+                        DeviceType::Output
+                    },
+                    speaker,
+                    start_time: raw.start_time,
+                    end_time: raw.end_time,
+                    words: TranscriptionWord::from_json(raw.words_json.as_deref()),
+                },
+            ))
+        });
+
+        Ok(try_join_all(futures).await?.into_iter().collect())
+    }
+
+    pub(crate) async fn get_ui_results_by_ids(
+        &self,
+        ids: &[i64],
+    ) -> Result<HashMap<i64, UiContent>, sqlx::Error> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows: Vec<UiContent> = sqlx::query_as(
+            r#"
+            SELECT
+                ui_monitoring.id,
+                ui_monitoring.text_output,
+                ui_monitoring.timestamp,
+                ui_monitoring.app as app_name,
+                ui_monitoring.window as window_name,
+                ui_monitoring.initial_traversal_at,
+                video_chunks.file_path,
+                frames.offset_index,
+                frames.name as frame_name,
+                frames.browser_url
+            FROM ui_monitoring
+            LEFT JOIN frames ON
+                frames.timestamp BETWEEN
+                    datetime(ui_monitoring.timestamp, '-1 seconds')
+                    AND datetime(ui_monitoring.timestamp, '+1 seconds')
+            LEFT JOIN video_chunks ON frames.video_chunk_id = video_chunks.id
+            WHERE ui_monitoring.id IN (SELECT value FROM json_each(?1))
+            GROUP BY ui_monitoring.id
+            "#,
+        )
+        .bind(serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| (row.id, row)).collect())
+    }
+}
+
+fn rrf(k: u32, rank: usize) -> f64 {
+    1.0 / (k as f64 + rank as f64)
+}
+
+fn frame_fts_query(
+    app_name: Option<&str>,
+    window_name: Option<&str>,
+    browser_url: Option<&str>,
+    focused: Option<bool>,
+    frame_name: Option<&str>,
+) -> String {
+    let mut parts = Vec::new();
+    if let Some(app) = app_name.filter(|a| !a.is_empty()) {
+        parts.push(format!("app_name:{}", app));
+    }
+    if let Some(window) = window_name.filter(|w| !w.is_empty()) {
+        parts.push(format!("window_name:{}", window));
+    }
+    if let Some(browser) = browser_url.filter(|b| !b.is_empty()) {
+        parts.push(format!("browser_url:{}", browser));
+    }
+    if let Some(is_focused) = focused {
+        parts.push(format!("focused:{}", if is_focused { "1" } else { "0" }));
+    }
+    if let Some(name) = frame_name.filter(|n| !n.is_empty()) {
+        parts.push(format!("name:{}", name));
+    }
+    parts.join(" ")
+}
+
+fn frame_fts_join(frame_query: &str) -> &'static str {
+    if frame_query.is_empty() {
+        ""
+    } else {
+        "JOIN frames_fts ON frames.id = frames_fts.id"
+    }
+}
+
+fn frame_fts_condition(frame_query: &str) -> &'static str {
+    if frame_query.is_empty() {
+        ""
+    } else {
+        "AND frames_fts MATCH ?2"
+    }
+}
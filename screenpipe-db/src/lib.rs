@@ -1,11 +1,25 @@
+mod archive_db;
+mod audio_dedup_db;
+mod audio_import_db;
 mod db;
+mod embedding_db;
+mod hybrid_db;
 mod migration_worker;
+mod ocr_queue_db;
+mod raw_sql_db;
+mod redaction_db;
+mod retention_db;
+mod retranscription_db;
+mod speaker_db;
+mod token_db;
 mod types;
 mod video_db;
 
 pub use db::DatabaseManager;
+pub use hybrid_db::DEFAULT_RRF_K;
 pub use migration_worker::{
-    create_migration_worker, MigrationCommand, MigrationConfig, MigrationResponse, MigrationStatus,
-    MigrationWorker,
+    create_migration_worker, create_migration_worker_for_job, EmbeddingFn, MigrationCommand,
+    MigrationConfig, MigrationJob, MigrationResponse, MigrationStatus, MigrationWorker,
 };
+pub use speaker_db::{normalize_embedding, MAX_CENTROID_WEIGHT};
 pub use types::*;
@@ -1,5 +1,6 @@
 use anyhow::{anyhow, Result};
 use chrono::Utc;
+use futures::future::BoxFuture;
 use serde::Serialize;
 use sqlx::{Row, SqlitePool};
 use std::sync::{
@@ -12,6 +13,46 @@ use tracing::{debug, error, info, warn};
 
 use crate::DatabaseManager;
 
+const OCR_TEXT_TO_FRAMES: &str = "ocr_text_to_frames";
+const TEXT_EMBEDDINGS: &str = "text_embeddings";
+const SPEAKER_REATTRIBUTION: &str = "speaker_reattribution";
+
+/// Cosine distance under which a past transcription is moved to a named speaker. Stricter than
+/// live matching since it rewrites history.
+const REATTRIBUTION_MAX_DISTANCE: f64 = 0.4;
+
+/// Computes the embedding of a text. Supplied by the caller so the database crate does not
+/// depend on a particular embedding model.
+pub type EmbeddingFn = Arc<dyn Fn(String) -> BoxFuture<'static, Result<Vec<f32>>> + Send + Sync>;
+
+/// Background migrations the worker can run
+#[derive(Clone)]
+pub enum MigrationJob {
+    /// Copy app, window and focus metadata from ocr_text to frames
+    OcrTextToFrames,
+    /// Generate embeddings for audio transcriptions and UI monitoring text that has none
+    TextEmbeddings(EmbeddingFn),
+    /// Move transcriptions of unnamed speakers to the named speaker whose voice they match
+    SpeakerReattribution,
+}
+
+impl MigrationJob {
+    /// Name used on the command line and in the migration_progress table
+    pub fn name(&self) -> &'static str {
+        match self {
+            MigrationJob::OcrTextToFrames => OCR_TEXT_TO_FRAMES,
+            MigrationJob::TextEmbeddings(_) => TEXT_EMBEDDINGS,
+            MigrationJob::SpeakerReattribution => SPEAKER_REATTRIBUTION,
+        }
+    }
+}
+
+impl std::fmt::Debug for MigrationJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 /// Status of a migration job
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum MigrationStatus {
@@ -83,6 +124,7 @@ impl Default for MigrationConfig {
 /// Worker that handles background migration of data
 pub struct MigrationWorker {
     db: Arc<DatabaseManager>,
+    job: MigrationJob,
     status: MigrationStatus,
     is_running: Arc<AtomicBool>,
     is_paused: Arc<AtomicBool>,
@@ -102,6 +144,7 @@ impl MigrationWorker {
     ) -> Self {
         Self {
             db,
+            job: MigrationJob::OcrTextToFrames,
             status: MigrationStatus::NotStarted,
             is_running: Arc::new(AtomicBool::new(false)),
             is_paused: Arc::new(AtomicBool::new(false)),
@@ -112,6 +155,12 @@ impl MigrationWorker {
         }
     }
 
+    /// Run `job` instead of the default ocr_text to frames migration
+    pub fn with_job(mut self, job: MigrationJob) -> Self {
+        self.job = job;
+        self
+    }
+
     /// Start the migration worker to process commands
     pub fn start(mut self) -> JoinHandle<()> {
         tokio::spawn(async move {
@@ -165,6 +214,7 @@ impl MigrationWorker {
 
         // Create clones of shared resources for the worker task
         let db = self.db.clone();
+        let job = self.job.clone();
         let config = self.config.clone();
         let is_running = self.is_running.clone();
         let is_paused = self.is_paused.clone();
@@ -173,14 +223,39 @@ impl MigrationWorker {
         // Spawn a task to handle the actual migration work
         let handle = tokio::spawn(async move {
             // Start the migration process
-            let result = migrate_ocr_data_to_frames(
-                &db.pool,
-                config,
-                is_running.clone(),
-                is_paused.clone(),
-                status_tx.clone(),
-            )
-            .await;
+            let result = match &job {
+                MigrationJob::OcrTextToFrames => {
+                    migrate_ocr_data_to_frames(
+                        &db.pool,
+                        config,
+                        is_running.clone(),
+                        is_paused.clone(),
+                        status_tx.clone(),
+                    )
+                    .await
+                }
+                MigrationJob::TextEmbeddings(embed) => {
+                    backfill_text_embeddings(
+                        &db,
+                        embed.clone(),
+                        config,
+                        is_running.clone(),
+                        is_paused.clone(),
+                        status_tx.clone(),
+                    )
+                    .await
+                }
+                MigrationJob::SpeakerReattribution => {
+                    reattribute_speakers(
+                        &db,
+                        config,
+                        is_running.clone(),
+                        is_paused.clone(),
+                        status_tx.clone(),
+                    )
+                    .await
+                }
+            };
 
             // Handle completion or failure
             match result {
@@ -200,8 +275,16 @@ impl MigrationWorker {
                     error!("Migration failed: {error_msg}");
 
                     // Try to get the current progress
-                    let processed = get_migration_progress(&db.pool).await.unwrap_or(0);
-                    let total = get_total_records(&db.pool).await.unwrap_or(0);
+                    let processed = get_migration_progress(&db.pool, job.name())
+                        .await
+                        .unwrap_or(0);
+                    let total = match &job {
+                        MigrationJob::OcrTextToFrames => get_total_records(&db.pool).await,
+                        MigrationJob::TextEmbeddings(_) | MigrationJob::SpeakerReattribution => {
+                            get_migration_total(&db.pool, job.name()).await
+                        }
+                    }
+                    .unwrap_or(0);
 
                     let _ = status_tx
                         .send(MigrationResponse {
@@ -268,16 +351,17 @@ async fn ensure_migration_table(pool: &SqlitePool) -> Result<()> {
 }
 
 /// Get the current migration progress
-async fn get_migration_progress(pool: &SqlitePool) -> Result<i64> {
+async fn get_migration_progress(pool: &SqlitePool, migration_name: &str) -> Result<i64> {
     ensure_migration_table(pool).await?;
 
     let progress = sqlx::query_scalar::<_, i64>(
         r#"
         SELECT processed_records FROM migration_progress 
-        WHERE migration_name = 'ocr_text_to_frames'
+        WHERE migration_name = ?
         ORDER BY id DESC LIMIT 1
         "#,
     )
+    .bind(migration_name)
     .fetch_optional(pool)
     .await?
     .unwrap_or(0);
@@ -285,6 +369,25 @@ async fn get_migration_progress(pool: &SqlitePool) -> Result<i64> {
     Ok(progress)
 }
 
+/// Get the total recorded when the migration last reported progress
+async fn get_migration_total(pool: &SqlitePool, migration_name: &str) -> Result<i64> {
+    ensure_migration_table(pool).await?;
+
+    let total = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT total_records FROM migration_progress
+        WHERE migration_name = ?
+        ORDER BY id DESC LIMIT 1
+        "#,
+    )
+    .bind(migration_name)
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or(0);
+
+    Ok(total)
+}
+
 /// Get the total number of records to migrate
 async fn get_total_records(pool: &SqlitePool) -> Result<i64> {
     let total = sqlx::query_scalar::<_, i64>(
@@ -304,6 +407,7 @@ async fn get_total_records(pool: &SqlitePool) -> Result<i64> {
 /// Update the migration progress
 async fn update_migration_progress(
     pool: &SqlitePool,
+    migration_name: &str,
     last_id: i64,
     total: i64,
     processed: i64,
@@ -318,9 +422,10 @@ async fn update_migration_progress(
     let existing = sqlx::query_scalar::<_, i64>(
         r#"
         SELECT id FROM migration_progress 
-        WHERE migration_name = 'ocr_text_to_frames'
+        WHERE migration_name = ?
         "#,
     )
+    .bind(migration_name)
     .fetch_optional(pool)
     .await?;
 
@@ -354,7 +459,7 @@ async fn update_migration_progress(
             VALUES (?, ?, ?, ?, ?, ?, ?)
             "#,
         )
-        .bind("ocr_text_to_frames")
+        .bind(migration_name)
         .bind(last_id)
         .bind(total)
         .bind(processed)
@@ -393,15 +498,16 @@ async fn migrate_ocr_data_to_frames(
     let last_processed_id = sqlx::query_scalar::<_, i64>(
         r#"
         SELECT last_processed_id FROM migration_progress 
-        WHERE migration_name = 'ocr_text_to_frames'
+        WHERE migration_name = ?
         ORDER BY id DESC LIMIT 1
         "#,
     )
+    .bind(OCR_TEXT_TO_FRAMES)
     .fetch_optional(pool)
     .await?
     .unwrap_or(0);
 
-    let mut processed_records = get_migration_progress(pool).await?;
+    let mut processed_records = get_migration_progress(pool, OCR_TEXT_TO_FRAMES).await?;
 
     info!(
         "Starting migration: total_records={}, already_processed={}, last_processed_id={}",
@@ -454,6 +560,7 @@ async fn migrate_ocr_data_to_frames(
                 // Update progress
                 if let Err(e) = update_migration_progress(
                     pool,
+                    OCR_TEXT_TO_FRAMES,
                     last_id,
                     total_records,
                     processed_records,
@@ -497,7 +604,15 @@ async fn migrate_ocr_data_to_frames(
 
     // Final update to mark as completed
     if processed_records >= total_records {
-        update_migration_progress(pool, last_id, total_records, processed_records, true).await?;
+        update_migration_progress(
+            pool,
+            OCR_TEXT_TO_FRAMES,
+            last_id,
+            total_records,
+            processed_records,
+            true,
+        )
+        .await?;
     }
 
     Ok((processed_records, duration))
@@ -571,6 +686,267 @@ async fn process_batch(pool: &SqlitePool, last_id: i64, batch_size: i64) -> Resu
     Ok((count, max_id))
 }
 
+/// Generate embeddings for every audio transcription and UI monitoring row that lacks one.
+///
+/// Rows without an embedding are the natural resume point, so a stopped backfill simply
+/// starts over with whatever is left.
+async fn backfill_text_embeddings(
+    db: &DatabaseManager,
+    embed: EmbeddingFn,
+    config: MigrationConfig,
+    is_running: Arc<AtomicBool>,
+    is_paused: Arc<AtomicBool>,
+    status_tx: mpsc::Sender<MigrationResponse>,
+) -> Result<(i64, u64)> {
+    let start_time = std::time::Instant::now();
+
+    ensure_migration_table(&db.pool).await?;
+
+    let total_records = db.count_text_without_embeddings().await?;
+
+    if total_records == 0 {
+        info!("No records to migrate");
+        return Ok((0, 0));
+    }
+
+    info!(
+        "Starting embedding backfill: total_records={}",
+        total_records
+    );
+
+    let _ = status_tx
+        .send(MigrationResponse {
+            status: MigrationStatus::Running {
+                total_records,
+                processed_records: 0,
+            },
+        })
+        .await;
+
+    let mut processed_records = 0;
+    // rows that failed are skipped for the rest of this run instead of being retried forever
+    let mut last_audio_id = 0;
+    let mut last_ui_id = 0;
+    let mut finished = false;
+
+    while is_running.load(Ordering::SeqCst) {
+        while is_paused.load(Ordering::SeqCst) && is_running.load(Ordering::SeqCst) {
+            let _ = status_tx
+                .send(MigrationResponse {
+                    status: MigrationStatus::Paused {
+                        total_records,
+                        processed_records,
+                    },
+                })
+                .await;
+            time::sleep(Duration::from_millis(500)).await;
+        }
+
+        if !is_running.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let mut batch = db
+            .get_audio_transcriptions_without_embeddings(last_audio_id, config.batch_size)
+            .await?;
+        let is_audio = !batch.is_empty();
+        if !is_audio {
+            batch = db
+                .get_ui_monitoring_without_embeddings(last_ui_id, config.batch_size)
+                .await?;
+        }
+        if batch.is_empty() {
+            finished = true;
+            break;
+        }
+
+        for row in batch {
+            if !is_running.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let result = match embed(row.text).await {
+                Ok(embedding) if is_audio => db
+                    .insert_audio_transcription_embedding(row.id, &embedding)
+                    .await
+                    .map_err(Into::into),
+                Ok(embedding) => db
+                    .insert_ui_monitoring_embedding(row.id, &embedding)
+                    .await
+                    .map_err(Into::into),
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = result {
+                if !config.continue_on_error {
+                    return Err(anyhow!("Migration failed: {}", e));
+                }
+                warn!("Failed to generate embedding for row {}: {}", row.id, e);
+            }
+
+            if is_audio {
+                last_audio_id = row.id;
+            } else {
+                last_ui_id = row.id;
+            }
+            processed_records += 1;
+        }
+
+        if let Err(e) = update_migration_progress(
+            &db.pool,
+            TEXT_EMBEDDINGS,
+            if is_audio { last_audio_id } else { last_ui_id },
+            total_records,
+            processed_records,
+            false,
+        )
+        .await
+        {
+            warn!("Failed to update migration progress: {}", e);
+        }
+
+        let _ = status_tx
+            .send(MigrationResponse {
+                status: MigrationStatus::Running {
+                    total_records,
+                    processed_records,
+                },
+            })
+            .await;
+
+        time::sleep(Duration::from_millis(config.batch_delay_ms)).await;
+    }
+
+    if finished {
+        update_migration_progress(
+            &db.pool,
+            TEXT_EMBEDDINGS,
+            last_ui_id,
+            total_records,
+            processed_records,
+            true,
+        )
+        .await?;
+    }
+
+    Ok((processed_records, start_time.elapsed().as_secs()))
+}
+
+/// Re-attribute past transcriptions to named speakers, e.g. after enrolling someone.
+///
+/// Only transcriptions recorded with a stored voice embedding can be re-attributed. Running it
+/// again is harmless, so a stopped run starts over.
+async fn reattribute_speakers(
+    db: &DatabaseManager,
+    config: MigrationConfig,
+    is_running: Arc<AtomicBool>,
+    is_paused: Arc<AtomicBool>,
+    status_tx: mpsc::Sender<MigrationResponse>,
+) -> Result<(i64, u64)> {
+    let start_time = std::time::Instant::now();
+
+    ensure_migration_table(&db.pool).await?;
+
+    let total_records = db.count_transcription_speaker_embeddings().await?;
+
+    if total_records == 0 {
+        info!("No records to migrate");
+        return Ok((0, 0));
+    }
+
+    info!(
+        "Starting speaker re-attribution: total_records={}",
+        total_records
+    );
+
+    let _ = status_tx
+        .send(MigrationResponse {
+            status: MigrationStatus::Running {
+                total_records,
+                processed_records: 0,
+            },
+        })
+        .await;
+
+    let mut processed_records = 0;
+    let mut reattributed = 0;
+    let mut last_id = 0;
+    let mut finished = false;
+
+    while is_running.load(Ordering::SeqCst) {
+        while is_paused.load(Ordering::SeqCst) && is_running.load(Ordering::SeqCst) {
+            let _ = status_tx
+                .send(MigrationResponse {
+                    status: MigrationStatus::Paused {
+                        total_records,
+                        processed_records,
+                    },
+                })
+                .await;
+            time::sleep(Duration::from_millis(500)).await;
+        }
+
+        if !is_running.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let batch = db
+            .reattribute_speakers(last_id, config.batch_size, REATTRIBUTION_MAX_DISTANCE)
+            .await?;
+        if batch.processed == 0 {
+            finished = true;
+            break;
+        }
+
+        processed_records += batch.processed;
+        reattributed += batch.reattributed;
+        last_id = batch.last_id;
+
+        if let Err(e) = update_migration_progress(
+            &db.pool,
+            SPEAKER_REATTRIBUTION,
+            last_id,
+            total_records,
+            processed_records,
+            false,
+        )
+        .await
+        {
+            warn!("Failed to update migration progress: {}", e);
+        }
+
+        let _ = status_tx
+            .send(MigrationResponse {
+                status: MigrationStatus::Running {
+                    total_records,
+                    processed_records,
+                },
+            })
+            .await;
+
+        time::sleep(Duration::from_millis(config.batch_delay_ms)).await;
+    }
+
+    info!(
+        "Speaker re-attribution moved {} of {} transcriptions",
+        reattributed, processed_records
+    );
+
+    if finished {
+        update_migration_progress(
+            &db.pool,
+            SPEAKER_REATTRIBUTION,
+            last_id,
+            total_records,
+            processed_records,
+            true,
+        )
+        .await?;
+    }
+
+    Ok((processed_records, start_time.elapsed().as_secs()))
+}
+
 impl MigrationConfig {
     pub fn new(batch_size: i64, batch_delay_ms: u64, continue_on_error: bool) -> Self {
         Self {
@@ -599,3 +975,24 @@ pub fn create_migration_worker(
 
     (cmd_tx, status_rx, handle)
 }
+
+/// Like [`create_migration_worker`], running `job` instead of the default migration
+pub fn create_migration_worker_for_job(
+    db: Arc<DatabaseManager>,
+    job: MigrationJob,
+    config: Option<MigrationConfig>,
+) -> (
+    mpsc::Sender<MigrationCommand>,
+    mpsc::Receiver<MigrationResponse>,
+    JoinHandle<()>,
+) {
+    let (cmd_tx, cmd_rx) = mpsc::channel(100);
+    let (status_tx, status_rx) = mpsc::channel(100);
+
+    let worker =
+        MigrationWorker::new(db, cmd_rx, status_tx, config.unwrap_or_default()).with_job(job);
+
+    let handle = worker.start();
+
+    (cmd_tx, status_rx, handle)
+}
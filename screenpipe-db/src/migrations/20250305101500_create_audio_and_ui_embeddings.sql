@@ -0,0 +1,22 @@
+-- Embeddings for audio transcriptions and ui monitoring text, next to ocr_text_embeddings
+CREATE TABLE IF NOT EXISTS audio_transcription_embeddings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    audio_transcription_id INTEGER NOT NULL,
+    embedding BLOB NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (audio_transcription_id) REFERENCES audio_transcriptions(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_audio_transcription_embeddings_transcription_id
+    ON audio_transcription_embeddings(audio_transcription_id);
+
+CREATE TABLE IF NOT EXISTS ui_monitoring_embeddings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ui_monitoring_id INTEGER NOT NULL,
+    embedding BLOB NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (ui_monitoring_id) REFERENCES ui_monitoring(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_ui_monitoring_embeddings_ui_monitoring_id
+    ON ui_monitoring_embeddings(ui_monitoring_id);
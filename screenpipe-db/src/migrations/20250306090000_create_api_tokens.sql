@@ -0,0 +1,15 @@
+-- Bearer tokens for the http api, only the sha256 of a token is stored
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL,
+    -- comma separated, e.g. "read-search,write"
+    scopes TEXT NOT NULL,
+    -- set for tokens issued to a pipe when it starts
+    pipe_id TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at DATETIME
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_api_tokens_token_hash ON api_tokens(token_hash);
+CREATE INDEX IF NOT EXISTS idx_api_tokens_pipe_id ON api_tokens(pipe_id);
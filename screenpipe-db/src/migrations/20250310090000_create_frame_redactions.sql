@@ -0,0 +1,18 @@
+-- Regions of a frame that were blurred or blacked out before it was written to the video chunk
+CREATE TABLE IF NOT EXISTS frame_redactions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    frame_id INTEGER NOT NULL,
+    -- what was detected in the region, e.g. "CREDIT_CARD"
+    pii_type TEXT NOT NULL,
+    -- "blur" or "black-box"
+    mode TEXT NOT NULL,
+    -- pixels in the full frame
+    x INTEGER NOT NULL,
+    y INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (frame_id) REFERENCES frames(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_frame_redactions_frame_id ON frame_redactions(frame_id);
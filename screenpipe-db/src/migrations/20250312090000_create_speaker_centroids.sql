@@ -0,0 +1,25 @@
+-- One mean voice embedding per speaker, updated as segments are attributed to them.
+-- Matching against it instead of the first embedding seen keeps a voice from splitting into
+-- many speakers as microphones and rooms change.
+CREATE TABLE IF NOT EXISTS speaker_centroids (
+    speaker_id INTEGER PRIMARY KEY,
+    embedding BLOB NOT NULL,
+    sample_count INTEGER NOT NULL DEFAULT 1,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (speaker_id) REFERENCES speakers(id) ON DELETE CASCADE
+);
+
+-- Seed existing speakers with their first embedding
+INSERT OR IGNORE INTO speaker_centroids (speaker_id, embedding, sample_count)
+SELECT speaker_id, embedding, 1
+FROM speaker_embeddings
+WHERE id IN (
+    SELECT MIN(id) FROM speaker_embeddings WHERE speaker_id IS NOT NULL GROUP BY speaker_id
+);
+
+-- Voice embedding of each transcribed segment, so past transcriptions can be re-attributed
+CREATE TABLE IF NOT EXISTS audio_transcription_speaker_embeddings (
+    audio_transcription_id INTEGER PRIMARY KEY,
+    embedding BLOB NOT NULL,
+    FOREIGN KEY (audio_transcription_id) REFERENCES audio_transcriptions(id) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+-- Word timings and confidences of each transcription as a json array, like ocr_text.text_json
+ALTER TABLE audio_transcriptions ADD COLUMN words_json TEXT;
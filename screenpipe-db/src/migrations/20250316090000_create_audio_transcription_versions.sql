@@ -0,0 +1,14 @@
+-- Every engine's transcription of a segment once it has been re-transcribed. The best one is
+-- also stored in audio_transcriptions, which is what search reads.
+CREATE TABLE IF NOT EXISTS audio_transcription_versions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    audio_transcription_id INTEGER NOT NULL,
+    transcription TEXT NOT NULL,
+    transcription_engine TEXT NOT NULL,
+    words_json TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (audio_transcription_id) REFERENCES audio_transcriptions(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_audio_transcription_versions_engine
+    ON audio_transcription_versions(audio_transcription_id, transcription_engine);
@@ -0,0 +1,3 @@
+-- Transcription of the same speech recorded by another device, set on the copy left out of search
+ALTER TABLE audio_transcriptions ADD COLUMN duplicate_of INTEGER DEFAULT NULL;
+CREATE INDEX IF NOT EXISTS idx_audio_transcriptions_duplicate_of ON audio_transcriptions(duplicate_of);
@@ -0,0 +1,16 @@
+-- Windows recorded without their text, waiting to be OCR'd by the ocr workers
+CREATE TABLE IF NOT EXISTS ocr_queue (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    frame_id INTEGER NOT NULL,
+    -- jpeg of the window
+    image BLOB NOT NULL,
+    -- failed OCR attempts, the window is dropped after a few
+    attempts INTEGER NOT NULL DEFAULT 0,
+    -- set while a worker OCRs the window, cleared on startup for OCR that was interrupted
+    claimed_at DATETIME DEFAULT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (frame_id) REFERENCES frames(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ocr_queue_claimed_at ON ocr_queue(claimed_at);
+CREATE INDEX IF NOT EXISTS idx_ocr_queue_frame_id ON ocr_queue(frame_id);
@@ -0,0 +1,71 @@
+-- The audio fts triggers matched rows on audio_chunk_id, so updating or deleting one segment
+-- rewrote or removed the fts rows of every segment of its chunk. Rows are now keyed on the
+-- transcription id and only touched when the text changes.
+PRAGMA foreign_keys = OFF;
+
+DROP TRIGGER IF EXISTS audio_transcriptions_ai;
+DROP TRIGGER IF EXISTS audio_transcriptions_update;
+DROP TRIGGER IF EXISTS audio_transcriptions_delete;
+DROP TABLE IF EXISTS audio_transcriptions_fts;
+
+CREATE VIRTUAL TABLE IF NOT EXISTS audio_transcriptions_fts USING fts5(
+    transcription,
+    device,
+    audio_chunk_id UNINDEXED,
+    speaker_id,
+    start_time UNINDEXED,
+    end_time UNINDEXED,
+    tokenize='unicode61'
+);
+
+INSERT INTO audio_transcriptions_fts(rowid, transcription, device, audio_chunk_id, speaker_id, start_time, end_time)
+SELECT
+    id,
+    transcription,
+    COALESCE(device, ''),
+    audio_chunk_id,
+    speaker_id,
+    start_time,
+    end_time
+FROM audio_transcriptions
+WHERE transcription IS NOT NULL
+  AND transcription != ''
+  AND audio_chunk_id IS NOT NULL;
+
+CREATE TRIGGER IF NOT EXISTS audio_transcriptions_ai AFTER INSERT ON audio_transcriptions
+WHEN NEW.transcription IS NOT NULL AND NEW.transcription != '' AND NEW.audio_chunk_id IS NOT NULL
+BEGIN
+    INSERT OR REPLACE INTO audio_transcriptions_fts(rowid, transcription, device, audio_chunk_id, speaker_id, start_time, end_time)
+    VALUES (
+        NEW.id,
+        NEW.transcription,
+        COALESCE(NEW.device, ''),
+        NEW.audio_chunk_id,
+        NEW.speaker_id,
+        NEW.start_time,
+        NEW.end_time
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS audio_transcriptions_update AFTER UPDATE ON audio_transcriptions
+WHEN OLD.transcription IS NOT NEW.transcription
+BEGIN
+    DELETE FROM audio_transcriptions_fts WHERE rowid = OLD.id;
+    INSERT INTO audio_transcriptions_fts(rowid, transcription, device, audio_chunk_id, speaker_id, start_time, end_time)
+    SELECT
+        NEW.id,
+        NEW.transcription,
+        COALESCE(NEW.device, ''),
+        NEW.audio_chunk_id,
+        NEW.speaker_id,
+        NEW.start_time,
+        NEW.end_time
+    WHERE NEW.transcription IS NOT NULL AND NEW.transcription != '' AND NEW.audio_chunk_id IS NOT NULL;
+END;
+
+CREATE TRIGGER IF NOT EXISTS audio_transcriptions_delete AFTER DELETE ON audio_transcriptions
+BEGIN
+    DELETE FROM audio_transcriptions_fts WHERE rowid = OLD.id;
+END;
+
+PRAGMA foreign_keys = ON;
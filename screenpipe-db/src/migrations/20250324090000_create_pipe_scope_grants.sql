@@ -0,0 +1,8 @@
+-- Scopes the user granted to a pipe, beyond read-search, with `screenpipe token grant-pipe`.
+-- Kept here rather than in the pipe's own files so a pipe can't grant itself scopes.
+CREATE TABLE IF NOT EXISTS pipe_scope_grants (
+    pipe_id TEXT PRIMARY KEY,
+    -- comma separated, e.g. "write,raw-sql"
+    scopes TEXT NOT NULL,
+    granted_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,3 @@
+-- Windows recorded while the selected OCR engine was unavailable, whose frame already has text
+-- from a fallback engine, or none. Their text is replaced once the selected engine OCRs them.
+ALTER TABLE ocr_queue ADD COLUMN replaces_text BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,5 @@
+-- Windows put back in the queue are tried again after a delay that grows each time, so an
+-- engine that is down isn't called in a loop. `attempts` only counts failures of the window
+-- itself, `retries` every time it was put back.
+ALTER TABLE ocr_queue ADD COLUMN retries INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE ocr_queue ADD COLUMN not_before DATETIME DEFAULT NULL;
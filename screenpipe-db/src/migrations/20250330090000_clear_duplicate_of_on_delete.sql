@@ -0,0 +1,6 @@
+-- Transcriptions hidden as duplicates of one that gets deleted, e.g. by retention pruning its
+-- chunk, are searchable again instead of pointing at a row that no longer exists
+CREATE TRIGGER IF NOT EXISTS audio_transcriptions_clear_duplicate_of AFTER DELETE ON audio_transcriptions
+BEGIN
+    UPDATE audio_transcriptions SET duplicate_of = NULL WHERE duplicate_of = OLD.id;
+END;
@@ -0,0 +1,190 @@
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+use crate::{DatabaseManager, OcrEngine, OcrQueueCounts, OcrQueueJob};
+
+impl DatabaseManager {
+    /// Queues a window of a frame for OCR. When more than `max_size` windows are waiting the
+    /// oldest are dropped, their frames are kept without text. Returns how many were dropped.
+    pub async fn enqueue_ocr_job(
+        &self,
+        frame_id: i64,
+        image: &[u8],
+        max_size: usize,
+    ) -> Result<u64, sqlx::Error> {
+        self.enqueue(frame_id, image, max_size, false).await
+    }
+
+    /// Queues a window of a frame that already has text, from a fallback engine or empty
+    /// because the selected engine was unavailable, to replace its text once the engine is back.
+    /// Dropped like [`Self::enqueue_ocr_job`].
+    pub async fn enqueue_deferred_ocr_job(
+        &self,
+        frame_id: i64,
+        image: &[u8],
+        max_size: usize,
+    ) -> Result<u64, sqlx::Error> {
+        self.enqueue(frame_id, image, max_size, true).await
+    }
+
+    async fn enqueue(
+        &self,
+        frame_id: i64,
+        image: &[u8],
+        max_size: usize,
+        replaces_text: bool,
+    ) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO ocr_queue (frame_id, image, replaces_text, created_at) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(frame_id)
+        .bind(image)
+        .bind(replaces_text)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+        let dropped = sqlx::query(
+            "DELETE FROM ocr_queue WHERE id IN (SELECT id FROM ocr_queue WHERE claimed_at IS NULL ORDER BY id DESC LIMIT -1 OFFSET ?1)",
+        )
+        .bind(max_size.max(1) as i64)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+        Ok(dropped)
+    }
+
+    /// Claims the oldest waiting window, so no other worker OCRs it. Windows put back until a
+    /// later time are skipped until then.
+    pub async fn claim_ocr_job(&self) -> Result<Option<OcrQueueJob>, sqlx::Error> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        let job = sqlx::query_as::<_, OcrQueueJob>(
+            r#"SELECT q.id, q.frame_id, q.image, q.attempts, q.retries, q.replaces_text, f.app_name, f.window_name, f.focused, f.browser_url, f.timestamp
+            FROM ocr_queue q
+            LEFT JOIN frames f ON f.id = q.frame_id
+            WHERE q.claimed_at IS NULL AND (q.not_before IS NULL OR q.not_before <= ?1)
+            ORDER BY q.id
+            LIMIT 1"#,
+        )
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(job) = &job {
+            sqlx::query("UPDATE ocr_queue SET claimed_at = ?1 WHERE id = ?2")
+                .bind(now)
+                .bind(job.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(job)
+    }
+
+    /// Puts a window whose OCR failed back in the queue until `retry_at`, or drops it after
+    /// `max_attempts`. Returns whether it was dropped.
+    pub async fn release_ocr_job(
+        &self,
+        id: i64,
+        max_attempts: i64,
+        retry_at: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE ocr_queue SET attempts = attempts + 1, retries = retries + 1, not_before = ?2, claimed_at = NULL WHERE id = ?1",
+        )
+        .bind(id)
+        .bind(retry_at)
+        .execute(&mut *tx)
+        .await?;
+        let dropped = sqlx::query("DELETE FROM ocr_queue WHERE id = ?1 AND attempts >= ?2")
+            .bind(id)
+            .bind(max_attempts)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(dropped > 0)
+    }
+
+    /// Puts a window back in the queue until `retry_at` without counting a failed attempt, for
+    /// windows that couldn't be OCR'd because the engine was unavailable
+    pub async fn postpone_ocr_job(
+        &self,
+        id: i64,
+        retry_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE ocr_queue SET retries = retries + 1, not_before = ?2, claimed_at = NULL WHERE id = ?1",
+        )
+        .bind(id)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Stores the text of a queued window, replacing the text of deferred windows, and removes it
+    /// from the queue. Returns false, without storing the text, when the window left the queue
+    /// meanwhile, like when its frame was deleted.
+    pub async fn complete_ocr_job(
+        &self,
+        id: i64,
+        frame_id: i64,
+        text: &str,
+        text_json: &str,
+        ocr_engine: Arc<OcrEngine>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let replaces_text: Option<bool> =
+            sqlx::query_scalar("DELETE FROM ocr_queue WHERE id = ?1 RETURNING replaces_text")
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let Some(replaces_text) = replaces_text else {
+            tx.rollback().await?;
+            return Ok(false);
+        };
+        if replaces_text {
+            sqlx::query("DELETE FROM ocr_text WHERE frame_id = ?1")
+                .bind(frame_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("INSERT INTO ocr_text (frame_id, text, text_json, ocr_engine, text_length) VALUES (?1, ?2, ?3, ?4, ?5)")
+            .bind(frame_id)
+            .bind(text)
+            .bind(text_json)
+            .bind(ocr_engine.to_string())
+            .bind(text.len() as i64)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Puts back the windows claimed by workers that stopped before finishing, like when
+    /// screenpipe was closed. Returns how many windows were put back.
+    pub async fn reset_ocr_jobs(&self) -> Result<u64, sqlx::Error> {
+        Ok(
+            sqlx::query("UPDATE ocr_queue SET claimed_at = NULL WHERE claimed_at IS NOT NULL")
+                .execute(&self.pool)
+                .await?
+                .rows_affected(),
+        )
+    }
+
+    pub async fn count_ocr_jobs(&self) -> Result<OcrQueueCounts, sqlx::Error> {
+        sqlx::query_as::<_, OcrQueueCounts>(
+            r#"SELECT
+                COALESCE(SUM(claimed_at IS NULL), 0) AS queued,
+                COALESCE(SUM(claimed_at IS NOT NULL), 0) AS in_progress,
+                COALESCE(SUM(replaces_text), 0) AS deferred,
+                (SELECT created_at FROM ocr_queue WHERE claimed_at IS NULL ORDER BY id LIMIT 1) AS oldest_queued_at
+            FROM ocr_queue"#,
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+}
@@ -0,0 +1,210 @@
+use futures::TryStreamExt;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Column, Row, TypeInfo, ValueRef};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::{DatabaseManager, RawSqlError, RawSqlLimits, RawSqlOutput};
+
+/// Statements a read-only query may start with
+const READ_ONLY_KEYWORDS: &[&str] = &["SELECT", "WITH", "VALUES", "EXPLAIN"];
+
+/// Virtual machine instructions between two checks of the deadline
+const PROGRESS_INTERVAL_OPS: i32 = 10_000;
+
+/// Interrupts the running statement when the query future is dropped, e.g. when the http
+/// client disconnects
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+impl DatabaseManager {
+    /// Runs a single read-only statement with the default [`RawSqlLimits`] and returns its rows.
+    pub async fn execute_raw_sql(&self, query: &str) -> Result<serde_json::Value, RawSqlError> {
+        let output = self
+            .execute_raw_sql_with_limits(query, RawSqlLimits::default())
+            .await?;
+        Ok(serde_json::Value::Array(output.rows))
+    }
+
+    /// Runs a single read-only statement on a `query_only` connection.
+    ///
+    /// Anything but `SELECT`, `WITH`, `VALUES` and `EXPLAIN` is rejected before it reaches
+    /// sqlite, and sqlite refuses writes hidden in e.g. a CTE. The statement is interrupted
+    /// once `limits.timeout` has passed or when the returned future is dropped.
+    pub async fn execute_raw_sql_with_limits(
+        &self,
+        query: &str,
+        limits: RawSqlLimits,
+    ) -> Result<RawSqlOutput, RawSqlError> {
+        check_read_only_statement(query)?;
+
+        let deadline = Instant::now() + limits.timeout;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let _cancel_on_drop = CancelOnDrop(cancelled.clone());
+        let timed_out = Arc::new(AtomicBool::new(false));
+
+        let mut conn = self.read_only_pool.acquire().await?;
+        {
+            // replaces the handler left by the previous query on this connection
+            let timed_out = timed_out.clone();
+            conn.lock_handle()
+                .await?
+                .set_progress_handler(PROGRESS_INTERVAL_OPS, move || {
+                    if Instant::now() >= deadline {
+                        timed_out.store(true, Ordering::Relaxed);
+                        return false;
+                    }
+                    !cancelled.load(Ordering::Relaxed)
+                });
+        }
+
+        let mut rows = Vec::new();
+        let mut truncated = false;
+        let result = tokio::time::timeout(limits.timeout, async {
+            let mut stream = sqlx::query(query).fetch(&mut *conn);
+            while let Some(row) = stream.try_next().await? {
+                if rows.len() == limits.max_rows {
+                    truncated = true;
+                    break;
+                }
+                rows.push(row_to_json(&row));
+            }
+            Ok::<_, sqlx::Error>(())
+        })
+        .await;
+
+        match result {
+            Ok(Ok(())) => {
+                conn.lock_handle().await?.remove_progress_handler();
+                Ok(RawSqlOutput { rows, truncated })
+            }
+            Ok(Err(_)) if timed_out.load(Ordering::Relaxed) => {
+                Err(RawSqlError::TimedOut(limits.timeout))
+            }
+            Ok(Err(e)) => Err(e.into()),
+            Err(_) => Err(RawSqlError::TimedOut(limits.timeout)),
+        }
+    }
+
+    /// Runs arbitrary sql with write access in a transaction, returning the number of rows
+    /// changed. Only meant for the admin endpoint.
+    pub async fn execute_raw_sql_mutation(&self, query: &str) -> Result<u64, RawSqlError> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(query).execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+}
+
+fn row_to_json(row: &SqliteRow) -> serde_json::Value {
+    let mut map = serde_json::Map::new();
+    for (i, column) in row.columns().iter().enumerate() {
+        if let Ok(value) = row.try_get_raw(i) {
+            let json_value = match value.type_info().name() {
+                "TEXT" => {
+                    let s: String = row.try_get(i).unwrap_or_default();
+                    serde_json::Value::String(s)
+                }
+                "INTEGER" => {
+                    let i: i64 = row.try_get(i).unwrap_or_default();
+                    serde_json::Value::Number(i.into())
+                }
+                "REAL" => {
+                    let f: f64 = row.try_get(i).unwrap_or_default();
+                    serde_json::Value::Number(serde_json::Number::from_f64(f).unwrap_or(0.into()))
+                }
+                _ => serde_json::Value::Null,
+            };
+            map.insert(column.name().to_string(), json_value);
+        }
+    }
+    serde_json::Value::Object(map)
+}
+
+/// Accepts a single statement that starts with one of [`READ_ONLY_KEYWORDS`]
+fn check_read_only_statement(query: &str) -> Result<(), RawSqlError> {
+    let statements = split_statements(query);
+    let statement = match statements.as_slice() {
+        [] => return Err(RawSqlError::Rejected("empty query".to_string())),
+        [statement] => statement,
+        _ => {
+            return Err(RawSqlError::Rejected(
+                "only a single statement is allowed".to_string(),
+            ))
+        }
+    };
+
+    let keyword: String = statement
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    if READ_ONLY_KEYWORDS.contains(&keyword.as_str()) {
+        Ok(())
+    } else {
+        Err(RawSqlError::Rejected(format!(
+            "only {} statements are allowed, use /raw_sql/mutate to change data",
+            READ_ONLY_KEYWORDS.join(", ")
+        )))
+    }
+}
+
+/// Splits sql on `;`, skipping comments and quoted strings and identifiers. Returns the
+/// non-empty statements with comments removed from their start.
+fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut chars = sql.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '-' if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                current.push(' ');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = '\0';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                current.push(' ');
+            }
+            '\'' | '"' | '`' | '[' => {
+                let close = if c == '[' { ']' } else { c };
+                current.push(c);
+                for c in chars.by_ref() {
+                    current.push(c);
+                    // doubled quotes are escapes and simply reopen the string
+                    if c == close {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                if !current.trim().is_empty() {
+                    statements.push(current.trim().to_string());
+                }
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.trim().is_empty() {
+        statements.push(current.trim().to_string());
+    }
+    statements
+}
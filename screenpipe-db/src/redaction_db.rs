@@ -0,0 +1,44 @@
+use crate::{DatabaseManager, FrameRedaction};
+
+impl DatabaseManager {
+    /// Records the regions that were redacted in a frame, for auditing.
+    pub async fn insert_frame_redactions(
+        &self,
+        frame_id: i64,
+        redactions: &[FrameRedaction],
+    ) -> Result<(), sqlx::Error> {
+        if redactions.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for redaction in redactions {
+            sqlx::query(
+                "INSERT INTO frame_redactions (frame_id, pii_type, mode, x, y, width, height) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )
+            .bind(frame_id)
+            .bind(&redaction.pii_type)
+            .bind(&redaction.mode)
+            .bind(redaction.x)
+            .bind(redaction.y)
+            .bind(redaction.width)
+            .bind(redaction.height)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_frame_redactions(
+        &self,
+        frame_id: i64,
+    ) -> Result<Vec<FrameRedaction>, sqlx::Error> {
+        sqlx::query_as::<_, FrameRedaction>(
+            "SELECT pii_type, mode, x, y, width, height FROM frame_redactions WHERE frame_id = ?1 ORDER BY id",
+        )
+        .bind(frame_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
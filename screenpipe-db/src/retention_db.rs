@@ -0,0 +1,166 @@
+use chrono::{DateTime, Utc};
+
+use crate::{DatabaseManager, PrunedRows, RetentionChunk};
+
+impl DatabaseManager {
+    /// Lists video chunks that are safe to prune, oldest first.
+    ///
+    /// The timestamp of a chunk is the timestamp of its newest frame. The most recent chunk
+    /// of every device is never returned because ffmpeg may still be writing to it.
+    pub async fn get_video_chunks_for_retention(
+        &self,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<Vec<RetentionChunk>, sqlx::Error> {
+        sqlx::query_as::<_, RetentionChunk>(
+            r#"
+            SELECT
+                video_chunks.id,
+                video_chunks.file_path,
+                MAX(frames.timestamp) as timestamp
+            FROM video_chunks
+            JOIN frames ON frames.video_chunk_id = video_chunks.id
+            WHERE video_chunks.id NOT IN (
+                SELECT MAX(id) FROM video_chunks GROUP BY device_name
+            )
+            GROUP BY video_chunks.id
+            HAVING (?1 IS NULL OR MAX(frames.timestamp) < ?1)
+            ORDER BY timestamp ASC
+            "#,
+        )
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Lists audio chunks that are safe to prune, oldest first.
+    pub async fn get_audio_chunks_for_retention(
+        &self,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<Vec<RetentionChunk>, sqlx::Error> {
+        sqlx::query_as::<_, RetentionChunk>(
+            r#"
+            SELECT id, file_path, timestamp
+            FROM audio_chunks
+            WHERE timestamp IS NOT NULL
+              AND (?1 IS NULL OR timestamp < ?1)
+            ORDER BY timestamp ASC
+            "#,
+        )
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Deletes video chunks together with their frames, ocr text, embeddings, tags and redactions
+    /// in a single transaction. FTS rows are removed by the `ocr_text_delete` and `frames_ad`
+    /// triggers.
+    pub async fn delete_video_chunks(&self, chunk_ids: &[i64]) -> Result<PrunedRows, sqlx::Error> {
+        let mut pruned = PrunedRows::default();
+        let mut tx = self.pool.begin().await?;
+
+        for chunk_id in chunk_ids {
+            pruned.embeddings += sqlx::query(
+                "DELETE FROM ocr_text_embeddings WHERE frame_id IN (SELECT id FROM frames WHERE video_chunk_id = ?1)",
+            )
+            .bind(chunk_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+            sqlx::query(
+                "DELETE FROM vision_tags WHERE vision_id IN (SELECT id FROM frames WHERE video_chunk_id = ?1)",
+            )
+            .bind(chunk_id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                "DELETE FROM frame_redactions WHERE frame_id IN (SELECT id FROM frames WHERE video_chunk_id = ?1)",
+            )
+            .bind(chunk_id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                "DELETE FROM ocr_queue WHERE frame_id IN (SELECT id FROM frames WHERE video_chunk_id = ?1)",
+            )
+            .bind(chunk_id)
+            .execute(&mut *tx)
+            .await?;
+
+            pruned.ocr_text += sqlx::query(
+                "DELETE FROM ocr_text WHERE frame_id IN (SELECT id FROM frames WHERE video_chunk_id = ?1)",
+            )
+            .bind(chunk_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+            pruned.frames += sqlx::query("DELETE FROM frames WHERE video_chunk_id = ?1")
+                .bind(chunk_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+            pruned.chunks += sqlx::query("DELETE FROM video_chunks WHERE id = ?1")
+                .bind(chunk_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        tx.commit().await?;
+        Ok(pruned)
+    }
+
+    /// Deletes audio chunks together with their transcriptions and tags in a single transaction.
+    /// FTS rows are removed by the `audio_transcriptions_delete` trigger.
+    pub async fn delete_audio_chunks(&self, chunk_ids: &[i64]) -> Result<PrunedRows, sqlx::Error> {
+        let mut pruned = PrunedRows::default();
+        let mut tx = self.pool.begin().await?;
+
+        for chunk_id in chunk_ids {
+            sqlx::query("DELETE FROM audio_tags WHERE audio_chunk_id = ?1")
+                .bind(chunk_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(
+                "DELETE FROM audio_transcription_embeddings WHERE audio_transcription_id IN (SELECT id FROM audio_transcriptions WHERE audio_chunk_id = ?1)",
+            )
+            .bind(chunk_id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                "DELETE FROM audio_transcription_speaker_embeddings WHERE audio_transcription_id IN (SELECT id FROM audio_transcriptions WHERE audio_chunk_id = ?1)",
+            )
+            .bind(chunk_id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                "DELETE FROM audio_transcription_versions WHERE audio_transcription_id IN (SELECT id FROM audio_transcriptions WHERE audio_chunk_id = ?1)",
+            )
+            .bind(chunk_id)
+            .execute(&mut *tx)
+            .await?;
+
+            pruned.audio_transcriptions +=
+                sqlx::query("DELETE FROM audio_transcriptions WHERE audio_chunk_id = ?1")
+                    .bind(chunk_id)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+
+            pruned.chunks += sqlx::query("DELETE FROM audio_chunks WHERE id = ?1")
+                .bind(chunk_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        tx.commit().await?;
+        Ok(pruned)
+    }
+}
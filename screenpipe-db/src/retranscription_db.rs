@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+use crate::{AudioTranscriptionVersion, DatabaseManager, RetranscriptionTarget, TranscriptionWord};
+
+#[derive(FromRow)]
+struct VersionRow {
+    transcription: String,
+    transcription_engine: String,
+    words_json: Option<String>,
+    created_at: DateTime<Utc>,
+    current: bool,
+}
+
+impl From<VersionRow> for AudioTranscriptionVersion {
+    fn from(row: VersionRow) -> Self {
+        AudioTranscriptionVersion {
+            words: TranscriptionWord::from_json(row.words_json.as_deref()),
+            transcription: row.transcription,
+            transcription_engine: row.transcription_engine,
+            created_at: row.created_at,
+            current: row.current,
+        }
+    }
+}
+
+impl DatabaseManager {
+    /// Transcriptions recorded between `start` and `end`, optionally from a single device,
+    /// grouped by audio chunk in recording order.
+    pub async fn get_transcriptions_for_retranscription(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        device: Option<&str>,
+    ) -> Result<Vec<RetranscriptionTarget>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                at.id, at.audio_chunk_id, ac.file_path,
+                COALESCE(at.transcription_engine, '') as transcription_engine,
+                at.start_time, at.end_time
+            FROM audio_transcriptions at
+            JOIN audio_chunks ac ON ac.id = at.audio_chunk_id
+            WHERE at.timestamp >= ?1 AND at.timestamp <= ?2
+              AND (?3 IS NULL OR at.device = ?3)
+            ORDER BY at.audio_chunk_id, at.start_time, at.id
+            "#,
+        )
+        .bind(start)
+        .bind(end)
+        .bind(device)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Adds the transcription of a segment by another engine, replacing an earlier version of
+    /// the same engine. The version recorded first is kept as a version too. With
+    /// `make_current` the new version is also stored in `audio_transcriptions`, so search
+    /// returns it.
+    pub async fn add_audio_transcription_version(
+        &self,
+        audio_transcription_id: i64,
+        transcription: &str,
+        transcription_engine: &str,
+        words: &[TranscriptionWord],
+        make_current: bool,
+    ) -> Result<(), sqlx::Error> {
+        let words_json = serde_json::to_string(words).unwrap_or_else(|_| "[]".to_string());
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO audio_transcription_versions
+                (audio_transcription_id, transcription, transcription_engine, words_json, created_at)
+            SELECT id, COALESCE(transcription, ''), COALESCE(transcription_engine, ''), words_json,
+                timestamp
+            FROM audio_transcriptions WHERE id = ?1
+            "#,
+        )
+        .bind(audio_transcription_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO audio_transcription_versions (audio_transcription_id, transcription, transcription_engine, words_json, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .bind(audio_transcription_id)
+        .bind(transcription)
+        .bind(transcription_engine)
+        .bind(&words_json)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+
+        if make_current {
+            sqlx::query(
+                "UPDATE audio_transcriptions SET transcription = ?1, transcription_engine = ?2, words_json = ?3, text_length = ?4 WHERE id = ?5",
+            )
+            .bind(transcription)
+            .bind(transcription_engine)
+            .bind(&words_json)
+            .bind(transcription.len() as i64)
+            .bind(audio_transcription_id)
+            .execute(&mut *tx)
+            .await?;
+
+            // embedded again from the new text by the text embedding task, see
+            // get_retranscribed_without_embeddings
+            sqlx::query(
+                "DELETE FROM audio_transcription_embeddings WHERE audio_transcription_id = ?1",
+            )
+            .bind(audio_transcription_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Every version of a transcription, oldest first. A transcription that was never
+    /// re-transcribed has a single version, empty when the transcription doesn't exist.
+    pub async fn get_audio_transcription_versions(
+        &self,
+        audio_transcription_id: i64,
+    ) -> Result<Vec<AudioTranscriptionVersion>, sqlx::Error> {
+        let versions: Vec<VersionRow> = sqlx::query_as(
+            r#"
+            SELECT
+                v.transcription, v.transcription_engine, v.words_json, v.created_at,
+                v.transcription_engine = COALESCE(at.transcription_engine, '') as current
+            FROM audio_transcription_versions v
+            JOIN audio_transcriptions at ON at.id = v.audio_transcription_id
+            WHERE v.audio_transcription_id = ?1
+            ORDER BY v.created_at, v.id
+            "#,
+        )
+        .bind(audio_transcription_id)
+        .fetch_all(&self.pool)
+        .await?;
+        if !versions.is_empty() {
+            return Ok(versions.into_iter().map(Into::into).collect());
+        }
+
+        let original: Option<VersionRow> = sqlx::query_as(
+            r#"
+            SELECT
+                COALESCE(transcription, '') as transcription,
+                COALESCE(transcription_engine, '') as transcription_engine,
+                words_json, timestamp as created_at, 1 as current
+            FROM audio_transcriptions WHERE id = ?1
+            "#,
+        )
+        .bind(audio_transcription_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(original.into_iter().map(Into::into).collect())
+    }
+}
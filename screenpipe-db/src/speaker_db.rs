@@ -0,0 +1,363 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Sqlite, Transaction};
+use zerocopy::AsBytes;
+
+use crate::{
+    DatabaseManager, DiarizationTranscription, ReattributionBatch, Speaker, SpeakerMergeSuggestion,
+};
+
+/// Weight a centroid is capped at when a segment is added, so it keeps following a voice as
+/// microphones and rooms change instead of freezing after a few hours of speech. Shared with the
+/// speakers of a recording session.
+pub const MAX_CENTROID_WEIGHT: i64 = 500;
+
+impl DatabaseManager {
+    /// Moves the centroid of `speaker_id` towards `embedding`.
+    pub async fn update_speaker_centroid(
+        &self,
+        speaker_id: i64,
+        embedding: &[f32],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        add_to_centroid(&mut tx, speaker_id, embedding, 1).await?;
+        tx.commit().await
+    }
+
+    /// Stores the voice embedding of a transcribed segment for later re-attribution. Failed
+    /// embeddings, which are all zeros, are not stored.
+    pub async fn insert_audio_transcription_speaker_embedding(
+        &self,
+        audio_transcription_id: i64,
+        embedding: &[f32],
+    ) -> Result<(), sqlx::Error> {
+        if normalize_embedding(embedding).is_none() {
+            return Ok(());
+        }
+        sqlx::query(
+            "INSERT OR REPLACE INTO audio_transcription_speaker_embeddings (audio_transcription_id, embedding) VALUES (?1, ?2)",
+        )
+        .bind(audio_transcription_id)
+        .bind(embedding.as_bytes())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Enrolls voice samples of a named person, one embedding per sample. Samples are added to
+    /// the speaker with that name if there is one, otherwise a speaker is created.
+    pub async fn enroll_speaker(
+        &self,
+        name: &str,
+        embeddings: &[Vec<f32>],
+    ) -> Result<Speaker, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let existing: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM speakers WHERE name = ?1 AND hallucination = 0 ORDER BY id LIMIT 1",
+        )
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let speaker_id = match existing {
+            Some(id) => id,
+            None => sqlx::query("INSERT INTO speakers (name) VALUES (?1)")
+                .bind(name)
+                .execute(&mut *tx)
+                .await?
+                .last_insert_rowid(),
+        };
+
+        for embedding in embeddings {
+            sqlx::query(
+                "INSERT INTO speaker_embeddings (embedding, speaker_id) VALUES (vec_f32(?1), ?2)",
+            )
+            .bind(embedding.as_bytes())
+            .bind(speaker_id)
+            .execute(&mut *tx)
+            .await?;
+            add_to_centroid(&mut tx, speaker_id, embedding, 1).await?;
+        }
+
+        tx.commit().await?;
+        self.get_speaker_by_id(speaker_id).await
+    }
+
+    /// Pairs of speakers whose centroids are within `max_distance`, closest first. Two
+    /// speakers with different names are never suggested.
+    pub async fn get_speaker_merge_suggestions(
+        &self,
+        max_distance: f64,
+        limit: u32,
+    ) -> Result<Vec<SpeakerMergeSuggestion>, sqlx::Error> {
+        let rows: Vec<(i64, String, i64, i64, String, i64, f64)> = sqlx::query_as(
+            r#"
+            SELECT
+                a.speaker_id, COALESCE(sa.name, ''), a.sample_count,
+                b.speaker_id, COALESCE(sb.name, ''), b.sample_count,
+                vec_distance_cosine(a.embedding, b.embedding) AS distance
+            FROM speaker_centroids a
+            JOIN speaker_centroids b ON b.speaker_id > a.speaker_id
+            JOIN speakers sa ON sa.id = a.speaker_id
+            JOIN speakers sb ON sb.id = b.speaker_id
+            WHERE sa.hallucination = 0 AND sb.hallucination = 0
+              AND (COALESCE(sa.name, '') = '' OR COALESCE(sb.name, '') = '' OR sa.name = sb.name)
+              AND vec_distance_cosine(a.embedding, b.embedding) < ?1
+            ORDER BY distance
+            LIMIT ?2
+            "#,
+        )
+        .bind(max_distance)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(a_id, a_name, a_count, b_id, b_name, b_count, distance)| {
+                // keep the named speaker, or the one heard the most
+                let keep_a = match (a_name.is_empty(), b_name.is_empty()) {
+                    (false, true) => true,
+                    (true, false) => false,
+                    _ => a_count >= b_count,
+                };
+                let ((keep_id, keep_name), (merge_id, merge_name)) = if keep_a {
+                    ((a_id, a_name), (b_id, b_name))
+                } else {
+                    ((b_id, b_name), (a_id, a_name))
+                };
+                SpeakerMergeSuggestion {
+                    speaker_to_keep_id: keep_id,
+                    speaker_to_keep_name: keep_name,
+                    speaker_to_merge_id: merge_id,
+                    speaker_to_merge_name: merge_name,
+                    distance,
+                }
+            })
+            .collect())
+    }
+
+    /// Number of transcriptions with a stored voice embedding.
+    pub async fn count_transcription_speaker_embeddings(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM audio_transcription_speaker_embeddings")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// Moves transcriptions with an id above `after_id` from unnamed speakers to the closest
+    /// named speaker within `max_distance`. Transcriptions of named speakers are left alone.
+    pub async fn reattribute_speakers(
+        &self,
+        after_id: i64,
+        limit: i64,
+        max_distance: f64,
+    ) -> Result<ReattributionBatch, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let rows: Vec<(i64, Option<i64>, String, Vec<u8>)> = sqlx::query_as(
+            r#"
+            SELECT e.audio_transcription_id, at.speaker_id, COALESCE(s.name, ''), e.embedding
+            FROM audio_transcription_speaker_embeddings e
+            JOIN audio_transcriptions at ON at.id = e.audio_transcription_id
+            LEFT JOIN speakers s ON s.id = at.speaker_id
+            WHERE e.audio_transcription_id > ?1
+            ORDER BY e.audio_transcription_id
+            LIMIT ?2
+            "#,
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut batch = ReattributionBatch {
+            last_id: after_id,
+            ..Default::default()
+        };
+        for (transcription_id, speaker_id, speaker_name, embedding) in rows {
+            batch.processed += 1;
+            batch.last_id = transcription_id;
+            if !speaker_name.is_empty() {
+                continue;
+            }
+
+            let named_speaker_id: Option<i64> = sqlx::query_scalar(
+                r#"
+                SELECT c.speaker_id
+                FROM speaker_centroids c
+                JOIN speakers s ON s.id = c.speaker_id
+                WHERE COALESCE(s.name, '') != '' AND s.hallucination = 0
+                  AND vec_distance_cosine(c.embedding, ?1) < ?2
+                ORDER BY vec_distance_cosine(c.embedding, ?1)
+                LIMIT 1
+                "#,
+            )
+            .bind(&embedding)
+            .bind(max_distance)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let Some(named_speaker_id) = named_speaker_id else {
+                continue;
+            };
+            if speaker_id == Some(named_speaker_id) {
+                continue;
+            }
+
+            sqlx::query("UPDATE audio_transcriptions SET speaker_id = ?1 WHERE id = ?2")
+                .bind(named_speaker_id)
+                .bind(transcription_id)
+                .execute(&mut *tx)
+                .await?;
+            batch.reattributed += 1;
+        }
+
+        tx.commit().await?;
+        Ok(batch)
+    }
+
+    /// Transcriptions recorded between `start` and `end`, optionally from a single device,
+    /// grouped by audio chunk in recording order.
+    pub async fn get_transcriptions_for_diarization(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        device: Option<&str>,
+    ) -> Result<Vec<DiarizationTranscription>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                at.id, at.audio_chunk_id, ac.file_path, at.device,
+                at.start_time, at.end_time, at.speaker_id
+            FROM audio_transcriptions at
+            JOIN audio_chunks ac ON ac.id = at.audio_chunk_id
+            WHERE at.timestamp >= ?1 AND at.timestamp <= ?2
+              AND (?3 IS NULL OR at.device = ?3)
+            ORDER BY at.audio_chunk_id, at.start_time, at.id
+            "#,
+        )
+        .bind(start)
+        .bind(end)
+        .bind(device)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Sets the speaker of each `(audio_transcription_id, speaker_id)` pair in one transaction.
+    /// Returns how many transcriptions changed speaker.
+    pub async fn assign_transcription_speakers(
+        &self,
+        assignments: &[(i64, i64)],
+    ) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut updated = 0;
+        for (transcription_id, speaker_id) in assignments {
+            updated += sqlx::query(
+                "UPDATE audio_transcriptions SET speaker_id = ?1 WHERE id = ?2 AND speaker_id IS NOT ?1",
+            )
+            .bind(speaker_id)
+            .bind(transcription_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+        tx.commit().await?;
+        Ok(updated)
+    }
+}
+
+/// Adds `embedding`, weighing `sample_count` segments, to the centroid of `speaker_id`.
+///
+/// Both are normalized before averaging so loud and quiet segments count the same, the first
+/// embedding of a speaker is stored as is.
+pub(crate) async fn add_to_centroid(
+    tx: &mut Transaction<'_, Sqlite>,
+    speaker_id: i64,
+    embedding: &[f32],
+    sample_count: i64,
+) -> Result<(), sqlx::Error> {
+    let Some(normalized) = normalize_embedding(embedding) else {
+        // failed embeddings are all zeros
+        return Ok(());
+    };
+
+    let current: Option<(Vec<u8>, i64)> = sqlx::query_as(
+        "SELECT embedding, sample_count FROM speaker_centroids WHERE speaker_id = ?1",
+    )
+    .bind(speaker_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let current = current.and_then(|(bytes, count)| {
+        let centroid = normalize_embedding(&embedding_from_bytes(&bytes).collect::<Vec<_>>())?;
+        (centroid.len() == normalized.len()).then_some((centroid, count))
+    });
+    let (centroid, count) = match current {
+        Some((centroid, count)) => {
+            let weight = count.min(MAX_CENTROID_WEIGHT) as f32;
+            let added = sample_count as f32;
+            let centroid: Vec<f32> = centroid
+                .iter()
+                .zip(&normalized)
+                .map(|(current, new)| (current * weight + new * added) / (weight + added))
+                .collect();
+            (centroid, count + sample_count)
+        }
+        None => (embedding.to_vec(), sample_count),
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO speaker_centroids (speaker_id, embedding, sample_count, updated_at)
+        VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)
+        ON CONFLICT(speaker_id) DO UPDATE SET
+            embedding = excluded.embedding,
+            sample_count = excluded.sample_count,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(speaker_id)
+    .bind(centroid.as_bytes())
+    .bind(count)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Folds the centroid of `from_speaker_id` into the one of `into_speaker_id`.
+pub(crate) async fn merge_centroids(
+    tx: &mut Transaction<'_, Sqlite>,
+    into_speaker_id: i64,
+    from_speaker_id: i64,
+) -> Result<(), sqlx::Error> {
+    let merged: Option<(Vec<u8>, i64)> = sqlx::query_as(
+        "SELECT embedding, sample_count FROM speaker_centroids WHERE speaker_id = ?1",
+    )
+    .bind(from_speaker_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    if let Some((bytes, count)) = merged {
+        let embedding: Vec<f32> = embedding_from_bytes(&bytes).collect();
+        add_to_centroid(tx, into_speaker_id, &embedding, count).await?;
+        sqlx::query("DELETE FROM speaker_centroids WHERE speaker_id = ?1")
+            .bind(from_speaker_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Scales to unit length so loud and quiet segments weigh the same in a centroid. None for the
+/// all zero embeddings of failed extractions.
+pub fn normalize_embedding(embedding: &[f32]) -> Option<Vec<f32>> {
+    let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm == 0.0 || !norm.is_finite() {
+        return None;
+    }
+    Some(embedding.iter().map(|v| v / norm).collect())
+}
+
+pub(crate) fn embedding_from_bytes(bytes: &[u8]) -> impl Iterator<Item = f32> + '_ {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+}
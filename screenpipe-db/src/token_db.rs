@@ -0,0 +1,158 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+
+use crate::{ApiToken, DatabaseManager};
+
+/// `last_used_at` is only refreshed this often, so authenticating a request stays a read
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+#[derive(FromRow)]
+struct ApiTokenRow {
+    id: i64,
+    name: String,
+    scopes: String,
+    pipe_id: Option<String>,
+    created_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<ApiTokenRow> for ApiToken {
+    fn from(row: ApiTokenRow) -> Self {
+        ApiToken {
+            id: row.id,
+            name: row.name,
+            scopes: row
+                .scopes
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+            pipe_id: row.pipe_id,
+            created_at: row.created_at,
+            last_used_at: row.last_used_at,
+        }
+    }
+}
+
+impl DatabaseManager {
+    pub async fn insert_api_token(
+        &self,
+        name: &str,
+        token_hash: &str,
+        scopes: &[String],
+        pipe_id: Option<&str>,
+    ) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query(
+            "INSERT INTO api_tokens (name, token_hash, scopes, pipe_id) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(name)
+        .bind(token_hash)
+        .bind(scopes.join(","))
+        .bind(pipe_id)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    /// Looks up a token by the hash of its secret and records that it was used.
+    pub async fn get_api_token_by_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<ApiToken>, sqlx::Error> {
+        let row = sqlx::query_as::<_, ApiTokenRow>(
+            "SELECT id, name, scopes, pipe_id, created_at, last_used_at
+             FROM api_tokens WHERE token_hash = ?1",
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let now = Utc::now();
+        let stale = row.last_used_at.is_none_or(|last_used| {
+            now - last_used >= Duration::seconds(LAST_USED_RESOLUTION_SECS)
+        });
+        if stale {
+            sqlx::query("UPDATE api_tokens SET last_used_at = ?1 WHERE id = ?2")
+                .bind(now)
+                .bind(row.id)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(Some(row.into()))
+    }
+
+    pub async fn list_api_tokens(&self) -> Result<Vec<ApiToken>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, ApiTokenRow>(
+            "SELECT id, name, scopes, pipe_id, created_at, last_used_at
+             FROM api_tokens ORDER BY id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(ApiToken::from).collect())
+    }
+
+    /// Returns false when no token has this id.
+    pub async fn delete_api_token(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replaces the scopes the user granted to a pipe
+    pub async fn set_pipe_scope_grant(
+        &self,
+        pipe_id: &str,
+        scopes: &[String],
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO pipe_scope_grants (pipe_id, scopes, granted_at) VALUES (?1, ?2, ?3)",
+        )
+        .bind(pipe_id)
+        .bind(scopes.join(","))
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Scopes the user granted to a pipe, empty when none were
+    pub async fn get_pipe_scope_grant(&self, pipe_id: &str) -> Result<Vec<String>, sqlx::Error> {
+        let scopes: Option<String> =
+            sqlx::query_scalar("SELECT scopes FROM pipe_scope_grants WHERE pipe_id = ?1")
+                .bind(pipe_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(scopes
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Returns false when the pipe had no grant.
+    pub async fn delete_pipe_scope_grant(&self, pipe_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM pipe_scope_grants WHERE pipe_id = ?1")
+            .bind(pipe_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Revokes every token issued to a pipe, returning how many there were.
+    pub async fn delete_pipe_api_tokens(&self, pipe_id: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE pipe_id = ?1")
+            .bind(pipe_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
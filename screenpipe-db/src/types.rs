@@ -67,6 +67,30 @@ pub struct OCRResult {
     pub device_name: String,
 }
 
+impl From<OCRResultRaw> for OCRResult {
+    fn from(raw: OCRResultRaw) -> Self {
+        OCRResult {
+            frame_id: raw.frame_id,
+            ocr_text: raw.ocr_text,
+            text_json: raw.text_json,
+            timestamp: raw.timestamp,
+            frame_name: raw.frame_name,
+            file_path: raw.file_path,
+            offset_index: raw.offset_index,
+            app_name: raw.app_name,
+            ocr_engine: raw.ocr_engine,
+            window_name: raw.window_name,
+            device_name: raw.device_name,
+            tags: raw
+                .tags
+                .map(|t| t.split(',').map(String::from).collect())
+                .unwrap_or_default(),
+            browser_url: raw.browser_url,
+            focused: raw.focused,
+        }
+    }
+}
+
 #[derive(OaSchema, Debug, Deserialize, PartialEq, Default, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum ContentType {
@@ -88,6 +112,7 @@ pub enum ContentType {
 
 #[derive(FromRow)]
 pub struct AudioResultRaw {
+    pub transcription_id: i64,
     pub audio_chunk_id: i64,
     pub transcription: String,
     pub timestamp: DateTime<Utc>,
@@ -100,6 +125,7 @@ pub struct AudioResultRaw {
     pub speaker_id: Option<i64>,
     pub start_time: Option<f64>,
     pub end_time: Option<f64>,
+    pub words_json: Option<String>,
 }
 
 #[derive(OaSchema, Debug, Serialize, Deserialize, FromRow, Clone)]
@@ -117,6 +143,7 @@ pub enum DeviceType {
 
 #[derive(OaSchema, Debug, Serialize, Deserialize)]
 pub struct AudioResult {
+    pub transcription_id: i64,
     pub audio_chunk_id: i64,
     pub transcription: String,
     pub timestamp: DateTime<Utc>,
@@ -129,6 +156,36 @@ pub struct AudioResult {
     pub speaker: Option<Speaker>,
     pub start_time: Option<f64>,
     pub end_time: Option<f64>,
+    pub words: Vec<TranscriptionWord>,
+}
+
+/// A word of a transcription, times in seconds from the start of the audio chunk like the
+/// `start_time` and `end_time` of the transcription
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct TranscriptionWord {
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+    /// Between 0 and 1, when the engine reports it
+    pub confidence: Option<f32>,
+}
+
+impl TranscriptionWord {
+    /// Words stored in `words_json`, empty for transcriptions recorded without them
+    pub fn from_json(words_json: Option<&str>) -> Vec<TranscriptionWord> {
+        words_json
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Transcriptions of the same speech recorded by two devices, the duplicate is left out of search
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioDuplicate {
+    pub kept_id: i64,
+    pub duplicate_id: i64,
+    /// Share of the words of the shorter transcription found in order in the other
+    pub similarity: f64,
 }
 
 #[derive(OaSchema, Debug, Deserialize, PartialEq)]
@@ -299,7 +356,7 @@ pub struct AudioDevice {
     pub device_type: DeviceType,
 }
 
-#[derive(OaSchema, Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(OaSchema, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum OcrEngine {
     Unstructured,
     #[default]
@@ -307,9 +364,21 @@ pub enum OcrEngine {
     WindowsNative,
     AppleNative,
     Custom(CustomOcrConfig),
+    /// Engine plugged in as an ocr backend, by name
+    Other(String),
 }
 
-#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+/// Name stored with the text in `ocr_text`
+impl Display for OcrEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OcrEngine::Other(name) => write!(f, "{}", name),
+            engine => write!(f, "{:?}", engine),
+        }
+    }
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CustomOcrConfig {
     pub api_url: String,
     pub api_key: String,
@@ -325,3 +394,361 @@ impl Default for CustomOcrConfig {
         }
     }
 }
+
+#[derive(OaSchema, Debug, Clone, FromRow, Serialize)]
+pub struct RetentionChunk {
+    pub id: i64,
+    pub file_path: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(OaSchema, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrunedRows {
+    pub chunks: u64,
+    pub frames: u64,
+    pub ocr_text: u64,
+    pub embeddings: u64,
+    pub audio_transcriptions: u64,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ArchiveVideoChunk {
+    pub id: i64,
+    pub file_path: String,
+    pub device_name: String,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ArchiveFrame {
+    pub id: i64,
+    pub video_chunk_id: i64,
+    pub offset_index: i64,
+    pub timestamp: DateTime<Utc>,
+    pub name: Option<String>,
+    pub device_name: String,
+    pub browser_url: Option<String>,
+    pub app_name: Option<String>,
+    pub window_name: Option<String>,
+    pub focused: Option<bool>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ArchiveOcrText {
+    pub frame_id: i64,
+    pub text: String,
+    pub text_json: Option<String>,
+    pub app_name: String,
+    pub ocr_engine: String,
+    pub window_name: Option<String>,
+    pub focused: Option<bool>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ArchiveOcrTextEmbedding {
+    pub frame_id: i64,
+    pub embedding: Vec<u8>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ArchiveAudioChunk {
+    pub id: i64,
+    pub file_path: String,
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ArchiveAudioTranscription {
+    pub id: i64,
+    pub audio_chunk_id: i64,
+    pub offset_index: i64,
+    pub timestamp: DateTime<Utc>,
+    pub transcription: String,
+    pub device: String,
+    pub is_input_device: bool,
+    pub speaker_id: Option<i64>,
+    pub transcription_engine: String,
+    pub start_time: Option<f64>,
+    pub end_time: Option<f64>,
+    /// Missing from archives exported before word timings were recorded
+    #[serde(default)]
+    pub words_json: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ArchiveSpeaker {
+    pub id: i64,
+    pub name: Option<String>,
+    pub metadata: Option<String>,
+    pub hallucination: Option<bool>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ArchiveSpeakerEmbedding {
+    pub speaker_id: i64,
+    pub embedding: Vec<u8>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ArchiveTag {
+    pub id: i64,
+    pub name: String,
+}
+
+/// A row of `vision_tags` or `audio_tags`. `target_id` is the frame id or audio chunk id.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ArchiveTagLink {
+    pub target_id: i64,
+    pub tag_id: i64,
+}
+
+/// Every row needed to move a time range of recordings to another database.
+/// Ids are those of the source database and are remapped on import.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArchiveData {
+    pub video_chunks: Vec<ArchiveVideoChunk>,
+    pub frames: Vec<ArchiveFrame>,
+    pub ocr_text: Vec<ArchiveOcrText>,
+    pub ocr_text_embeddings: Vec<ArchiveOcrTextEmbedding>,
+    pub audio_chunks: Vec<ArchiveAudioChunk>,
+    pub audio_transcriptions: Vec<ArchiveAudioTranscription>,
+    pub speakers: Vec<ArchiveSpeaker>,
+    pub speaker_embeddings: Vec<ArchiveSpeakerEmbedding>,
+    pub tags: Vec<ArchiveTag>,
+    pub vision_tags: Vec<ArchiveTagLink>,
+    pub audio_tags: Vec<ArchiveTagLink>,
+}
+
+#[derive(OaSchema, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArchiveImportStats {
+    pub video_chunks_imported: u64,
+    pub frames_imported: u64,
+    pub frames_skipped: u64,
+    pub ocr_text_imported: u64,
+    pub embeddings_imported: u64,
+    pub audio_chunks_imported: u64,
+    pub audio_transcriptions_imported: u64,
+    pub audio_transcriptions_skipped: u64,
+    pub speakers_imported: u64,
+    pub speakers_merged: u64,
+    pub tags_imported: u64,
+}
+
+/// How a hybrid search result was ranked. Ranks are 1-based positions in each candidate list.
+#[derive(OaSchema, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HybridScore {
+    /// Reciprocal rank fusion score, higher is better
+    pub rrf_score: f64,
+    pub keyword_rank: Option<usize>,
+    /// FTS5 bm25 score, lower is better
+    pub keyword_score: Option<f64>,
+    pub vector_rank: Option<usize>,
+    /// Cosine distance to the query embedding, lower is better
+    pub vector_distance: Option<f64>,
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize)]
+pub struct HybridSearchResult {
+    pub result: SearchResult,
+    pub score: HybridScore,
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize)]
+pub struct SemanticSearchResult {
+    pub result: SearchResult,
+    /// Cosine distance to the query embedding, lower is better
+    pub distance: f64,
+}
+
+/// A row whose text has no embedding yet
+#[derive(Debug, Clone, FromRow)]
+pub struct PendingEmbedding {
+    pub id: i64,
+    pub text: String,
+}
+
+/// An api token, without the secret itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub pipe_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Bounds on a query run through [`DatabaseManager::execute_raw_sql_with_limits`]
+///
+/// [`DatabaseManager::execute_raw_sql_with_limits`]: crate::DatabaseManager::execute_raw_sql_with_limits
+#[derive(Debug, Clone, Copy)]
+pub struct RawSqlLimits {
+    /// Rows past this are dropped and the output is marked truncated
+    pub max_rows: usize,
+    pub timeout: std::time::Duration,
+}
+
+impl Default for RawSqlLimits {
+    fn default() -> Self {
+        Self {
+            max_rows: 10_000,
+            timeout: std::time::Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RawSqlOutput {
+    pub rows: Vec<serde_json::Value>,
+    pub truncated: bool,
+}
+
+#[derive(Debug)]
+pub enum RawSqlError {
+    /// The statement is not allowed on the read-only connection
+    Rejected(String),
+    TimedOut(std::time::Duration),
+    Database(sqlx::Error),
+}
+
+impl Display for RawSqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RawSqlError::Rejected(reason) => write!(f, "query rejected: {}", reason),
+            RawSqlError::TimedOut(timeout) => {
+                write!(f, "query cancelled after {}ms", timeout.as_millis())
+            }
+            RawSqlError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl StdError for RawSqlError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            RawSqlError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for RawSqlError {
+    fn from(e: sqlx::Error) -> Self {
+        RawSqlError::Database(e)
+    }
+}
+
+/// A region of a frame that was redacted before the frame was encoded
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct FrameRedaction {
+    pub pii_type: String,
+    pub mode: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Text read by the ui monitor, see [`crate::DatabaseManager::get_ui_monitoring_written_after`]
+#[derive(Debug, Clone, FromRow)]
+pub struct UiMonitoringText {
+    pub id: i64,
+    pub app: String,
+    pub text: String,
+    pub timestamp: String,
+}
+
+/// Two speakers whose voices are close enough to likely be the same person, oriented so it
+/// can be passed to `/speakers/merge` as is
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeakerMergeSuggestion {
+    pub speaker_to_keep_id: i64,
+    pub speaker_to_keep_name: String,
+    pub speaker_to_merge_id: i64,
+    pub speaker_to_merge_name: String,
+    /// Cosine distance between the voices of the two speakers, lower is more alike
+    pub distance: f64,
+}
+
+/// Outcome of re-attributing a batch of transcriptions to named speakers
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReattributionBatch {
+    /// Transcriptions looked at
+    pub processed: i64,
+    /// Transcriptions moved to another speaker
+    pub reattributed: i64,
+    /// Id to pass as `after_id` for the next batch
+    pub last_id: i64,
+}
+
+/// A transcription and the audio chunk it was cut from, as read by re-transcription
+#[derive(Debug, Clone, FromRow)]
+pub struct RetranscriptionTarget {
+    pub id: i64,
+    pub audio_chunk_id: i64,
+    pub file_path: String,
+    /// Engine of the version search currently returns
+    pub transcription_engine: String,
+    /// Offsets of the transcribed segment in the chunk, in seconds. Missing for transcriptions
+    /// of a whole chunk.
+    pub start_time: Option<f64>,
+    pub end_time: Option<f64>,
+}
+
+/// The transcription of a segment by one engine
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct AudioTranscriptionVersion {
+    pub transcription: String,
+    pub transcription_engine: String,
+    pub words: Vec<TranscriptionWord>,
+    pub created_at: DateTime<Utc>,
+    /// The version stored in `audio_transcriptions`, which search returns
+    pub current: bool,
+}
+
+/// A transcription and the audio chunk it was cut from, as read by batch diarization
+#[derive(Debug, Clone, FromRow)]
+pub struct DiarizationTranscription {
+    pub id: i64,
+    pub audio_chunk_id: i64,
+    pub file_path: String,
+    pub device: String,
+    /// Offsets of the transcribed segment in the chunk, in seconds
+    pub start_time: Option<f64>,
+    pub end_time: Option<f64>,
+    pub speaker_id: Option<i64>,
+}
+
+/// A window waiting in the ocr queue, with the metadata of its frame
+#[derive(Debug, Clone, FromRow)]
+pub struct OcrQueueJob {
+    pub id: i64,
+    pub frame_id: i64,
+    /// Jpeg of the window
+    pub image: Vec<u8>,
+    /// Failed attempts, see [`crate::DatabaseManager::release_ocr_job`]
+    pub attempts: i64,
+    /// Times the window was put back in the queue, failed or postponed
+    pub retries: i64,
+    /// Whether the frame already has text, see
+    /// [`crate::DatabaseManager::enqueue_deferred_ocr_job`]
+    pub replaces_text: bool,
+    pub app_name: Option<String>,
+    pub window_name: Option<String>,
+    pub focused: Option<bool>,
+    pub browser_url: Option<String>,
+    /// When the frame was recorded
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// Windows in the ocr queue
+#[derive(Debug, Clone, Default, PartialEq, FromRow)]
+pub struct OcrQueueCounts {
+    /// Windows waiting for a worker
+    pub queued: i64,
+    /// Windows being OCR'd
+    pub in_progress: i64,
+    /// Windows, waiting or being OCR'd, to OCR again with the selected engine
+    pub deferred: i64,
+    /// When the oldest waiting window was queued
+    pub oldest_queued_at: Option<DateTime<Utc>>,
+}
@@ -2,9 +2,12 @@
 mod tests {
     use std::sync::Arc;
 
-    use chrono::Utc;
+    use chrono::{DateTime, Utc};
     use screenpipe_db::{
-        AudioDevice, ContentType, DatabaseManager, DeviceType, Frame, OcrEngine, SearchResult,
+        create_migration_worker_for_job, AudioDevice, ContentType, DatabaseManager, DeviceType,
+        EmbeddingFn, Frame, FrameRedaction, MigrationCommand, MigrationJob, MigrationStatus,
+        OcrEngine, RawSqlError, RawSqlLimits, SearchResult, TagContentType, TranscriptionWord,
+        DEFAULT_RRF_K,
     };
 
     async fn setup_test_db() -> DatabaseManager {
@@ -821,7 +824,7 @@ mod tests {
         }
 
         // insert a speaker with a name
-        let speaker = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        let speaker = db.insert_speaker(&[0.1; 512]).await.unwrap();
         db.update_speaker_name(speaker.id, "test name")
             .await
             .unwrap();
@@ -888,7 +891,7 @@ mod tests {
         }
 
         // insert a speaker with a name
-        let speaker = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        let speaker = db.insert_speaker(&[0.1; 512]).await.unwrap();
         db.update_speaker_name(speaker.id, "test name")
             .await
             .unwrap();
@@ -910,7 +913,7 @@ mod tests {
     async fn test_merge_speakers() {
         let db = setup_test_db().await;
 
-        let speaker_1 = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        let speaker_1 = db.insert_speaker(&[0.1; 512]).await.unwrap();
         db.update_speaker_name(speaker_1.id, "speaker 1")
             .await
             .unwrap();
@@ -957,7 +960,7 @@ mod tests {
     async fn test_search_speakers() {
         let db = setup_test_db().await;
 
-        let speaker = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        let speaker = db.insert_speaker(&[0.1; 512]).await.unwrap();
         db.update_speaker_name(speaker.id, "test name")
             .await
             .unwrap();
@@ -971,7 +974,7 @@ mod tests {
     async fn test_delete_speaker() {
         let db = setup_test_db().await;
 
-        let speaker = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        let speaker = db.insert_speaker(&[0.1; 512]).await.unwrap();
 
         let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
         db.insert_audio_transcription(
@@ -1004,7 +1007,7 @@ mod tests {
     async fn test_mark_speaker_as_hallucination() {
         let db = setup_test_db().await;
 
-        let speaker = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        let speaker = db.insert_speaker(&[0.1; 512]).await.unwrap();
         db.mark_speaker_as_hallucination(speaker.id).await.unwrap();
 
         let speakers = db.search_speakers("").await.unwrap();
@@ -1016,7 +1019,7 @@ mod tests {
         let db = setup_test_db().await;
 
         // Create first speaker with audio data
-        let speaker = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        let speaker = db.insert_speaker(&[0.1; 512]).await.unwrap();
         db.update_speaker_name(speaker.id, "test name")
             .await
             .unwrap();
@@ -1445,4 +1448,1890 @@ mod tests {
             .unwrap();
         assert_eq!(count, 0, "Should count zero results for non-matching query");
     }
+
+    #[tokio::test]
+    async fn test_video_retention_skips_latest_chunk_and_prunes_rows() {
+        let db = setup_test_db().await;
+
+        let old_chunk_id = db
+            .insert_video_chunk("old_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let old_frame_id = db
+            .insert_frame(
+                "test_device",
+                Some(Utc::now() - chrono::Duration::days(10)),
+                None,
+                Some("test"),
+                Some(""),
+                false,
+            )
+            .await
+            .unwrap();
+        db.insert_ocr_text(old_frame_id, "old text", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+
+        db.insert_video_chunk("current_video.mp4", "test_device")
+            .await
+            .unwrap();
+        db.insert_frame("test_device", None, None, Some("test"), Some(""), false)
+            .await
+            .unwrap();
+
+        // the chunk currently being written is never a candidate
+        let candidates = db.get_video_chunks_for_retention(None).await.unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].id, old_chunk_id);
+
+        let candidates = db
+            .get_video_chunks_for_retention(Some(Utc::now() - chrono::Duration::days(30)))
+            .await
+            .unwrap();
+        assert!(candidates.is_empty());
+
+        let pruned = db.delete_video_chunks(&[old_chunk_id]).await.unwrap();
+        assert_eq!(pruned.chunks, 1);
+        assert_eq!(pruned.frames, 1);
+        assert_eq!(pruned.ocr_text, 1);
+
+        let results = db
+            .search(
+                "old",
+                ContentType::OCR,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_audio_retention_prunes_transcriptions() {
+        let db = setup_test_db().await;
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "Hello from audio",
+            0,
+            "",
+            &AudioDevice {
+                name: "test".to_string(),
+                device_type: DeviceType::Output,
+            },
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let candidates = db
+            .get_audio_chunks_for_retention(Some(Utc::now() + chrono::Duration::minutes(1)))
+            .await
+            .unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].id, audio_chunk_id);
+
+        let pruned = db.delete_audio_chunks(&[audio_chunk_id]).await.unwrap();
+        assert_eq!(pruned.chunks, 1);
+        assert_eq!(pruned.audio_transcriptions, 1);
+
+        let candidates = db.get_audio_chunks_for_retention(None).await.unwrap();
+        assert!(candidates.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_archive_round_trip_remaps_ids_and_dedups() {
+        let source = setup_test_db().await;
+        source
+            .insert_video_chunk("archived_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = source
+            .insert_frame(
+                "test_device",
+                None,
+                None,
+                Some("editor"),
+                Some("notes"),
+                true,
+            )
+            .await
+            .unwrap();
+        source
+            .insert_ocr_text(
+                frame_id,
+                "archived screen text",
+                "[]",
+                Arc::new(OcrEngine::Tesseract),
+            )
+            .await
+            .unwrap();
+        source
+            .add_tags(frame_id, TagContentType::Vision, vec!["work".to_string()])
+            .await
+            .unwrap();
+
+        let speaker = source.insert_speaker(&[0.1; 512]).await.unwrap();
+        let audio_chunk_id = source
+            .insert_audio_chunk("archived_audio.mp4")
+            .await
+            .unwrap();
+        source
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "archived meeting notes",
+                0,
+                "",
+                &AudioDevice {
+                    name: "test".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                Some(speaker.id),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let data = source
+            .export_archive_data(
+                Utc::now() - chrono::Duration::hours(1),
+                Utc::now() + chrono::Duration::hours(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(data.frames.len(), 1);
+        assert_eq!(data.ocr_text[0].text_json.as_deref(), Some("[]"));
+        assert_eq!(data.audio_transcriptions.len(), 1);
+        assert_eq!(data.speaker_embeddings.len(), 1);
+        assert_eq!(data.tags[0].name, "work");
+
+        let target = setup_test_db().await;
+        // shift the target ids so a missing remap would be caught
+        target
+            .insert_video_chunk("existing_video.mp4", "other_device")
+            .await
+            .unwrap();
+        target
+            .insert_frame("other_device", None, None, None, None, false)
+            .await
+            .unwrap();
+
+        let stats = target.import_archive_data(&data).await.unwrap();
+        assert_eq!(stats.video_chunks_imported, 1);
+        assert_eq!(stats.frames_imported, 1);
+        assert_eq!(stats.ocr_text_imported, 1);
+        assert_eq!(stats.audio_transcriptions_imported, 1);
+        assert_eq!(stats.speakers_imported, 1);
+
+        let results = target
+            .search(
+                "archived",
+                ContentType::All,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        for result in results {
+            match result {
+                SearchResult::OCR(ocr) => {
+                    assert_ne!(ocr.frame_id, frame_id);
+                    assert_eq!(ocr.file_path, "archived_video.mp4");
+                    assert_eq!(ocr.tags, vec!["work".to_string()]);
+                }
+                SearchResult::Audio(audio) => {
+                    assert!(audio.speaker.is_some());
+                }
+                _ => panic!("unexpected result type"),
+            }
+        }
+
+        // importing the same archive twice must not duplicate anything
+        let stats = target.import_archive_data(&data).await.unwrap();
+        assert_eq!(stats.video_chunks_imported, 0);
+        assert_eq!(stats.frames_imported, 0);
+        assert_eq!(stats.frames_skipped, 1);
+        assert_eq!(stats.audio_transcriptions_skipped, 1);
+        assert_eq!(stats.speakers_merged, 1);
+        assert_eq!(stats.tags_imported, 0);
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_fuses_keyword_and_vector_ranks() {
+        let db = setup_test_db().await;
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+
+        // keyword only match
+        let keyword_frame = db
+            .insert_frame("test_device", None, None, Some("terminal"), Some(""), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(
+            keyword_frame,
+            "quarterly budget spreadsheet",
+            "",
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+        db.insert_embeddings(keyword_frame, "[0.0, 1.0, 0.0]".to_string())
+            .await
+            .unwrap();
+
+        // keyword and vector match
+        let both_frame = db
+            .insert_frame("test_device", None, None, Some("sheets"), Some(""), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(
+            both_frame,
+            "budget review",
+            "",
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+        db.insert_embeddings(both_frame, "[1.0, 0.0, 0.0]".to_string())
+            .await
+            .unwrap();
+
+        // vector only match
+        let vector_frame = db
+            .insert_frame("test_device", None, None, Some("sheets"), Some(""), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(
+            vector_frame,
+            "expenses forecast",
+            "",
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+        db.insert_embeddings(vector_frame, "[0.9, 0.1, 0.0]".to_string())
+            .await
+            .unwrap();
+
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "let's talk about the budget",
+            0,
+            "",
+            &AudioDevice {
+                name: "test".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let query_embedding = [1.0_f32, 0.0, 0.0];
+        let (results, total) = db
+            .search_hybrid(
+                "budget",
+                Some(&query_embedding),
+                ContentType::All,
+                10,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                0.5,
+                DEFAULT_RRF_K,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(total, 4);
+        match &results[0].result {
+            SearchResult::OCR(ocr) => assert_eq!(ocr.frame_id, both_frame),
+            _ => panic!("expected the frame matching both lists first"),
+        }
+        assert!(results[0].score.keyword_rank.is_some());
+        assert_eq!(results[0].score.vector_rank, Some(1));
+        assert!(results
+            .iter()
+            .any(|r| matches!(r.result, SearchResult::Audio(_))));
+        let vector_only = results
+            .iter()
+            .find(|r| matches!(&r.result, SearchResult::OCR(ocr) if ocr.frame_id == vector_frame))
+            .unwrap();
+        assert!(vector_only.score.keyword_rank.is_none());
+        assert_eq!(vector_only.score.vector_rank, Some(2));
+
+        // filters apply to both lists and drop audio like the keyword search does
+        let (results, total) = db
+            .search_hybrid(
+                "budget",
+                Some(&query_embedding),
+                ContentType::All,
+                10,
+                0,
+                None,
+                None,
+                Some("terminal"),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                0.5,
+                DEFAULT_RRF_K,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(total, 1);
+        match &results[0].result {
+            SearchResult::OCR(ocr) => assert_eq!(ocr.frame_id, keyword_frame),
+            _ => panic!("expected an ocr result"),
+        }
+    }
+
+    async fn insert_ui_text(db: &DatabaseManager, text: &str) -> i64 {
+        sqlx::query(
+            r#"
+            INSERT INTO ui_monitoring (
+                text_output,
+                timestamp,
+                app,
+                window,
+                initial_traversal_at
+            ) VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(text)
+        .bind(Utc::now())
+        .bind("test_app")
+        .bind("test_window")
+        .bind(Utc::now())
+        .execute(&db.pool)
+        .await
+        .unwrap()
+        .last_insert_rowid()
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search_covers_audio_and_ui() {
+        let db = setup_test_db().await;
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("sheets"), Some(""), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(
+            frame_id,
+            "budget review",
+            "",
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+        db.insert_embeddings(frame_id, "[0.8, 0.2, 0.0]".to_string())
+            .await
+            .unwrap();
+
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        let transcription_id = db
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "we should cut the travel costs",
+                0,
+                "",
+                &AudioDevice {
+                    name: "test".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let ui_id = insert_ui_text(&db, "Expense report").await;
+
+        assert_eq!(db.count_text_without_embeddings().await.unwrap(), 2);
+        let pending = db
+            .get_audio_transcriptions_without_embeddings(0, 10)
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, transcription_id);
+
+        db.insert_audio_transcription_embedding(transcription_id, &[1.0, 0.0, 0.0])
+            .await
+            .unwrap();
+        db.insert_ui_monitoring_embedding(ui_id, &[0.0, 1.0, 0.0])
+            .await
+            .unwrap();
+        assert_eq!(db.count_text_without_embeddings().await.unwrap(), 0);
+        assert!(db
+            .get_ui_monitoring_without_embeddings(0, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let query_embedding = [1.0_f32, 0.0, 0.0];
+        let results = db
+            .search_similar_content(&query_embedding, ContentType::All, 10, 0.5, None, None)
+            .await
+            .unwrap();
+        // the ui row is orthogonal to the query and falls outside the threshold
+        assert_eq!(results.len(), 2);
+        match &results[0].result {
+            SearchResult::Audio(audio) => assert_eq!(audio.audio_chunk_id, audio_chunk_id),
+            _ => panic!("expected the transcription first"),
+        }
+        assert!(results[0].distance < results[1].distance);
+        assert!(matches!(&results[1].result, SearchResult::OCR(ocr) if ocr.frame_id == frame_id));
+
+        let results = db
+            .search_similar_content(&[0.0, 1.0, 0.0], ContentType::UI, 10, 0.5, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(&results[0].result, SearchResult::UI(ui) if ui.id == ui_id));
+    }
+
+    #[tokio::test]
+    async fn test_text_embedding_backfill_worker() {
+        let db = Arc::new(setup_test_db().await);
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        for (i, text) in ["first", "second", ""].iter().enumerate() {
+            db.insert_audio_transcription(
+                audio_chunk_id,
+                text,
+                i as i64,
+                "",
+                &AudioDevice {
+                    name: "test".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+        insert_ui_text(&db, "ui text").await;
+        insert_ui_text(&db, "fails to embed").await;
+
+        let embed: EmbeddingFn = Arc::new(|text: String| {
+            Box::pin(async move {
+                if text.contains("fails") {
+                    Err(anyhow::anyhow!("model unavailable"))
+                } else {
+                    Ok(vec![1.0, 0.0, 0.0])
+                }
+            })
+        });
+        let (cmd_tx, mut status_rx, _handle) =
+            create_migration_worker_for_job(db.clone(), MigrationJob::TextEmbeddings(embed), None);
+        cmd_tx.send(MigrationCommand::Start).await.unwrap();
+
+        let mut completed = None;
+        while let Some(response) = status_rx.recv().await {
+            if let MigrationStatus::Completed { total_records, .. } = response.status {
+                completed = Some(total_records);
+                break;
+            }
+        }
+        // empty transcriptions are never embedded, failures are skipped and counted
+        assert_eq!(completed, Some(4));
+        assert_eq!(db.count_text_without_embeddings().await.unwrap(), 1);
+        assert_eq!(
+            db.get_ui_monitoring_without_embeddings(0, 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[cfg(not(feature = "sqlcipher"))]
+    #[tokio::test]
+    async fn test_encryption_key_requires_sqlcipher() {
+        let key = "00".repeat(32);
+        let result = DatabaseManager::new_with_key("sqlite::memory:", Some(&key)).await;
+        assert!(matches!(result, Err(sqlx::Error::Configuration(_))));
+    }
+
+    #[tokio::test]
+    async fn test_api_tokens() {
+        let db = setup_test_db().await;
+
+        let scopes = vec!["read-search".to_string(), "write".to_string()];
+        let id = db
+            .insert_api_token("cli", "hash-a", &scopes, None)
+            .await
+            .unwrap();
+        db.insert_api_token("pipe search", "hash-b", &scopes[..1], Some("search"))
+            .await
+            .unwrap();
+
+        let token = db.get_api_token_by_hash("hash-a").await.unwrap().unwrap();
+        assert_eq!(token.id, id);
+        assert_eq!(token.scopes, scopes);
+        assert!(token.last_used_at.is_none());
+        assert!(db.get_api_token_by_hash("unknown").await.unwrap().is_none());
+
+        let tokens = db.list_api_tokens().await.unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert!(tokens[0].last_used_at.is_some());
+        assert_eq!(tokens[1].pipe_id.as_deref(), Some("search"));
+
+        assert_eq!(db.delete_pipe_api_tokens("search").await.unwrap(), 1);
+        assert!(db.delete_api_token(id).await.unwrap());
+        assert!(!db.delete_api_token(id).await.unwrap());
+        assert!(db.list_api_tokens().await.unwrap().is_empty());
+
+        assert!(db.get_pipe_scope_grant("search").await.unwrap().is_empty());
+        db.set_pipe_scope_grant("search", &scopes).await.unwrap();
+        db.set_pipe_scope_grant("search", &scopes[1..])
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_pipe_scope_grant("search").await.unwrap(),
+            vec!["write".to_string()]
+        );
+        assert!(db.delete_pipe_scope_grant("search").await.unwrap());
+        assert!(!db.delete_pipe_scope_grant("search").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_raw_sql_is_read_only() {
+        let db = setup_test_db().await;
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+
+        let rows = db
+            .execute_raw_sql("-- chunks\nSELECT file_path, ';' AS sep FROM video_chunks;")
+            .await
+            .unwrap();
+        assert_eq!(rows[0]["file_path"], "test_video.mp4");
+        assert_eq!(rows[0]["sep"], ";");
+
+        for query in [
+            "DELETE FROM video_chunks",
+            "SELECT 1; DELETE FROM video_chunks",
+            "/* SELECT */ DROP TABLE video_chunks",
+            "   ",
+        ] {
+            assert!(
+                matches!(
+                    db.execute_raw_sql(query).await,
+                    Err(RawSqlError::Rejected(_))
+                ),
+                "{}",
+                query
+            );
+        }
+
+        // writes hidden behind an allowed keyword are refused by sqlite itself
+        let hidden_write = db
+            .execute_raw_sql("WITH doomed AS (SELECT id FROM video_chunks) DELETE FROM video_chunks WHERE id IN doomed")
+            .await;
+        assert!(matches!(hidden_write, Err(RawSqlError::Database(_))));
+
+        let changed = db
+            .execute_raw_sql_mutation("UPDATE video_chunks SET device_name = 'renamed'")
+            .await
+            .unwrap();
+        assert_eq!(changed, 1);
+        let rows = db
+            .execute_raw_sql("SELECT device_name FROM video_chunks")
+            .await
+            .unwrap();
+        assert_eq!(rows[0]["device_name"], "renamed");
+    }
+
+    #[tokio::test]
+    async fn test_raw_sql_limits() {
+        let db = setup_test_db().await;
+
+        let output = db
+            .execute_raw_sql_with_limits(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 100) SELECT i FROM n",
+                RawSqlLimits {
+                    max_rows: 10,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(output.rows.len(), 10);
+        assert!(output.truncated);
+
+        let endless = db
+            .execute_raw_sql_with_limits(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) SELECT COUNT(*) FROM n",
+                RawSqlLimits {
+                    timeout: std::time::Duration::from_millis(200),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(endless, Err(RawSqlError::TimedOut(_))));
+
+        // the interrupted connection is usable again
+        let rows = db.execute_raw_sql("SELECT 1 AS one").await.unwrap();
+        assert_eq!(rows[0]["one"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_frame_redactions() {
+        let db = setup_test_db().await;
+        let chunk_id = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("test"), Some(""), false)
+            .await
+            .unwrap();
+
+        let redactions = vec![
+            FrameRedaction {
+                pii_type: "CREDIT_CARD".to_string(),
+                mode: "blur".to_string(),
+                x: 10,
+                y: 20,
+                width: 300,
+                height: 24,
+            },
+            FrameRedaction {
+                pii_type: "EMAIL".to_string(),
+                mode: "blur".to_string(),
+                x: 10,
+                y: 60,
+                width: 200,
+                height: 24,
+            },
+        ];
+        db.insert_frame_redactions(frame_id, &redactions)
+            .await
+            .unwrap();
+        assert_eq!(db.get_frame_redactions(frame_id).await.unwrap(), redactions);
+
+        db.delete_video_chunks(&[chunk_id]).await.unwrap();
+        assert!(db.get_frame_redactions(frame_id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ui_monitoring_cursor() {
+        let db = setup_test_db().await;
+        assert!(db
+            .get_latest_ui_monitoring_cursor()
+            .await
+            .unwrap()
+            .is_none());
+
+        for (app, timestamp) in [
+            ("first", "2025-03-10T10:00:00Z"),
+            ("second", "2025-03-10T10:00:00Z"),
+            ("third", "2025-03-10T10:00:05Z"),
+        ] {
+            sqlx::query(
+                "INSERT INTO ui_monitoring (text_output, timestamp, app, window) VALUES (?1, ?2, ?3, 'w')",
+            )
+            .bind(format!("text from {}", app))
+            .bind(timestamp)
+            .bind(app)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+
+        let (timestamp, id) = db.get_latest_ui_monitoring_cursor().await.unwrap().unwrap();
+        assert_eq!(timestamp, "2025-03-10T10:00:05Z");
+
+        let rows = db
+            .get_ui_monitoring_written_after("2025-03-10T10:00:00Z", 1, 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            rows.iter().map(|row| row.app.as_str()).collect::<Vec<_>>(),
+            vec!["second", "third"]
+        );
+        assert!(db
+            .get_ui_monitoring_written_after(&timestamp, id, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert!(db
+            .update_ui_monitoring_text(id, "text from third", "[REDACTED]")
+            .await
+            .unwrap());
+        // the text changed since it was read
+        assert!(!db
+            .update_ui_monitoring_text(id, "text from third", "[REDACTED]")
+            .await
+            .unwrap());
+    }
+
+    /// A voice embedding pointing mostly along `axis`
+    fn voice(axis: usize, noise: f32) -> Vec<f32> {
+        let mut embedding = vec![noise; 512];
+        embedding[axis] = 1.0;
+        embedding
+    }
+
+    async fn insert_transcription_of(db: &DatabaseManager, speaker_id: i64, path: &str) -> i64 {
+        let audio_chunk_id = db.insert_audio_chunk(path).await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "test transcription",
+            0,
+            "",
+            &AudioDevice {
+                name: "test".to_string(),
+                device_type: DeviceType::Input,
+            },
+            Some(speaker_id),
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_speaker_enrollment_and_merge_suggestions() {
+        let db = setup_test_db().await;
+
+        let alice = db
+            .enroll_speaker("alice", &[voice(0, 0.0), voice(0, 0.02)])
+            .await
+            .unwrap();
+        assert_eq!(alice.name, "alice");
+        // enrolling again adds to the same speaker
+        let again = db.enroll_speaker("alice", &[voice(0, 0.01)]).await.unwrap();
+        assert_eq!(again.id, alice.id);
+
+        let fragment = db.insert_speaker(&voice(0, 0.05)).await.unwrap();
+        let bob = db.insert_speaker(&voice(1, 0.0)).await.unwrap();
+
+        // the centroid follows the segments matched to a speaker
+        db.update_speaker_centroid(bob.id, &voice(1, 0.01))
+            .await
+            .unwrap();
+        let matched = db
+            .get_speaker_from_embedding(&voice(1, 0.005))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(matched.id, bob.id);
+
+        let suggestions = db.get_speaker_merge_suggestions(0.3, 10).await.unwrap();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].speaker_to_keep_id, alice.id);
+        assert_eq!(suggestions[0].speaker_to_merge_id, fragment.id);
+
+        db.merge_speakers(alice.id, fragment.id).await.unwrap();
+        assert!(db
+            .get_speaker_merge_suggestions(0.3, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let matched = db
+            .get_speaker_from_embedding(&voice(0, 0.05))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(matched.id, alice.id);
+    }
+
+    #[tokio::test]
+    async fn test_speaker_reattribution() {
+        let db = Arc::new(setup_test_db().await);
+
+        let alice = db.enroll_speaker("alice", &[voice(0, 0.0)]).await.unwrap();
+        let fragment = db.insert_speaker(&voice(0, 0.1)).await.unwrap();
+        let other = db.insert_speaker(&voice(1, 0.0)).await.unwrap();
+
+        let sounds_like_alice = insert_transcription_of(&db, fragment.id, "audio0").await;
+        db.insert_audio_transcription_speaker_embedding(sounds_like_alice, &voice(0, 0.05))
+            .await
+            .unwrap();
+        let someone_else = insert_transcription_of(&db, other.id, "audio1").await;
+        db.insert_audio_transcription_speaker_embedding(someone_else, &voice(1, 0.0))
+            .await
+            .unwrap();
+        // recorded before voice embeddings were stored
+        insert_transcription_of(&db, fragment.id, "audio2").await;
+        let same_chunk = db.get_or_insert_audio_chunk("audio0").await.unwrap();
+        let sibling = db
+            .insert_audio_transcription(
+                same_chunk,
+                "later in the same chunk",
+                0,
+                "",
+                &AudioDevice {
+                    name: "test".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                Some(other.id),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let (cmd_tx, mut status_rx, _handle) =
+            create_migration_worker_for_job(db.clone(), MigrationJob::SpeakerReattribution, None);
+        cmd_tx.send(MigrationCommand::Start).await.unwrap();
+
+        let mut completed = None;
+        while let Some(response) = status_rx.recv().await {
+            if let MigrationStatus::Completed { total_records, .. } = response.status {
+                completed = Some(total_records);
+                break;
+            }
+        }
+        assert_eq!(completed, Some(2));
+
+        let speakers: Vec<i64> =
+            sqlx::query_scalar("SELECT speaker_id FROM audio_transcriptions ORDER BY id")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(speakers, vec![alice.id, other.id, fragment.id, other.id]);
+
+        // re-attributing a segment leaves the text of the rest of its chunk searchable
+        let results = db
+            .search_audio("later", 10, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].transcription_id, sibling);
+    }
+
+    #[tokio::test]
+    async fn test_transcriptions_for_diarization() {
+        let db = setup_test_db().await;
+        let first = db.insert_speaker(&voice(0, 0.0)).await.unwrap();
+        let second = db.insert_speaker(&voice(1, 0.0)).await.unwrap();
+
+        let mut ids = Vec::new();
+        for (path, device, start_time, text) in [
+            ("meeting_mic.mp4", "mic", 0.0, "opening remarks"),
+            ("meeting_mic.mp4", "mic", 4.0, "budget review"),
+            ("meeting_speakers.mp4", "speakers", 1.0, "remote question"),
+        ] {
+            let audio_chunk_id = db.get_or_insert_audio_chunk(path).await.unwrap();
+            let id = db
+                .insert_audio_transcription(
+                    audio_chunk_id,
+                    text,
+                    0,
+                    "",
+                    &AudioDevice {
+                        name: device.to_string(),
+                        device_type: DeviceType::Input,
+                    },
+                    Some(first.id),
+                    Some(start_time),
+                    Some(start_time + 2.0),
+                )
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+
+        let now = Utc::now();
+        let window = db
+            .get_transcriptions_for_diarization(now - chrono::Duration::hours(1), now, None)
+            .await
+            .unwrap();
+        assert_eq!(window.iter().map(|t| t.id).collect::<Vec<_>>(), ids);
+        assert_eq!(window[0].file_path, "meeting_mic.mp4");
+        assert_eq!(window[1].start_time, Some(4.0));
+        assert_eq!(window[1].end_time, Some(6.0));
+
+        let mic = db
+            .get_transcriptions_for_diarization(now - chrono::Duration::hours(1), now, Some("mic"))
+            .await
+            .unwrap();
+        assert_eq!(mic.len(), 2);
+        let before = db
+            .get_transcriptions_for_diarization(
+                now - chrono::Duration::hours(2),
+                now - chrono::Duration::hours(1),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(before.is_empty());
+
+        // only transcriptions whose speaker changes are counted
+        let updated = db
+            .assign_transcription_speakers(&[(ids[0], second.id), (ids[1], first.id)])
+            .await
+            .unwrap();
+        assert_eq!(updated, 1);
+
+        let speakers: Vec<i64> =
+            sqlx::query_scalar("SELECT speaker_id FROM audio_transcriptions ORDER BY id")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(speakers, vec![second.id, first.id, first.id]);
+
+        // the other segment of the chunk keeps its search text
+        let results = db
+            .search_audio("budget", 10, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].transcription_id, ids[1]);
+    }
+
+    #[tokio::test]
+    async fn test_transcription_words() {
+        let db = setup_test_db().await;
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "test".to_string(),
+            device_type: DeviceType::Input,
+        };
+        // recorded before word timings were stored
+        let without_words = db
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "goodbye moon",
+                0,
+                "",
+                &device,
+                None,
+                Some(0.0),
+                Some(1.0),
+            )
+            .await
+            .unwrap();
+        let audio = db
+            .get_audio_transcription(without_words)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(audio.words.is_empty());
+
+        let words = vec![
+            TranscriptionWord {
+                text: "hello".to_string(),
+                start: 2.0,
+                end: 2.4,
+                confidence: Some(0.9),
+            },
+            TranscriptionWord {
+                text: "world".to_string(),
+                start: 2.5,
+                end: 3.0,
+                confidence: None,
+            },
+        ];
+        let id = db
+            .insert_audio_transcription_with_words(
+                audio_chunk_id,
+                "hello world",
+                0,
+                "",
+                &device,
+                None,
+                Some(2.0),
+                Some(3.0),
+                &words,
+            )
+            .await
+            .unwrap();
+
+        let audio = db.get_audio_transcription(id).await.unwrap().unwrap();
+        assert_eq!(audio.transcription_id, id);
+        assert_eq!(audio.words, words);
+        assert!(db.get_audio_transcription(id + 1).await.unwrap().is_none());
+
+        let results = db
+            .search_audio("hello", 10, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].transcription_id, id);
+        assert_eq!(results[0].words, words);
+
+        // trimming the overlap with the next transcription drops the timings of the words cut
+        let other_chunk_id = db.insert_audio_chunk("other_audio.mp4").await.unwrap();
+        let trimmed = db
+            .insert_audio_transcription_with_words(
+                other_chunk_id,
+                "hello world",
+                0,
+                "",
+                &device,
+                None,
+                Some(2.0),
+                Some(3.0),
+                &words,
+            )
+            .await
+            .unwrap();
+        db.update_audio_transcription(other_chunk_id, "hello")
+            .await
+            .unwrap();
+        let audio = db.get_audio_transcription(trimmed).await.unwrap().unwrap();
+        assert_eq!(audio.transcription, "hello");
+        assert_eq!(audio.words, words[..1]);
+
+        db.update_audio_transcription(audio_chunk_id, "goodbye")
+            .await
+            .unwrap();
+        let audio = db
+            .get_audio_transcription(without_words)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(audio.words.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_audio_fts_rows_are_kept_per_segment() {
+        let db = setup_test_db().await;
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "test".to_string(),
+            device_type: DeviceType::Input,
+        };
+        let first = db
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "alpha segment",
+                0,
+                "",
+                &device,
+                None,
+                Some(0.0),
+                Some(1.0),
+            )
+            .await
+            .unwrap();
+        let second = db
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "bravo segment",
+                0,
+                "",
+                &device,
+                None,
+                Some(1.0),
+                Some(2.0),
+            )
+            .await
+            .unwrap();
+
+        // updates of other columns leave the fts rows alone
+        sqlx::query("UPDATE audio_transcriptions SET speaker_id = 1 WHERE id = ?1")
+            .bind(second)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let fts: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT rowid, transcription FROM audio_transcriptions_fts ORDER BY rowid",
+        )
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            fts,
+            vec![
+                (first, "alpha segment".to_string()),
+                (second, "bravo segment".to_string())
+            ]
+        );
+
+        // a changed segment only rewrites its own row
+        sqlx::query(
+            "UPDATE audio_transcriptions SET transcription = 'charlie segment' WHERE id = ?1",
+        )
+        .bind(second)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let fts: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT rowid, transcription FROM audio_transcriptions_fts ORDER BY rowid",
+        )
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            fts,
+            vec![
+                (first, "alpha segment".to_string()),
+                (second, "charlie segment".to_string())
+            ]
+        );
+        let results = db
+            .search_audio("alpha", 10, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].transcription_id, first);
+
+        // and a deleted one only removes its own row
+        sqlx::query("DELETE FROM audio_transcriptions WHERE id = ?1")
+            .bind(second)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let fts: Vec<i64> =
+            sqlx::query_scalar("SELECT rowid FROM audio_transcriptions_fts ORDER BY rowid")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(fts, vec![first]);
+    }
+
+    #[tokio::test]
+    async fn test_audio_transcription_versions() {
+        let db = setup_test_db().await;
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        let id = db
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "helo wrld",
+                0,
+                "WhisperTinyQuantized",
+                &AudioDevice {
+                    name: "test".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                None,
+                Some(0.0),
+                Some(2.0),
+            )
+            .await
+            .unwrap();
+
+        let versions = db.get_audio_transcription_versions(id).await.unwrap();
+        assert_eq!(versions.len(), 1);
+        assert!(versions[0].current);
+        assert!(db
+            .get_audio_transcription_versions(id + 1)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let now = Utc::now();
+        let targets = db
+            .get_transcriptions_for_retranscription(now - chrono::Duration::hours(1), now, None)
+            .await
+            .unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].transcription_engine, "WhisperTinyQuantized");
+        assert_eq!(targets[0].file_path, "test_audio.mp4");
+
+        let sibling = db
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "next segment",
+                0,
+                "WhisperTinyQuantized",
+                &AudioDevice {
+                    name: "test".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                None,
+                Some(2.0),
+                Some(4.0),
+            )
+            .await
+            .unwrap();
+
+        let words = vec![TranscriptionWord {
+            text: "hello".to_string(),
+            start: 0.1,
+            end: 0.5,
+            confidence: Some(0.9),
+        }];
+        db.add_audio_transcription_version(id, "hello world", "WhisperLargeV3Turbo", &words, true)
+            .await
+            .unwrap();
+        // a worse version is kept but not searched
+        db.add_audio_transcription_version(id, "hallo world", "WhisperTiny", &[], false)
+            .await
+            .unwrap();
+
+        let versions = db.get_audio_transcription_versions(id).await.unwrap();
+        let engines: Vec<(&str, bool)> = versions
+            .iter()
+            .map(|v| (v.transcription_engine.as_str(), v.current))
+            .collect();
+        assert_eq!(
+            engines,
+            vec![
+                ("WhisperTinyQuantized", false),
+                ("WhisperLargeV3Turbo", true),
+                ("WhisperTiny", false),
+            ]
+        );
+        assert_eq!(versions[0].transcription, "helo wrld");
+        assert_eq!(versions[1].words, words);
+
+        let results = db
+            .search_audio("hello", 10, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].transcription, "hello world");
+        assert_eq!(results[0].transcription_engine, "WhisperLargeV3Turbo");
+        assert_eq!(results[0].words, words);
+
+        // the other segment of the chunk keeps its own text
+        let results = db
+            .search_audio("next", 10, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].transcription_id, sibling);
+        assert_eq!(results[0].transcription, "next segment");
+        assert!(db
+            .search_audio("helo", 10, 0, None, None, None, None, None)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // running the same engine again replaces its version
+        db.add_audio_transcription_version(id, "hello world!", "WhisperLargeV3Turbo", &[], true)
+            .await
+            .unwrap();
+        let versions = db.get_audio_transcription_versions(id).await.unwrap();
+        assert_eq!(versions.len(), 3);
+        let audio = db.get_audio_transcription(id).await.unwrap().unwrap();
+        assert_eq!(audio.transcription, "hello world!");
+
+        // the replaced text is embedded again, once
+        let pending = db
+            .get_retranscribed_without_embeddings(0, 10)
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].1.id, id);
+        assert_eq!(pending[0].1.text, "hello world!");
+        db.insert_audio_transcription_embedding(id, &[1.0, 0.0, 0.0])
+            .await
+            .unwrap();
+        assert!(db
+            .get_retranscribed_without_embeddings(0, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let (_, _, latest_version) = db.get_latest_text_ids().await.unwrap();
+        assert_eq!(latest_version, pending[0].0);
+    }
+
+    #[tokio::test]
+    async fn test_imported_audio_timestamps_and_speaker_hints() {
+        let db = setup_test_db().await;
+        let alice = db.enroll_speaker("alice", &[voice(0, 0.0)]).await.unwrap();
+        let bob = db.enroll_speaker("bob", &[voice(1, 0.0)]).await.unwrap();
+        let unknown = db.insert_speaker(&voice(2, 0.0)).await.unwrap();
+
+        let call = db.insert_audio_chunk("phone_call.mp4").await.unwrap();
+        let mut ids = Vec::new();
+        for (text, start_time, axis) in [
+            ("imported call hello", 0.0, 0),
+            ("imported call hi", 5.5, 1),
+            ("imported call bye", 10.0, 2),
+        ] {
+            let id = db
+                .insert_audio_transcription(
+                    call,
+                    text,
+                    0,
+                    "WhisperLargeV3Turbo",
+                    &AudioDevice {
+                        name: "phone".to_string(),
+                        device_type: DeviceType::Input,
+                    },
+                    Some(unknown.id),
+                    Some(start_time),
+                    Some(start_time + 5.0),
+                )
+                .await
+                .unwrap();
+            db.insert_audio_transcription_speaker_embedding(id, &voice(axis, 0.01))
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+
+        let recorded_at = Utc::now() - chrono::Duration::days(30);
+        db.set_audio_chunk_timestamp(call, recorded_at)
+            .await
+            .unwrap();
+        let results = db
+            .search_audio(
+                "imported",
+                10,
+                0,
+                Some(recorded_at - chrono::Duration::minutes(1)),
+                Some(recorded_at + chrono::Duration::minutes(1)),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        let timestamps: Vec<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT timestamp FROM audio_transcriptions WHERE audio_chunk_id = ?1 ORDER BY id",
+        )
+        .bind(call)
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            timestamps,
+            vec![
+                recorded_at,
+                recorded_at + chrono::Duration::milliseconds(5500),
+                recorded_at + chrono::Duration::seconds(10),
+            ]
+        );
+
+        let updated = db
+            .apply_speaker_hints(call, &["alice".to_string(), "bob".to_string()], 0.4)
+            .await
+            .unwrap();
+        assert_eq!(updated, 2);
+        let speakers: Vec<i64> = sqlx::query_scalar(
+            "SELECT speaker_id FROM audio_transcriptions WHERE audio_chunk_id = ?1 ORDER BY id",
+        )
+        .bind(call)
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+        assert_eq!(speakers, vec![alice.id, bob.id, unknown.id]);
+        // the per segment updates leave the text of every segment searchable
+        for (id, word) in ids.iter().zip(["hello", "hi", "bye"]) {
+            let results = db
+                .search_audio(word, 10, 0, None, None, None, None, None)
+                .await
+                .unwrap();
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].transcription_id, *id);
+        }
+
+        // a single name takes every transcription, creating the speaker
+        let memo = db.insert_audio_chunk("voice_memo.mp4").await.unwrap();
+        let memo_transcription = db
+            .insert_audio_transcription(
+                memo,
+                "note to self",
+                0,
+                "WhisperLargeV3Turbo",
+                &AudioDevice {
+                    name: "phone".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                Some(unknown.id),
+                Some(0.0),
+                Some(3.0),
+            )
+            .await
+            .unwrap();
+        db.insert_audio_transcription_speaker_embedding(memo_transcription, &voice(3, 0.0))
+            .await
+            .unwrap();
+        assert_eq!(
+            db.apply_speaker_hints(memo, &["carol".to_string()], 0.4)
+                .await
+                .unwrap(),
+            1
+        );
+        let carol = db.search_speakers("carol").await.unwrap();
+        assert_eq!(carol.len(), 1);
+        let matched = db
+            .get_speaker_from_embedding(&voice(3, 0.0))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(matched.id, carol[0].id);
+    }
+
+    #[tokio::test]
+    async fn test_cross_device_transcript_dedup() {
+        let db = setup_test_db().await;
+        let microphone = AudioDevice {
+            name: "microphone".to_string(),
+            device_type: DeviceType::Input,
+        };
+        let speakers = AudioDevice {
+            name: "speakers".to_string(),
+            device_type: DeviceType::Output,
+        };
+        let gap = chrono::Duration::seconds(60);
+
+        // the other side of a call, heard by the output device and through the microphone
+        let mic_chunk = db.insert_audio_chunk("mic.mp4").await.unwrap();
+        let output_chunk = db.insert_audio_chunk("output.mp4").await.unwrap();
+        let heard = db
+            .insert_audio_transcription(
+                mic_chunk,
+                "so the quarterly numbers look great",
+                0,
+                "",
+                &microphone,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let played = db
+            .insert_audio_transcription(
+                output_chunk,
+                "So, the quarterly numbers look great.",
+                0,
+                "",
+                &speakers,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        // too short to tell apart from a coincidence
+        let short_chunk = db.insert_audio_chunk("mic_short.mp4").await.unwrap();
+        let short = db
+            .insert_audio_transcription(short_chunk, "okay", 0, "", &microphone, None, None, None)
+            .await
+            .unwrap();
+
+        assert!(db
+            .deduplicate_audio_transcription(short, gap, 0.8)
+            .await
+            .unwrap()
+            .is_none());
+        let duplicate = db
+            .deduplicate_audio_transcription(played, gap, 0.8)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(duplicate.kept_id, played);
+        assert_eq!(duplicate.duplicate_id, heard);
+        assert_eq!(duplicate.similarity, 1.0);
+        // already handled
+        assert!(db
+            .deduplicate_audio_transcription(heard, gap, 0.8)
+            .await
+            .unwrap()
+            .is_none());
+
+        let results = db
+            .search_audio("quarterly", 10, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].transcription_id, played);
+        assert_eq!(
+            db.count_search_results(
+                "quarterly",
+                ContentType::Audio,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None
+            )
+            .await
+            .unwrap(),
+            1
+        );
+
+        // the microphone also caught someone in the room, so it has speech the output lacks
+        let both = db
+            .insert_audio_transcription(
+                mic_chunk,
+                "sorry let me check that and the next slide shows revenue by region",
+                2,
+                "",
+                &microphone,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let remote = db
+            .insert_audio_transcription(
+                output_chunk,
+                "the next slide shows revenue by region",
+                1,
+                "",
+                &speakers,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let duplicate = db
+            .deduplicate_audio_transcription(remote, gap, 0.8)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(duplicate.kept_id, both);
+        assert_eq!(duplicate.duplicate_id, remote);
+        // marking a segment doesn't touch the search text of the rest of its chunk
+        let results = db
+            .search_audio("quarterly", 10, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].transcription_id, played);
+
+        // different speech at the same time isn't a duplicate
+        let other = db
+            .insert_audio_transcription(
+                output_chunk,
+                "meanwhile the weather turned cold",
+                2,
+                "",
+                &speakers,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(db
+            .deduplicate_audio_transcription(other, gap, 0.8)
+            .await
+            .unwrap()
+            .is_none());
+
+        assert_eq!(db.get_latest_audio_transcription_id().await.unwrap(), other);
+        assert_eq!(
+            db.get_audio_transcription_ids_after(short, 10)
+                .await
+                .unwrap(),
+            vec![both, remote, other]
+        );
+
+        // the microphone copy is back in search once the one it duplicated is deleted
+        db.delete_audio_chunks(&[output_chunk]).await.unwrap();
+        let results = db
+            .search_audio("quarterly", 10, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].transcription_id, heard);
+    }
+
+    #[tokio::test]
+    async fn test_replace_ocr_text() {
+        let db = setup_test_db().await;
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("test"), Some(""), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(frame_id, "", "[]", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+
+        let search = |query: &'static str| {
+            let db = &db;
+            async move {
+                db.search(
+                    query,
+                    ContentType::OCR,
+                    100,
+                    0,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap()
+            }
+        };
+
+        // text found later by another engine is searchable, the earlier text isn't
+        db.replace_ocr_text(
+            frame_id,
+            "quarterly report",
+            "[]",
+            Arc::new(OcrEngine::Other("custom".to_string())),
+        )
+        .await
+        .unwrap();
+        db.replace_ocr_text(
+            frame_id,
+            "annual report",
+            "[]",
+            Arc::new(OcrEngine::Other("custom".to_string())),
+        )
+        .await
+        .unwrap();
+
+        assert!(search("quarterly").await.is_empty());
+        let results = search("annual").await;
+        assert_eq!(results.len(), 1);
+        match &results[0] {
+            SearchResult::OCR(ocr_result) => {
+                assert_eq!(ocr_result.frame_id, frame_id);
+                assert_eq!(ocr_result.ocr_text, "annual report");
+            }
+            _ => panic!("Expected OCR result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ocr_queue() {
+        let db = setup_test_db().await;
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let mut frame_ids = Vec::new();
+        for window in ["a", "b", "c"] {
+            frame_ids.push(
+                db.insert_frame("test_device", None, None, Some("app"), Some(window), true)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // the oldest window is dropped when the queue is full
+        assert_eq!(db.enqueue_ocr_job(frame_ids[0], b"a", 2).await.unwrap(), 0);
+        assert_eq!(db.enqueue_ocr_job(frame_ids[1], b"b", 2).await.unwrap(), 0);
+        assert_eq!(db.enqueue_ocr_job(frame_ids[2], b"c", 2).await.unwrap(), 1);
+
+        let first = db.claim_ocr_job().await.unwrap().unwrap();
+        assert_eq!(first.frame_id, frame_ids[1]);
+        assert_eq!(first.image, b"b");
+        assert_eq!(first.window_name.as_deref(), Some("b"));
+        let second = db.claim_ocr_job().await.unwrap().unwrap();
+        assert_eq!(second.frame_id, frame_ids[2]);
+        assert!(db.claim_ocr_job().await.unwrap().is_none());
+
+        let counts = db.count_ocr_jobs().await.unwrap();
+        assert_eq!((counts.queued, counts.in_progress), (0, 2));
+        assert!(counts.oldest_queued_at.is_none());
+
+        // claimed windows aren't dropped for new ones
+        assert_eq!(db.enqueue_ocr_job(frame_ids[0], b"a", 1).await.unwrap(), 0);
+        assert_eq!(db.enqueue_ocr_job(frame_ids[0], b"a", 1).await.unwrap(), 1);
+
+        // failed windows are queued again, then dropped
+        assert!(!db.release_ocr_job(first.id, 2, Utc::now()).await.unwrap());
+        let counts = db.count_ocr_jobs().await.unwrap();
+        assert_eq!((counts.queued, counts.in_progress), (2, 1));
+        assert!(counts.oldest_queued_at.is_some());
+        let retried = db.claim_ocr_job().await.unwrap().unwrap();
+        assert_eq!((retried.id, retried.attempts), (first.id, 1));
+        assert!(db.release_ocr_job(retried.id, 2, Utc::now()).await.unwrap());
+
+        assert!(db
+            .complete_ocr_job(
+                second.id,
+                second.frame_id,
+                "hello",
+                "[]",
+                Arc::new(OcrEngine::Tesseract)
+            )
+            .await
+            .unwrap());
+        assert!(!db
+            .complete_ocr_job(
+                second.id,
+                second.frame_id,
+                "hello",
+                "[]",
+                Arc::new(OcrEngine::Tesseract)
+            )
+            .await
+            .unwrap());
+        let results = db
+            .search(
+                "hello",
+                ContentType::OCR,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        // windows claimed when screenpipe stopped are queued again
+        db.claim_ocr_job().await.unwrap().unwrap();
+        assert_eq!(db.reset_ocr_jobs().await.unwrap(), 1);
+        let counts = db.count_ocr_jobs().await.unwrap();
+        assert_eq!((counts.queued, counts.in_progress), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_ocr_jobs_wait_before_being_retried() {
+        let db = setup_test_db().await;
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("app"), Some("a"), true)
+            .await
+            .unwrap();
+        db.enqueue_deferred_ocr_job(frame_id, b"a", 10)
+            .await
+            .unwrap();
+
+        // postponed while the engine is unavailable, without counting a failed attempt
+        let job = db.claim_ocr_job().await.unwrap().unwrap();
+        db.postpone_ocr_job(job.id, Utc::now() + chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        assert!(db.claim_ocr_job().await.unwrap().is_none());
+        assert_eq!(db.count_ocr_jobs().await.unwrap().queued, 1);
+
+        db.postpone_ocr_job(job.id, Utc::now()).await.unwrap();
+        let job = db.claim_ocr_job().await.unwrap().unwrap();
+        assert_eq!((job.attempts, job.retries), (0, 2));
+
+        // failed attempts wait too
+        assert!(!db
+            .release_ocr_job(job.id, 5, Utc::now() + chrono::Duration::hours(1))
+            .await
+            .unwrap());
+        assert!(db.claim_ocr_job().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_deferred_ocr_jobs_replace_the_text() {
+        let db = setup_test_db().await;
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("app"), Some("a"), true)
+            .await
+            .unwrap();
+        // read by a fallback engine while the selected one was unavailable
+        db.insert_ocr_text(frame_id, "helo wrld", "[]", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+        db.enqueue_deferred_ocr_job(frame_id, b"a", 10)
+            .await
+            .unwrap();
+        db.enqueue_ocr_job(frame_id, b"b", 10).await.unwrap();
+        assert_eq!(db.count_ocr_jobs().await.unwrap().deferred, 1);
+
+        let deferred = db.claim_ocr_job().await.unwrap().unwrap();
+        assert!(deferred.replaces_text);
+        assert!(!db.claim_ocr_job().await.unwrap().unwrap().replaces_text);
+        assert!(db
+            .complete_ocr_job(
+                deferred.id,
+                frame_id,
+                "hello world",
+                "[]",
+                Arc::new(OcrEngine::Unstructured)
+            )
+            .await
+            .unwrap());
+        assert_eq!(db.count_ocr_jobs().await.unwrap().deferred, 0);
+
+        let search = |query: &'static str| {
+            let db = &db;
+            async move {
+                db.search(
+                    query,
+                    ContentType::OCR,
+                    100,
+                    0,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap()
+                .len()
+            }
+        };
+        assert_eq!(search("hello").await, 1);
+        assert_eq!(search("helo").await, 0);
+    }
 }
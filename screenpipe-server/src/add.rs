@@ -1,17 +1,13 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use image::DynamicImage;
 use regex::Regex;
+use screenpipe_audio::core::device::AudioDevice;
+use screenpipe_audio::transcription::import::{AudioImport, AudioImporter};
 use screenpipe_db::DatabaseManager;
-use screenpipe_vision::utils::{compare_with_previous_image, OcrEngine};
-
-#[cfg(target_os = "macos")]
-use screenpipe_vision::perform_ocr_apple;
-
-#[cfg(target_os = "windows")]
-use screenpipe_vision::perform_ocr_windows;
-
-#[allow(unused)]
-use screenpipe_vision::perform_ocr_tesseract;
+use screenpipe_vision::ocr_backend::{default_ocr_backend_name, OcrOutput};
+use screenpipe_vision::utils::compare_with_previous_image;
+use screenpipe_vision::OcrBackendRegistry;
 
 use serde_json::json;
 use std::path::Path;
@@ -25,7 +21,6 @@ use uuid::Uuid;
 use walkdir::WalkDir;
 
 use crate::{
-    cli::CliOcrEngine,
     text_embeds::generate_embedding,
     video_utils::{extract_frames_from_video, get_video_metadata, VideoMetadataOverrides},
 };
@@ -37,7 +32,7 @@ pub async fn handle_index_command(
     pattern: Option<String>,
     db: Arc<DatabaseManager>,
     output_format: crate::cli::OutputFormat,
-    ocr_engine: Option<CliOcrEngine>,
+    ocr_engine: Option<String>,
     metadata_override: Option<PathBuf>,
     copy_videos: bool,
     use_embedding: bool,
@@ -78,6 +73,10 @@ pub async fn handle_index_command(
         }
     }
 
+    // Use specified OCR engine or fall back to platform default
+    let ocr_backend = OcrBackendRegistry::default()
+        .create(ocr_engine.as_deref().unwrap_or(default_ocr_backend_name()))?;
+
     let mut total_frames = 0;
     let mut total_text = 0;
 
@@ -165,35 +164,16 @@ pub async fn handle_index_command(
 
             previous_image = Some(frame.clone());
 
-            // Use specified OCR engine or fall back to platform default
-            let engine = match ocr_engine {
-                Some(ref cli_engine) => cli_engine.clone().into(),
-                None => {
-                    #[cfg(target_os = "macos")]
-                    let engine = OcrEngine::AppleNative;
-                    #[cfg(target_os = "windows")]
-                    let engine = OcrEngine::WindowsNative;
-                    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-                    let engine = OcrEngine::Tesseract;
-                    engine
-                }
-            };
-
             // Do OCR processing directly
-            let (text, _, confidence): (String, String, Option<f64>) = match engine.clone() {
-                #[cfg(target_os = "macos")]
-                OcrEngine::AppleNative => perform_ocr_apple(frame, &[]),
-                #[cfg(target_os = "windows")]
-                OcrEngine::WindowsNative => perform_ocr_windows(&frame).await.unwrap(),
-                _ => {
-                    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-                    {
-                        perform_ocr_tesseract(&frame, Vec::new());
-                    }
-                    warn!("unsupported ocr engine");
-                    ("".to_string(), "".to_string(), None)
+            let output = match ocr_backend.recognize(frame, &[]).await {
+                Ok(output) => output,
+                Err(e) => {
+                    warn!("failed to ocr frame {}: {}", frame_counter, e);
+                    OcrOutput::default()
                 }
             };
+            let text_json = serde_json::to_string(&output.text_json())?;
+            let (text, confidence) = (output.text, output.confidence);
 
             // Handle OCR results
             total_frames += 1;
@@ -225,8 +205,8 @@ pub async fn handle_index_command(
                 .insert_ocr_text(
                     frame_ids[idx],
                     &text,
-                    "{}", // empty json
-                    ocr_engine.clone().unwrap().into(),
+                    &text_json,
+                    Arc::new(ocr_backend.engine()),
                 )
                 .await
             {
@@ -300,8 +280,84 @@ pub async fn handle_index_command(
     Ok(())
 }
 
+/// Extensions of the audio files picked up by `add-audio`
+const AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3", "m4a", "aac", "opus", "ogg", "flac", "webm"];
+
+/// Imports the audio files under `path` as recorded by `device`, see [`AudioImporter`]. Files
+/// that fail are reported and skipped.
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_audio_import_command(
+    importer: &AudioImporter,
+    path: &str,
+    pattern: Option<&str>,
+    timestamp: Option<DateTime<Utc>>,
+    device: AudioDevice,
+    speakers: Vec<String>,
+    output_format: crate::cli::OutputFormat,
+) -> Result<()> {
+    let audio_files = find_files(path, pattern, AUDIO_EXTENSIONS)?;
+    info!("found {} audio files to import", audio_files.len());
+
+    let mut imported = Vec::new();
+    let mut failed = 0;
+    for audio_path in audio_files {
+        let timestamp = match timestamp {
+            Some(timestamp) => timestamp,
+            None => DateTime::<Utc>::from(fs::metadata(&audio_path).await?.modified()?),
+        };
+        let import = AudioImport {
+            timestamp,
+            device: device.clone(),
+            speakers: speakers.clone(),
+        };
+
+        match importer.import_file(&audio_path, &import).await {
+            Ok(summary) => {
+                if output_format == crate::cli::OutputFormat::Text {
+                    println!(
+                        "imported {} recorded at {}: {} transcriptions from {:.0}s of audio",
+                        audio_path.display(),
+                        timestamp,
+                        summary.transcriptions,
+                        summary.duration
+                    );
+                }
+                imported.push(json!({
+                    "file": audio_path.to_string_lossy(),
+                    "timestamp": timestamp,
+                    "summary": summary
+                }));
+            }
+            Err(e) => {
+                error!("failed to import {}: {}", audio_path.display(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    match output_format {
+        crate::cli::OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({
+                "data": imported,
+                "failed": failed,
+                "success": failed == 0
+            }))?
+        ),
+        crate::cli::OutputFormat::Text => {
+            println!("imported {} audio files, {} failed", imported.len(), failed);
+        }
+    }
+
+    Ok(())
+}
+
 fn find_video_files(root: &str, pattern: Option<&str>) -> Result<Vec<PathBuf>> {
-    let mut video_files = Vec::new();
+    find_files(root, pattern, &["mp4", "mov", "avi"])
+}
+
+fn find_files(root: &str, pattern: Option<&str>, extensions: &[&str]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
     let regex = pattern.map(Regex::new).transpose()?;
 
     for entry in WalkDir::new(root)
@@ -312,18 +368,18 @@ fn find_video_files(root: &str, pattern: Option<&str>) -> Result<Vec<PathBuf>> {
         let path = entry.path();
         if path.is_file() {
             if let Some(ext) = path.extension() {
-                if ext == "mp4" || ext == "mov" || ext == "avi" {
+                if extensions.iter().any(|extension| ext == *extension) {
                     if let Some(ref regex) = regex {
                         if regex.is_match(&path.to_string_lossy()) {
-                            video_files.push(path.to_path_buf());
+                            files.push(path.to_path_buf());
                         }
                     } else {
-                        video_files.push(path.to_path_buf());
+                        files.push(path.to_path_buf());
                     }
                 }
             }
         }
     }
 
-    Ok(video_files)
+    Ok(files)
 }
@@ -0,0 +1,358 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use screenpipe_core::encryption;
+use screenpipe_db::{ArchiveData, ArchiveImportStats, DatabaseManager};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Bumped whenever the layout of the archive or of `data.json` changes incompatibly.
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const DATA_ENTRY: &str = "data.json";
+const VIDEO_DIR: &str = "media/video";
+const AUDIO_DIR: &str = "media/audio";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    pub screenpipe_version: String,
+    pub created_at: DateTime<Utc>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub video_chunks: usize,
+    pub frames: usize,
+    pub audio_chunks: usize,
+    pub audio_transcriptions: usize,
+    /// Media referenced by the database that was not found on disk during export
+    pub missing_files: Vec<String>,
+    /// Whether the recordings were encrypted at rest where they were exported. The archive
+    /// holds them decrypted so it can be imported with another key or none.
+    #[serde(default)]
+    pub encrypted: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveImportReport {
+    pub manifest: ArchiveManifest,
+    pub files_extracted: u64,
+    pub files_existing: u64,
+    pub stats: ArchiveImportStats,
+}
+
+/// Writes every recording between `from` and `to` to a zstd compressed tarball at `out`.
+///
+/// The archive holds `manifest.json`, `data.json` with the database rows, and the media files
+/// under `media/`, decrypted. File paths in `data.json` are relative to the archive root.
+pub async fn export_archive(
+    db: &DatabaseManager,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    out: &Path,
+) -> Result<ArchiveManifest> {
+    if from > to {
+        return Err(anyhow!("--from must be before --to"));
+    }
+
+    let mut data = db.export_archive_data(from, to).await?;
+    info!(
+        "exporting {} video chunks, {} frames, {} audio chunks and {} transcriptions",
+        data.video_chunks.len(),
+        data.frames.len(),
+        data.audio_chunks.len(),
+        data.audio_transcriptions.len()
+    );
+
+    let mut media = Vec::new();
+    let mut used_names = HashSet::new();
+    for chunk in data.video_chunks.iter_mut() {
+        let entry = archive_entry_name(VIDEO_DIR, chunk.id, &chunk.file_path, &mut used_names);
+        media.push((PathBuf::from(&chunk.file_path), entry.clone()));
+        chunk.file_path = entry;
+    }
+    for chunk in data.audio_chunks.iter_mut() {
+        let entry = archive_entry_name(AUDIO_DIR, chunk.id, &chunk.file_path, &mut used_names);
+        media.push((PathBuf::from(&chunk.file_path), entry.clone()));
+        chunk.file_path = entry;
+    }
+
+    let missing_files = media
+        .iter()
+        .filter(|(path, _)| !path.is_file())
+        .map(|(path, _)| path.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    for path in &missing_files {
+        warn!("media file not found, exporting its rows only: {}", path);
+    }
+
+    let manifest = ArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        screenpipe_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+        from,
+        to,
+        video_chunks: data.video_chunks.len(),
+        frames: data.frames.len(),
+        audio_chunks: data.audio_chunks.len(),
+        audio_transcriptions: data.audio_transcriptions.len(),
+        missing_files,
+        encrypted: encryption::is_enabled(),
+    };
+
+    let out = out.to_path_buf();
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    let data_json = serde_json::to_vec(&data)?;
+    tokio::task::spawn_blocking(move || write_archive(&out, &manifest_json, &data_json, &media))
+        .await??;
+
+    Ok(manifest)
+}
+
+/// Merges an archive produced by [`export_archive`] into the database and data directory.
+///
+/// Media is extracted to `<screenpipe_dir>/data` and encrypted when encryption is enabled. A
+/// file that already exists with the same name and size is reused, so importing the same
+/// archive twice is a no-op.
+pub async fn import_archive(
+    db: &DatabaseManager,
+    archive: &Path,
+    screenpipe_dir: &Path,
+) -> Result<ArchiveImportReport> {
+    let archive = archive.to_path_buf();
+    let data_dir = screenpipe_dir.join("data");
+    tokio::fs::create_dir_all(&data_dir).await?;
+
+    let extracted =
+        tokio::task::spawn_blocking(move || read_archive(&archive, &data_dir)).await??;
+    let ExtractedArchive {
+        manifest,
+        mut data,
+        media_paths,
+        new_files,
+        files_existing,
+    } = extracted;
+
+    for chunk in data.video_chunks.iter_mut() {
+        if let Some(path) = media_paths.get(&chunk.file_path) {
+            chunk.file_path = path.to_string_lossy().into_owned();
+        }
+    }
+    for chunk in data.audio_chunks.iter_mut() {
+        if let Some(path) = media_paths.get(&chunk.file_path) {
+            chunk.file_path = path.to_string_lossy().into_owned();
+        }
+    }
+
+    let stats = match db.import_archive_data(&data).await {
+        Ok(stats) => stats,
+        Err(e) => {
+            // the transaction was rolled back, don't leave orphaned media behind
+            for path in &new_files {
+                if let Err(e) = tokio::fs::remove_file(path).await {
+                    warn!("failed to remove {}: {}", path.display(), e);
+                }
+            }
+            return Err(e.into());
+        }
+    };
+
+    info!(
+        "imported {} frames and {} transcriptions from archive created at {}",
+        stats.frames_imported, stats.audio_transcriptions_imported, manifest.created_at
+    );
+
+    Ok(ArchiveImportReport {
+        manifest,
+        files_extracted: new_files.len() as u64,
+        files_existing,
+        stats,
+    })
+}
+
+/// Picks a unique entry name for a media file, prefixing the chunk id on collisions.
+fn archive_entry_name(
+    dir: &str,
+    id: i64,
+    file_path: &str,
+    used_names: &mut HashSet<String>,
+) -> String {
+    let file_name = Path::new(file_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| id.to_string());
+    let mut entry = format!("{}/{}", dir, file_name);
+    if !used_names.insert(entry.clone()) {
+        entry = format!("{}/{}_{}", dir, id, file_name);
+        used_names.insert(entry.clone());
+    }
+    entry
+}
+
+fn write_archive(
+    out: &Path,
+    manifest_json: &[u8],
+    data_json: &[u8],
+    media: &[(PathBuf, String)],
+) -> Result<()> {
+    // write next to the destination and rename at the end so a failed export never leaves
+    // a truncated archive behind
+    let partial = out.with_extension("partial");
+    let result = (|| -> Result<()> {
+        let encoder = zstd::Encoder::new(File::create(&partial)?, 0)?;
+        let mut builder = tar::Builder::new(encoder);
+
+        append_bytes(&mut builder, MANIFEST_ENTRY, manifest_json)?;
+        append_bytes(&mut builder, DATA_ENTRY, data_json)?;
+        for (path, entry) in media {
+            if !path.is_file() {
+                continue;
+            }
+            debug!("adding {} as {}", path.display(), entry);
+            // decrypted one at a time, the copy is removed once it's in the archive
+            let media = encryption::open_media_blocking(path)?;
+            builder.append_path_with_name(media.path(), entry)?;
+        }
+
+        builder.into_inner()?.finish()?;
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => {
+            std::fs::rename(&partial, out)?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+fn append_bytes<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    name: &str,
+    bytes: &[u8],
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp() as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, name, bytes)?;
+    Ok(())
+}
+
+struct ExtractedArchive {
+    manifest: ArchiveManifest,
+    data: ArchiveData,
+    /// Archive entry name to the local path of the media file
+    media_paths: HashMap<String, PathBuf>,
+    new_files: Vec<PathBuf>,
+    files_existing: u64,
+}
+
+fn read_archive(archive: &Path, data_dir: &Path) -> Result<ExtractedArchive> {
+    let decoder = zstd::Decoder::new(File::open(archive)?)?;
+    let mut tar = tar::Archive::new(decoder);
+
+    let mut manifest: Option<ArchiveManifest> = None;
+    let mut data: Option<ArchiveData> = None;
+    let mut media_paths = HashMap::new();
+    let mut new_files = Vec::new();
+    let mut files_existing = 0;
+
+    let result = (|| -> Result<()> {
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let entry_name = entry.path()?.to_string_lossy().into_owned();
+
+            if entry_name == MANIFEST_ENTRY {
+                let mut json = String::new();
+                entry.read_to_string(&mut json)?;
+                let parsed: ArchiveManifest = serde_json::from_str(&json)?;
+                if parsed.format_version > ARCHIVE_FORMAT_VERSION {
+                    return Err(anyhow!(
+                        "archive format version {} is newer than the supported version {}, please update screenpipe",
+                        parsed.format_version,
+                        ARCHIVE_FORMAT_VERSION
+                    ));
+                }
+                manifest = Some(parsed);
+            } else if entry_name == DATA_ENTRY {
+                let mut json = Vec::new();
+                entry.read_to_end(&mut json)?;
+                data = Some(serde_json::from_slice(&json)?);
+            } else if entry_name.starts_with("media/") {
+                if manifest.is_none() {
+                    return Err(anyhow!("archive is missing {}", MANIFEST_ENTRY));
+                }
+                // only the file name is used so entries can never escape the data directory
+                let Some(file_name) = Path::new(&entry_name).file_name() else {
+                    continue;
+                };
+                let size = entry.header().size()?;
+                let (dest, exists) = local_media_path(data_dir, Path::new(file_name), size);
+                if exists {
+                    files_existing += 1;
+                } else {
+                    entry.unpack(&dest)?;
+                    new_files.push(dest.clone());
+                    encryption::encrypt_file_if_enabled(&dest)?;
+                }
+                media_paths.insert(entry_name, dest);
+            } else {
+                debug!("skipping unknown archive entry: {}", entry_name);
+            }
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        for path in &new_files {
+            let _ = std::fs::remove_file(path);
+        }
+        return Err(e);
+    }
+
+    Ok(ExtractedArchive {
+        manifest: manifest.ok_or_else(|| anyhow!("archive is missing {}", MANIFEST_ENTRY))?,
+        data: data.ok_or_else(|| anyhow!("archive is missing {}", DATA_ENTRY))?,
+        media_paths,
+        new_files,
+        files_existing,
+    })
+}
+
+/// Returns where a media file should live locally and whether an identical file is already
+/// there, encrypted or not. A different file with the same name gets a numbered suffix instead
+/// of being replaced.
+fn local_media_path(data_dir: &Path, file_name: &Path, size: u64) -> (PathBuf, bool) {
+    let stem = file_name
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = file_name
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut candidate = data_dir.join(file_name);
+    let mut n = 1;
+    loop {
+        match std::fs::metadata(&candidate) {
+            Ok(meta) if meta.len() == size || meta.len() == encryption::encrypted_len(size) => {
+                return (candidate, true)
+            }
+            Ok(_) => {
+                candidate = data_dir.join(format!("{}_imported_{}{}", stem, n, ext));
+                n += 1;
+            }
+            Err(_) => return (candidate, false),
+        }
+    }
+}
@@ -0,0 +1,425 @@
+//! Access control for the http api: scoped bearer tokens and the origins allowed by CORS.
+//!
+//! Tokens are random strings handed out once; only their sha256 is stored. Every route maps to
+//! a [`Scope`] in [`required_scope`], and a request is let through when its token has it.
+
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use clap::ValueEnum;
+use rand::RngCore;
+use screenpipe_db::{ApiToken, DatabaseManager};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{debug, debug_span, error, warn, Span};
+
+/// Environment variable holding the token used by pipes and the cli
+pub const TOKEN_ENV_VAR: &str = "SCREENPIPE_API_TOKEN";
+
+const TOKEN_PREFIX: &str = "sp_";
+const TOKEN_BYTES: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// Search and read recorded content
+    ReadSearch,
+    /// Add, tag or delete content and control recording
+    Write,
+    /// Install, configure and run pipes
+    PipesAdmin,
+    /// Control the desktop through the operator api
+    Operator,
+    /// Run read-only sql against the database
+    RawSql,
+    /// Run sql that changes data through /raw_sql/mutate
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 6] = [
+        Scope::ReadSearch,
+        Scope::Write,
+        Scope::PipesAdmin,
+        Scope::Operator,
+        Scope::RawSql,
+        Scope::Admin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ReadSearch => "read-search",
+            Scope::Write => "write",
+            Scope::PipesAdmin => "pipes-admin",
+            Scope::Operator => "operator",
+            Scope::RawSql => "raw-sql",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Scope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("unknown scope: {}", s))
+    }
+}
+
+/// Returns the scope a request needs, `None` for routes that are always public.
+pub fn required_scope(method: &Method, path: &str) -> Option<Scope> {
+    if matches!(
+        path,
+        "/health" | "/ws/health" | "/openapi.yaml" | "/openapi.json"
+    ) {
+        return None;
+    }
+    if path == "/raw_sql/mutate" {
+        return Some(Scope::Admin);
+    }
+    if path == "/raw_sql" {
+        return Some(Scope::RawSql);
+    }
+    if path.starts_with("/experimental/operator") {
+        return Some(Scope::Operator);
+    }
+    if path.starts_with("/pipes/") {
+        return Some(Scope::PipesAdmin);
+    }
+
+    let is_write = match path {
+        "/add" | "/experimental/frames/merge" | "/retention/run" => true,
+        "/retention" => method == Method::POST,
+        "/audio/diarize" | "/audio/retranscribe" => method == Method::POST,
+        _ => {
+            path.starts_with("/tags/")
+                || path.starts_with("/audio/start")
+                || path.starts_with("/audio/stop")
+                || path.starts_with("/audio/device/")
+                || (path.starts_with("/speakers/") && method == Method::POST)
+        }
+    };
+    if is_write {
+        Some(Scope::Write)
+    } else {
+        // everything else reads recorded data, including routes added later
+        Some(Scope::ReadSearch)
+    }
+}
+
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", TOKEN_PREFIX, hex)
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+pub struct AuthManager {
+    db: Arc<DatabaseManager>,
+}
+
+impl AuthManager {
+    pub fn new(db: Arc<DatabaseManager>) -> Self {
+        Self { db }
+    }
+
+    /// Stores a new token and returns its id and secret. The secret can't be recovered later.
+    pub async fn create_token(
+        &self,
+        name: &str,
+        scopes: &[Scope],
+        pipe_id: Option<&str>,
+    ) -> Result<(i64, String)> {
+        let token = generate_token();
+        let scopes: Vec<String> = scopes.iter().map(|s| s.to_string()).collect();
+        let id = self
+            .db
+            .insert_api_token(name, &hash_token(&token), &scopes, pipe_id)
+            .await?;
+        Ok((id, token))
+    }
+
+    pub async fn authenticate(&self, token: &str) -> Result<Option<ApiToken>> {
+        Ok(self.db.get_api_token_by_hash(&hash_token(token)).await?)
+    }
+
+    /// Issues a fresh token for a pipe that is starting, revoking the ones from earlier runs
+    pub async fn issue_pipe_token(&self, pipe_id: &str, scopes: &[Scope]) -> Result<String> {
+        self.revoke_pipe_tokens(pipe_id).await?;
+        let (_, token) = self
+            .create_token(&format!("pipe {}", pipe_id), scopes, Some(pipe_id))
+            .await?;
+        Ok(token)
+    }
+
+    pub async fn revoke_pipe_tokens(&self, pipe_id: &str) -> Result<()> {
+        self.db.delete_pipe_api_tokens(pipe_id).await?;
+        Ok(())
+    }
+
+    /// Grants scopes to a pipe, replacing the ones granted before. Applies from its next start.
+    pub async fn grant_pipe_scopes(&self, pipe_id: &str, scopes: &[Scope]) -> Result<()> {
+        let scopes: Vec<String> = scopes.iter().map(|s| s.to_string()).collect();
+        self.db.set_pipe_scope_grant(pipe_id, &scopes).await?;
+        Ok(())
+    }
+
+    /// Scopes of the token issued to a pipe: read-search and whatever the user granted with
+    /// [`Self::grant_pipe_scopes`]. The pipe's own files are only read to point out scopes it
+    /// asks for that weren't granted, a pipe can't grant itself scopes.
+    pub async fn pipe_scopes(&self, pipe_id: &str, pipe_dir: &Path) -> Result<Vec<Scope>> {
+        let mut scopes = vec![Scope::ReadSearch];
+        for scope in self.db.get_pipe_scope_grant(pipe_id).await? {
+            match scope.parse() {
+                Ok(scope) if !scopes.contains(&scope) => scopes.push(scope),
+                Ok(_) => {}
+                Err(e) => warn!("ignoring scope granted to pipe {}: {}", pipe_id, e),
+            }
+        }
+
+        for scope in requested_pipe_scopes(pipe_dir).await {
+            if !scopes.contains(&scope) {
+                warn!(
+                    "pipe {} asks for the {} scope, grant it with `screenpipe token grant-pipe {} --scope {}`",
+                    pipe_id, scope, pipe_id, scope
+                );
+            }
+        }
+        Ok(scopes)
+    }
+}
+
+/// Scopes a pipe asks for in the `scopes` field of its pipe.json. Only a request, the pipe gets
+/// them once the user grants them, see [`AuthManager::pipe_scopes`].
+pub async fn requested_pipe_scopes(pipe_dir: &Path) -> Vec<Scope> {
+    let config = tokio::fs::read_to_string(pipe_dir.join("pipe.json"))
+        .await
+        .ok()
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok());
+
+    config
+        .as_ref()
+        .and_then(|c| c.get("scopes"))
+        .and_then(|s| s.as_array())
+        .map(|scopes| {
+            scopes
+                .iter()
+                .filter_map(|s| s.as_str())
+                .filter_map(|s| s.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Rejects requests without a token that has the scope of the route
+pub async fn require_auth(
+    State(auth): State<Arc<AuthManager>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(scope) = required_scope(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    let Some(token) = request_token(&request) else {
+        return auth_error(StatusCode::UNAUTHORIZED, "missing bearer token".to_string());
+    };
+
+    match auth.authenticate(&token).await {
+        Ok(Some(api_token)) if api_token.scopes.iter().any(|s| s == scope.as_str()) => {
+            next.run(request).await
+        }
+        Ok(Some(api_token)) => {
+            debug!(
+                "token {} lacks scope {} for {}",
+                api_token.id,
+                scope,
+                request.uri().path()
+            );
+            auth_error(
+                StatusCode::FORBIDDEN,
+                format!("token lacks the {} scope", scope),
+            )
+        }
+        Ok(None) => auth_error(StatusCode::UNAUTHORIZED, "invalid token".to_string()),
+        Err(e) => {
+            error!("failed to check api token: {}", e);
+            auth_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to check token".to_string(),
+            )
+        }
+    }
+}
+
+/// Serves only requests addressed to this machine by name when auth is disabled, so a web page
+/// can't reach the api by pointing its own domain at 127.0.0.1 (dns rebinding).
+pub async fn require_local_host(request: Request, next: Next) -> Response {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .or_else(|| request.uri().authority().map(|a| a.as_str()));
+    match host {
+        Some(host) if !is_local_host(host_name(host)) => {
+            debug!("rejecting request for host {}", host);
+            auth_error(
+                StatusCode::FORBIDDEN,
+                format!(
+                    "host {} is not allowed, only localhost is served without --enable-auth",
+                    host
+                ),
+            )
+        }
+        _ => next.run(request).await,
+    }
+}
+
+/// Reads the bearer token. Browsers can't set headers on websockets, so those routes also
+/// accept a `token` query parameter.
+fn request_token(request: &Request) -> Option<String> {
+    if let Some(value) = request.headers().get(header::AUTHORIZATION) {
+        return value
+            .to_str()
+            .ok()
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(|t| t.trim().to_string());
+    }
+
+    let path = request.uri().path();
+    let is_stream =
+        path.starts_with("/ws/") || path == "/stream/frames" || path == "/frames/export";
+    if !is_stream {
+        return None;
+    }
+    request.uri().query().and_then(|query| {
+        query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            (percent_decode(key)? == "token").then(|| percent_decode(value))?
+        })
+    })
+}
+
+/// Span of each http request, like tower-http's default one but with the `token` query
+/// parameter redacted so it never reaches the logs
+pub fn request_span(request: &Request) -> Span {
+    debug_span!(
+        "request",
+        method = %request.method(),
+        uri = %redact_token(request.uri()),
+        version = ?request.version()
+    )
+}
+
+pub fn redact_token(uri: &Uri) -> String {
+    let Some(query) = uri.query() else {
+        return uri.path().to_string();
+    };
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if percent_decode(key).as_deref() == Some("token") => {
+                format!("{}=[redacted]", key)
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{}?{}", uri.path(), query)
+}
+
+/// Decodes a query string component, `None` when it isn't valid
+fn percent_decode(component: &str) -> Option<String> {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = component.get(i + 1..i + 3)?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn auth_error(status: StatusCode, message: String) -> Response {
+    let mut response = (status, Json(json!({ "error": message }))).into_response();
+    if status == StatusCode::UNAUTHORIZED {
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    }
+    response
+}
+
+/// Origins allowed by CORS: the desktop app, pages served from this machine and any origin
+/// passed with `--allowed-origin`. `*` allows every origin.
+pub fn is_allowed_origin(origin: &HeaderValue, extra_origins: &[String]) -> bool {
+    let Ok(origin) = origin.to_str() else {
+        return false;
+    };
+    if extra_origins
+        .iter()
+        .any(|allowed| allowed == "*" || allowed.trim_end_matches('/') == origin)
+    {
+        return true;
+    }
+
+    let Some((scheme, authority)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = host_name(authority);
+
+    match scheme {
+        "http" | "https" => is_local_host(host),
+        "tauri" => host == "localhost",
+        _ => false,
+    }
+}
+
+/// Host of an authority, without its port and the brackets of ipv6 addresses
+fn host_name(authority: &str) -> &str {
+    if let Some(rest) = authority.strip_prefix('[') {
+        rest.split(']').next().unwrap_or_default()
+    } else {
+        authority.split(':').next().unwrap_or_default()
+    }
+}
+
+fn is_local_host(host: &str) -> bool {
+    matches!(host, "localhost" | "127.0.0.1" | "::1" | "tauri.localhost")
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use clap::Parser;
 #[allow(unused_imports)]
 use colored::Colorize;
@@ -9,22 +10,46 @@ use screenpipe_audio::{
     audio_manager::AudioManagerBuilder,
     core::device::{
         default_input_device, default_output_device, list_audio_devices, parse_audio_device,
+        AudioDevice, DeviceType,
+    },
+    core::engine::AudioTranscriptionEngine,
+    decode_audio_file,
+    transcription::{
+        backend::create_transcription_backend, import::AudioImporter,
+        retranscribe::retranscribe_window,
+    },
+    vad::{
+        create_vad_engine,
+        regions::{speech_probabilities, speech_regions},
     },
 };
-use screenpipe_core::find_ffmpeg_path;
+use screenpipe_core::{
+    encryption::{self, EncryptionKey},
+    find_ffmpeg_path,
+    pii_removal::{self, PiiDetector},
+};
 use screenpipe_db::{
-    create_migration_worker, DatabaseManager, MigrationCommand, MigrationConfig, MigrationStatus,
+    create_migration_worker_for_job, DatabaseManager, MigrationCommand, MigrationConfig,
+    MigrationJob, MigrationStatus,
 };
 use screenpipe_server::{
     cli::{
-        AudioCommand, Cli, CliAudioTranscriptionEngine, CliOcrEngine, Command, MigrationSubCommand,
-        OutputFormat, PipeCommand, VisionCommand, McpCommand,
+        AudioCommand, Cli, CliAudioTranscriptionEngine, Command, MigrationSubCommand,
+        OutputFormat, PipeCommand, TokenCommand, VisionCommand, McpCommand, vad_config,
     },
-    handle_index_command,
+    archive::{export_archive, import_archive},
+    auth::TOKEN_ENV_VAR,
+    handle_audio_import_command, handle_index_command,
     pipe_manager::PipeInfo,
-    start_continuous_recording, watch_pid, PipeManager, ResourceMonitor, SCServer,
+    start_continuous_recording,
+    text_embeds::{embedding_fn, start_text_embedding_task},
+    transcript_dedup::start_transcript_dedup_task,
+    ui_pii::start_ui_pii_removal_task,
+    watch_pid, AuthManager, OcrQueue, PipeManager, ResourceMonitor, RetentionManager, SCServer,
 };
 use screenpipe_vision::monitor::list_monitors;
+use screenpipe_vision::roi::RoiConfig;
+use screenpipe_vision::OcrBackendRegistry;
 #[cfg(target_os = "macos")]
 use screenpipe_vision::run_ui;
 use serde_json::{json, Value};
@@ -78,6 +103,38 @@ fn get_base_dir(custom_path: &Option<String>) -> anyhow::Result<PathBuf> {
     Ok(base_dir)
 }
 
+/// Encrypts recordings left in plaintext by a previous run, e.g. a chunk that was still being
+/// written when screenpipe stopped or media recorded before encryption was turned on
+fn encrypt_leftover_recordings(data_dir: &Path, started_at: std::time::SystemTime) {
+    let entries = match fs::read_dir(data_dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("failed to read data dir for encryption: {}", e);
+            return;
+        }
+    };
+
+    let mut encrypted = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_media = path.extension().is_some_and(|ext| ext == "mp4");
+        let is_old = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified < started_at);
+        if !is_media || !is_old || encryption::is_encrypted_file(&path).unwrap_or(true) {
+            continue;
+        }
+        match encryption::encrypt_file_if_enabled(&path) {
+            Ok(()) => encrypted += 1,
+            Err(e) => error!("failed to encrypt {}: {}", path.display(), e),
+        }
+    }
+    if encrypted > 0 {
+        info!("encrypted {} plaintext recordings", encrypted);
+    }
+}
+
 fn setup_logging(local_data_dir: &PathBuf, cli: &Cli) -> anyhow::Result<WorkerGuard> {
     let file_appender = RollingFileAppender::builder()
         .rotation(Rotation::DAILY)
@@ -241,6 +298,18 @@ async fn main() -> anyhow::Result<()> {
         Arc::new(PipeManager::new(PathBuf::from("")))
     };
 
+    let encryption_key = EncryptionKey::load(cli.encryption_keyfile.as_deref())?;
+    let db_key = encryption_key.as_ref().map(|key| key.database_key());
+    if let Some(ref key) = encryption_key {
+        encryption::init(key)?;
+    }
+
+    let pii_detector = match cli.pii_config {
+        Some(ref path) => PiiDetector::from_file(path)?,
+        None => PiiDetector::default(),
+    };
+    pii_removal::init(pii_detector, cli.use_pii_removal)?;
+
     if let Some(ref command) = cli.command {
         match command {
             Command::Audio { subcommand } => match subcommand {
@@ -272,6 +341,81 @@ async fn main() -> anyhow::Result<()> {
                     }
                     return Ok(());
                 }
+                AudioCommand::VadTest {
+                    file,
+                    vad_engine,
+                    vad_sensitivity,
+                    speech_threshold,
+                    silence_threshold,
+                    hangover_ms,
+                    min_speech_ms,
+                    probabilities,
+                    output,
+                } => {
+                    let config = vad_config(
+                        vad_sensitivity,
+                        *speech_threshold,
+                        *silence_threshold,
+                        *hangover_ms,
+                        *min_speech_ms,
+                    );
+                    let audio = {
+                        let file = file.clone();
+                        tokio::task::spawn_blocking(move || decode_audio_file(&file, 16000))
+                            .await??
+                    };
+                    let mut engine = create_vad_engine(vad_engine.clone().into()).await?;
+                    engine.set_config(config);
+                    let frames = speech_probabilities(engine.as_mut(), &audio)?;
+                    let regions = speech_regions(&frames, &config);
+                    let speech: f64 = regions.iter().map(|r| r.end - r.start).sum();
+                    let duration = audio.len() as f64 / 16000.0;
+
+                    match output {
+                        OutputFormat::Json => {
+                            let mut result = json!({
+                                "data": regions,
+                                "duration": duration,
+                                "speech_duration": speech,
+                                "success": true
+                            });
+                            if *probabilities {
+                                result["frames"] = json!(frames);
+                            }
+                            println!("{}", serde_json::to_string_pretty(&result)?);
+                        }
+                        OutputFormat::Text => {
+                            println!(
+                                "speech threshold {}, silence threshold {}, hangover {}ms, min speech {}ms",
+                                config.speech_threshold,
+                                config.silence_threshold,
+                                config.hangover.as_millis(),
+                                config.min_speech_duration.as_millis()
+                            );
+                            if *probabilities {
+                                for frame in frames.iter() {
+                                    println!("  {:>8.1}s  {:.3}", frame.start, frame.probability);
+                                }
+                            }
+                            println!("speech regions:");
+                            for region in regions.iter() {
+                                println!(
+                                    "  {:>8.1}s - {:>8.1}s  ({:.1}s)",
+                                    region.start,
+                                    region.end,
+                                    region.end - region.start
+                                );
+                            }
+                            println!(
+                                "{} regions, {:.1}s of speech in {:.1}s",
+                                regions.len(),
+                                speech,
+                                duration
+                            );
+                        }
+                    }
+                    return Ok(());
+                }
             },
             Command::Vision { subcommand } => match subcommand {
                 VisionCommand::List { output } => {
@@ -322,10 +466,10 @@ async fn main() -> anyhow::Result<()> {
                 // Initialize the database
                 let local_data_dir = get_base_dir(data_dir)?;
                 let db = Arc::new(
-                    DatabaseManager::new(&format!(
-                        "{}/db.sqlite",
-                        local_data_dir.to_string_lossy()
-                    ))
+                    DatabaseManager::new_with_key(
+                        &format!("{}/db.sqlite", local_data_dir.to_string_lossy()),
+                        db_key.as_deref(),
+                    )
                     .await
                     .map_err(|e| {
                         error!("failed to initialize database: {:?}", e);
@@ -336,9 +480,21 @@ async fn main() -> anyhow::Result<()> {
                 // Create a migration worker config
                 let config = MigrationConfig::new(*batch_size, *batch_delay_ms, *continue_on_error);
 
+                let job = match migration_name.as_str() {
+                    "ocr_text_to_frames" => MigrationJob::OcrTextToFrames,
+                    "text_embeddings" => MigrationJob::TextEmbeddings(embedding_fn()),
+                    "speaker_reattribution" => MigrationJob::SpeakerReattribution,
+                    other => {
+                        return Err(anyhow::anyhow!(
+                            "unknown migration: {}, expected ocr_text_to_frames, text_embeddings or speaker_reattribution",
+                            other
+                        ));
+                    }
+                };
+
                 // Start the migration worker
                 let (cmd_tx, mut status_rx, worker_handle) =
-                    create_migration_worker(db, Some(config));
+                    create_migration_worker_for_job(db, job, Some(config));
 
                 // Process the specified subcommand or default to status
                 let cmd = match subcommand {
@@ -522,10 +678,10 @@ async fn main() -> anyhow::Result<()> {
                 }
 
                 let db = Arc::new(
-                    DatabaseManager::new(&format!(
-                        "{}/db.sqlite",
-                        local_data_dir.to_string_lossy()
-                    ))
+                    DatabaseManager::new_with_key(
+                        &format!("{}/db.sqlite", local_data_dir.to_string_lossy()),
+                        db_key.as_deref(),
+                    )
                     .await
                     .map_err(|e| {
                         error!("failed to initialize database: {:?}", e);
@@ -546,10 +702,245 @@ async fn main() -> anyhow::Result<()> {
                 .await?;
                 return Ok(());
             }
+            Command::AddAudio {
+                path,
+                pattern,
+                timestamp,
+                device,
+                speakers,
+                data_dir,
+                output,
+            } => {
+                let local_data_dir = get_base_dir(data_dir)?;
+                let db = Arc::new(
+                    DatabaseManager::new_with_key(
+                        &format!("{}/db.sqlite", local_data_dir.to_string_lossy()),
+                        db_key.as_deref(),
+                    )
+                    .await?,
+                );
+                let output_path = local_data_dir.join("data");
+                fs::create_dir_all(&output_path)?;
+                let languages = cli.unique_languages().map_err(|e| anyhow::anyhow!(e))?;
+
+                let engine: Arc<AudioTranscriptionEngine> =
+                    Arc::new(cli.audio_transcription_engine.clone().into());
+                let backend = tokio::task::spawn_blocking({
+                    let engine = engine.clone();
+                    let deepgram_api_key = cli.deepgram_api_key.clone();
+                    let openai_compatible_config = cli.openai_compatible_config();
+                    move || {
+                        create_transcription_backend(
+                            engine,
+                            None,
+                            deepgram_api_key,
+                            openai_compatible_config,
+                        )
+                    }
+                })
+                .await??;
+
+                let importer = AudioImporter::new(
+                    db,
+                    engine,
+                    backend,
+                    cli.vad_engine.clone().into(),
+                    vad_config(
+                        &cli.vad_sensitivity,
+                        cli.vad_speech_threshold,
+                        cli.vad_silence_threshold,
+                        cli.vad_hangover_ms,
+                        cli.vad_min_speech_ms,
+                    ),
+                    languages,
+                    Duration::from_secs(cli.audio_chunk_duration),
+                    output_path,
+                )
+                .await?;
+                handle_audio_import_command(
+                    &importer,
+                    path,
+                    pattern.as_deref(),
+                    *timestamp,
+                    AudioDevice::new(device.clone(), DeviceType::Input),
+                    speakers.clone(),
+                    output.clone(),
+                )
+                .await?;
+                return Ok(());
+            }
             Command::Mcp { subcommand } => {
                 handle_mcp_command(subcommand, &local_data_dir_clone).await?;
                 return Ok(());
             }
+            Command::Export {
+                out,
+                from,
+                to,
+                data_dir,
+                output,
+            } => {
+                let local_data_dir = get_base_dir(data_dir)?;
+                let db = DatabaseManager::new_with_key(
+                    &format!("{}/db.sqlite", local_data_dir.to_string_lossy()),
+                    db_key.as_deref(),
+                )
+                .await?;
+                let from = from.unwrap_or(DateTime::UNIX_EPOCH);
+                let to = to.unwrap_or_else(Utc::now);
+
+                let manifest = export_archive(&db, from, to, out).await?;
+                match output {
+                    OutputFormat::Json => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&json!({
+                            "data": manifest,
+                            "success": true
+                        }))?
+                    ),
+                    OutputFormat::Text => {
+                        println!("exported archive to {}", out.display());
+                        println!("  video chunks: {}", manifest.video_chunks);
+                        println!("  frames: {}", manifest.frames);
+                        println!("  audio chunks: {}", manifest.audio_chunks);
+                        println!("  transcriptions: {}", manifest.audio_transcriptions);
+                        if !manifest.missing_files.is_empty() {
+                            println!(
+                                "  {} media files were missing on disk and not included",
+                                manifest.missing_files.len()
+                            );
+                        }
+                        if manifest.encrypted {
+                            println!("  recordings are decrypted in the archive, keep it safe");
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            Command::Import {
+                archive,
+                data_dir,
+                output,
+            } => {
+                let local_data_dir = get_base_dir(data_dir)?;
+                let db = DatabaseManager::new_with_key(
+                    &format!("{}/db.sqlite", local_data_dir.to_string_lossy()),
+                    db_key.as_deref(),
+                )
+                .await?;
+
+                let report = import_archive(&db, archive, &local_data_dir).await?;
+                match output {
+                    OutputFormat::Json => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&json!({
+                            "data": report,
+                            "success": true
+                        }))?
+                    ),
+                    OutputFormat::Text => {
+                        let stats = &report.stats;
+                        println!(
+                            "imported archive created by screenpipe {} at {}",
+                            report.manifest.screenpipe_version, report.manifest.created_at
+                        );
+                        println!(
+                            "  media files: {} extracted, {} already present",
+                            report.files_extracted, report.files_existing
+                        );
+                        println!(
+                            "  frames: {} imported, {} already present",
+                            stats.frames_imported, stats.frames_skipped
+                        );
+                        println!(
+                            "  transcriptions: {} imported, {} already present",
+                            stats.audio_transcriptions_imported, stats.audio_transcriptions_skipped
+                        );
+                        println!(
+                            "  speakers: {} imported, {} merged",
+                            stats.speakers_imported, stats.speakers_merged
+                        );
+                    }
+                }
+                return Ok(());
+            }
+            Command::Retranscribe {
+                engine,
+                from,
+                to,
+                device,
+                data_dir,
+                output,
+            } => {
+                let local_data_dir = get_base_dir(data_dir)?;
+                let db = DatabaseManager::new_with_key(
+                    &format!("{}/db.sqlite", local_data_dir.to_string_lossy()),
+                    db_key.as_deref(),
+                )
+                .await?;
+                let to = to.unwrap_or_else(Utc::now);
+                let languages = cli.unique_languages().map_err(|e| anyhow::anyhow!(e))?;
+
+                let engine: Arc<AudioTranscriptionEngine> = Arc::new(engine.clone().into());
+                let backend = tokio::task::spawn_blocking({
+                    let engine = engine.clone();
+                    let deepgram_api_key = cli.deepgram_api_key.clone();
+                    let openai_compatible_config = cli.openai_compatible_config();
+                    move || {
+                        create_transcription_backend(
+                            engine,
+                            None,
+                            deepgram_api_key,
+                            openai_compatible_config,
+                        )
+                    }
+                })
+                .await??;
+
+                let summary = retranscribe_window(
+                    &db,
+                    *from,
+                    to,
+                    device.as_deref(),
+                    &engine,
+                    backend,
+                    languages,
+                    |_| {},
+                )
+                .await?;
+                match output {
+                    OutputFormat::Json => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&json!({
+                            "data": summary,
+                            "success": true
+                        }))?
+                    ),
+                    OutputFormat::Text => {
+                        println!(
+                            "re-transcribed audio from {} to {} with {}",
+                            from, to, engine
+                        );
+                        println!(
+                            "  audio chunks: {}, {} could not be read",
+                            summary.chunks, summary.chunks_failed
+                        );
+                        println!(
+                            "  transcriptions: {}, {} failed",
+                            summary.transcriptions, summary.transcriptions_failed
+                        );
+                        println!(
+                            "  now searched with the new engine: {}",
+                            summary.transcriptions_replaced
+                        );
+                    }
+                }
+                return Ok(());
+            }
+            Command::Token { subcommand } => {
+                handle_token_command(subcommand, db_key.as_deref()).await?;
+                return Ok(());
+            }
         }
     }
 
@@ -625,16 +1016,54 @@ async fn main() -> anyhow::Result<()> {
     resource_monitor.start_monitoring(Duration::from_secs(30), Some(Duration::from_secs(60)));
 
     let db = Arc::new(
-        DatabaseManager::new(&format!("{}/db.sqlite", local_data_dir.to_string_lossy()))
-            .await
-            .map_err(|e| {
-                eprintln!("failed to initialize database: {:?}", e);
-                e
-            })?,
+        DatabaseManager::new_with_key(
+            &format!("{}/db.sqlite", local_data_dir.to_string_lossy()),
+            db_key.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("failed to initialize database: {:?}", e);
+            e
+        })?,
     );
 
     let db_server = db.clone();
 
+    let auth = if cli.enable_auth {
+        let auth = Arc::new(AuthManager::new(db.clone()));
+        if db.list_api_tokens().await?.is_empty() {
+            warn!(
+                "auth is enabled but no api token exists, create one with `screenpipe token create`"
+            );
+        }
+        Some(auth)
+    } else {
+        warn!(
+            "auth is disabled, any program on this machine can use the api, start with --enable-auth to require a token"
+        );
+        None
+    };
+    // pipes started from here on get their own api token
+    let pipe_manager = match &auth {
+        Some(auth) if cli.enable_pipe_manager => {
+            Arc::new(PipeManager::new(local_data_dir_clone.clone()).with_auth(auth.clone()))
+        }
+        _ => pipe_manager,
+    };
+
+    if encryption::is_enabled() {
+        let data_dir = local_data_dir.join("data");
+        let started_at = std::time::SystemTime::now();
+        tokio::task::spawn_blocking(move || {
+            match encryption::remove_leftover_decrypted_media(started_at) {
+                Ok(0) => {}
+                Ok(removed) => info!("removed {} leftover decrypted recordings", removed),
+                Err(e) => error!("failed to remove leftover decrypted recordings: {}", e),
+            }
+            encrypt_leftover_recordings(&data_dir, started_at)
+        });
+    }
+
     let warning_ocr_engine_clone = cli.ocr_engine.clone();
     let warning_audio_transcription_engine_clone = cli.audio_transcription_engine.clone();
     let monitor_ids = if cli.monitor_id.is_empty() {
@@ -678,12 +1107,16 @@ async fn main() -> anyhow::Result<()> {
     let mut audio_manager_builder = AudioManagerBuilder::new()
         .audio_chunk_duration(audio_chunk_duration)
         .vad_engine(vad_engine.into())
+        .vad_config(cli.vad_config())
+        .preprocessing(cli.audio_preprocessing())
+        .device_preprocessing(cli.preprocessing_by_device())
         .vad_sensitivity(cli.vad_sensitivity.into())
         .languages(languages.clone())
         .transcription_engine(cli.audio_transcription_engine.into())
         .realtime(cli.enable_realtime_audio_transcription)
         .enabled_devices(audio_devices)
         .deepgram_api_key(cli.deepgram_api_key.clone())
+        .openai_compatible_config(cli.openai_compatible_config())
         .output_path(PathBuf::from(output_path_clone.clone().to_string()));
 
     let audio_manager = match audio_manager_builder.build(db.clone()).await {
@@ -694,6 +1127,17 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
+    let ocr_backend = match OcrBackendRegistry::default().create(&cli.ocr_engine) {
+        Ok(backend) => backend,
+        Err(e) => {
+            error!("{e}");
+            return Ok(());
+        }
+    };
+    let ocr_queue = Arc::new(OcrQueue::new(db.clone(), cli.ocr_queue_config()));
+    let ocr_queue_backend = ocr_backend.clone();
+    let ocr_queue_recording = ocr_queue.clone();
+
     let handle = {
         let runtime = &tokio::runtime::Handle::current();
         runtime.spawn(async move {
@@ -704,15 +1148,18 @@ async fn main() -> anyhow::Result<()> {
                     output_path_clone.clone(),
                     fps,
                     Duration::from_secs(cli.video_chunk_duration),
-                    Arc::new(cli.ocr_engine.clone().into()),
+                    ocr_backend.clone(),
                     monitor_ids_clone.clone(),
                     cli.use_pii_removal,
+                    cli.frame_pii_redaction,
                     cli.disable_vision,
                     &vision_handle,
                     &cli.ignored_windows,
                     &cli.included_windows,
                     languages_clone.clone(),
                     cli.capture_unfocused_windows,
+                    cli.enable_roi_ocr.then(RoiConfig::default),
+                    ocr_queue_recording.clone(),
                     cli.enable_realtime_audio_transcription,
                 );
 
@@ -748,6 +1195,41 @@ async fn main() -> anyhow::Result<()> {
     #[cfg(feature = "llm")]
     debug!("LLM initialized");
 
+    let retention_config = cli.retention_config();
+    let retention_manager = Arc::new(RetentionManager::new(db.clone(), retention_config.clone()));
+    retention_manager.clone().start(shutdown_tx.subscribe());
+
+    if cli.enable_text_embeddings {
+        start_text_embedding_task(db.clone(), Duration::from_secs(30), shutdown_tx.subscribe());
+    }
+
+    if cli.use_pii_removal && cli.enable_ui_monitoring {
+        start_ui_pii_removal_task(db.clone(), Duration::from_secs(10), shutdown_tx.subscribe());
+    }
+
+    if !cli.disable_vision {
+        // also OCRs windows recorded while the selected engine was unavailable, and windows left
+        // in the queue by a previous run with the queue enabled
+        ocr_queue.clone().start(
+            ocr_queue_backend,
+            languages.clone(),
+            cli.use_pii_removal,
+            cli.enable_realtime_vision,
+            shutdown_tx.subscribe(),
+        );
+    }
+
+    if !cli.disable_audio && !cli.disable_transcript_dedup {
+        // both devices' chunks of the same speech are transcribed one after the other
+        start_transcript_dedup_task(
+            db.clone(),
+            Duration::from_secs(10),
+            chrono::Duration::seconds(cli.audio_chunk_duration as i64 * 2),
+            cli.transcript_dedup_similarity,
+            shutdown_tx.subscribe(),
+        );
+    }
+
     let server = SCServer::new(
         db_server,
         SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), cli.port),
@@ -758,7 +1240,14 @@ async fn main() -> anyhow::Result<()> {
         cli.enable_ui_monitoring,
         audio_manager.clone(),
         cli.enable_pipe_manager,
-    );
+    )
+    .with_retention_manager(retention_manager)
+    .with_ocr_queue(ocr_queue)
+    .with_allowed_origins(cli.allowed_origin.clone());
+    let server = match auth {
+        Some(auth) => server.with_auth(auth),
+        None => server,
+    };
 
     // print screenpipe in gradient
     println!("\n\n{}", DISPLAY.truecolor(147, 112, 219).bold());
@@ -796,10 +1285,7 @@ async fn main() -> anyhow::Result<()> {
         "│ audio engine           │ {:<34} │",
         format!("{:?}", warning_audio_transcription_engine_clone)
     );
-    println!(
-        "│ ocr engine             │ {:<34} │",
-        format!("{:?}", ocr_engine_clone)
-    );
+    println!("│ ocr engine             │ {:<34} │", ocr_engine_clone);
     println!(
         "│ vad engine             │ {:<34} │",
         format!("{:?}", vad_engine_clone)
@@ -820,6 +1306,19 @@ async fn main() -> anyhow::Result<()> {
     println!("│ local llm              │ {:<34} │", cli.enable_llm);
 
     println!("│ use pii removal        │ {:<34} │", cli.use_pii_removal);
+    println!(
+        "│ pii config             │ {:<34} │",
+        cli.pii_config
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "built in rules".to_string())
+    );
+    println!(
+        "│ frame pii redaction    │ {:<34} │",
+        cli.frame_pii_redaction
+            .map(|mode| mode.as_str())
+            .unwrap_or("disabled")
+    );
     println!(
         "│ ignored windows        │ {:<34} │",
         format_cell(&format!("{:?}", &ignored_windows_clone), VALUE_WIDTH)
@@ -840,6 +1339,52 @@ async fn main() -> anyhow::Result<()> {
         "│ capture unfocused wins │ {:<34} │",
         cli.capture_unfocused_windows
     );
+    println!("│ roi ocr                │ {:<34} │", cli.enable_roi_ocr);
+    println!(
+        "│ ocr queue              │ {:<34} │",
+        if cli.enable_ocr_queue {
+            format!("{} workers", cli.ocr_workers)
+        } else {
+            "disabled".to_string()
+        }
+    );
+    println!(
+        "│ retention              │ {:<34} │",
+        if retention_config.is_enabled() {
+            format_cell(
+                &format!(
+                    "video {:?}d/{:?}gb audio {:?}d/{:?}gb",
+                    retention_config.vision.max_age_days,
+                    retention_config.vision.max_size_gb,
+                    retention_config.audio.max_age_days,
+                    retention_config.audio.max_size_gb
+                ),
+                VALUE_WIDTH,
+            )
+        } else {
+            "disabled".to_string()
+        }
+    );
+    println!(
+        "│ text embeddings        │ {:<34} │",
+        cli.enable_text_embeddings
+    );
+    println!(
+        "│ auth                   │ {:<34} │",
+        if cli.enable_auth {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    println!(
+        "│ encryption             │ {:<34} │",
+        if encryption::is_enabled() {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
     println!(
         "│ auto-destruct pid      │ {:<34} │",
         cli.auto_destruct_pid.unwrap_or(0)
@@ -853,6 +1398,9 @@ async fn main() -> anyhow::Result<()> {
             "not set"
         }
     );
+    if let Some(endpoint) = &cli.openai_compatible_endpoint {
+        println!("│ transcription endpoint │ {:<34} │", endpoint);
+    }
 
     const VALUE_WIDTH: usize = 34;
 
@@ -1007,7 +1555,7 @@ async fn main() -> anyhow::Result<()> {
 
     // Add warning for cloud arguments and telemetry
     if warning_audio_transcription_engine_clone == CliAudioTranscriptionEngine::Deepgram
-        || warning_ocr_engine_clone == CliOcrEngine::Unstructured
+        || warning_ocr_engine_clone == "unstructured"
     {
         println!(
             "{}",
@@ -1172,6 +1720,122 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn handle_token_command(command: &TokenCommand, db_key: Option<&str>) -> anyhow::Result<()> {
+    let data_dir = match command {
+        TokenCommand::Create { data_dir, .. }
+        | TokenCommand::List { data_dir, .. }
+        | TokenCommand::Revoke { data_dir, .. }
+        | TokenCommand::GrantPipe { data_dir, .. }
+        | TokenCommand::RevokePipe { data_dir, .. } => data_dir,
+    };
+    let local_data_dir = get_base_dir(data_dir)?;
+    let db = Arc::new(
+        DatabaseManager::new_with_key(
+            &format!("{}/db.sqlite", local_data_dir.to_string_lossy()),
+            db_key,
+        )
+        .await?,
+    );
+
+    match command {
+        TokenCommand::Create {
+            name,
+            scope,
+            output,
+            ..
+        } => {
+            let (id, token) = AuthManager::new(db).create_token(name, scope, None).await?;
+            let scopes: Vec<&str> = scope.iter().map(|s| s.as_str()).collect();
+            match output {
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&json!({
+                        "data": { "id": id, "name": name, "scopes": scopes, "token": token },
+                        "success": true
+                    }))?
+                ),
+                OutputFormat::Text => {
+                    println!(
+                        "created token {} ({}) with scopes: {}",
+                        id,
+                        name,
+                        scopes.join(", ")
+                    );
+                    println!("{}", token);
+                    println!("store it now, it can't be shown again");
+                }
+            }
+        }
+        TokenCommand::List { output, .. } => {
+            let tokens = db.list_api_tokens().await?;
+            match output {
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&json!({
+                        "data": tokens,
+                        "success": true
+                    }))?
+                ),
+                OutputFormat::Text => {
+                    println!("api tokens:");
+                    for token in tokens {
+                        println!(
+                            "  {}. {} [{}]{} last used: {}",
+                            token.id,
+                            token.name,
+                            token.scopes.join(", "),
+                            token
+                                .pipe_id
+                                .map(|pipe| format!(" pipe: {}", pipe))
+                                .unwrap_or_default(),
+                            token
+                                .last_used_at
+                                .map(|t| t.to_rfc3339())
+                                .unwrap_or_else(|| "never".to_string())
+                        );
+                    }
+                }
+            }
+        }
+        TokenCommand::Revoke { id, .. } => {
+            if db.delete_api_token(*id).await? {
+                println!("revoked token {}", id);
+            } else {
+                anyhow::bail!("no token with id {}", id);
+            }
+        }
+        TokenCommand::GrantPipe { pipe_id, scope, .. } => {
+            AuthManager::new(db)
+                .grant_pipe_scopes(pipe_id, scope)
+                .await?;
+            let scopes: Vec<&str> = scope.iter().map(|s| s.as_str()).collect();
+            println!(
+                "granted pipe {} the scopes: {}, restart it to apply",
+                pipe_id,
+                scopes.join(", ")
+            );
+        }
+        TokenCommand::RevokePipe { pipe_id, .. } => {
+            db.delete_pipe_scope_grant(pipe_id).await?;
+            AuthManager::new(db).revoke_pipe_tokens(pipe_id).await?;
+            println!("revoked the scopes and tokens of pipe {}", pipe_id);
+        }
+    }
+    Ok(())
+}
+
+/// Http client for the local api, sending SCREENPIPE_API_TOKEN when it is set
+fn api_client() -> anyhow::Result<Client> {
+    let mut headers = HeaderMap::new();
+    if let Ok(token) = env::var(TOKEN_ENV_VAR) {
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token.trim()))?,
+        );
+    }
+    Ok(Client::builder().default_headers(headers).build()?)
+}
+
 async fn handle_pipe_command(
     command: &PipeCommand,
     pipe_manager: &Arc<PipeManager>,
@@ -1183,7 +1847,7 @@ async fn handle_pipe_command(
         return Ok(());
     }
 
-    let client = reqwest::Client::new();
+    let client = api_client()?;
     let server_url = "http://localhost";
 
     match command {
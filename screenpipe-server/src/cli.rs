@@ -1,14 +1,18 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use chrono::{DateTime, Utc};
 
 use clap::{Parser, Subcommand, ValueHint};
 use clap_complete::{generate, Shell};
 use clap::CommandFactory;
-use screenpipe_audio::{vad::{VadSensitivity, VadEngineEnum}, core::engine::AudioTranscriptionEngine as CoreAudioTranscriptionEngine};
-use screenpipe_vision::{custom_ocr::CustomOcrConfig, utils::OcrEngine as CoreOcrEngine};
+use screenpipe_audio::{preprocessing::AudioPreprocessing, vad::{VadConfig, VadSensitivity, VadEngineEnum}, core::engine::AudioTranscriptionEngine as CoreAudioTranscriptionEngine, transcription::openai_compatible::OpenAiCompatibleConfig};
+use screenpipe_vision::{ocr_backend::default_ocr_backend_name, redaction::RedactionMode, OcrBackendRegistry};
 use clap::ValueEnum;
 use screenpipe_core::Language;
-use screenpipe_db::OcrEngine as DBOcrEngine;
-use screenpipe_db::CustomOcrConfig as DBCustomOcrConfig;
+use crate::auth::Scope;
+use crate::ocr_queue::OcrQueueConfig;
+use crate::retention::{RetentionConfig, RetentionPolicy};
+
 #[derive(Clone, Debug, ValueEnum, PartialEq)]
 pub enum CliAudioTranscriptionEngine {
     #[clap(name = "deepgram")]
@@ -25,6 +29,8 @@ pub enum CliAudioTranscriptionEngine {
     WhisperLargeV3Turbo,
     #[clap(name = "whisper-large-v3-turbo-quantized")]
     WhisperLargeV3TurboQuantized,
+    #[clap(name = "openai-compatible")]
+    OpenAiCompatible,
 }
 
 impl From<CliAudioTranscriptionEngine> for CoreAudioTranscriptionEngine {
@@ -41,64 +47,13 @@ impl From<CliAudioTranscriptionEngine> for CoreAudioTranscriptionEngine {
             CliAudioTranscriptionEngine::WhisperLargeV3TurboQuantized => {
                 CoreAudioTranscriptionEngine::WhisperLargeV3TurboQuantized
             }
-        }
-    }
-}
-
-#[derive(Clone, Debug, ValueEnum, PartialEq)]
-pub enum CliOcrEngine {
-    Unstructured,
-    #[cfg(target_os = "linux")]
-    Tesseract,
-    #[cfg(target_os = "windows")]
-    WindowsNative,
-    #[cfg(target_os = "macos")]
-    AppleNative,
-    Custom,
-}
-
-impl From<CliOcrEngine> for Arc<DBOcrEngine> {
-    fn from(cli_engine: CliOcrEngine) -> Self {
-        match cli_engine {
-            CliOcrEngine::Unstructured => Arc::new(DBOcrEngine::Unstructured),
-            #[cfg(target_os = "macos")]
-            CliOcrEngine::AppleNative => Arc::new(DBOcrEngine::AppleNative),
-            #[cfg(target_os = "linux")]
-            CliOcrEngine::Tesseract => Arc::new(DBOcrEngine::Tesseract),
-            #[cfg(target_os = "windows")]
-            CliOcrEngine::WindowsNative => Arc::new(DBOcrEngine::WindowsNative),
-            CliOcrEngine::Custom => Arc::new(DBOcrEngine::Custom(DBCustomOcrConfig::default())),
-        }
-    }
-}
-
-impl From<CliOcrEngine> for CoreOcrEngine {
-    fn from(cli_engine: CliOcrEngine) -> Self {
-        match cli_engine {
-            CliOcrEngine::Unstructured => CoreOcrEngine::Unstructured,
-            #[cfg(target_os = "linux")]
-            CliOcrEngine::Tesseract => CoreOcrEngine::Tesseract,
-            #[cfg(target_os = "windows")]
-            CliOcrEngine::WindowsNative => CoreOcrEngine::WindowsNative,
-            #[cfg(target_os = "macos")]
-            CliOcrEngine::AppleNative => CoreOcrEngine::AppleNative,
-            CliOcrEngine::Custom => {
-                // Try to read config from environment variable
-                if let Ok(config_str) = std::env::var("SCREENPIPE_CUSTOM_OCR_CONFIG") {
-                    match serde_json::from_str(&config_str) {
-                        Ok(config) => CoreOcrEngine::Custom(config),
-                        Err(e) => {
-                            tracing::warn!("failed to parse custom ocr config from env: {}", e);
-                            CoreOcrEngine::Custom(CustomOcrConfig::default())
-                        }
-                    }
-                } else {
-                    CoreOcrEngine::Custom(CustomOcrConfig::default())
-                }
+            CliAudioTranscriptionEngine::OpenAiCompatible => {
+                CoreAudioTranscriptionEngine::OpenAiCompatible
             }
         }
     }
 }
+
 #[derive(Clone, Debug, ValueEnum, PartialEq)]
 pub enum CliVadEngine {
     #[clap(name = "webrtc")]
@@ -133,6 +88,58 @@ impl From<CliVadSensitivity> for VadSensitivity {
     }
 }
 
+fn parse_ocr_engine(name: &str) -> Result<String, String> {
+    let registry = OcrBackendRegistry::default();
+    if registry.names().any(|engine| engine == name) {
+        Ok(name.to_string())
+    } else {
+        Err(format!(
+            "unknown ocr engine {}, available: {}",
+            name,
+            registry.names().collect::<Vec<_>>().join(", ")
+        ))
+    }
+}
+
+fn parse_device_preprocessing(value: &str) -> Result<(String, AudioPreprocessing), String> {
+    let (device, stages) = value
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected DEVICE=STAGES, got {}", value))?;
+    let mut preprocessing = AudioPreprocessing::default();
+    for stage in stages.split(',').map(str::trim) {
+        match stage {
+            "noise-suppression" => preprocessing.noise_suppression = true,
+            "auto-gain" => preprocessing.auto_gain = true,
+            "echo-cancellation" => preprocessing.echo_cancellation = true,
+            "none" => {}
+            _ => return Err(format!("unknown preprocessing stage: {}", stage)),
+        }
+    }
+    Ok((device.trim().to_string(), preprocessing))
+}
+
+/// The sensitivity preset with the thresholds and durations given on the command line
+pub fn vad_config(
+    sensitivity: &CliVadSensitivity,
+    speech_threshold: Option<f32>,
+    silence_threshold: Option<f32>,
+    hangover_ms: Option<u64>,
+    min_speech_ms: Option<u64>,
+) -> VadConfig {
+    let preset = VadConfig::from(VadSensitivity::from(sensitivity.clone()));
+    let speech_threshold = speech_threshold.unwrap_or(preset.speech_threshold);
+    VadConfig {
+        speech_threshold,
+        silence_threshold: silence_threshold
+            .unwrap_or(preset.silence_threshold)
+            .min(speech_threshold),
+        hangover: hangover_ms.map_or(preset.hangover, Duration::from_millis),
+        min_speech_duration: min_speech_ms
+            .map_or(preset.min_speech_duration, Duration::from_millis),
+        ..preset
+    }
+}
+
 #[derive(Parser)]
 #[command(
     author, 
@@ -163,7 +170,8 @@ pub struct Cli {
     #[arg(long, default_value_t = false)]
     pub disable_audio: bool,
 
-    /// Audio devices to use (can be specified multiple times)
+    /// Audio devices to use (can be specified multiple times). Besides sound cards, audio can be read as PCM from a file, a named pipe or a TCP port, e.g.
+    /// -i "/tmp/meeting.wav (file)" -i "/tmp/recorder.pcm?rate=48000&channels=2 (fifo)" -i "127.0.0.1:4000?format=f32le (tcp)"
     #[arg(short = 'i', long)]
     pub audio_device: Vec<String>,
 
@@ -184,6 +192,7 @@ pub struct Cli {
     /// WhisperTiny is a local, lightweight transcription model, recommended for high data privacy.
     /// WhisperDistilLargeV3 is a local, lightweight transcription model (-a whisper-large), recommended for higher quality audio than tiny.
     /// WhisperLargeV3Turbo is a local, lightweight transcription model (-a whisper-large-v3-turbo), recommended for higher quality audio than tiny.
+    /// OpenAiCompatible sends audio to any server implementing OpenAI's /v1/audio/transcriptions (-a openai-compatible), e.g. a self-hosted whisper.cpp server, see --openai-compatible-endpoint.
     #[arg(short = 'a', long, value_enum, default_value_t = CliAudioTranscriptionEngine::WhisperTinyQuantized)]
     pub audio_transcription_engine: CliAudioTranscriptionEngine,
 
@@ -196,23 +205,19 @@ pub struct Cli {
     pub enable_realtime_vision: bool,
 
     /// OCR engine to use.
-    /// AppleNative is the default local OCR engine for macOS.
-    /// WindowsNative is a local OCR engine for Windows.
-    /// Unstructured is a cloud OCR engine (free of charge on us for now), recommended for high quality OCR.
-    /// Tesseract is a local OCR engine (not supported on macOS)
-    #[cfg_attr(
-        target_os = "macos",
-        arg(short = 'o', long, value_enum, default_value_t = CliOcrEngine::AppleNative)
-    )]
-    #[cfg_attr(
-        target_os = "windows",
-        arg(short = 'o', long, value_enum, default_value_t = CliOcrEngine::WindowsNative)
+    /// apple-native is the default local OCR engine for macOS.
+    /// windows-native is a local OCR engine for Windows.
+    /// unstructured is a cloud OCR engine (free of charge on us for now), recommended for high quality OCR.
+    /// tesseract is a local OCR engine (not supported on macOS).
+    /// custom is an OCR service configured with SCREENPIPE_CUSTOM_OCR_CONFIG
+    /// onnx is a local OCR engine running paddle ocr models on the CPU, without tesseract
+    #[arg(
+        short = 'o',
+        long,
+        value_parser = parse_ocr_engine,
+        default_value_t = default_ocr_backend_name().to_string()
     )]
-    #[cfg_attr(
-        not(any(target_os = "macos", target_os = "windows")),
-        arg(short = 'o', long, value_enum, default_value_t = CliOcrEngine::Tesseract)
-    )]
-    pub ocr_engine: CliOcrEngine,
+    pub ocr_engine: String,
 
     /// Monitor IDs to use, these will be used to select the monitors to record
     #[arg(short = 'm', long)]
@@ -221,10 +226,18 @@ pub struct Cli {
     #[arg(short = 'l', long, value_enum)]
     pub language: Vec<Language>,
 
-    /// Enable PII removal from OCR text property that is saved to db and returned in search results
+    /// Enable PII removal from OCR text, audio transcriptions and UI text that is saved to db and returned in search results
     #[arg(long, default_value_t = false)]
     pub use_pii_removal: bool,
 
+    /// JSON file configuring PII detection: built in rules to disable, custom regex rules and the apps it applies to
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub pii_config: Option<PathBuf>,
+
+    /// Blur or black out PII found by OCR in the recorded frames themselves, the redacted regions are kept in the db
+    #[arg(long, value_enum)]
+    pub frame_pii_redaction: Option<RedactionMode>,
+
     /// Disable vision recording
     #[arg(long, default_value_t = false)]
     pub disable_vision: bool,
@@ -253,6 +266,18 @@ pub struct Cli {
     #[arg(long = "deepgram-api-key")]
     pub deepgram_api_key: Option<String>,
 
+    /// Transcription endpoint used with -a openai-compatible, e.g. http://localhost:8080/v1/audio/transcriptions
+    #[arg(long)]
+    pub openai_compatible_endpoint: Option<String>,
+
+    /// API key sent as a bearer token to the OpenAI compatible transcription endpoint
+    #[arg(long)]
+    pub openai_compatible_api_key: Option<String>,
+
+    /// Model requested from the OpenAI compatible transcription endpoint
+    #[arg(long, default_value = "whisper-1")]
+    pub openai_compatible_model: String,
+
     /// PID to watch for auto-destruction. If provided, screenpipe will stop when this PID is no longer running.
     #[arg(long)]
     pub auto_destruct_pid: Option<u32>,
@@ -261,6 +286,51 @@ pub struct Cli {
     #[arg(long, value_enum, default_value_t = CliVadSensitivity::High)]
     pub vad_sensitivity: CliVadSensitivity,
 
+    /// Speech probability (0-1) above which a 100ms frame is speech. Any --vad-* tuning flag makes recording use the --vad-sensitivity preset with the given overrides, see `screenpipe audio vad-test`
+    #[arg(long)]
+    pub vad_speech_threshold: Option<f32>,
+
+    /// Speech probability (0-1) below which a frame is silence, frames in between continue what came before
+    #[arg(long)]
+    pub vad_silence_threshold: Option<f32>,
+
+    /// Silence in milliseconds after speech still counted as speech, bridges pauses between words
+    #[arg(long)]
+    pub vad_hangover_ms: Option<u64>,
+
+    /// Speech shorter than this many milliseconds is ignored, e.g. keyboard clicks and door slams
+    #[arg(long)]
+    pub vad_min_speech_ms: Option<u64>,
+
+    /// Remove steady background noise from input devices before transcription
+    #[arg(long, default_value_t = false)]
+    pub noise_suppression: bool,
+
+    /// Bring the level of input devices to the same loudness before transcription
+    #[arg(long, default_value_t = false)]
+    pub auto_gain: bool,
+
+    /// Remove what the speakers play from input devices, e.g. the other side of a call. Needs an output device to be recorded
+    #[arg(long, default_value_t = false)]
+    pub echo_cancellation: bool,
+
+    /// Output device used as echo reference, e.g. "Speakers (output)". Default to the one heard best
+    #[arg(long)]
+    pub echo_reference: Option<String>,
+
+    /// Preprocessing of one device instead of the defaults above, as DEVICE=STAGES with stages among noise-suppression, auto-gain, echo-cancellation or none, e.g.
+    /// --device-preprocessing "MacBook Pro Microphone (input)=echo-cancellation,noise-suppression"
+    #[arg(long, value_parser = parse_device_preprocessing)]
+    pub device_preprocessing: Vec<(String, AudioPreprocessing)>,
+
+    /// Disable hiding transcriptions of the same speech recorded by several devices, e.g. a call heard by the output device and the microphone
+    #[arg(long, default_value_t = false)]
+    pub disable_transcript_dedup: bool,
+
+    /// Share of words two transcriptions from different devices need in common to be duplicates, between 0 and 1
+    #[arg(long, default_value_t = 0.8)]
+    pub transcript_dedup_similarity: f64,
+
     /// Disable telemetry
     #[arg(long, default_value_t = false)]
     pub disable_telemetry: bool,
@@ -281,10 +351,77 @@ pub struct Cli {
     #[arg(long, default_value_t = false)]
     pub capture_unfocused_windows: bool,
 
+    /// Only OCR the parts of windows that changed since the previous frame, reusing the text of
+    /// the rest. Engines that don't locate lines, like windows native, still OCR whole windows
+    #[arg(long, default_value_t = false)]
+    pub enable_roi_ocr: bool,
+
+    /// Record frames without waiting for their OCR, windows are OCR'd by a pool of workers from
+    /// a queue kept in the database so a slow OCR engine doesn't lower the capture rate. Frame
+    /// pii redaction and roi ocr need the text while capturing and can't be combined with it
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["frame_pii_redaction", "enable_roi_ocr"]
+    )]
+    pub enable_ocr_queue: bool,
+
+    /// Windows OCR'd at the same time from the OCR queue
+    #[arg(long, default_value_t = 2)]
+    pub ocr_workers: usize,
+
+    /// Windows waiting in the OCR queue at most, the oldest are dropped first and their frames
+    /// keep no text
+    #[arg(long, default_value_t = 2000)]
+    pub ocr_queue_max_size: usize,
+
     /// Enable pipe functionality (default: false)
     #[arg(long, default_value_t = false)]
     pub enable_pipe_manager: bool,
 
+    /// Delete screen recordings (video chunks, frames and OCR) older than this many days
+    #[arg(long)]
+    pub retention_video_max_age_days: Option<u64>,
+
+    /// Delete audio recordings and transcriptions older than this many days
+    #[arg(long)]
+    pub retention_audio_max_age_days: Option<u64>,
+
+    /// Maximum disk space in GB for screen recordings, oldest chunks are deleted first
+    #[arg(long)]
+    pub retention_video_max_size_gb: Option<f64>,
+
+    /// Maximum disk space in GB for audio recordings, oldest chunks are deleted first
+    #[arg(long)]
+    pub retention_audio_max_size_gb: Option<f64>,
+
+    /// Interval in seconds between retention checks
+    #[arg(long, default_value_t = 3600)]
+    pub retention_check_interval_secs: u64,
+
+    /// Generate embeddings for new audio transcriptions and UI monitoring text with Ollama's
+    /// nomic-embed-text model, so /semantic-search/content can find them. Older text is embedded
+    /// with `screenpipe migrate --migration-name text_embeddings start`
+    #[arg(long, default_value_t = false)]
+    pub enable_text_embeddings: bool,
+
+    /// Encrypt the database and recorded media at rest. The file must hold a 64 character hex
+    /// key, e.g. from `openssl rand -hex 32`. The key can also be passed in the
+    /// SCREENPIPE_ENCRYPTION_KEY environment variable. Requires a build with `--features sqlcipher`
+    #[arg(long, global = true, value_hint = ValueHint::FilePath)]
+    pub encryption_keyfile: Option<PathBuf>,
+
+    /// Require a bearer token on every api route except /health. Create tokens with
+    /// `screenpipe token create`, pipes get their own token in SCREENPIPE_API_TOKEN. Without
+    /// it, only requests addressed to localhost are served
+    #[arg(long, default_value_t = false)]
+    pub enable_auth: bool,
+
+    /// Extra origin allowed to call the api from a browser, e.g. https://example.com.
+    /// localhost and the desktop app are always allowed, `*` allows every origin
+    #[arg(long)]
+    pub allowed_origin: Vec<String>,
+
     #[command(subcommand)]
     pub command: Option<Command>,
 
@@ -302,6 +439,73 @@ impl Cli {
         }
         Ok(unique_langs.into_iter().collect())
     }
+    pub fn retention_config(&self) -> RetentionConfig {
+        RetentionConfig {
+            vision: RetentionPolicy {
+                max_age_days: self.retention_video_max_age_days,
+                max_size_gb: self.retention_video_max_size_gb,
+            },
+            audio: RetentionPolicy {
+                max_age_days: self.retention_audio_max_age_days,
+                max_size_gb: self.retention_audio_max_size_gb,
+            },
+            check_interval_secs: self.retention_check_interval_secs,
+        }
+    }
+    pub fn ocr_queue_config(&self) -> OcrQueueConfig {
+        OcrQueueConfig {
+            enabled: self.enable_ocr_queue,
+            workers: self.ocr_workers,
+            max_size: self.ocr_queue_max_size,
+        }
+    }
+    /// Preprocessing of input devices without their own
+    pub fn audio_preprocessing(&self) -> AudioPreprocessing {
+        AudioPreprocessing {
+            noise_suppression: self.noise_suppression,
+            auto_gain: self.auto_gain,
+            echo_cancellation: self.echo_cancellation,
+            echo_reference: self.echo_reference.clone(),
+        }
+    }
+    pub fn preprocessing_by_device(&self) -> HashMap<String, AudioPreprocessing> {
+        self.device_preprocessing
+            .iter()
+            .map(|(device, preprocessing)| {
+                let preprocessing = AudioPreprocessing {
+                    echo_reference: self.echo_reference.clone(),
+                    ..preprocessing.clone()
+                };
+                (device.clone(), preprocessing)
+            })
+            .collect()
+    }
+    /// Speech detection tuned on the command line, `None` keeps the engine's defaults
+    pub fn vad_config(&self) -> Option<VadConfig> {
+        if self.vad_speech_threshold.is_none()
+            && self.vad_silence_threshold.is_none()
+            && self.vad_hangover_ms.is_none()
+            && self.vad_min_speech_ms.is_none()
+        {
+            return None;
+        }
+        Some(vad_config(
+            &self.vad_sensitivity,
+            self.vad_speech_threshold,
+            self.vad_silence_threshold,
+            self.vad_hangover_ms,
+            self.vad_min_speech_ms,
+        ))
+    }
+    pub fn openai_compatible_config(&self) -> Option<OpenAiCompatibleConfig> {
+        self.openai_compatible_endpoint
+            .as_ref()
+            .map(|endpoint| OpenAiCompatibleConfig {
+                api_key: self.openai_compatible_api_key.clone(),
+                model: self.openai_compatible_model.clone(),
+                ..OpenAiCompatibleConfig::new(endpoint.clone())
+            })
+    }
     pub fn handle_completions(&self, shell: Shell) -> anyhow::Result<()> {
         let mut cmd = Self::command();
         generate(shell, &mut cmd, "screenpipe", &mut std::io::stdout());
@@ -331,7 +535,7 @@ pub enum Command {
         #[command(subcommand)]
         subcommand: McpCommand,
     },
-    /// Add video files to existing screenpipe data (OCR only) - DOES NOT SUPPORT AUDIO
+    /// Add video files to existing screenpipe data (OCR only) - use `add-audio` for audio
     Add {
         /// Path to folder containing video files
         path: String,
@@ -345,8 +549,8 @@ pub enum Command {
         #[arg(long)]
         pattern: Option<String>,
         /// OCR engine to use
-        #[arg(short = 'o', long, value_enum)]
-        ocr_engine: Option<CliOcrEngine>,
+        #[arg(short = 'o', long, value_parser = parse_ocr_engine)]
+        ocr_engine: Option<String>,
         /// Path to JSON file containing metadata overrides
         #[arg(long, value_hint = ValueHint::FilePath)]
         metadata_override: Option<PathBuf>,
@@ -360,6 +564,88 @@ pub enum Command {
         #[arg(long, default_value_t = false)]
         use_embedding: bool,
     },
+    /// Add audio files recorded outside screenpipe, e.g. phone calls or voice memos, to
+    /// existing screenpipe data. Runs them through VAD, transcription and speaker matching
+    /// like live audio
+    AddAudio {
+        /// Path to an audio file or a folder containing audio files (wav, mp3, m4a, opus, ...)
+        path: String,
+        /// Regex pattern to filter files (e.g. "call.*\.m4a$")
+        #[arg(long)]
+        pattern: Option<String>,
+        /// When the recording started (RFC 3339). Defaults to the modification time of each file
+        #[arg(long)]
+        timestamp: Option<DateTime<Utc>>,
+        /// Device name the audio is stored under
+        #[arg(long, default_value = "imported")]
+        device: String,
+        /// Name of a person heard in the recording, can be repeated. With a single name every
+        /// transcription is attributed to that person
+        #[arg(long = "speaker")]
+        speakers: Vec<String>,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short = 'o', long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Export recorded data to a portable archive (.tar.zst)
+    Export {
+        /// Path of the archive to write
+        #[arg(long, value_hint = ValueHint::FilePath)]
+        out: PathBuf,
+        /// Start of the time range to export (RFC 3339). Defaults to the oldest recording
+        #[arg(long)]
+        from: Option<DateTime<Utc>>,
+        /// End of the time range to export (RFC 3339). Defaults to now
+        #[arg(long)]
+        to: Option<DateTime<Utc>>,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short = 'o', long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Import an archive created with `export` into existing screenpipe data
+    Import {
+        /// Path of the archive to import
+        #[arg(value_hint = ValueHint::FilePath)]
+        archive: PathBuf,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short = 'o', long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Transcribe recorded audio again with another engine, e.g. a larger model overnight
+    Retranscribe {
+        /// Engine to transcribe with
+        #[arg(long, value_enum)]
+        engine: CliAudioTranscriptionEngine,
+        /// Start of the time range to re-transcribe (RFC 3339)
+        #[arg(long)]
+        from: DateTime<Utc>,
+        /// End of the time range to re-transcribe (RFC 3339). Defaults to now
+        #[arg(long)]
+        to: Option<DateTime<Utc>>,
+        /// Only re-transcribe audio of this device
+        #[arg(long)]
+        device: Option<String>,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short = 'o', long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Manage api tokens used with --enable-auth
+    Token {
+        #[command(subcommand)]
+        subcommand: TokenCommand,
+    },
     /// Run data migrations in the background
     Migrate {
         /// The name of the migration to run
@@ -404,6 +690,62 @@ pub enum MigrationSubCommand {
     Status,
 }
 
+#[derive(Subcommand)]
+pub enum TokenCommand {
+    /// Create a token. The token is only shown once
+    Create {
+        /// Name to recognize the token by
+        #[arg(long)]
+        name: String,
+        /// Scope granted to the token, can be repeated
+        #[arg(long, value_enum, default_values_t = [Scope::ReadSearch])]
+        scope: Vec<Scope>,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// List tokens
+    List {
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Revoke a token
+    Revoke {
+        /// ID of the token, as shown by `token list`
+        id: i64,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+    },
+    /// Grant scopes to a pipe beyond read-search, replacing the ones granted before. Applies
+    /// from the next start of the pipe
+    GrantPipe {
+        /// ID of the pipe
+        pipe_id: String,
+        /// Scope granted to the pipe, can be repeated
+        #[arg(long, value_enum, required = true)]
+        scope: Vec<Scope>,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+    },
+    /// Take back the scopes granted to a pipe and revoke its tokens
+    RevokePipe {
+        /// ID of the pipe
+        pipe_id: String,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum AudioCommand {
     /// List available audio devices
@@ -412,6 +754,36 @@ pub enum AudioCommand {
         #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
         output: OutputFormat,
     },
+    /// Print the speech regions voice activity detection finds in an audio file, to tune it
+    VadTest {
+        /// Audio file in any format ffmpeg reads
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// VAD engine to use for speech detection
+        #[arg(long, value_enum, default_value_t = CliVadEngine::Silero)]
+        vad_engine: CliVadEngine,
+        /// Sensitivity preset the other options override, recording uses medium unless given --vad-* tuning flags
+        #[arg(long, value_enum, default_value_t = CliVadSensitivity::Medium)]
+        vad_sensitivity: CliVadSensitivity,
+        /// Speech probability (0-1) above which a 100ms frame is speech
+        #[arg(long)]
+        speech_threshold: Option<f32>,
+        /// Speech probability (0-1) below which a frame is silence
+        #[arg(long)]
+        silence_threshold: Option<f32>,
+        /// Silence in milliseconds after speech still counted as speech
+        #[arg(long)]
+        hangover_ms: Option<u64>,
+        /// Speech shorter than this many milliseconds is ignored
+        #[arg(long)]
+        min_speech_ms: Option<u64>,
+        /// Also print the speech probability of every frame
+        #[arg(long, default_value_t = false)]
+        probabilities: bool,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
 }
 
 #[derive(Subcommand)]
@@ -1,12 +1,16 @@
+use crate::ocr_queue::OcrQueue;
 use crate::VideoCapture;
 use anyhow::Result;
 use futures::future::join_all;
-use screenpipe_core::pii_removal::remove_pii;
+use screenpipe_core::pii_removal::{pii_applies_to_app, remove_pii};
 use screenpipe_core::Language;
-use screenpipe_db::{DatabaseManager, Speaker};
+use screenpipe_db::{DatabaseManager, FrameRedaction, Speaker};
 use screenpipe_events::{poll_meetings_events, send_event};
 use screenpipe_vision::core::WindowOcr;
-use screenpipe_vision::OcrEngine;
+use screenpipe_vision::redaction::RedactionMode;
+use screenpipe_vision::roi::RoiConfig;
+use screenpipe_vision::OcrBackend;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::runtime::Handle;
@@ -18,15 +22,18 @@ pub async fn start_continuous_recording(
     output_path: Arc<String>,
     fps: f64,
     video_chunk_duration: Duration,
-    ocr_engine: Arc<OcrEngine>,
+    ocr_engine: Arc<dyn OcrBackend>,
     monitor_ids: Vec<u32>,
     use_pii_removal: bool,
+    frame_pii_redaction: Option<RedactionMode>,
     vision_disabled: bool,
     vision_handle: &Handle,
     ignored_windows: &[String],
     include_windows: &[String],
     languages: Vec<Language>,
     capture_unfocused_windows: bool,
+    roi_ocr: Option<RoiConfig>,
+    ocr_queue: Arc<OcrQueue>,
     realtime_vision: bool,
 ) -> Result<()> {
     info!("Starting video recording for monitors {:?}", monitor_ids);
@@ -41,6 +48,8 @@ pub async fn start_continuous_recording(
                 let include_windows_video = include_windows.to_vec();
 
                 let languages = languages.clone();
+                let roi_ocr = roi_ocr.clone();
+                let ocr_queue = ocr_queue.clone();
 
                 info!("Starting video recording for monitor {}", monitor_id);
                 vision_handle.spawn(async move {
@@ -54,11 +63,14 @@ pub async fn start_continuous_recording(
                             ocr_engine.clone(),
                             monitor_id,
                             use_pii_removal,
+                            frame_pii_redaction,
                             &ignored_windows_video,
                             &include_windows_video,
                             video_chunk_duration,
                             languages.clone(),
                             capture_unfocused_windows,
+                            roi_ocr.clone(),
+                            ocr_queue.clone(),
                             realtime_vision,
                         )
                         .await
@@ -115,14 +127,17 @@ async fn record_video(
     db: Arc<DatabaseManager>,
     output_path: Arc<String>,
     fps: f64,
-    ocr_engine: Arc<OcrEngine>,
+    ocr_engine: Arc<dyn OcrBackend>,
     monitor_id: u32,
     use_pii_removal: bool,
+    frame_pii_redaction: Option<RedactionMode>,
     ignored_windows: &[String],
     include_windows: &[String],
     video_chunk_duration: Duration,
     languages: Vec<Language>,
     capture_unfocused_windows: bool,
+    roi_ocr: Option<RoiConfig>,
+    ocr_queue: Arc<OcrQueue>,
     realtime_vision: bool,
 ) -> Result<()> {
     info!("record_video: Starting for monitor {}", monitor_id);
@@ -164,12 +179,15 @@ async fn record_video(
         fps,
         video_chunk_duration,
         new_chunk_callback,
-        Arc::clone(&ocr_engine),
+        // queued windows are captured without their text
+        (!ocr_queue.enabled()).then(|| Arc::clone(&ocr_engine)),
         monitor_id,
         ignored_windows,
         include_windows,
         languages,
         capture_unfocused_windows,
+        roi_ocr,
+        frame_pii_redaction,
     );
 
     info!(
@@ -263,14 +281,47 @@ async fn record_video(
                             frame_id,
                             insert_duration.as_millis()
                         );
-                        let text_json =
-                            serde_json::to_string(&window_result.text_json).unwrap_or_default();
 
-                        let text = if use_pii_removal {
-                            &remove_pii(&window_result.text)
+                        if let Some(mode) = frame_pii_redaction
+                            .filter(|_| !window_result.redacted_regions.is_empty())
+                        {
+                            let redactions = window_result
+                                .redacted_regions
+                                .iter()
+                                .map(|region| FrameRedaction {
+                                    pii_type: region.pii_type.to_string(),
+                                    mode: mode.as_str().to_string(),
+                                    x: region.x,
+                                    y: region.y,
+                                    width: region.width,
+                                    height: region.height,
+                                })
+                                .collect::<Vec<_>>();
+                            if let Err(e) = db.insert_frame_redactions(frame_id, &redactions).await
+                            {
+                                error!("Failed to record redactions for frame {}: {}", frame_id, e);
+                            }
+                        }
+
+                        if ocr_queue.enabled() {
+                            if let Err(e) = ocr_queue.push(frame_id, &window_result.image).await {
+                                error!("Failed to queue ocr of frame {}: {}", frame_id, e);
+                            }
+                            continue;
+                        }
+
+                        let strip_pii =
+                            use_pii_removal && pii_applies_to_app(&window_result.app_name);
+                        let (text, text_json_entries) = if strip_pii {
+                            (
+                                &remove_pii(&window_result.text),
+                                &remove_pii_from_text_json(&window_result.text_json),
+                            )
                         } else {
-                            &window_result.text
+                            (&window_result.text, &window_result.text_json)
                         };
+                        let text_json =
+                            serde_json::to_string(text_json_entries).unwrap_or_default();
 
                         if realtime_vision {
                             let send_event_start = std::time::Instant::now();
@@ -279,7 +330,7 @@ async fn record_video(
                                 WindowOcr {
                                     image: Some(frame.image.clone()),
                                     text: text.clone(),
-                                    text_json: window_result.text_json.clone(),
+                                    text_json: text_json_entries.clone(),
                                     app_name: window_result.app_name.clone(),
                                     window_name: window_result.window_name.clone(),
                                     focused: window_result.focused,
@@ -302,13 +353,12 @@ async fn record_video(
                         }
 
                         let insert_ocr_start = std::time::Instant::now();
+                        let engine = window_result
+                            .fallback_engine
+                            .clone()
+                            .unwrap_or_else(|| ocr_engine.engine());
                         if let Err(e) = db
-                            .insert_ocr_text(
-                                frame_id,
-                                text,
-                                &text_json,
-                                Arc::new((*ocr_engine).clone().into()),
-                            )
+                            .insert_ocr_text(frame_id, text, &text_json, Arc::new(engine))
                             .await
                         {
                             error!(
@@ -326,6 +376,14 @@ async fn record_video(
                                 );
                             }
                             consecutive_db_errors = 0; // Reset on success
+                            if window_result.ocr_deferred {
+                                // OCR'd again once the selected engine is back
+                                if let Err(e) =
+                                    ocr_queue.defer(frame_id, &window_result.image).await
+                                {
+                                    warn!("failed to defer ocr of frame {}: {}", frame_id, e);
+                                }
+                            }
                             debug!(
                                 "OCR text inserted for frame {} in {}ms",
                                 frame_id,
@@ -384,3 +442,19 @@ pub async fn merge_speakers(
         Err(e) => Err(anyhow::anyhow!("Failed to merge speakers: {}", e)),
     }
 }
+
+/// The ocr boxes keep the text of each line, which would otherwise still hold the pii
+pub(crate) fn remove_pii_from_text_json(
+    text_json: &[HashMap<String, String>],
+) -> Vec<HashMap<String, String>> {
+    text_json
+        .iter()
+        .map(|entry| {
+            let mut entry = entry.clone();
+            if let Some(text) = entry.get_mut("text") {
+                *text = remove_pii(text);
+            }
+            entry
+        })
+        .collect()
+}
@@ -0,0 +1,70 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+/// Processes the rows written after startup, every `interval` until shutdown.
+///
+/// `start` gives the cursor of the last row written before startup. `fetch` returns at most
+/// `batch_size` rows after a cursor, each with the cursor to resume from once it's processed.
+/// Each pass drains everything written since the previous one.
+pub(crate) fn start_cursor_task<C, R, S, F, FetchFut, P, ProcessFut>(
+    name: &'static str,
+    interval: Duration,
+    batch_size: usize,
+    start: S,
+    fetch: F,
+    process: P,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) -> JoinHandle<()>
+where
+    C: Clone + Send + 'static,
+    R: Send + 'static,
+    S: Future<Output = Result<C, sqlx::Error>> + Send + 'static,
+    F: Fn(C) -> FetchFut + Send + 'static,
+    FetchFut: Future<Output = Result<Vec<(C, R)>, sqlx::Error>> + Send,
+    P: Fn(R) -> ProcessFut + Send + 'static,
+    ProcessFut: Future<Output = ()> + Send,
+{
+    tokio::spawn(async move {
+        let mut cursor = match start.await {
+            Ok(cursor) => cursor,
+            Err(e) => {
+                error!("failed to start {} task: {}", name, e);
+                return;
+            }
+        };
+        info!("{} task started", name);
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, stopping {} task", name);
+                    break;
+                }
+            }
+
+            loop {
+                let rows = match fetch(cursor.clone()).await {
+                    Ok(rows) => rows,
+                    Err(e) => {
+                        error!("failed to fetch rows for {}: {}", name, e);
+                        break;
+                    }
+                };
+                let fetched = rows.len();
+
+                for (next, row) in rows {
+                    process(row).await;
+                    cursor = next;
+                }
+
+                if fetched < batch_size {
+                    break;
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,137 @@
+use crate::jobs::{Job, JobRegistry};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use screenpipe_audio::speaker::diarization::{diarize_window, DiarizationProgress};
+use screenpipe_db::DatabaseManager;
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{error, info};
+
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiarizationJobState {
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize)]
+pub struct DiarizationJob {
+    pub id: u64,
+    pub state: DiarizationJobState,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub device: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub chunks_total: usize,
+    pub chunks_processed: usize,
+    /// Chunks that could not be read, their transcriptions keep their speaker
+    pub chunks_failed: usize,
+    pub segments: usize,
+    /// Speaker of each voice heard in the window
+    pub speaker_ids: Vec<i64>,
+    pub transcriptions_updated: u64,
+    pub error: Option<String>,
+}
+
+impl Job for DiarizationJob {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn is_running(&self) -> bool {
+        self.state == DiarizationJobState::Running
+    }
+}
+
+/// Runs batch diarization over a conversation window in the background, one job at a time.
+///
+/// Speakers are assigned per segment while recording, this re-clusters the whole window so a
+/// voice keeps a single speaker, see [`diarize_window`].
+pub struct DiarizationManager {
+    db: Arc<DatabaseManager>,
+    jobs: JobRegistry<DiarizationJob>,
+}
+
+impl DiarizationManager {
+    pub fn new(db: Arc<DatabaseManager>) -> Self {
+        Self {
+            db,
+            jobs: JobRegistry::new("diarization"),
+        }
+    }
+
+    /// Starts diarizing the transcriptions recorded between `start_time` and `end_time`.
+    /// Fails while another job is running.
+    pub fn start(
+        self: &Arc<Self>,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        device: Option<String>,
+    ) -> Result<DiarizationJob> {
+        let job = self.jobs.start(|id| DiarizationJob {
+            id,
+            state: DiarizationJobState::Running,
+            start_time,
+            end_time,
+            device,
+            started_at: Utc::now(),
+            finished_at: None,
+            chunks_total: 0,
+            chunks_processed: 0,
+            chunks_failed: 0,
+            segments: 0,
+            speaker_ids: Vec::new(),
+            transcriptions_updated: 0,
+            error: None,
+        })?;
+
+        info!(
+            "starting diarization job {} from {} to {}",
+            job.id, start_time, end_time
+        );
+        let manager = self.clone();
+        let (id, device) = (job.id, job.device.clone());
+        tokio::spawn(async move {
+            let result = diarize_window(
+                &manager.db,
+                start_time,
+                end_time,
+                device.as_deref(),
+                |progress: DiarizationProgress| {
+                    manager.jobs.update(id, |job| {
+                        job.chunks_total = progress.chunks_total;
+                        job.chunks_processed = progress.chunks_processed;
+                    })
+                },
+            )
+            .await;
+
+            manager.jobs.update(id, |job| {
+                job.finished_at = Some(Utc::now());
+                match result {
+                    Ok(summary) => {
+                        job.state = DiarizationJobState::Completed;
+                        job.chunks_failed = summary.chunks_failed;
+                        job.segments = summary.segments;
+                        job.speaker_ids = summary.speakers;
+                        job.transcriptions_updated = summary.transcriptions_updated;
+                    }
+                    Err(e) => {
+                        error!("diarization job {} failed: {}", id, e);
+                        job.state = DiarizationJobState::Failed;
+                        job.error = Some(e.to_string());
+                    }
+                }
+            });
+        });
+
+        Ok(job)
+    }
+
+    pub fn job(&self, id: u64) -> Option<DiarizationJob> {
+        self.jobs.get(id)
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::{bail, Result};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Jobs kept for the status endpoint, oldest finished jobs are dropped first
+const MAX_JOBS: usize = 20;
+
+/// Status of a background job, kept by a [`JobRegistry`]
+pub(crate) trait Job: Clone {
+    fn id(&self) -> u64;
+    fn is_running(&self) -> bool;
+}
+
+struct Jobs<J> {
+    next_id: u64,
+    jobs: VecDeque<J>,
+}
+
+/// Status of the background jobs of a manager, which runs one job at a time
+pub(crate) struct JobRegistry<J> {
+    kind: &'static str,
+    jobs: Mutex<Jobs<J>>,
+}
+
+impl<J: Job> JobRegistry<J> {
+    /// `kind` names the jobs in errors, like "diarization"
+    pub fn new(kind: &'static str) -> Self {
+        Self {
+            kind,
+            jobs: Mutex::new(Jobs {
+                next_id: 0,
+                jobs: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Adds the job built by `new` from its id. Fails while another job is running.
+    pub fn start(&self, new: impl FnOnce(u64) -> J) -> Result<J> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(running) = jobs.jobs.iter().find(|job| job.is_running()) {
+            bail!("{} job {} is still running", self.kind, running.id());
+        }
+
+        jobs.next_id += 1;
+        let job = new(jobs.next_id);
+        if jobs.jobs.len() >= MAX_JOBS {
+            if let Some(finished) = jobs.jobs.iter().position(|job| !job.is_running()) {
+                jobs.jobs.remove(finished);
+            }
+        }
+        jobs.jobs.push_back(job.clone());
+        Ok(job)
+    }
+
+    pub fn get(&self, id: u64) -> Option<J> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.jobs.iter().find(|job| job.id() == id).cloned()
+    }
+
+    pub fn update(&self, id: u64, f: impl FnOnce(&mut J)) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.jobs.iter_mut().find(|job| job.id() == id) {
+            f(job);
+        }
+    }
+}
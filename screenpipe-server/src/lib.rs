@@ -1,23 +1,36 @@
 mod add;
+pub mod archive;
+pub mod auth;
 mod auto_destruct;
 pub mod chunking;
 pub mod cli;
 pub mod core;
+mod cursor_task;
+pub mod diarization;
 pub mod filtering;
+mod jobs;
+pub mod ocr_queue;
 pub mod pipe_manager;
 mod resource_monitor;
+pub mod retention;
+pub mod retranscription;
 mod server;
 pub mod text_embeds;
+pub mod transcript_dedup;
+pub mod ui_pii;
 mod video;
 pub mod video_cache;
 pub mod video_utils;
-pub use add::handle_index_command;
+pub use add::{handle_audio_import_command, handle_index_command};
+pub use auth::{AuthManager, Scope};
 pub use auto_destruct::watch_pid;
 pub use axum::Json as JsonResponse;
 pub use cli::Cli;
 pub use core::start_continuous_recording;
+pub use ocr_queue::{OcrQueue, OcrQueueConfig};
 pub use pipe_manager::PipeManager;
 pub use resource_monitor::{ResourceMonitor, RestartSignal};
+pub use retention::{RetentionConfig, RetentionManager, RetentionPolicy};
 pub use screenpipe_core::Language;
 pub use server::health_check;
 pub use server::AppState;
@@ -0,0 +1,390 @@
+use crate::core::remove_pii_from_text_json;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use image::DynamicImage;
+use oasgen::OaSchema;
+use screenpipe_core::pii_removal::{pii_applies_to_app, remove_pii};
+use screenpipe_core::Language;
+use screenpipe_db::{DatabaseManager, OcrQueueJob};
+use screenpipe_events::send_event;
+use screenpipe_vision::core::WindowOcr;
+use screenpipe_vision::custom_ocr::CUSTOM_OCR_METRICS;
+use screenpipe_vision::OcrBackend;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Notify, Semaphore};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+/// Failed attempts after which a window is dropped, for images the engine keeps failing on.
+/// Windows put back while the engine is unavailable don't count.
+const MAX_ATTEMPTS: i64 = 5;
+/// Delay before a window put back in the queue is tried again, doubled each time it's put back
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(10);
+/// Longest delay between two tries, windows deferred during a long outage are tried this often
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(600);
+/// Share of the delay drawn at random, so windows put back together aren't all retried at once
+const RETRY_JITTER: f64 = 0.2;
+/// How often idle workers look for windows put back until a later time
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcrQueueConfig {
+    /// Whether recorded windows are queued for OCR instead of OCR'd while capturing
+    pub enabled: bool,
+    /// Windows OCR'd at the same time
+    pub workers: usize,
+    /// Windows waiting at most, the oldest are dropped first
+    pub max_size: usize,
+}
+
+impl Default for OcrQueueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            workers: 2,
+            max_size: 2000,
+        }
+    }
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize)]
+pub struct OcrQueueStatus {
+    pub config: OcrQueueConfig,
+    /// Windows waiting for a worker
+    pub queued: i64,
+    /// Windows being OCR'd
+    pub in_progress: i64,
+    /// Windows, waiting or being OCR'd, recorded while the selected engine was unavailable.
+    /// Their frames have text from a fallback engine, or none, until the engine is back.
+    pub deferred: i64,
+    /// When the oldest waiting window was queued, how far OCR is behind capture
+    pub oldest_queued_at: Option<DateTime<Utc>>,
+    /// Windows OCR'd since startup, deferred ones included
+    pub completed: u64,
+    /// Failed attempts since startup, the windows are tried again
+    pub failed: u64,
+    /// Windows dropped since startup because the queue was full or the engine kept failing on
+    /// them, their frames have no text
+    pub dropped: u64,
+}
+
+/// Windows recorded without their text, OCR'd by a pool of workers so a slow engine doesn't
+/// lower the capture rate. Kept in the database, windows still queued when screenpipe stops
+/// are OCR'd on the next start.
+///
+/// Windows recorded while the selected engine was unavailable, like a custom ocr service that
+/// is down, are queued too, to replace their text once the engine is back.
+pub struct OcrQueue {
+    db: Arc<DatabaseManager>,
+    config: OcrQueueConfig,
+    queued: Notify,
+    completed: AtomicU64,
+    failed: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl OcrQueue {
+    pub fn new(db: Arc<DatabaseManager>, config: OcrQueueConfig) -> Self {
+        Self {
+            db,
+            config,
+            queued: Notify::new(),
+            completed: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Queues a window of a recorded frame for OCR
+    pub async fn push(&self, frame_id: i64, image: &DynamicImage) -> Result<()> {
+        self.enqueue(frame_id, &encode_window(image)?, false).await
+    }
+
+    /// Queues a window whose text is from a fallback engine, or empty, because the selected
+    /// engine was unavailable
+    pub async fn defer(&self, frame_id: i64, image: &DynamicImage) -> Result<()> {
+        self.enqueue(frame_id, &encode_window(image)?, true).await
+    }
+
+    async fn enqueue(&self, frame_id: i64, image: &[u8], deferred: bool) -> Result<()> {
+        let dropped = if deferred {
+            self.db
+                .enqueue_deferred_ocr_job(frame_id, image, self.config.max_size)
+                .await?
+        } else {
+            self.db
+                .enqueue_ocr_job(frame_id, image, self.config.max_size)
+                .await?
+        };
+        if dropped > 0 {
+            warn!(
+                "ocr queue is full ({} windows), dropped the {} oldest",
+                self.config.max_size, dropped
+            );
+            self.dropped.fetch_add(dropped, Ordering::Relaxed);
+        }
+        self.queued.notify_one();
+        Ok(())
+    }
+
+    pub async fn status(&self) -> Result<OcrQueueStatus> {
+        let counts = self.db.count_ocr_jobs().await?;
+        Ok(OcrQueueStatus {
+            config: self.config.clone(),
+            queued: counts.queued,
+            in_progress: counts.in_progress,
+            deferred: counts.deferred,
+            oldest_queued_at: counts.oldest_queued_at,
+            completed: self.completed.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Starts the workers. They run even when the queue is disabled, for deferred windows and
+    /// the windows left from a previous run.
+    ///
+    /// A window is only claimed when a worker is idle, windows wait in the database rather
+    /// than in memory while the engine is slower than capture.
+    pub fn start(
+        self: Arc<Self>,
+        ocr_engine: Arc<dyn OcrBackend>,
+        languages: Vec<Language>,
+        use_pii_removal: bool,
+        realtime_vision: bool,
+        mut shutdown_rx: broadcast::Receiver<()>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            match self.db.reset_ocr_jobs().await {
+                Ok(0) => {}
+                Ok(reset) => info!(
+                    "{} windows whose ocr was interrupted are queued again",
+                    reset
+                ),
+                Err(e) => error!("failed to reset interrupted ocr jobs: {}", e),
+            }
+            let workers = Arc::new(Semaphore::new(self.config.workers.max(1)));
+            info!(
+                "ocr queue started with {} workers",
+                self.config.workers.max(1)
+            );
+
+            loop {
+                let worker = tokio::select! {
+                    worker = workers.clone().acquire_owned() => worker.expect("semaphore closed"),
+                    _ = shutdown_rx.recv() => {
+                        info!("received shutdown signal, stopping ocr queue");
+                        break;
+                    }
+                };
+
+                let job = match self.db.claim_ocr_job().await {
+                    Ok(job) => job,
+                    Err(e) => {
+                        error!("failed to claim ocr job: {}", e);
+                        None
+                    }
+                };
+                let Some(job) = job else {
+                    drop(worker);
+                    tokio::select! {
+                        _ = self.queued.notified() => {}
+                        _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                        _ = shutdown_rx.recv() => {
+                            info!("received shutdown signal, stopping ocr queue");
+                            break;
+                        }
+                    }
+                    continue;
+                };
+
+                let queue = self.clone();
+                let ocr_engine = ocr_engine.clone();
+                let languages = languages.clone();
+                tokio::spawn(async move {
+                    let frame_id = job.frame_id;
+                    if let Err(e) = queue
+                        .process(
+                            job,
+                            ocr_engine.as_ref(),
+                            &languages,
+                            use_pii_removal,
+                            realtime_vision,
+                        )
+                        .await
+                    {
+                        warn!("ocr of queued window of frame {} failed: {}", frame_id, e);
+                    }
+                    drop(worker);
+                });
+            }
+        })
+    }
+
+    async fn process(
+        &self,
+        job: OcrQueueJob,
+        ocr_engine: &dyn OcrBackend,
+        languages: &[Language],
+        use_pii_removal: bool,
+        realtime_vision: bool,
+    ) -> Result<()> {
+        let output = match image::load_from_memory(&job.image) {
+            Ok(image) => ocr_engine.recognize(&image, languages).await,
+            Err(e) => Err(e.into()),
+        };
+        let output = match output {
+            // the text of deferred windows is only replaced by the selected engine's
+            Ok(output) if job.replaces_text && output.fallback_engine.is_some() => {
+                self.postpone(&job, ocr_engine).await;
+                return Ok(());
+            }
+            Ok(output) => output,
+            Err(_) if !ocr_engine.is_available() => {
+                self.postpone(&job, ocr_engine).await;
+                return Ok(());
+            }
+            Err(e) => {
+                self.fail(&job, ocr_engine).await;
+                return Err(e);
+            }
+        };
+
+        let app_name = job.app_name.clone().unwrap_or_default();
+        let strip_pii = use_pii_removal && pii_applies_to_app(&app_name);
+        let text_json = output.text_json();
+        let (text, text_json) = if strip_pii {
+            (
+                remove_pii(&output.text),
+                remove_pii_from_text_json(&text_json),
+            )
+        } else {
+            (output.text, text_json)
+        };
+        let engine = output
+            .fallback_engine
+            .clone()
+            .unwrap_or_else(|| ocr_engine.engine());
+        let stored = match self
+            .db
+            .complete_ocr_job(
+                job.id,
+                job.frame_id,
+                &text,
+                &serde_json::to_string(&text_json).unwrap_or_default(),
+                Arc::new(engine),
+            )
+            .await
+        {
+            Ok(stored) => stored,
+            Err(e) => {
+                self.fail(&job, ocr_engine).await;
+                return Err(e.into());
+            }
+        };
+        if !stored {
+            debug!("frame {} was deleted before its ocr", job.frame_id);
+            return Ok(());
+        }
+        self.completed.fetch_add(1, Ordering::Relaxed);
+
+        if output.fallback_engine.is_some() {
+            // OCR'd again once the selected engine is back
+            if let Err(e) = self.enqueue(job.frame_id, &job.image, true).await {
+                warn!("failed to defer ocr of frame {}: {}", job.frame_id, e);
+            }
+        }
+
+        // the text of deferred windows was sent when they were recorded
+        if realtime_vision && !job.replaces_text {
+            let recorded_ago = job
+                .timestamp
+                .and_then(|timestamp| (Utc::now() - timestamp).to_std().ok())
+                .unwrap_or_default();
+            let event = WindowOcr {
+                image: None,
+                text,
+                text_json,
+                app_name,
+                window_name: job.window_name.unwrap_or_default(),
+                focused: job.focused.unwrap_or_default(),
+                confidence: output.confidence.unwrap_or(0.0),
+                timestamp: Instant::now()
+                    .checked_sub(recorded_ago)
+                    .unwrap_or_else(Instant::now),
+                browser_url: job.browser_url,
+            };
+            if let Err(e) = send_event("ocr_result", event) {
+                error!("Failed to send OCR event: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts back a window the engine was unavailable for, without counting a failed attempt
+    async fn postpone(&self, job: &OcrQueueJob, ocr_engine: &dyn OcrBackend) {
+        let retry_at = retry_at(job.retries);
+        debug!(
+            "{} is unavailable, ocr of frame {} postponed until {}",
+            ocr_engine.name(),
+            job.frame_id,
+            retry_at
+        );
+        match self.db.postpone_ocr_job(job.id, retry_at).await {
+            Ok(()) => record_retry(ocr_engine),
+            Err(e) => error!("failed to postpone ocr job {}: {}", job.id, e),
+        }
+    }
+
+    async fn fail(&self, job: &OcrQueueJob, ocr_engine: &dyn OcrBackend) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        match self
+            .db
+            .release_ocr_job(job.id, MAX_ATTEMPTS, retry_at(job.retries))
+            .await
+        {
+            Ok(true) => {
+                warn!(
+                    "dropping ocr of frame {} after {} attempts",
+                    job.frame_id, MAX_ATTEMPTS
+                );
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(false) => record_retry(ocr_engine),
+            Err(e) => error!("failed to release ocr job {}: {}", job.id, e),
+        }
+    }
+}
+
+/// When a window put back `retries` times before is tried again
+fn retry_at(retries: i64) -> DateTime<Utc> {
+    let delay = RETRY_BASE_DELAY
+        .saturating_mul(1 << retries.clamp(0, 16))
+        .min(RETRY_MAX_DELAY)
+        .mul_f64(1.0 + RETRY_JITTER * (2.0 * fastrand::f64() - 1.0));
+    Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default()
+}
+
+/// Counts the windows of the custom ocr service put back, reported with its metrics
+fn record_retry(ocr_engine: &dyn OcrBackend) {
+    if matches!(ocr_engine.engine(), screenpipe_db::OcrEngine::Custom(_)) {
+        CUSTOM_OCR_METRICS.record_retry();
+    }
+}
+
+/// Jpeg of a window waiting to be OCR'd, smaller than the raw pixels
+fn encode_window(image: &DynamicImage) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    image.to_rgb8().write_to(
+        &mut std::io::Cursor::new(&mut buffer),
+        image::ImageFormat::Jpeg,
+    )?;
+    Ok(buffer)
+}
@@ -12,6 +12,8 @@ use tokio::sync::mpsc::{self, Sender};
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
+use crate::auth::{AuthManager, TOKEN_ENV_VAR};
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct PipeInfo {
     pub id: String,
@@ -32,6 +34,7 @@ struct PipeHandle {
 pub struct PipeManager {
     screenpipe_dir: PathBuf,
     running_pipes: Arc<RwLock<HashMap<String, PipeHandle>>>,
+    auth: Option<Arc<AuthManager>>,
 }
 
 impl PipeManager {
@@ -39,9 +42,16 @@ impl PipeManager {
         PipeManager {
             screenpipe_dir,
             running_pipes: Arc::new(RwLock::new(HashMap::new())),
+            auth: None,
         }
     }
 
+    /// Gives every pipe its own api token, passed in `SCREENPIPE_API_TOKEN`
+    pub fn with_auth(mut self, auth: Arc<AuthManager>) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
     pub async fn update_config(&self, id: &str, new_config: Value) -> Result<()> {
         debug!("Updating config for pipe: {}", id);
         let pipe_dir = self.screenpipe_dir.join("pipes").join(id);
@@ -344,6 +354,10 @@ impl PipeManager {
     }
 
     pub async fn stop_pipe(&self, id: &str) -> Result<()> {
+        if let Some(auth) = &self.auth {
+            auth.revoke_pipe_tokens(id).await?;
+        }
+
         let mut pipes = self.running_pipes.write().await;
         if let Some(handle) = pipes.remove(id) {
             info!("stopping pipe: {}", id);
@@ -521,8 +535,17 @@ impl PipeManager {
         let running_pipes = self.running_pipes.clone();
         let id_for_map = id.clone();
 
+        let mut extra_env = Vec::new();
+        if let Some(auth) = &self.auth {
+            let scopes = auth
+                .pipe_scopes(&id, &screenpipe_dir.join("pipes").join(&id))
+                .await?;
+            let token = auth.issue_pipe_token(&id, &scopes).await?;
+            extra_env.push((TOKEN_ENV_VAR.to_string(), token));
+        }
+
         Ok(async move {
-            match screenpipe_core::run_pipe(&id, screenpipe_dir.clone()).await {
+            match screenpipe_core::run_pipe_with_env(&id, screenpipe_dir.clone(), extra_env).await {
                 Ok((mut child, pipe_state)) => {
                     let (kill_tx, mut kill_rx) = mpsc::channel::<()>(1);
 
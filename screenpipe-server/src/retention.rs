@@ -0,0 +1,315 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use screenpipe_db::{DatabaseManager, PrunedRows, RetentionChunk};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+/// Number of chunks deleted per database transaction
+const DELETE_BATCH_SIZE: usize = 100;
+
+const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionContentType {
+    Vision,
+    Audio,
+}
+
+/// Limits applied to a single content type. A `None` limit is not enforced.
+#[derive(OaSchema, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub max_age_days: Option<u64>,
+    pub max_size_gb: Option<f64>,
+}
+
+impl RetentionPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_days.is_some() || self.max_size_gb.is_some()
+    }
+
+    fn cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.max_age_days
+            .map(|days| now - chrono::Duration::days(days as i64))
+    }
+
+    fn max_size_bytes(&self) -> Option<u64> {
+        self.max_size_gb
+            .map(|gb| (gb.max(0.0) * BYTES_PER_GB) as u64)
+    }
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    pub vision: RetentionPolicy,
+    pub audio: RetentionPolicy,
+    pub check_interval_secs: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            vision: RetentionPolicy::default(),
+            audio: RetentionPolicy::default(),
+            check_interval_secs: 3600,
+        }
+    }
+}
+
+impl RetentionConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.vision.is_enabled() || self.audio.is_enabled()
+    }
+}
+
+#[derive(OaSchema, Debug, Clone, Default, Serialize)]
+pub struct ContentRetentionReport {
+    /// Size of the prunable chunks on disk before this run
+    pub disk_usage_bytes: u64,
+    pub chunks_deleted: u64,
+    pub frames_deleted: u64,
+    pub ocr_text_deleted: u64,
+    pub embeddings_deleted: u64,
+    pub audio_transcriptions_deleted: u64,
+    pub bytes_reclaimed: u64,
+    /// Files whose rows were deleted but that could not be removed from disk
+    pub failed_files: Vec<String>,
+}
+
+impl ContentRetentionReport {
+    fn add_pruned_rows(&mut self, rows: &PrunedRows) {
+        self.chunks_deleted += rows.chunks;
+        self.frames_deleted += rows.frames;
+        self.ocr_text_deleted += rows.ocr_text;
+        self.embeddings_deleted += rows.embeddings;
+        self.audio_transcriptions_deleted += rows.audio_transcriptions;
+    }
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize)]
+pub struct RetentionReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub dry_run: bool,
+    pub vision: ContentRetentionReport,
+    pub audio: ContentRetentionReport,
+}
+
+impl RetentionReport {
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.vision.bytes_reclaimed + self.audio.bytes_reclaimed
+    }
+}
+
+/// Enforces max-age and max-disk-size policies on recorded video and audio chunks.
+pub struct RetentionManager {
+    db: Arc<DatabaseManager>,
+    config: RwLock<RetentionConfig>,
+    last_report: RwLock<Option<RetentionReport>>,
+    // serializes runs so the background loop and the api never prune concurrently
+    run_lock: Mutex<()>,
+}
+
+impl RetentionManager {
+    pub fn new(db: Arc<DatabaseManager>, config: RetentionConfig) -> Self {
+        Self {
+            db,
+            config: RwLock::new(config),
+            last_report: RwLock::new(None),
+            run_lock: Mutex::new(()),
+        }
+    }
+
+    pub async fn config(&self) -> RetentionConfig {
+        self.config.read().await.clone()
+    }
+
+    pub async fn update_config(&self, config: RetentionConfig) {
+        info!("updating retention config: {:?}", config);
+        *self.config.write().await = config;
+    }
+
+    pub async fn last_report(&self) -> Option<RetentionReport> {
+        self.last_report.read().await.clone()
+    }
+
+    /// Applies the current policies once. With `dry_run` nothing is deleted and the report
+    /// describes what would have been reclaimed.
+    pub async fn run_once(&self, dry_run: bool) -> Result<RetentionReport> {
+        let _guard = self.run_lock.lock().await;
+        let config = self.config().await;
+        let started_at = Utc::now();
+
+        let vision = self
+            .prune(
+                RetentionContentType::Vision,
+                &config.vision,
+                started_at,
+                dry_run,
+            )
+            .await?;
+        let audio = self
+            .prune(
+                RetentionContentType::Audio,
+                &config.audio,
+                started_at,
+                dry_run,
+            )
+            .await?;
+
+        let report = RetentionReport {
+            started_at,
+            finished_at: Utc::now(),
+            dry_run,
+            vision,
+            audio,
+        };
+
+        info!(
+            "retention run finished (dry_run: {}): {} video chunks, {} audio chunks, {} bytes reclaimed",
+            dry_run,
+            report.vision.chunks_deleted,
+            report.audio.chunks_deleted,
+            report.bytes_reclaimed()
+        );
+
+        if !dry_run {
+            *self.last_report.write().await = Some(report.clone());
+        }
+
+        Ok(report)
+    }
+
+    /// Runs the retention policies periodically until a shutdown signal is received.
+    pub fn start(self: Arc<Self>, mut shutdown_rx: broadcast::Receiver<()>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("retention manager started");
+            loop {
+                let config = self.config().await;
+                if config.is_enabled() {
+                    if let Err(e) = self.run_once(false).await {
+                        error!("retention run failed: {}", e);
+                    }
+                } else {
+                    debug!("no retention policy configured, skipping run");
+                }
+
+                let interval = Duration::from_secs(config.check_interval_secs.max(60));
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    _ = shutdown_rx.recv() => {
+                        info!("received shutdown signal, stopping retention manager");
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    async fn prune(
+        &self,
+        content_type: RetentionContentType,
+        policy: &RetentionPolicy,
+        now: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<ContentRetentionReport> {
+        let mut report = ContentRetentionReport::default();
+        if !policy.is_enabled() {
+            return Ok(report);
+        }
+
+        let chunks = match content_type {
+            RetentionContentType::Vision => self.db.get_video_chunks_for_retention(None).await?,
+            RetentionContentType::Audio => self.db.get_audio_chunks_for_retention(None).await?,
+        };
+
+        let mut sized_chunks = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            let size = file_size(&chunk.file_path).await;
+            report.disk_usage_bytes += size;
+            sized_chunks.push((chunk, size));
+        }
+
+        let to_delete = select_chunks_to_delete(sized_chunks, policy, now);
+        debug!(
+            "retention selected {} {:?} chunks for deletion",
+            to_delete.len(),
+            content_type
+        );
+
+        if dry_run {
+            for (_, size) in &to_delete {
+                report.chunks_deleted += 1;
+                report.bytes_reclaimed += size;
+            }
+            return Ok(report);
+        }
+
+        for batch in to_delete.chunks(DELETE_BATCH_SIZE) {
+            let ids: Vec<i64> = batch.iter().map(|(chunk, _)| chunk.id).collect();
+            // rows go first so the database never points at a file that no longer exists
+            let pruned = match content_type {
+                RetentionContentType::Vision => self.db.delete_video_chunks(&ids).await?,
+                RetentionContentType::Audio => self.db.delete_audio_chunks(&ids).await?,
+            };
+            report.add_pruned_rows(&pruned);
+
+            for (chunk, size) in batch {
+                if chunk.file_path.is_empty() {
+                    continue;
+                }
+                match tokio::fs::remove_file(&chunk.file_path).await {
+                    Ok(_) => report.bytes_reclaimed += size,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        warn!("failed to remove {}: {}", chunk.file_path, e);
+                        report.failed_files.push(chunk.file_path.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Picks every chunk older than the max age, then the oldest remaining chunks until the
+/// total size fits under the size limit. `chunks` must be sorted oldest first.
+fn select_chunks_to_delete(
+    chunks: Vec<(RetentionChunk, u64)>,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> Vec<(RetentionChunk, u64)> {
+    let cutoff = policy.cutoff(now);
+    let mut remaining_size: u64 = chunks.iter().map(|(_, size)| size).sum();
+    let max_size = policy.max_size_bytes();
+
+    chunks
+        .into_iter()
+        .filter(|(chunk, size)| {
+            let too_old = cutoff.is_some_and(|cutoff| chunk.timestamp < cutoff);
+            let over_size = max_size.is_some_and(|max| remaining_size > max);
+            if too_old || over_size {
+                remaining_size = remaining_size.saturating_sub(*size);
+                true
+            } else {
+                false
+            }
+        })
+        .collect()
+}
+
+async fn file_size(path: &str) -> u64 {
+    if path.is_empty() {
+        return 0;
+    }
+    tokio::fs::metadata(path)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0)
+}
@@ -0,0 +1,164 @@
+use crate::jobs::{Job, JobRegistry};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use screenpipe_audio::audio_manager::AudioManager;
+use screenpipe_audio::core::engine::AudioTranscriptionEngine;
+use screenpipe_audio::transcription::backend::create_transcription_backend;
+use screenpipe_audio::transcription::retranscribe::{retranscribe_window, RetranscriptionProgress};
+use screenpipe_db::DatabaseManager;
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{error, info};
+
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetranscriptionJobState {
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize)]
+pub struct RetranscriptionJob {
+    pub id: u64,
+    pub state: RetranscriptionJobState,
+    pub engine: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub device: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub chunks_total: usize,
+    pub chunks_processed: usize,
+    /// Chunks that could not be read, their transcriptions are unchanged
+    pub chunks_failed: usize,
+    pub transcriptions: usize,
+    pub transcriptions_failed: usize,
+    /// Transcriptions for which search now returns the text of this engine
+    pub transcriptions_replaced: usize,
+    pub error: Option<String>,
+}
+
+impl Job for RetranscriptionJob {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn is_running(&self) -> bool {
+        self.state == RetranscriptionJobState::Running
+    }
+}
+
+/// Runs re-transcription of recorded audio in the background, one job at a time, with the
+/// deepgram key, openai compatible endpoint and languages the audio manager was started with.
+pub struct RetranscriptionManager {
+    db: Arc<DatabaseManager>,
+    audio_manager: Arc<AudioManager>,
+    jobs: JobRegistry<RetranscriptionJob>,
+}
+
+impl RetranscriptionManager {
+    pub fn new(db: Arc<DatabaseManager>, audio_manager: Arc<AudioManager>) -> Self {
+        Self {
+            db,
+            audio_manager,
+            jobs: JobRegistry::new("re-transcription"),
+        }
+    }
+
+    /// Starts re-transcribing the audio recorded between `start_time` and `end_time` with
+    /// `engine`. Fails while another job is running.
+    pub fn start(
+        self: &Arc<Self>,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        device: Option<String>,
+        engine: AudioTranscriptionEngine,
+    ) -> Result<RetranscriptionJob> {
+        let job = self.jobs.start(|id| RetranscriptionJob {
+            id,
+            state: RetranscriptionJobState::Running,
+            engine: engine.to_string(),
+            start_time,
+            end_time,
+            device,
+            started_at: Utc::now(),
+            finished_at: None,
+            chunks_total: 0,
+            chunks_processed: 0,
+            chunks_failed: 0,
+            transcriptions: 0,
+            transcriptions_failed: 0,
+            transcriptions_replaced: 0,
+            error: None,
+        })?;
+
+        info!(
+            "starting re-transcription job {} from {} to {} with {}",
+            job.id, start_time, end_time, job.engine
+        );
+        let manager = self.clone();
+        let (id, device) = (job.id, job.device.clone());
+        tokio::spawn(async move {
+            let options = manager.audio_manager.options().await;
+            let engine = Arc::new(engine);
+            let result = async {
+                let backend = tokio::task::spawn_blocking({
+                    let engine = engine.clone();
+                    move || {
+                        create_transcription_backend(
+                            engine,
+                            None,
+                            options.deepgram_api_key,
+                            options.openai_compatible_config,
+                        )
+                    }
+                })
+                .await??;
+
+                retranscribe_window(
+                    &manager.db,
+                    start_time,
+                    end_time,
+                    device.as_deref(),
+                    &engine,
+                    backend,
+                    options.languages,
+                    |progress: RetranscriptionProgress| {
+                        manager.jobs.update(id, |job| {
+                            job.chunks_total = progress.chunks_total;
+                            job.chunks_processed = progress.chunks_processed;
+                        })
+                    },
+                )
+                .await
+            }
+            .await;
+
+            manager.jobs.update(id, |job| {
+                job.finished_at = Some(Utc::now());
+                match result {
+                    Ok(summary) => {
+                        job.state = RetranscriptionJobState::Completed;
+                        job.chunks_failed = summary.chunks_failed;
+                        job.transcriptions = summary.transcriptions;
+                        job.transcriptions_failed = summary.transcriptions_failed;
+                        job.transcriptions_replaced = summary.transcriptions_replaced;
+                    }
+                    Err(e) => {
+                        error!("re-transcription job {} failed: {}", id, e);
+                        job.state = RetranscriptionJobState::Failed;
+                        job.error = Some(e.to_string());
+                    }
+                }
+            });
+        });
+
+        Ok(job)
+    }
+
+    pub fn job(&self, id: u64) -> Option<RetranscriptionJob> {
+        self.jobs.get(id)
+    }
+}
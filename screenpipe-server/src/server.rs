@@ -1,22 +1,24 @@
 use axum::{
-    body::Body,
+    body::{Body, Bytes},
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        Json, Path, Query, State,
+        DefaultBodyLimit, Json, Path, Query, State,
     },
     http::StatusCode,
+    middleware,
     response::{IntoResponse, Json as JsonResponse, Response},
-    routing::get,
+    routing::{get, post},
     serve, Router,
 };
 use oasgen::{oasgen, OaSchema, Server};
 
-use screenpipe_core::Desktop;
+use screenpipe_core::{encryption, Desktop};
 
 use chrono::TimeZone;
 use screenpipe_db::{
-    ContentType, DatabaseManager, FrameData, Order, SearchMatch, SearchResult, Speaker,
-    TagContentType,
+    AudioResult, AudioTranscriptionVersion, ContentType, DatabaseManager, FrameData,
+    FrameRedaction, HybridScore, Order, RawSqlError, RawSqlLimits, SearchMatch, SearchResult,
+    Speaker, SpeakerMergeSuggestion, TagContentType, TranscriptionWord, DEFAULT_RRF_K,
 };
 
 use tokio_util::io::ReaderStream;
@@ -31,7 +33,12 @@ use image::ImageFormat::{self};
 use screenpipe_events::{send_event, subscribe_to_all_events, Event as ScreenpipeEvent};
 
 use crate::{
+    auth::{is_allowed_origin, request_span, require_auth, require_local_host, AuthManager},
+    diarization::{DiarizationJob, DiarizationManager},
     embedding::embedding_endpoint::create_embeddings,
+    ocr_queue::{OcrQueue, OcrQueueConfig, OcrQueueStatus},
+    retention::{RetentionConfig, RetentionManager, RetentionReport},
+    retranscription::{RetranscriptionJob, RetranscriptionManager},
     video::{finish_ffmpeg_process, start_ffmpeg_process, write_frame_to_ffmpeg, MAX_FPS},
     video_cache::{AudioEntry, DeviceFrame, FrameCache, FrameMetadata, TimeSeriesFrame},
     video_utils::{
@@ -46,9 +53,12 @@ use screenpipe_audio::{
     core::device::{
         default_input_device, default_output_device, list_audio_devices, AudioDevice, DeviceType,
     },
+    core::engine::AudioTranscriptionEngine,
+    speaker::enrollment::compute_enrollment_embeddings,
 };
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
+use screenpipe_vision::custom_ocr::CUSTOM_OCR_METRICS;
 use screenpipe_vision::monitor::{get_monitor_by_id, list_monitors};
 use screenpipe_vision::OcrEngine;
 use serde::{Deserialize, Deserializer, Serialize};
@@ -69,8 +79,11 @@ use tokio::{
     time::timeout,
 };
 
-use tower_http::{cors::Any, trace::TraceLayer};
-use tower_http::{cors::CorsLayer, trace::DefaultMakeSpan};
+use tower_http::cors::CorsLayer;
+use tower_http::{
+    cors::{AllowOrigin, Any},
+    trace::TraceLayer,
+};
 
 use enigo::{Enigo, Key, Settings};
 use std::str::FromStr;
@@ -96,6 +109,10 @@ pub struct AppState {
     pub frame_cache: Option<Arc<FrameCache>>,
     pub frame_image_cache: Option<Arc<Mutex<FrameImageCache>>>,
     pub element_cache: Arc<Mutex<Option<(Vec<UIElement>, Instant, String)>>>,
+    pub retention_manager: Arc<RetentionManager>,
+    pub diarization_manager: Arc<DiarizationManager>,
+    pub retranscription_manager: Arc<RetranscriptionManager>,
+    pub ocr_queue: Arc<OcrQueue>,
 }
 
 // Update the SearchQuery struct
@@ -195,6 +212,41 @@ pub enum ContentItem {
     UI(UiContent),
 }
 
+impl ContentItem {
+    fn from_search_result(result: &SearchResult) -> Self {
+        match result {
+            SearchResult::OCR(ocr) => ContentItem::OCR(OCRContent {
+                frame_id: ocr.frame_id,
+                text: ocr.ocr_text.clone(),
+                timestamp: ocr.timestamp,
+                file_path: ocr.file_path.clone(),
+                offset_index: ocr.offset_index,
+                app_name: ocr.app_name.clone(),
+                window_name: ocr.window_name.clone(),
+                tags: ocr.tags.clone(),
+                frame: None,
+                frame_name: Some(ocr.frame_name.clone()),
+                browser_url: ocr.browser_url.clone(),
+                focused: ocr.focused,
+                device_name: ocr.device_name.clone(),
+            }),
+            SearchResult::Audio(audio) => ContentItem::Audio(AudioContent::from(audio)),
+            SearchResult::UI(ui) => ContentItem::UI(UiContent {
+                id: ui.id,
+                text: ui.text.clone(),
+                timestamp: ui.timestamp,
+                app_name: ui.app_name.clone(),
+                window_name: ui.window_name.clone(),
+                initial_traversal_at: ui.initial_traversal_at,
+                file_path: ui.file_path.clone(),
+                offset_index: ui.offset_index,
+                frame_name: ui.frame_name.clone(),
+                browser_url: ui.browser_url.clone(),
+            }),
+        }
+    }
+}
+
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
 pub struct OCRContent {
     pub frame_id: i64,
@@ -214,6 +266,7 @@ pub struct OCRContent {
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
 pub struct AudioContent {
+    pub transcription_id: i64,
     pub chunk_id: i64,
     pub transcription: String,
     pub timestamp: DateTime<Utc>,
@@ -225,6 +278,28 @@ pub struct AudioContent {
     pub speaker: Option<Speaker>,
     pub start_time: Option<f64>,
     pub end_time: Option<f64>,
+    /// Word timings, in seconds from the start of the audio file like `start_time`
+    pub words: Vec<TranscriptionWord>,
+}
+
+impl From<&AudioResult> for AudioContent {
+    fn from(audio: &AudioResult) -> Self {
+        AudioContent {
+            transcription_id: audio.transcription_id,
+            chunk_id: audio.audio_chunk_id,
+            transcription: audio.transcription.clone(),
+            timestamp: audio.timestamp,
+            file_path: audio.file_path.clone(),
+            offset_index: audio.offset_index,
+            tags: audio.tags.clone(),
+            device_name: audio.device_name.clone(),
+            device_type: audio.device_type.clone().into(),
+            speaker: audio.speaker.clone(),
+            start_time: audio.start_time,
+            end_time: audio.end_time,
+            words: audio.words.clone(),
+        }
+    }
 }
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
@@ -373,48 +448,7 @@ pub(crate) async fn search(
 
     let mut content_items: Vec<ContentItem> = results
         .iter()
-        .map(|result| match result {
-            SearchResult::OCR(ocr) => ContentItem::OCR(OCRContent {
-                frame_id: ocr.frame_id,
-                text: ocr.ocr_text.clone(),
-                timestamp: ocr.timestamp,
-                file_path: ocr.file_path.clone(),
-                offset_index: ocr.offset_index,
-                app_name: ocr.app_name.clone(),
-                window_name: ocr.window_name.clone(),
-                tags: ocr.tags.clone(),
-                frame: None,
-                frame_name: Some(ocr.frame_name.clone()),
-                browser_url: ocr.browser_url.clone(),
-                focused: ocr.focused,
-                device_name: ocr.device_name.clone(),
-            }),
-            SearchResult::Audio(audio) => ContentItem::Audio(AudioContent {
-                chunk_id: audio.audio_chunk_id,
-                transcription: audio.transcription.clone(),
-                timestamp: audio.timestamp,
-                file_path: audio.file_path.clone(),
-                offset_index: audio.offset_index,
-                tags: audio.tags.clone(),
-                device_name: audio.device_name.clone(),
-                device_type: audio.device_type.clone().into(),
-                speaker: audio.speaker.clone(),
-                start_time: audio.start_time,
-                end_time: audio.end_time,
-            }),
-            SearchResult::UI(ui) => ContentItem::UI(UiContent {
-                id: ui.id,
-                text: ui.text.clone(),
-                timestamp: ui.timestamp,
-                app_name: ui.app_name.clone(),
-                window_name: ui.window_name.clone(),
-                initial_traversal_at: ui.initial_traversal_at,
-                file_path: ui.file_path.clone(),
-                offset_index: ui.offset_index,
-                frame_name: ui.frame_name.clone(),
-                browser_url: ui.browser_url.clone(),
-            }),
-        })
+        .map(ContentItem::from_search_result)
         .collect();
 
     if query.include_frames {
@@ -1118,6 +1152,10 @@ pub struct SCServer {
     audio_disabled: bool,
     ui_monitoring_enabled: bool,
     enable_pipe: bool,
+    retention_manager: Arc<RetentionManager>,
+    ocr_queue: Arc<OcrQueue>,
+    auth: Option<Arc<AuthManager>>,
+    allowed_origins: Vec<String>,
 }
 
 impl SCServer {
@@ -1133,6 +1171,11 @@ impl SCServer {
         audio_manager: Arc<AudioManager>,
         enable_pipe: bool,
     ) -> Self {
+        let retention_manager = Arc::new(RetentionManager::new(
+            db.clone(),
+            RetentionConfig::default(),
+        ));
+        let ocr_queue = Arc::new(OcrQueue::new(db.clone(), OcrQueueConfig::default()));
         SCServer {
             db,
             addr,
@@ -1143,9 +1186,36 @@ impl SCServer {
             ui_monitoring_enabled,
             audio_manager,
             enable_pipe,
+            retention_manager,
+            ocr_queue,
+            auth: None,
+            allowed_origins: Vec::new(),
         }
     }
 
+    pub fn with_retention_manager(mut self, retention_manager: Arc<RetentionManager>) -> Self {
+        self.retention_manager = retention_manager;
+        self
+    }
+
+    /// Queue of windows waiting for OCR, reported by `/ocr/queue`
+    pub fn with_ocr_queue(mut self, ocr_queue: Arc<OcrQueue>) -> Self {
+        self.ocr_queue = ocr_queue;
+        self
+    }
+
+    /// Requires a scoped bearer token on every route except the health check
+    pub fn with_auth(mut self, auth: Arc<AuthManager>) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Origins allowed by CORS on top of localhost and the desktop app
+    pub fn with_allowed_origins(mut self, allowed_origins: Vec<String>) -> Self {
+        self.allowed_origins = allowed_origins;
+        self
+    }
+
     pub async fn start(self, enable_frame_cache: bool) -> Result<(), std::io::Error> {
         // Create the OpenAPI server
         let app = self.create_router(enable_frame_cache).await;
@@ -1185,7 +1255,8 @@ impl SCServer {
             } else {
                 None
             },
-            frame_image_cache: if enable_frame_cache {
+            // cached frames are plaintext jpgs on disk, so they are not kept when encrypted
+            frame_image_cache: if enable_frame_cache && !encryption::is_enabled() {
                 Some(Arc::new(Mutex::new(LruCache::new(
                     NonZeroUsize::new(100).unwrap(),
                 ))))
@@ -1193,10 +1264,20 @@ impl SCServer {
                 None
             },
             element_cache: Arc::new(Mutex::new(None)),
+            retention_manager: self.retention_manager.clone(),
+            diarization_manager: Arc::new(DiarizationManager::new(self.db.clone())),
+            retranscription_manager: Arc::new(RetranscriptionManager::new(
+                self.db.clone(),
+                self.audio_manager.clone(),
+            )),
+            ocr_queue: self.ocr_queue.clone(),
         });
 
+        let allowed_origins = self.allowed_origins.clone();
         let cors = CorsLayer::new()
-            .allow_origin(Any)
+            .allow_origin(AllowOrigin::predicate(move |origin, _| {
+                is_allowed_origin(origin, &allowed_origins)
+            }))
             .allow_methods(Any)
             .allow_headers(Any)
             .expose_headers([
@@ -1205,6 +1286,7 @@ impl SCServer {
             ]);
         let server = Server::axum()
             .get("/search", search)
+            .get("/search/hybrid", hybrid_search_handler)
             .get("/audio/list", api_list_audio_devices)
             .get("/vision/list", api_list_monitors)
             .post("/tags/:content_type/:id", add_tags)
@@ -1220,6 +1302,7 @@ impl SCServer {
             .post("/pipes/delete", delete_pipe_handler)
             .post("/pipes/purge", purge_pipe_handler)
             .get("/frames/:frame_id", get_frame_data)
+            .get("/frames/:frame_id/redactions", get_frame_redactions)
             .get("/health", health_check)
             .post("/raw_sql", execute_raw_sql)
             .post("/add", add_to_database)
@@ -1230,6 +1313,10 @@ impl SCServer {
             .post("/speakers/hallucination", mark_as_hallucination_handler)
             .post("/speakers/merge", merge_speakers_handler)
             .get("/speakers/similar", get_similar_speakers_handler)
+            .get(
+                "/speakers/merge-suggestions",
+                get_speaker_merge_suggestions_handler,
+            )
             .post("/experimental/frames/merge", merge_frames_handler)
             .get("/experimental/validate/media", validate_media_handler)
             .post("/experimental/operator", find_elements_handler)
@@ -1263,26 +1350,68 @@ impl SCServer {
             .post("/audio/start", start_audio)
             .post("/audio/stop", stop_audio)
             .get("/semantic-search", semantic_search_handler)
+            .get("/semantic-search/content", semantic_content_search_handler)
             .get("/pipes/build-status/:pipe_id", get_pipe_build_status)
             .get("/search/keyword", keyword_search_handler)
             .post("/v1/embeddings", create_embeddings)
             .post("/audio/device/start", start_audio_device)
             .post("/audio/device/stop", stop_audio_device)
+            .get("/retention", get_retention_handler)
+            .post("/retention", update_retention_handler)
+            .post("/retention/run", run_retention_handler)
+            .post("/audio/diarize", start_diarization_handler)
+            .get("/audio/diarize/:job_id", get_diarization_job_handler)
+            .get(
+                "/audio/transcriptions/:transcription_id",
+                get_audio_transcription_handler,
+            )
+            .get(
+                "/audio/transcriptions/:transcription_id/versions",
+                get_audio_transcription_versions_handler,
+            )
+            .post("/audio/retranscribe", start_retranscription_handler)
+            .get(
+                "/audio/retranscribe/:job_id",
+                get_retranscription_job_handler,
+            )
+            .get("/ocr/metrics", get_ocr_metrics_handler)
+            .get("/ocr/queue", get_ocr_queue_handler);
+        // without auth any local process could change data, so sql with write access is only
+        // offered behind a token with the admin scope
+        let server = match &self.auth {
+            Some(_) => server.post("/raw_sql/mutate", execute_raw_sql_mutation),
+            None => server,
+        };
+        let server = server
             .route_yaml_spec("/openapi.yaml")
             .route_json_spec("/openapi.json")
             .freeze();
 
         // Build the main router with all routes
-        Router::new()
+        let router = Router::new()
             .merge(server.into_router())
             // NOTE: websockerts and sse is not supported by openapi so we move it down here
             .route("/stream/frames", get(stream_frames_handler))
             .route("/ws/events", get(ws_events_handler))
             .route("/ws/health", get(ws_health_handler))
             .route("/frames/export", get(handle_video_export_ws))
-            .with_state(app_state)
+            // raw audio uploads aren't described by openapi either
+            .route(
+                "/speakers/enroll",
+                post(enroll_speaker_handler)
+                    .layer(DefaultBodyLimit::max(MAX_ENROLLMENT_SAMPLE_BYTES)),
+            )
+            .with_state(app_state);
+
+        // cors wraps auth so preflight requests never need a token
+        let router = match &self.auth {
+            Some(auth) => router.layer(middleware::from_fn_with_state(auth.clone(), require_auth)),
+            None => router.layer(middleware::from_fn(require_local_host)),
+        };
+
+        router
             .layer(cors)
-            .layer(TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::default()))
+            .layer(TraceLayer::new_for_http().make_span_with(request_span))
     }
 }
 
@@ -1319,20 +1448,89 @@ async fn validate_media_handler(
     }
 }
 
+/// Upper bounds for the limits a /raw_sql caller can ask for
+const MAX_RAW_SQL_ROWS: usize = 100_000;
+const MAX_RAW_SQL_TIMEOUT_MS: u64 = 60_000;
+
 #[derive(OaSchema, Deserialize)]
 struct RawSqlQuery {
     query: String,
+    /// Rows returned at most, defaults to 10000
+    #[serde(default)]
+    max_rows: Option<usize>,
+    /// The query is cancelled after this, defaults to 10 seconds
+    #[serde(default)]
+    timeout_ms: Option<u64>,
 }
 
+/// Runs a single read-only statement. Responses cut off at `max_rows` carry an
+/// `x-screenpipe-truncated: true` header.
 #[oasgen]
 async fn execute_raw_sql(
     State(state): State<Arc<AppState>>,
     JsonResponse(payload): JsonResponse<RawSqlQuery>,
-) -> Result<JsonResponse<serde_json::Value>, (StatusCode, JsonResponse<serde_json::Value>)> {
-    match state.db.execute_raw_sql(&payload.query).await {
-        Ok(result) => Ok(JsonResponse(result)),
+) -> Result<Response, (StatusCode, JsonResponse<serde_json::Value>)> {
+    let defaults = RawSqlLimits::default();
+    let limits = RawSqlLimits {
+        max_rows: payload
+            .max_rows
+            .unwrap_or(defaults.max_rows)
+            .min(MAX_RAW_SQL_ROWS),
+        timeout: payload
+            .timeout_ms
+            .map(|ms| Duration::from_millis(ms.min(MAX_RAW_SQL_TIMEOUT_MS)))
+            .unwrap_or(defaults.timeout),
+    };
+
+    match state
+        .db
+        .execute_raw_sql_with_limits(&payload.query, limits)
+        .await
+    {
+        Ok(output) => {
+            let mut response = JsonResponse(output.rows).into_response();
+            if output.truncated {
+                response.headers_mut().insert(
+                    "x-screenpipe-truncated",
+                    axum::http::HeaderValue::from_static("true"),
+                );
+            }
+            Ok(response)
+        }
         Err(e) => {
+            let status = match e {
+                RawSqlError::Rejected(_) => StatusCode::BAD_REQUEST,
+                RawSqlError::TimedOut(_) => StatusCode::REQUEST_TIMEOUT,
+                RawSqlError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
             error!("Failed to execute raw SQL query: {}", e);
+            Err((status, JsonResponse(json!({"error": e.to_string()}))))
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+struct RawSqlMutation {
+    query: String,
+}
+
+#[derive(OaSchema, Serialize)]
+struct RawSqlMutationResponse {
+    rows_affected: u64,
+}
+
+/// Runs sql with write access in a transaction. Only registered when auth is enabled, needs
+/// the admin scope
+#[oasgen]
+async fn execute_raw_sql_mutation(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<RawSqlMutation>,
+) -> Result<JsonResponse<RawSqlMutationResponse>, (StatusCode, JsonResponse<serde_json::Value>)> {
+    info!("executing raw sql mutation");
+    match state.db.execute_raw_sql_mutation(&payload.query).await {
+        Ok(rows_affected) => Ok(JsonResponse(RawSqlMutationResponse { rows_affected })),
+        Err(e) => {
+            error!("failed to execute raw sql mutation: {}", e);
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 JsonResponse(json!({"error": e.to_string()})),
@@ -1768,6 +1966,24 @@ pub struct GetSimilarSpeakersRequest {
     limit: u32,
 }
 
+#[derive(OaSchema, Deserialize, Debug)]
+pub struct SpeakerMergeSuggestionsRequest {
+    /// Cosine distance under which two voices are suggested as the same person
+    #[serde(default = "default_merge_suggestion_distance")]
+    max_distance: f64,
+    #[serde(default = "default_limit")]
+    limit: u32,
+}
+
+fn default_merge_suggestion_distance() -> f64 {
+    0.3
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EnrollSpeakerRequest {
+    name: String,
+}
+
 fn from_comma_separated_array<'de, D>(deserializer: D) -> Result<Option<Vec<i64>>, D::Error>
 where
     D: Deserializer<'de>,
@@ -1960,6 +2176,84 @@ async fn get_similar_speakers_handler(
 
     Ok(JsonResponse(similar_speakers))
 }
+
+#[oasgen]
+async fn get_speaker_merge_suggestions_handler(
+    State(state): State<Arc<AppState>>,
+    Query(request): Query<SpeakerMergeSuggestionsRequest>,
+) -> Result<JsonResponse<Vec<SpeakerMergeSuggestion>>, (StatusCode, JsonResponse<Value>)> {
+    let suggestions = state
+        .db
+        .get_speaker_merge_suggestions(request.max_distance, request.limit)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    Ok(JsonResponse(suggestions))
+}
+
+/// Largest voice sample accepted by `/speakers/enroll`, a few minutes of uncompressed audio
+const MAX_ENROLLMENT_SAMPLE_BYTES: usize = 100 * 1024 * 1024;
+
+/// Enrolls a recording of one person speaking, sent as the request body in any format the
+/// recorder can decode:
+/// `curl --data-binary @alice.wav "localhost:3030/speakers/enroll?name=alice"`
+async fn enroll_speaker_handler(
+    State(state): State<Arc<AppState>>,
+    Query(request): Query<EnrollSpeakerRequest>,
+    body: Bytes,
+) -> Result<JsonResponse<Speaker>, (StatusCode, JsonResponse<Value>)> {
+    let name = request.name.trim();
+    if name.is_empty() || body.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "a name and a voice sample in the body are required"})),
+        ));
+    }
+
+    let internal_error = |e: std::io::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": e.to_string()})),
+        )
+    };
+    let sample = tempfile::NamedTempFile::new().map_err(internal_error)?;
+    tokio::fs::write(sample.path(), &body)
+        .await
+        .map_err(internal_error)?;
+
+    let embeddings = compute_enrollment_embeddings(sample.path())
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                JsonResponse(json!({"error": format!("failed to read voice sample: {}", e)})),
+            )
+        })?;
+
+    let speaker = state
+        .db
+        .enroll_speaker(name, &embeddings)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            )
+        })?;
+    info!(
+        "enrolled speaker {} ({}) from {} voice windows",
+        speaker.id,
+        name,
+        embeddings.len()
+    );
+
+    Ok(JsonResponse(speaker))
+}
 // #[derive(OaSchema, Deserialize)]
 // pub struct AudioDeviceControlRequest {
 //     device_name: String,
@@ -2068,13 +2362,33 @@ struct EventsQuery {
     images: Option<bool>,
 }
 
-#[derive(Debug, OaSchema, Deserialize)]
+#[derive(OaSchema, Deserialize)]
 struct SemanticSearchQuery {
     text: String,
     limit: Option<u32>,
     threshold: Option<f32>,
 }
 
+#[derive(Debug, OaSchema, Deserialize)]
+struct SemanticContentSearchQuery {
+    text: String,
+    limit: Option<u32>,
+    threshold: Option<f32>,
+    #[serde(default)]
+    content_type: ContentType,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+}
+
+#[derive(OaSchema, Serialize, Deserialize)]
+pub struct SemanticContentItem {
+    pub item: ContentItem,
+    /// Cosine distance to the query embedding, lower is better
+    pub distance: f64,
+}
+
 #[oasgen]
 async fn semantic_search_handler(
     Query(query): Query<SemanticSearchQuery>,
@@ -2120,6 +2434,198 @@ async fn semantic_search_handler(
     }
 }
 
+/// Semantic search over screen text, audio transcriptions and ui text, see
+/// [`semantic_search_handler`] for screen text only
+#[oasgen]
+async fn semantic_content_search_handler(
+    Query(query): Query<SemanticContentSearchQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Vec<SemanticContentItem>>, (StatusCode, JsonResponse<Value>)> {
+    let limit = query.limit.unwrap_or(10);
+    let threshold = query.threshold.unwrap_or(0.3);
+
+    debug!(
+        "semantic search for '{}' with content_type {:?}, limit {} and threshold {}",
+        query.text, query.content_type, limit, threshold
+    );
+
+    // Generate embedding for search text
+    let embedding = match generate_embedding(&query.text, 0).await {
+        Ok(emb) => emb,
+        Err(e) => {
+            error!("failed to generate embedding: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to generate embedding: {}", e)})),
+            ));
+        }
+    };
+
+    // Search database for similar embeddings
+    match state
+        .db
+        .search_similar_content(
+            &embedding,
+            query.content_type,
+            limit,
+            threshold,
+            query.start_time,
+            query.end_time,
+        )
+        .await
+    {
+        Ok(results) => {
+            debug!("found {} similar results", results.len());
+            Ok(JsonResponse(
+                results
+                    .iter()
+                    .map(|r| SemanticContentItem {
+                        item: ContentItem::from_search_result(&r.result),
+                        distance: r.distance,
+                    })
+                    .collect(),
+            ))
+        }
+        Err(e) => {
+            error!("failed to search embeddings: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to search embeddings: {}", e)})),
+            ))
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct HybridSearchQuery {
+    q: String,
+    #[serde(default = "default_limit")]
+    limit: u32,
+    #[serde(default)]
+    offset: u32,
+    #[serde(default)]
+    content_type: ContentType,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    app_name: Option<String>,
+    #[serde(default)]
+    window_name: Option<String>,
+    #[serde(default)]
+    frame_name: Option<String>,
+    #[serde(default)]
+    min_length: Option<usize>,
+    #[serde(default)]
+    max_length: Option<usize>,
+    #[serde(
+        deserialize_with = "from_comma_separated_array",
+        default = "default_speaker_ids"
+    )]
+    speaker_ids: Option<Vec<i64>>,
+    #[serde(default)]
+    focused: Option<bool>,
+    #[serde(default)]
+    browser_url: Option<String>,
+    /// Maximum cosine distance for a vector match
+    #[serde(default)]
+    threshold: Option<f32>,
+    /// Reciprocal rank fusion constant, higher values flatten the rank differences
+    #[serde(default)]
+    rrf_k: Option<u32>,
+}
+
+#[derive(OaSchema, Serialize, Deserialize)]
+pub struct HybridContentItem {
+    pub item: ContentItem,
+    pub score: HybridScore,
+}
+
+#[derive(OaSchema, Serialize, Deserialize)]
+pub struct HybridSearchResponse {
+    pub data: Vec<HybridContentItem>,
+    pub pagination: PaginationInfo,
+    /// False when no query embedding could be generated and only keyword ranks were fused
+    pub vector_search: bool,
+}
+
+#[oasgen]
+async fn hybrid_search_handler(
+    Query(query): Query<HybridSearchQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<HybridSearchResponse>, (StatusCode, JsonResponse<Value>)> {
+    if query.q.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "q must not be empty"})),
+        ));
+    }
+
+    debug!(
+        "hybrid search for '{}' with content_type {:?}",
+        query.q, query.content_type
+    );
+
+    // keyword ranks still work without the embedding model, so a failure only degrades results
+    let embedding = match generate_embedding(&query.q, 0).await {
+        Ok(embedding) => Some(embedding),
+        Err(e) => {
+            warn!(
+                "failed to generate embedding, using keyword ranks only: {}",
+                e
+            );
+            None
+        }
+    };
+
+    let (results, total) = state
+        .db
+        .search_hybrid(
+            &query.q,
+            embedding.as_deref(),
+            query.content_type.clone(),
+            query.limit,
+            query.offset,
+            query.start_time,
+            query.end_time,
+            query.app_name.as_deref(),
+            query.window_name.as_deref(),
+            query.min_length,
+            query.max_length,
+            query.speaker_ids.clone(),
+            query.frame_name.as_deref(),
+            query.browser_url.as_deref(),
+            query.focused,
+            query.threshold.unwrap_or(0.3),
+            query.rrf_k.unwrap_or(DEFAULT_RRF_K),
+        )
+        .await
+        .map_err(|e| {
+            error!("failed to perform hybrid search: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to perform hybrid search: {}", e)})),
+            )
+        })?;
+
+    Ok(JsonResponse(HybridSearchResponse {
+        data: results
+            .iter()
+            .map(|r| HybridContentItem {
+                item: ContentItem::from_search_result(&r.result),
+                score: r.score.clone(),
+            })
+            .collect(),
+        pagination: PaginationInfo {
+            limit: query.limit,
+            offset: query.offset,
+            total: total as i64,
+        },
+        vector_search: embedding.is_some(),
+    }))
+}
+
 #[derive(Serialize, OaSchema, Deserialize)]
 pub struct VisionDeviceControlRequest {
     device_id: u32,
@@ -2363,6 +2869,306 @@ async fn stop_audio_device(
     }))
 }
 
+#[derive(OaSchema, Serialize)]
+struct RetentionStatusResponse {
+    config: RetentionConfig,
+    last_report: Option<RetentionReport>,
+}
+
+#[derive(OaSchema, Deserialize)]
+struct RunRetentionRequest {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[oasgen]
+async fn get_retention_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<RetentionStatusResponse>, (StatusCode, JsonResponse<Value>)> {
+    Ok(JsonResponse(RetentionStatusResponse {
+        config: state.retention_manager.config().await,
+        last_report: state.retention_manager.last_report().await,
+    }))
+}
+
+#[oasgen]
+async fn update_retention_handler(
+    State(state): State<Arc<AppState>>,
+    Json(config): Json<RetentionConfig>,
+) -> Result<JsonResponse<RetentionConfig>, (StatusCode, JsonResponse<Value>)> {
+    let invalid_size = [config.vision.max_size_gb, config.audio.max_size_gb]
+        .iter()
+        .flatten()
+        .any(|gb| !gb.is_finite() || *gb < 0.0);
+    if invalid_size {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "max_size_gb must be a non-negative number"})),
+        ));
+    }
+
+    state.retention_manager.update_config(config).await;
+    Ok(JsonResponse(state.retention_manager.config().await))
+}
+
+#[oasgen]
+async fn run_retention_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RunRetentionRequest>,
+) -> Result<JsonResponse<RetentionReport>, (StatusCode, JsonResponse<Value>)> {
+    match state.retention_manager.run_once(payload.dry_run).await {
+        Ok(report) => Ok(JsonResponse(report)),
+        Err(e) => {
+            error!("retention run failed: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("retention run failed: {}", e)})),
+            ))
+        }
+    }
+}
+
+/// Longest window a diarization job covers, segmenting a whole day of audio takes hours
+const MAX_DIARIZATION_WINDOW_HOURS: i64 = 12;
+
+#[derive(OaSchema, Deserialize)]
+struct DiarizationRequest {
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    /// Only diarize transcriptions of this device, e.g. the microphone used in a meeting
+    #[serde(default)]
+    device: Option<String>,
+}
+
+/// Starts re-assigning the speakers of the transcriptions in a window, e.g. a meeting. Poll
+/// `/audio/diarize/:job_id` for progress.
+#[oasgen]
+async fn start_diarization_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<DiarizationRequest>,
+) -> Result<JsonResponse<DiarizationJob>, (StatusCode, JsonResponse<Value>)> {
+    if payload.end_time <= payload.start_time {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "end_time must be after start_time"})),
+        ));
+    }
+    let window = payload.end_time - payload.start_time;
+    if window > chrono::Duration::hours(MAX_DIARIZATION_WINDOW_HOURS) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({
+                "error": format!("window is longer than {} hours", MAX_DIARIZATION_WINDOW_HOURS)
+            })),
+        ));
+    }
+
+    match state
+        .diarization_manager
+        .start(payload.start_time, payload.end_time, payload.device)
+    {
+        Ok(job) => Ok(JsonResponse(job)),
+        Err(e) => Err((
+            StatusCode::CONFLICT,
+            JsonResponse(json!({"error": e.to_string()})),
+        )),
+    }
+}
+
+#[oasgen]
+async fn get_diarization_job_handler(
+    Path(job_id): Path<u64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<DiarizationJob>, (StatusCode, JsonResponse<Value>)> {
+    match state.diarization_manager.job(job_id) {
+        Some(job) => Ok(JsonResponse(job)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("diarization job {} not found", job_id)})),
+        )),
+    }
+}
+
+/// A single transcription with the timing of its words
+#[oasgen]
+async fn get_audio_transcription_handler(
+    Path(transcription_id): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<AudioContent>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.get_audio_transcription(transcription_id).await {
+        Ok(Some(audio)) => Ok(JsonResponse(AudioContent::from(&audio))),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("transcription {} not found", transcription_id)})),
+        )),
+        Err(e) => {
+            error!("failed to get transcription {}: {}", transcription_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("Database error: {}", e)})),
+            ))
+        }
+    }
+}
+
+/// Every transcription of a segment, one per engine that transcribed it. `current` marks the
+/// one returned by search.
+#[oasgen]
+async fn get_audio_transcription_versions_handler(
+    Path(transcription_id): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Vec<AudioTranscriptionVersion>>, (StatusCode, JsonResponse<Value>)> {
+    match state
+        .db
+        .get_audio_transcription_versions(transcription_id)
+        .await
+    {
+        Ok(versions) if versions.is_empty() => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("transcription {} not found", transcription_id)})),
+        )),
+        Ok(versions) => Ok(JsonResponse(versions)),
+        Err(e) => {
+            error!(
+                "failed to get versions of transcription {}: {}",
+                transcription_id, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("Database error: {}", e)})),
+            ))
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+struct RetranscriptionRequest {
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    /// Only re-transcribe audio of this device
+    #[serde(default)]
+    device: Option<String>,
+    /// Engine to transcribe with, e.g. `WhisperLargeV3` or `Deepgram`
+    engine: String,
+}
+
+/// Starts transcribing the audio recorded in a window again with another engine. Poll
+/// `/audio/retranscribe/:job_id` for progress.
+#[oasgen]
+async fn start_retranscription_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RetranscriptionRequest>,
+) -> Result<JsonResponse<RetranscriptionJob>, (StatusCode, JsonResponse<Value>)> {
+    if payload.end_time <= payload.start_time {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "end_time must be after start_time"})),
+        ));
+    }
+    let engine = AudioTranscriptionEngine::from_str(&payload.engine)
+        .map_err(|e| (StatusCode::BAD_REQUEST, JsonResponse(json!({"error": e}))))?;
+
+    match state.retranscription_manager.start(
+        payload.start_time,
+        payload.end_time,
+        payload.device,
+        engine,
+    ) {
+        Ok(job) => Ok(JsonResponse(job)),
+        Err(e) => Err((
+            StatusCode::CONFLICT,
+            JsonResponse(json!({"error": e.to_string()})),
+        )),
+    }
+}
+
+#[oasgen]
+async fn get_retranscription_job_handler(
+    Path(job_id): Path<u64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<RetranscriptionJob>, (StatusCode, JsonResponse<Value>)> {
+    match state.retranscription_manager.job(job_id) {
+        Some(job) => Ok(JsonResponse(job)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("re-transcription job {} not found", job_id)})),
+        )),
+    }
+}
+
+#[derive(OaSchema, Serialize)]
+pub struct CustomOcrMetricsResponse {
+    pub requests: u64,
+    /// Images OCR'd by the service
+    pub images: u64,
+    pub failures: u64,
+    /// Images put back in the ocr queue to be sent again later
+    pub retries: u64,
+    /// Times the service was found failing and stopped being called for a while
+    pub circuit_opens: u64,
+    /// Images not sent because the service was failing
+    pub rejected: u64,
+    /// Images OCR'd by the fallback engine instead
+    pub fallbacks: u64,
+    pub circuit_open: bool,
+}
+
+#[derive(OaSchema, Serialize)]
+pub struct OcrMetricsResponse {
+    pub custom: CustomOcrMetricsResponse,
+    /// Windows recorded while the selected engine was unavailable, to OCR again with it, see
+    /// `/ocr/queue`
+    pub deferred: i64,
+}
+
+/// Failures of the custom ocr service since startup and windows waiting to be OCR'd again
+#[oasgen]
+async fn get_ocr_metrics_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<OcrMetricsResponse>, (StatusCode, JsonResponse<Value>)> {
+    let deferred = match state.ocr_queue.status().await {
+        Ok(status) => status.deferred,
+        Err(e) => {
+            error!("failed to get ocr queue status: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to get ocr queue status: {}", e)})),
+            ));
+        }
+    };
+    let custom = CUSTOM_OCR_METRICS.snapshot();
+    Ok(JsonResponse(OcrMetricsResponse {
+        custom: CustomOcrMetricsResponse {
+            requests: custom.requests,
+            images: custom.images,
+            failures: custom.failures,
+            retries: custom.retries,
+            circuit_opens: custom.circuit_opens,
+            rejected: custom.rejected,
+            fallbacks: custom.fallbacks,
+            circuit_open: custom.circuit_open,
+        },
+        deferred,
+    }))
+}
+
+/// Windows waiting for OCR, when frames are recorded without waiting for their text
+#[oasgen]
+async fn get_ocr_queue_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<OcrQueueStatus>, (StatusCode, JsonResponse<Value>)> {
+    match state.ocr_queue.status().await {
+        Ok(status) => Ok(JsonResponse(status)),
+        Err(e) => {
+            error!("failed to get ocr queue status: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to get ocr queue status: {}", e)})),
+            ))
+        }
+    }
+}
+
 fn deserialize_frame_ids<'de, D>(deserializer: D) -> Result<Vec<i64>, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -2829,7 +3635,11 @@ pub async fn get_frame_data(
                         }
 
                         debug!("Frame {} extracted in {:?}", frame_id, start_time.elapsed());
-                        serve_file(&frame_path).await
+                        if encryption::is_enabled() {
+                            serve_and_remove_file(&frame_path).await
+                        } else {
+                            serve_file(&frame_path).await
+                        }
                     }
                     Err(e) => {
                         error!("Failed to extract frame {}: {}", frame_id, e);
@@ -2876,6 +3686,26 @@ pub async fn get_frame_data(
     }
 }
 
+/// Regions of the frame that were redacted before it was recorded, see `--frame-pii-redaction`
+#[oasgen]
+pub async fn get_frame_redactions(
+    State(state): State<Arc<AppState>>,
+    Path(frame_id): Path<i64>,
+) -> Result<JsonResponse<Vec<FrameRedaction>>, (StatusCode, JsonResponse<Value>)> {
+    state
+        .db
+        .get_frame_redactions(frame_id)
+        .await
+        .map(JsonResponse)
+        .map_err(|e| {
+            error!("failed to get redactions for frame {}: {}", frame_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("Database error: {}", e)})),
+            )
+        })
+}
+
 async fn serve_file(path: &str) -> Result<Response, (StatusCode, JsonResponse<Value>)> {
     match File::open(path).await {
         Ok(file) => {
@@ -2902,6 +3732,31 @@ async fn serve_file(path: &str) -> Result<Response, (StatusCode, JsonResponse<Va
     }
 }
 
+/// Serves a frame extracted from an encrypted recording without leaving it on disk
+async fn serve_and_remove_file(path: &str) -> Result<Response, (StatusCode, JsonResponse<Value>)> {
+    let data = tokio::fs::read(path).await;
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        warn!("failed to remove extracted frame {}: {}", path, e);
+    }
+    let data = data.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": format!("Failed to open file: {}", e)})),
+        )
+    })?;
+
+    Response::builder()
+        .header("content-type", "image/jpeg")
+        .header("cache-control", "no-store")
+        .body(Body::from(data))
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("Failed to create response: {}", e)})),
+            )
+        })
+}
+
 // Add these new functions before stream_frames_handler
 async fn fetch_and_process_frames(
     db: Arc<DatabaseManager>,
@@ -1,7 +1,16 @@
 use anyhow::Result;
 use reqwest::Client;
+use screenpipe_core::pii_removal;
+use screenpipe_db::{DatabaseManager, EmbeddingFn, PendingEmbedding};
 use serde::{Deserialize, Serialize};
-use tracing::{debug, error, info};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+/// Rows embedded per table on each pass of the background task
+const EMBEDDING_BATCH_SIZE: i64 = 50;
 
 #[derive(Debug, Serialize)]
 struct OllamaRequest {
@@ -21,7 +30,7 @@ pub async fn generate_embedding(text: &str, frame_id: i64) -> Result<Vec<f32>> {
     debug!("generating embedding for frame_id: {}, text: {}", frame_id, text);
 
     // Check if Ollama server is running
-    if let Err(e) = client.get("http://localhost:11434/api/version").send().await {
+    if let Err(e) = ollama_version(&client).await {
         error!("ollama server not running: {}", e);
         return Err(anyhow::anyhow!("ollama server not running"));
     }
@@ -47,3 +56,143 @@ pub async fn generate_embedding(text: &str, frame_id: i64) -> Result<Vec<f32>> {
     
     Ok(embedding.embedding)
 }
+
+async fn ollama_version(client: &Client) -> reqwest::Result<reqwest::Response> {
+    client
+        .get("http://localhost:11434/api/version")
+        .send()
+        .await
+}
+
+/// Whether the Ollama server answers, to tell it being down from a text it can't embed
+async fn ollama_is_running() -> bool {
+    ollama_version(&Client::new()).await.is_ok()
+}
+
+/// [`generate_embedding`] in the shape expected by the `text_embeddings` migration job
+pub fn embedding_fn() -> EmbeddingFn {
+    Arc::new(|text: String| Box::pin(async move { generate_embedding(&text, 0).await }))
+}
+
+/// Embeds audio transcriptions and UI monitoring text as they are recorded.
+///
+/// Only rows newer than the ones present at startup are picked up, and transcriptions whose
+/// text is replaced by a re-transcription since. History is left to the `text_embeddings`
+/// migration so it can be paused and throttled.
+pub fn start_text_embedding_task(
+    db: Arc<DatabaseManager>,
+    interval: Duration,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let (mut last_audio_id, mut last_ui_id, mut last_version_id) =
+            match db.get_latest_text_ids().await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    error!("failed to start text embedding task: {}", e);
+                    return;
+                }
+            };
+        info!("text embedding task started");
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, stopping text embedding task");
+                    break;
+                }
+            }
+
+            match db
+                .get_audio_transcriptions_without_embeddings(last_audio_id, EMBEDDING_BATCH_SIZE)
+                .await
+            {
+                Ok(rows) => {
+                    last_audio_id = embed_rows(by_id(rows), last_audio_id, |id, embedding| {
+                        let db = db.clone();
+                        async move {
+                            db.insert_audio_transcription_embedding(id, &embedding)
+                                .await
+                        }
+                    })
+                    .await
+                }
+                Err(e) => error!("failed to fetch transcriptions to embed: {}", e),
+            }
+
+            match db
+                .get_retranscribed_without_embeddings(last_version_id, EMBEDDING_BATCH_SIZE)
+                .await
+            {
+                Ok(rows) => {
+                    last_version_id = embed_rows(rows, last_version_id, |id, embedding| {
+                        let db = db.clone();
+                        async move {
+                            db.insert_audio_transcription_embedding(id, &embedding)
+                                .await
+                        }
+                    })
+                    .await
+                }
+                Err(e) => error!("failed to fetch re-transcriptions to embed: {}", e),
+            }
+
+            match db
+                .get_ui_monitoring_without_embeddings(last_ui_id, EMBEDDING_BATCH_SIZE)
+                .await
+            {
+                Ok(rows) => {
+                    last_ui_id = embed_rows(by_id(rows), last_ui_id, |id, embedding| {
+                        let db = db.clone();
+                        async move { db.insert_ui_monitoring_embedding(id, &embedding).await }
+                    })
+                    .await
+                }
+                Err(e) => error!("failed to fetch ui text to embed: {}", e),
+            }
+        }
+    })
+}
+
+/// Embeds and stores `rows`, each with the cursor to resume from once it's done, returning the
+/// cursor to resume from on the next pass.
+///
+/// Stops at the first row that fails while Ollama is unreachable, so it's retried once it's
+/// back. Rows failing while it's up, e.g. a text the model rejects, are skipped.
+async fn embed_rows<F, Fut>(rows: Vec<(i64, PendingEmbedding)>, mut cursor: i64, insert: F) -> i64
+where
+    F: Fn(i64, Vec<f32>) -> Fut,
+    Fut: std::future::Future<Output = Result<(), sqlx::Error>>,
+{
+    for (next, row) in rows {
+        // the ui text pii sweep may not have reached this row yet
+        let text = if pii_removal::is_enabled() {
+            pii_removal::remove_pii(&row.text)
+        } else {
+            row.text
+        };
+        let embedding = match generate_embedding(&text, 0).await {
+            Ok(embedding) => embedding,
+            Err(e) if !ollama_is_running().await => {
+                warn!("failed to generate embedding, retrying later: {}", e);
+                break;
+            }
+            Err(e) => {
+                warn!("skipping embedding of row {}: {}", row.id, e);
+                cursor = next;
+                continue;
+            }
+        };
+        if let Err(e) = insert(row.id, embedding).await {
+            error!("failed to store embedding for row {}: {}", row.id, e);
+        }
+        cursor = next;
+    }
+    cursor
+}
+
+/// Rows of a table read in id order, resumed from their own id
+fn by_id(rows: Vec<PendingEmbedding>) -> Vec<(i64, PendingEmbedding)> {
+    rows.into_iter().map(|row| (row.id, row)).collect()
+}
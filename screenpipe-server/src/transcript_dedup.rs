@@ -0,0 +1,66 @@
+use crate::cursor_task::start_cursor_task;
+use screenpipe_db::DatabaseManager;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+/// Transcriptions checked on each pass of the background task
+const TRANSCRIPT_DEDUP_BATCH_SIZE: u32 = 200;
+
+/// Hides transcriptions of the same speech recorded by several devices as they are written.
+///
+/// A call played on speakers is recorded by the output device and picked up by the microphone,
+/// each new transcription is compared with those of the other devices within `max_time_gap`.
+/// Rows written before startup are left alone.
+pub fn start_transcript_dedup_task(
+    db: Arc<DatabaseManager>,
+    interval: Duration,
+    max_time_gap: chrono::Duration,
+    min_similarity: f64,
+    shutdown_rx: broadcast::Receiver<()>,
+) -> JoinHandle<()> {
+    let start = {
+        let db = db.clone();
+        async move { db.get_latest_audio_transcription_id().await }
+    };
+    let fetch = {
+        let db = db.clone();
+        move |cursor| {
+            let db = db.clone();
+            async move {
+                let ids = db
+                    .get_audio_transcription_ids_after(cursor, TRANSCRIPT_DEDUP_BATCH_SIZE)
+                    .await?;
+                Ok(ids.into_iter().map(|id| (id, id)).collect())
+            }
+        }
+    };
+    let process = move |id| {
+        let db = db.clone();
+        async move {
+            match db
+                .deduplicate_audio_transcription(id, max_time_gap, min_similarity)
+                .await
+            {
+                Ok(Some(duplicate)) => debug!(
+                    "transcription {} duplicates {} (similarity {:.2})",
+                    duplicate.duplicate_id, duplicate.kept_id, duplicate.similarity
+                ),
+                Ok(None) => {}
+                Err(e) => error!("failed to deduplicate transcription {}: {}", id, e),
+            }
+        }
+    };
+
+    start_cursor_task(
+        "transcript dedup",
+        interval,
+        TRANSCRIPT_DEDUP_BATCH_SIZE as usize,
+        start,
+        fetch,
+        process,
+        shutdown_rx,
+    )
+}
@@ -0,0 +1,75 @@
+use crate::cursor_task::start_cursor_task;
+use screenpipe_core::pii_removal::remove_pii_in_app;
+use screenpipe_db::DatabaseManager;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+/// Ui monitoring rows checked on each pass of the background task
+const UI_PII_BATCH_SIZE: i64 = 200;
+
+/// Removes pii from ui monitoring text as it is recorded.
+///
+/// The ui monitor writes to the database directly, so the text is cleaned after the fact. Rows
+/// written before startup are left alone.
+pub fn start_ui_pii_removal_task(
+    db: Arc<DatabaseManager>,
+    interval: Duration,
+    shutdown_rx: broadcast::Receiver<()>,
+) -> JoinHandle<()> {
+    let start = {
+        let db = db.clone();
+        async move {
+            Ok(db
+                .get_latest_ui_monitoring_cursor()
+                .await?
+                .unwrap_or_default())
+        }
+    };
+    let fetch = {
+        let db = db.clone();
+        move |(timestamp, id): (String, i64)| {
+            let db = db.clone();
+            async move {
+                let rows = db
+                    .get_ui_monitoring_written_after(&timestamp, id, UI_PII_BATCH_SIZE)
+                    .await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|row| ((row.timestamp.clone(), row.id), row))
+                    .collect())
+            }
+        }
+    };
+    let process = move |row: screenpipe_db::UiMonitoringText| {
+        let db = db.clone();
+        async move {
+            let redacted = remove_pii_in_app(&row.text, &row.app);
+            if redacted == row.text {
+                return;
+            }
+            match db
+                .update_ui_monitoring_text(row.id, &row.text, &redacted)
+                .await
+            {
+                Ok(true) => debug!("removed pii from ui text {}", row.id),
+                // rewritten by the ui monitor meanwhile, which also moved its timestamp past
+                // the cursor so it comes back on a later pass
+                Ok(false) => {}
+                Err(e) => error!("failed to remove pii from ui text {}: {}", row.id, e),
+            }
+        }
+    };
+
+    start_cursor_task(
+        "ui pii removal",
+        interval,
+        UI_PII_BATCH_SIZE as usize,
+        start,
+        fetch,
+        process,
+        shutdown_rx,
+    )
+}
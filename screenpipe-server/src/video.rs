@@ -1,13 +1,15 @@
 use chrono::Utc;
 use crossbeam::queue::ArrayQueue;
 use image::ImageFormat::{self};
-use screenpipe_core::{find_ffmpeg_path, Language};
+use screenpipe_core::{encryption, find_ffmpeg_path, Language};
 use screenpipe_vision::monitor::get_monitor_by_id;
+use screenpipe_vision::redaction::{redact_frame, RedactionMode};
+use screenpipe_vision::roi::RoiConfig;
 use screenpipe_vision::{
-    capture_screenshot_by_window::WindowFilters, continuous_capture, CaptureResult, OcrEngine,
+    capture_screenshot_by_window::WindowFilters, continuous_capture, CaptureResult, OcrBackend,
 };
 use std::borrow::Cow;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -43,12 +45,14 @@ impl VideoCapture {
         fps: f64,
         video_chunk_duration: Duration,
         new_chunk_callback: impl Fn(&str) + Send + Sync + 'static,
-        ocr_engine: Arc<OcrEngine>,
+        ocr_engine: Option<Arc<dyn OcrBackend>>,
         monitor_id: u32,
         ignore_list: &[String],
         include_list: &[String],
         languages: Vec<Language>,
         capture_unfocused_windows: bool,
+        roi_ocr: Option<RoiConfig>,
+        frame_redaction: Option<RedactionMode>,
     ) -> Self {
         let fps = if fps.is_finite() && fps > 0.0 {
             fps
@@ -82,6 +86,7 @@ impl VideoCapture {
         let capture_result_sender = result_sender.clone();
         let capture_interval = interval;
         let capture_unfocused = capture_unfocused_windows;
+        let capture_roi_ocr = roi_ocr;
 
         // Store task handles for health monitoring
         let capture_thread = tokio::spawn(async move {
@@ -106,11 +111,12 @@ impl VideoCapture {
                 match continuous_capture(
                     capture_result_sender.clone(),
                     capture_interval,
-                    (*capture_ocr_engine).clone(),
+                    capture_ocr_engine.clone(),
                     monitor_id,
                     capture_window_filters.clone(),
                     capture_languages.clone(),
                     capture_unfocused,
+                    capture_roi_ocr.clone(),
                 )
                 .await
                 {
@@ -175,7 +181,7 @@ impl VideoCapture {
                 true
             }
 
-            while let Some(result) = result_receiver.recv().await {
+            while let Some(mut result) = result_receiver.recv().await {
                 let frame_number = result.frame_number;
                 processed_count += 1;
 
@@ -199,6 +205,15 @@ impl VideoCapture {
 
                 debug!("Received frame {} for queueing", frame_number);
 
+                // both queues share the frame, so pii is gone from the video and from what gets
+                // streamed or inserted
+                if let Some(mode) = frame_redaction {
+                    let redacted = redact_frame(&mut result, mode);
+                    if redacted > 0 {
+                        debug!("Redacted {} regions in frame {}", redacted, frame_number);
+                    }
+                }
+
                 let result = Arc::new(result);
 
                 let video_pushed = push_to_queue(&capture_video_frame_queue, &result, "Video");
@@ -421,6 +436,7 @@ async fn save_frames_as_video(
     let mut frame_count = 0;
     let mut current_ffmpeg: Option<Child> = None;
     let mut current_stdin: Option<ChildStdin> = None;
+    let mut current_file: Option<String> = None;
 
     // Track health metrics
     let start_time = std::time::Instant::now();
@@ -437,6 +453,9 @@ async fn save_frames_as_video(
                     monitor_id, frame_count
                 );
                 finish_ffmpeg_process(child, current_stdin.take()).await;
+                if let Some(file) = current_file.take() {
+                    encrypt_finished_chunk(file);
+                }
                 chunks_total += 1;
             }
 
@@ -471,6 +490,7 @@ async fn save_frames_as_video(
 
                     current_ffmpeg = Some(child);
                     current_stdin = Some(stdin);
+                    current_file = Some(output_file.clone());
                     info!(
                         "New FFmpeg process started for file: {} (monitor {})",
                         output_file, monitor_id
@@ -546,6 +566,20 @@ fn encode_frame(frame: &CaptureResult) -> Vec<u8> {
     buffer
 }
 
+/// ffmpeg needs a seekable plaintext file to write the mp4, so chunks are encrypted once
+/// the process has exited. The chunk being recorded stays plaintext until then, chunks left
+/// plaintext by a crash are encrypted on the next start
+fn encrypt_finished_chunk(file: String) {
+    if !encryption::is_enabled() {
+        return;
+    }
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = encryption::encrypt_file_if_enabled(Path::new(&file)) {
+            error!("failed to encrypt video chunk {}: {}", file, e);
+        }
+    });
+}
+
 fn create_output_file(output_path: &str, monitor_id: u32) -> String {
     let time = Utc::now();
     let formatted_time = time.format("%Y-%m-%d_%H-%M-%S").to_string();
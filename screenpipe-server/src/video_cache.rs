@@ -2,7 +2,10 @@ use anyhow::Result;
 use bincode;
 use chrono::{DateTime, Duration, Utc};
 use dirs::cache_dir;
-use screenpipe_core::find_ffmpeg_path;
+use screenpipe_core::{
+    encryption::{self, open_media},
+    find_ffmpeg_path,
+};
 use screenpipe_db::{DatabaseManager, FrameData, OCREntry};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -164,7 +167,11 @@ impl FrameDiskCache {
     }
 
     async fn load_index(&mut self) -> Result<()> {
-        match fs::read(&self.index_path).await {
+        let index = fs::read(&self.index_path)
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|data| encryption::decrypt_if_encrypted(&data));
+        match index {
             Ok(data) if !data.is_empty() => match bincode::deserialize::<Vec<CachedFrame>>(&data) {
                 Ok(frames) => {
                     for frame in frames {
@@ -200,7 +207,7 @@ impl FrameDiskCache {
             bincode::serialize(&frames)?
         };
 
-        fs::write(&temp_path, encoded).await?;
+        fs::write(&temp_path, encryption::encrypt_if_enabled(&encoded)?).await?;
         fs::rename(&temp_path, &self.index_path).await?;
         Ok(())
     }
@@ -228,6 +235,8 @@ impl FrameDiskCache {
         let mut hasher = Sha256::new();
         hasher.update(frame_data);
         let checksum = format!("{:x}", hasher.finalize());
+        // checksums cover the plaintext, sizes what ends up on disk
+        let stored_data = encryption::encrypt_if_enabled(frame_data)?;
 
         let cached_frame = CachedFrame {
             timestamp,
@@ -244,7 +253,7 @@ impl FrameDiskCache {
                     .join(" "),
                 ocr_text: device_data.text.clone(),
             },
-            frame_size: frame_data.len() as u64,
+            frame_size: stored_data.len() as u64,
             compression: CompressionType::Jpeg {
                 quality: self.config.compression_quality,
             },
@@ -253,7 +262,7 @@ impl FrameDiskCache {
             audio_entries: audio_entries.to_vec(),
         };
 
-        fs::write(&frame_path, frame_data).await?;
+        fs::write(&frame_path, &stored_data).await?;
 
         self.entries.insert(
             (timestamp, device_id.to_string()),
@@ -264,7 +273,7 @@ impl FrameDiskCache {
             },
         );
 
-        self.total_size += frame_data.len() as u64;
+        self.total_size += stored_data.len() as u64;
         self.save_index().await?;
 
         Ok(())
@@ -304,7 +313,7 @@ impl FrameDiskCache {
 
             if should_verify {
                 debug!("verifying checksum for cached frame");
-                let frame_data = fs::read(&frame_path).await?;
+                let frame_data = encryption::decrypt_if_encrypted(&fs::read(&frame_path).await?)?;
                 let mut hasher = Sha256::new();
                 hasher.update(&frame_data);
                 let checksum = format!("{:x}", hasher.finalize());
@@ -321,7 +330,7 @@ impl FrameDiskCache {
                 )))
             } else {
                 // Fast path - skip checksum verification
-                let frame_data = fs::read(&frame_path).await?;
+                let frame_data = encryption::decrypt_if_encrypted(&fs::read(&frame_path).await?)?;
                 Ok(Some((
                     frame_data,
                     entry.frame.metadata.clone(),
@@ -647,13 +656,16 @@ async fn extract_frame(
     frame_tx: FrameChannel,
     cache_tx: mpsc::Sender<CacheMessage>,
 ) -> Result<usize> {
-    if !is_video_file_complete(&ffmpeg, &video_file_path).await? {
+    let media = open_media(&video_file_path).await?;
+    // encrypted chunks are only written once ffmpeg has finished them
+    if !media.is_decrypted() && !is_video_file_complete(&ffmpeg, &video_file_path).await? {
         debug!("skipping incomplete video file: {}", video_file_path);
         return Ok(0);
     }
+    let video_file_path = media.path_str();
 
     // Get source FPS from video metadata
-    let source_fps = match get_video_fps(&ffmpeg, &video_file_path).await {
+    let source_fps = match get_video_fps(&ffmpeg, video_file_path).await {
         Ok(fps) => fps,
         Err(e) => {
             error!("failed to get video fps, using default 1fps: {}", e);
@@ -700,7 +712,7 @@ async fn extract_frame(
     let mut cmd = Command::new(&ffmpeg);
     cmd.args([
         "-i",
-        &video_file_path,
+        video_file_path,
         "-vf",
         &format!("{},format=yuv420p,scale=iw*0.8:ih*0.8", select_filter),
         "-strict",
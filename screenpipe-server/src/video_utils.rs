@@ -4,7 +4,10 @@ use chrono::NaiveDateTime;
 use chrono::{DateTime, Utc};
 use image::DynamicImage;
 use oasgen::OaSchema;
-use screenpipe_core::find_ffmpeg_path;
+use screenpipe_core::{
+    encryption::{self, open_media},
+    find_ffmpeg_path,
+};
 use screenpipe_db::VideoMetadata as DBVideoMetadata;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
@@ -38,6 +41,8 @@ struct Stream {
 
 pub async fn extract_frame(file_path: &str, offset_index: i64) -> Result<String> {
     let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
+    let media = open_media(file_path).await?;
+    let file_path = media.path_str();
 
     let offset_seconds = offset_index as f64 / 1000.0;
     let offset_str = format!("{:.3}", offset_seconds);
@@ -114,10 +119,11 @@ pub async fn validate_media(file_path: &str) -> Result<()> {
     if !try_exists(file_path).await? {
         return Err(anyhow::anyhow!("media file does not exist: {}", file_path));
     }
+    let media = open_media(file_path).await?;
 
     let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
     let status = Command::new(ffmpeg_path)
-        .args(["-v", "error", "-i", file_path, "-f", "null", "-"])
+        .args(["-v", "error", "-i", media.path_str(), "-f", "null", "-"])
         .output()
         .await?;
 
@@ -148,14 +154,18 @@ pub async fn merge_videos(
     // create a temporary file to store the list of input videos
     let temp_file = output_dir.join("input_list.txt");
     let mut file = tokio::fs::File::create(&temp_file).await?;
+    // decrypted copies of encrypted inputs have to outlive the ffmpeg call
+    let mut inputs = Vec::with_capacity(request.video_paths.len());
     for video_path in &request.video_paths {
         // video validation before writing in txt
         if let Err(e) = validate_media(video_path).await {
             error!("invalid file in merging, skipping: {:?}", e);
             continue;
         }
+        let media = open_media(video_path).await?;
         // Escape single quotes in the file path
-        let escaped_path = video_path.replace("'", "'\\''");
+        let escaped_path = media.path_str().replace("'", "'\\''");
+        inputs.push(media);
         tokio::io::AsyncWriteExt::write_all(
             &mut file,
             format!("file '{}'\n", escaped_path).as_bytes(),
@@ -182,6 +192,7 @@ pub async fn merge_videos(
 
     // clean up the temporary file
     tokio::fs::remove_file(temp_file).await?;
+    drop(inputs);
 
     // log ffmpeg's output
     let stdout = String::from_utf8_lossy(&status.stdout);
@@ -192,6 +203,7 @@ pub async fn merge_videos(
     if status.status.success() {
         match output_path.try_exists() {
             Ok(true) => {
+                encryption::encrypt_file_if_enabled(&output_path)?;
                 info!("videos merged successfully: {:?}", output_path);
                 Ok(MergeVideosResponse {
                     video_path: output_path.to_string_lossy().into_owned(),
@@ -235,6 +247,8 @@ pub async fn extract_frames_from_video(
             video_path.display()
         ));
     }
+    let media = open_media(video_path).await?;
+    let video_path = media.path();
 
     // Get source FPS and calculate target FPS
     let source_fps = match get_video_fps(&ffmpeg_path, video_path.to_str().unwrap()).await {
@@ -556,6 +570,8 @@ impl VideoMetadataOverride {
 
 pub async fn extract_frame_from_video(file_path: &str, offset_index: i64) -> Result<String> {
     let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
+    let media = open_media(file_path).await?;
+    let file_path = media.path_str();
 
     let source_fps = match get_video_fps(&ffmpeg_path, file_path).await {
         Ok(fps) => fps,
@@ -660,6 +676,8 @@ pub async fn extract_high_quality_frame(
     output_dir: &Path,
 ) -> Result<String> {
     let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
+    let media = open_media(file_path).await?;
+    let file_path = media.path_str();
 
     let source_fps = match get_video_fps(&ffmpeg_path, file_path).await {
         Ok(fps) => fps,
@@ -0,0 +1,71 @@
+use chrono::{Duration, Utc};
+use screenpipe_core::encryption::{self, EncryptionKey};
+use screenpipe_db::DatabaseManager;
+use screenpipe_server::archive::{export_archive, import_archive};
+use std::io::Read;
+
+// encryption is enabled for the whole process, so this lives apart from the plaintext archive
+// test
+#[tokio::test]
+async fn test_archive_of_encrypted_recordings() {
+    let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    let _ = encryption::init(&EncryptionKey::from_hex(key).unwrap());
+
+    let source_dir = tempfile::tempdir().unwrap();
+    let target_dir = tempfile::tempdir().unwrap();
+    let archive_path = source_dir.path().join("archive.tar.zst");
+
+    let video_path = source_dir.path().join("monitor_1_2025-01-01_10-00-00.mp4");
+    std::fs::write(&video_path, b"not really a video").unwrap();
+    encryption::encrypt_file_if_enabled(&video_path).unwrap();
+
+    let source = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    source
+        .insert_video_chunk(video_path.to_str().unwrap(), "monitor_1")
+        .await
+        .unwrap();
+    source
+        .insert_frame("monitor_1", None, None, Some("editor"), Some("notes"), true)
+        .await
+        .unwrap();
+
+    let manifest = export_archive(
+        &source,
+        Utc::now() - Duration::hours(1),
+        Utc::now() + Duration::hours(1),
+        &archive_path,
+    )
+    .await
+    .unwrap();
+    assert!(manifest.encrypted);
+
+    // the archive holds the decrypted recording
+    let decoder = zstd::Decoder::new(std::fs::File::open(&archive_path).unwrap()).unwrap();
+    let mut archive = tar::Archive::new(decoder);
+    let mut media = Vec::new();
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        if entry.path().unwrap().starts_with("media") {
+            entry.read_to_end(&mut media).unwrap();
+        }
+    }
+    assert_eq!(media, b"not really a video");
+
+    // and it's encrypted again on import
+    let target = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    let report = import_archive(&target, &archive_path, target_dir.path())
+        .await
+        .unwrap();
+    assert_eq!(report.files_extracted, 1);
+    let imported_video = target_dir
+        .path()
+        .join("data")
+        .join("monitor_1_2025-01-01_10-00-00.mp4");
+    assert!(encryption::is_encrypted_file(&imported_video).unwrap());
+
+    let report = import_archive(&target, &archive_path, target_dir.path())
+        .await
+        .unwrap();
+    assert_eq!(report.files_extracted, 0);
+    assert_eq!(report.files_existing, 1);
+}
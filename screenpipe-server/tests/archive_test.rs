@@ -0,0 +1,71 @@
+use chrono::{Duration, Utc};
+use screenpipe_db::{DatabaseManager, OcrEngine};
+use screenpipe_server::archive::{export_archive, import_archive};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_export_then_import_archive() {
+    let source_dir = tempfile::tempdir().unwrap();
+    let target_dir = tempfile::tempdir().unwrap();
+    let archive_path = source_dir.path().join("archive.tar.zst");
+
+    let video_path = source_dir.path().join("monitor_1_2025-01-01_10-00-00.mp4");
+    std::fs::write(&video_path, b"not really a video").unwrap();
+
+    let source = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    source
+        .insert_video_chunk(video_path.to_str().unwrap(), "monitor_1")
+        .await
+        .unwrap();
+    let frame_id = source
+        .insert_frame("monitor_1", None, None, Some("editor"), Some("notes"), true)
+        .await
+        .unwrap();
+    source
+        .insert_ocr_text(
+            frame_id,
+            "portable archive",
+            "[]",
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+
+    let manifest = export_archive(
+        &source,
+        Utc::now() - Duration::hours(1),
+        Utc::now() + Duration::hours(1),
+        &archive_path,
+    )
+    .await
+    .unwrap();
+    assert_eq!(manifest.video_chunks, 1);
+    assert_eq!(manifest.frames, 1);
+    assert!(manifest.missing_files.is_empty());
+
+    let target = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    let report = import_archive(&target, &archive_path, target_dir.path())
+        .await
+        .unwrap();
+    assert_eq!(report.files_extracted, 1);
+    assert_eq!(report.stats.frames_imported, 1);
+    assert_eq!(report.stats.ocr_text_imported, 1);
+
+    let imported_video = target_dir
+        .path()
+        .join("data")
+        .join("monitor_1_2025-01-01_10-00-00.mp4");
+    assert_eq!(
+        std::fs::read(&imported_video).unwrap(),
+        b"not really a video"
+    );
+
+    // a second import reuses the extracted file and existing rows
+    let report = import_archive(&target, &archive_path, target_dir.path())
+        .await
+        .unwrap();
+    assert_eq!(report.files_extracted, 0);
+    assert_eq!(report.files_existing, 1);
+    assert_eq!(report.stats.frames_imported, 0);
+    assert_eq!(report.stats.frames_skipped, 1);
+}
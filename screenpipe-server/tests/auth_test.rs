@@ -0,0 +1,357 @@
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use screenpipe_audio::audio_manager::AudioManagerBuilder;
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+use tower::ServiceExt;
+
+use screenpipe_db::DatabaseManager;
+use screenpipe_server::{
+    auth::{hash_token, is_allowed_origin, redact_token, required_scope},
+    AuthManager, PipeManager, SCServer, Scope,
+};
+
+async fn setup_test_app() -> (Router, Arc<AuthManager>) {
+    let db = Arc::new(DatabaseManager::new("sqlite::memory:").await.unwrap());
+    let auth = Arc::new(AuthManager::new(db.clone()));
+
+    let audio_manager = Arc::new(
+        AudioManagerBuilder::new()
+            .output_path("/tmp/screenpipe".into())
+            .build(db.clone())
+            .await
+            .unwrap(),
+    );
+
+    let app = SCServer::new(
+        db.clone(),
+        SocketAddr::from(([127, 0, 0, 1], 23949)),
+        PathBuf::from(""),
+        Arc::new(PipeManager::new(PathBuf::from(""))),
+        false,
+        false,
+        false,
+        audio_manager,
+        true,
+    )
+    .with_auth(auth.clone())
+    .with_allowed_origins(vec!["https://example.com".to_string()]);
+
+    (app.create_router(false).await, auth)
+}
+
+async fn get(app: &Router, uri: &str, token: Option<&str>) -> StatusCode {
+    let mut request = Request::builder().uri(uri);
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn test_routes_require_a_token_with_the_right_scope() {
+    let (app, auth) = setup_test_app().await;
+    let (_, reader) = auth
+        .create_token("reader", &[Scope::ReadSearch], None)
+        .await
+        .unwrap();
+    let (_, writer) = auth
+        .create_token("writer", &[Scope::Write], None)
+        .await
+        .unwrap();
+
+    assert_eq!(get(&app, "/health", None).await, StatusCode::OK);
+    assert_eq!(get(&app, "/search", None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        get(&app, "/search", Some("sp_not_a_token")).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        get(&app, "/search", Some(&writer)).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(get(&app, "/search", Some(&reader)).await, StatusCode::OK);
+
+    let raw_sql = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/raw_sql")
+                .header("Authorization", format!("Bearer {}", reader))
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"query": "SELECT 1"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(raw_sql.status(), StatusCode::FORBIDDEN);
+
+    let (_, admin) = auth
+        .create_token("admin", &[Scope::Admin], None)
+        .await
+        .unwrap();
+    for (token, status) in [(&reader, StatusCode::FORBIDDEN), (&admin, StatusCode::OK)] {
+        let mutation = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/raw_sql/mutate")
+                    .header("Authorization", format!("Bearer {}", token))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"query": "DELETE FROM video_chunks"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(mutation.status(), status);
+    }
+}
+
+#[tokio::test]
+async fn test_pipe_tokens_are_replaced_on_restart() {
+    let (app, auth) = setup_test_app().await;
+
+    let first = auth
+        .issue_pipe_token("search", &[Scope::ReadSearch])
+        .await
+        .unwrap();
+    let second = auth
+        .issue_pipe_token("search", &[Scope::ReadSearch])
+        .await
+        .unwrap();
+    assert_eq!(
+        get(&app, "/search", Some(&first)).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(get(&app, "/search", Some(&second)).await, StatusCode::OK);
+
+    auth.revoke_pipe_tokens("search").await.unwrap();
+    assert_eq!(
+        get(&app, "/search", Some(&second)).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_pipes_only_get_scopes_granted_by_the_user() {
+    let (_, auth) = setup_test_app().await;
+    let pipe_dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        pipe_dir.path().join("pipe.json"),
+        r#"{"scopes": ["admin", "raw-sql"]}"#,
+    )
+    .unwrap();
+
+    // asking in pipe.json isn't enough
+    assert_eq!(
+        auth.pipe_scopes("search", pipe_dir.path()).await.unwrap(),
+        vec![Scope::ReadSearch]
+    );
+
+    auth.grant_pipe_scopes("search", &[Scope::RawSql])
+        .await
+        .unwrap();
+    assert_eq!(
+        auth.pipe_scopes("search", pipe_dir.path()).await.unwrap(),
+        vec![Scope::ReadSearch, Scope::RawSql]
+    );
+    // grants are per pipe
+    assert_eq!(
+        auth.pipe_scopes("other", pipe_dir.path()).await.unwrap(),
+        vec![Scope::ReadSearch]
+    );
+}
+
+#[tokio::test]
+async fn test_cors_only_allows_local_and_configured_origins() {
+    let (app, _) = setup_test_app().await;
+
+    for (origin, allowed) in [
+        ("http://localhost:3000", true),
+        ("tauri://localhost", true),
+        ("https://example.com", true),
+        ("https://evil.example", false),
+        ("http://localhost.evil.example", false),
+    ] {
+        // preflight requests are answered without a token
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("OPTIONS")
+                    .uri("/search")
+                    .header("Origin", origin)
+                    .header("Access-Control-Request-Method", "GET")
+                    .header("Access-Control-Request-Headers", "authorization")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response
+                .headers()
+                .contains_key("access-control-allow-origin"),
+            allowed,
+            "{}",
+            origin
+        );
+    }
+
+    assert!(is_allowed_origin(
+        &"http://[::1]:8080".parse().unwrap(),
+        &[]
+    ));
+    assert!(is_allowed_origin(
+        &"https://evil.example".parse().unwrap(),
+        &["*".to_string()]
+    ));
+}
+
+#[tokio::test]
+async fn test_stream_routes_take_the_token_from_the_query() {
+    let (app, auth) = setup_test_app().await;
+    let (_, reader) = auth
+        .create_token("reader", &[Scope::ReadSearch], None)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get(&app, "/ws/events?token=sp_not_a_token", None).await,
+        StatusCode::UNAUTHORIZED
+    );
+    // percent-encoded, like browsers using encodeURIComponent send it
+    let encoded = reader.replacen('_', "%5F", 1);
+    assert_ne!(
+        get(&app, &format!("/ws/events?token={}", encoded), None).await,
+        StatusCode::UNAUTHORIZED
+    );
+    // only the streaming routes read it
+    assert_eq!(
+        get(&app, &format!("/search?token={}", reader), None).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    assert_eq!(
+        redact_token(
+            &format!("/ws/events?images=true&token={}", reader)
+                .parse()
+                .unwrap()
+        ),
+        "/ws/events?images=true&token=[redacted]"
+    );
+    assert_eq!(
+        redact_token(&"/frames/export?%74oken=sp_secret".parse().unwrap()),
+        "/frames/export?%74oken=[redacted]"
+    );
+    assert_eq!(
+        redact_token(&"/search?q=token".parse().unwrap()),
+        "/search?q=token"
+    );
+}
+
+#[tokio::test]
+async fn test_without_auth_only_local_hosts_are_served() {
+    let db = Arc::new(DatabaseManager::new("sqlite::memory:").await.unwrap());
+    let audio_manager = Arc::new(
+        AudioManagerBuilder::new()
+            .output_path("/tmp/screenpipe".into())
+            .build(db.clone())
+            .await
+            .unwrap(),
+    );
+    let app = SCServer::new(
+        db,
+        SocketAddr::from(([127, 0, 0, 1], 23950)),
+        PathBuf::from(""),
+        Arc::new(PipeManager::new(PathBuf::from(""))),
+        false,
+        false,
+        false,
+        audio_manager,
+        true,
+    )
+    .create_router(false)
+    .await;
+
+    for (host, status) in [
+        ("localhost:3030", StatusCode::OK),
+        ("127.0.0.1:3030", StatusCode::OK),
+        ("[::1]:3030", StatusCode::OK),
+        // a domain of an attacker resolving to 127.0.0.1
+        ("evil.example:3030", StatusCode::FORBIDDEN),
+        ("192.168.1.20:3030", StatusCode::FORBIDDEN),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/search")
+                    .header("Host", host)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), status, "{}", host);
+    }
+}
+
+#[test]
+fn test_required_scopes() {
+    assert_eq!(required_scope(&Method::GET, "/health"), None);
+    assert_eq!(
+        required_scope(&Method::POST, "/raw_sql"),
+        Some(Scope::RawSql)
+    );
+    assert_eq!(
+        required_scope(&Method::POST, "/raw_sql/mutate"),
+        Some(Scope::Admin)
+    );
+    assert_eq!(
+        required_scope(&Method::POST, "/experimental/operator/click"),
+        Some(Scope::Operator)
+    );
+    assert_eq!(
+        required_scope(&Method::GET, "/pipes/list"),
+        Some(Scope::PipesAdmin)
+    );
+    assert_eq!(required_scope(&Method::POST, "/add"), Some(Scope::Write));
+    assert_eq!(
+        required_scope(&Method::POST, "/speakers/merge"),
+        Some(Scope::Write)
+    );
+    assert_eq!(
+        required_scope(&Method::GET, "/speakers/search"),
+        Some(Scope::ReadSearch)
+    );
+    assert_eq!(
+        required_scope(&Method::POST, "/audio/diarize"),
+        Some(Scope::Write)
+    );
+    assert_eq!(
+        required_scope(&Method::GET, "/audio/diarize/1"),
+        Some(Scope::ReadSearch)
+    );
+    assert_eq!(
+        required_scope(&Method::POST, "/audio/retranscribe"),
+        Some(Scope::Write)
+    );
+    assert_eq!(
+        required_scope(&Method::GET, "/audio/transcriptions/1/versions"),
+        Some(Scope::ReadSearch)
+    );
+    assert_eq!(
+        required_scope(&Method::GET, "/frames/42"),
+        Some(Scope::ReadSearch)
+    );
+    assert_ne!(hash_token("sp_a"), hash_token("sp_b"));
+}
@@ -781,4 +781,66 @@ mod tests {
             }
         }
     }
+
+    async fn post_json(
+        app: &Router,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> axum::response::Response {
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_raw_sql_is_read_only() {
+        let (app, db) = setup_test_app().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+
+        let response = post_json(
+            &app,
+            "/raw_sql",
+            serde_json::json!({"query": "DELETE FROM video_chunks"}),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let error_response: TestErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert!(error_response.error.contains("/raw_sql/mutate"));
+
+        let response = post_json(
+            &app,
+            "/raw_sql",
+            serde_json::json!({
+                "query": "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n LIMIT 50) SELECT x FROM n",
+                "max_rows": 10
+            }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-screenpipe-truncated"], "true");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let rows: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(rows.len(), 10);
+
+        // only offered when auth is enabled
+        let response = post_json(
+            &app,
+            "/raw_sql/mutate",
+            serde_json::json!({"query": "DELETE FROM video_chunks"}),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
 }
@@ -74,7 +74,7 @@ async fn test_index_command_with_sql() -> Result<()> {
         None,
         db.clone(),
         OutputFormat::Text,
-        Some(screenpipe_server::cli::CliOcrEngine::Custom),
+        Some("custom".to_string()),
         None,
         false,
         false,
@@ -20,11 +20,12 @@ async fn benchmark_continuous_capture(duration_secs: u64) -> f64 {
         if let Err(e) = continuous_capture(
             result_tx,
             Duration::from_millis(100),
-            OcrEngine::Tesseract,
+            Some(OcrEngine::Tesseract.into()),
             get_default_monitor().await.id(),
             window_filters,
             vec![],
             false,
+            None,
         )
         .await
         {
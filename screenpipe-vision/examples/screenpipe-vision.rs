@@ -44,11 +44,12 @@ async fn main() {
     let _ = continuous_capture(
         result_tx,
         Duration::from_secs_f32(1.0 / cli.fps),
-        OcrEngine::AppleNative,
+        Some(OcrEngine::AppleNative.into()),
         monitor_id.unwrap(),
         window_filters,
         languages.clone(),
         false,
+        None,
     )
     .await;
 
@@ -91,17 +91,18 @@ async fn main() -> Result<()> {
             result_tx,
             Duration::from_secs_f64(1.0 / cli.fps),
             // if apple use apple otherwise if windows use windows native otherwise use tesseract
-            if cfg!(target_os = "macos") {
-                OcrEngine::AppleNative
+            Some(if cfg!(target_os = "macos") {
+                OcrEngine::AppleNative.into()
             } else if cfg!(target_os = "windows") {
-                OcrEngine::WindowsNative
+                OcrEngine::WindowsNative.into()
             } else {
-                OcrEngine::Tesseract
-            },
+                OcrEngine::Tesseract.into()
+            }),
             id,
             window_filters,
             vec![],
             false,
+            None,
         )
         .await
     });
@@ -141,6 +141,9 @@ pub struct CapturedWindow {
     pub window_name: String,
     pub process_id: i32,
     pub is_focused: bool,
+    /// Top left corner of the window in the monitor screenshot, in pixels. `None` when the
+    /// platform didn't report where the window is.
+    pub position: Option<(i32, i32)>,
 }
 
 pub struct WindowFilters {
@@ -255,9 +258,15 @@ pub async fn capture_all_visible_windows(
                 }
             };
 
+            // window coordinates are logical, the captured image is in pixels
+            let bounds = match (window.x(), window.y(), window.width()) {
+                (Ok(x), Ok(y), Ok(width)) if width > 0 => Some((x, y, width)),
+                _ => None,
+            };
+
             // Capture image immediately while we have access to the window
             match window.capture_image() {
-                Ok(buffer) => Some((app_name, title, is_focused, buffer, process_id)),
+                Ok(buffer) => Some((app_name, title, is_focused, buffer, process_id, bounds)),
                 Err(e) => {
                     error!(
                         "Failed to capture image for window {} ({}): {}",
@@ -274,7 +283,16 @@ pub async fn capture_all_visible_windows(
     }
 
     // Process the captured data
-    for (app_name, window_name, is_focused, buffer, process_id) in windows_data {
+    let (monitor_x, monitor_y) = monitor.position();
+    for (app_name, window_name, is_focused, buffer, process_id, bounds) in windows_data {
+        let position = bounds.map(|(x, y, width)| {
+            let scale = buffer.width() as f64 / width as f64;
+            (
+                ((x - monitor_x) as f64 * scale).round() as i32,
+                ((y - monitor_y) as f64 * scale).round() as i32,
+            )
+        });
+
         // Convert to DynamicImage
         let image = DynamicImage::ImageRgba8(
             image::ImageBuffer::from_raw(buffer.width(), buffer.height(), buffer.into_raw())
@@ -294,6 +312,7 @@ pub async fn capture_all_visible_windows(
                 window_name,
                 process_id: process_id as i32,
                 is_focused,
+                position,
             });
         }
     }
@@ -1,25 +1,22 @@
-#[cfg(target_os = "macos")]
-use crate::apple::perform_ocr_apple;
 use crate::capture_screenshot_by_window::CapturedWindow;
 use crate::capture_screenshot_by_window::WindowFilters;
-use crate::custom_ocr::perform_ocr_custom;
-#[cfg(target_os = "windows")]
-use crate::microsoft::perform_ocr_windows;
 use crate::monitor::get_monitor_by_id;
-use crate::tesseract::perform_ocr_tesseract;
-use crate::utils::OcrEngine;
+use crate::ocr_backend::OcrBackend;
+use crate::redaction::RedactedRegion;
+use crate::roi::{
+    blocks_confidence, blocks_text, merge_blocks, plan_roi_ocr, CachedWindow, RoiConfig,
+    RoiOcrCache, RoiPlan,
+};
 use crate::utils::{capture_screenshot, compare_with_previous_image};
 use anyhow::Result;
 use base64::{engine::general_purpose, Engine as _};
 use image::codecs::jpeg::JpegEncoder;
 use image::DynamicImage;
 use screenpipe_core::Language;
-use screenpipe_integrations::unstructured_ocr::perform_ocr_cloud;
 use serde::Deserialize;
 use serde::Deserializer;
 use serde::Serialize;
 use serde::Serializer;
-use serde_json;
 use std::sync::Arc;
 use std::{
     collections::HashMap,
@@ -116,6 +113,16 @@ pub struct WindowOcrResult {
     pub focused: bool,
     pub confidence: f64,
     pub browser_url: Option<String>,
+    /// Top left corner of the window in the frame, in pixels
+    pub position: Option<(i32, i32)>,
+    /// Parts of the frame covering this window that were redacted, see [`crate::redaction`]
+    pub redacted_regions: Vec<RedactedRegion>,
+    /// Engine the text is from when the selected one was unavailable, see
+    /// [`crate::ocr_backend::OcrOutput::fallback_engine`]
+    pub fallback_engine: Option<screenpipe_db::OcrEngine>,
+    /// Whether the selected engine was unavailable, the window is to be OCR'd again later. Its
+    /// text is from `fallback_engine`, or empty without one.
+    pub ocr_deferred: bool,
 }
 
 pub struct OcrTaskData {
@@ -144,16 +151,21 @@ impl std::fmt::Display for ContinuousCaptureError {
     }
 }
 
+/// Captures the monitor and sends the OCR'd windows of frames that changed. Without
+/// `ocr_engine` windows are sent without their text, for callers that OCR them later.
+#[allow(clippy::too_many_arguments)]
 pub async fn continuous_capture(
     result_tx: Sender<CaptureResult>,
     interval: Duration,
-    ocr_engine: OcrEngine,
+    ocr_engine: Option<Arc<dyn OcrBackend>>,
     monitor_id: u32,
     window_filters: Arc<WindowFilters>,
     languages: Vec<Language>,
     capture_unfocused_windows: bool,
+    roi_ocr: Option<RoiConfig>,
 ) -> Result<(), ContinuousCaptureError> {
     let mut frame_counter: u64 = 0;
+    let mut roi_cache = roi_ocr.map(RoiOcrCache::new);
     let mut previous_image: Option<DynamicImage> = None;
     let mut max_average: Option<MaxAverageFrame> = None;
     let mut max_avg_value = 0.0;
@@ -209,8 +221,13 @@ pub async fn continuous_capture(
 
         // 5. Process max average frame if available
         if let Some(max_avg_frame) = max_average.take() {
-            if let Err(e) =
-                process_max_average_frame(max_avg_frame, &ocr_engine, languages.clone()).await
+            if let Err(e) = process_max_average_frame(
+                max_avg_frame,
+                ocr_engine.as_deref(),
+                languages.clone(),
+                roi_cache.as_mut(),
+            )
+            .await
             {
                 error!("Error processing max average frame: {}", e);
             }
@@ -280,8 +297,9 @@ async fn should_skip_frame(
 
 async fn process_max_average_frame(
     max_avg_frame: MaxAverageFrame,
-    ocr_engine: &OcrEngine,
+    ocr_engine: Option<&dyn OcrBackend>,
     languages: Vec<Language>,
+    roi_cache: Option<&mut RoiOcrCache>,
 ) -> Result<(), ContinuousCaptureError> {
     let ocr_task_data = OcrTaskData {
         image: max_avg_frame.image,
@@ -291,7 +309,11 @@ async fn process_max_average_frame(
         result_tx: max_avg_frame.result_tx,
     };
 
-    if let Err(e) = process_ocr_task(ocr_task_data, ocr_engine, languages).await {
+    let result = match ocr_engine {
+        Some(ocr_engine) => process_ocr_task(ocr_task_data, ocr_engine, languages, roi_cache).await,
+        None => send_windows_without_ocr(ocr_task_data).await,
+    };
+    if let Err(e) = result {
         error!("Error processing OCR task: {}", e);
         return Err(ContinuousCaptureError::ErrorProcessingOcr(e.to_string()));
     }
@@ -309,10 +331,13 @@ pub struct MaxAverageFrame {
     pub average: f64,
 }
 
+/// OCRs the windows of a frame. With a [`RoiOcrCache`] windows seen in the previous frame
+/// only have their changed regions OCR'd, see [`crate::roi`].
 pub async fn process_ocr_task(
     ocr_task_data: OcrTaskData,
-    ocr_engine: &OcrEngine,
+    ocr_engine: &dyn OcrBackend,
     languages: Vec<Language>,
+    mut roi_cache: Option<&mut RoiOcrCache>,
 ) -> Result<(), ContinuousCaptureError> {
     let OcrTaskData {
         image,
@@ -331,6 +356,10 @@ pub async fn process_ocr_task(
     let mut window_ocr_results = Vec::new();
     let mut total_confidence = 0.0;
     let mut window_count = 0;
+    let windows: Vec<(String, String)> = window_images
+        .iter()
+        .map(|w| (w.app_name.clone(), w.window_name.clone()))
+        .collect();
 
     for captured_window in window_images {
         let ocr_result = process_window_ocr(
@@ -339,12 +368,16 @@ pub async fn process_ocr_task(
             &languages,
             &mut total_confidence,
             &mut window_count,
+            roi_cache.as_deref_mut(),
         )
         .await
         .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string()))?;
 
         window_ocr_results.push(ocr_result);
     }
+    if let Some(cache) = roi_cache {
+        cache.retain(&windows);
+    }
 
     // Create and send the result
     let capture_result = CaptureResult {
@@ -364,12 +397,62 @@ pub async fn process_ocr_task(
     Ok(())
 }
 
+/// Sends the windows of a frame with empty text, for windows OCR'd after capture
+async fn send_windows_without_ocr(
+    ocr_task_data: OcrTaskData,
+) -> Result<(), ContinuousCaptureError> {
+    let OcrTaskData {
+        image,
+        window_images,
+        frame_number,
+        timestamp,
+        result_tx,
+    } = ocr_task_data;
+
+    let mut window_ocr_results = Vec::with_capacity(window_images.len());
+    for captured_window in window_images {
+        let browser_url = get_browser_url_if_needed(
+            &captured_window.app_name,
+            captured_window.is_focused,
+            captured_window.process_id,
+            &captured_window.window_name,
+        )
+        .await;
+        window_ocr_results.push(WindowOcrResult {
+            image: captured_window.image,
+            window_name: captured_window.window_name,
+            app_name: captured_window.app_name,
+            text: String::new(),
+            text_json: Vec::new(),
+            focused: captured_window.is_focused,
+            confidence: 0.0,
+            browser_url,
+            position: captured_window.position,
+            redacted_regions: Vec::new(),
+            fallback_engine: None,
+            ocr_deferred: false,
+        });
+    }
+
+    send_ocr_result(
+        &result_tx,
+        CaptureResult {
+            image,
+            frame_number,
+            timestamp,
+            window_ocr_results,
+        },
+    )
+    .await
+}
+
 async fn process_window_ocr(
     captured_window: CapturedWindow,
-    ocr_engine: &OcrEngine,
+    ocr_engine: &dyn OcrBackend,
     languages: &[Language],
     total_confidence: &mut f64,
     window_count: &mut u32,
+    roi_cache: Option<&mut RoiOcrCache>,
 ) -> Result<WindowOcrResult, ContinuousCaptureError> {
     let app_name = captured_window.app_name.clone();
 
@@ -383,10 +466,24 @@ async fn process_window_ocr(
     .await;
 
     // Perform OCR based on the selected engine
-    let (window_text, window_json_output, confidence) =
-        perform_ocr_with_engine(ocr_engine, &captured_window.image, languages.to_vec())
-            .await
-            .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string()))?;
+    let ocr = match roi_cache {
+        Some(cache) => perform_roi_ocr(cache, ocr_engine, &captured_window, languages).await,
+        None => perform_full_ocr(ocr_engine, &captured_window.image, languages).await,
+    };
+    // the window is recorded without text rather than losing the frame
+    let (window_text, text_json, confidence, fallback_engine, ocr_deferred) = match ocr {
+        Ok((text, text_json, confidence, fallback_engine)) => {
+            let ocr_deferred = fallback_engine.is_some();
+            (text, text_json, confidence, fallback_engine, ocr_deferred)
+        }
+        Err(e) => {
+            warn!(
+                "ocr of window {} failed, deferring it: {}",
+                captured_window.window_name, e
+            );
+            (String::new(), Vec::new(), None, None, true)
+        }
+    };
 
     // Update confidence metrics
     if let Some(conf) = confidence {
@@ -399,13 +496,115 @@ async fn process_window_ocr(
         window_name: captured_window.window_name,
         app_name: captured_window.app_name,
         text: window_text,
-        text_json: parse_json_output(&window_json_output),
+        text_json,
         focused: captured_window.is_focused,
         confidence: confidence.unwrap_or(0.0),
         browser_url,
+        position: captured_window.position,
+        redacted_regions: Vec::new(),
+        fallback_engine,
+        ocr_deferred,
     })
 }
 
+type WindowText = (
+    String,
+    Vec<HashMap<String, String>>,
+    Option<f64>,
+    Option<screenpipe_db::OcrEngine>,
+);
+
+async fn perform_full_ocr(
+    ocr_engine: &dyn OcrBackend,
+    image: &DynamicImage,
+    languages: &[Language],
+) -> Result<WindowText, ContinuousCaptureError> {
+    let output = ocr_engine
+        .recognize(image, languages)
+        .await
+        .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string()))?;
+    let text_json = output.text_json();
+    Ok((
+        output.text,
+        text_json,
+        output.confidence,
+        output.fallback_engine,
+    ))
+}
+
+/// OCRs what changed in the window since it was last OCR'd and keeps the result for the next
+/// frame. Text from a fallback engine isn't kept, so the window is OCR'd in full again.
+async fn perform_roi_ocr(
+    cache: &mut RoiOcrCache,
+    ocr_engine: &dyn OcrBackend,
+    window: &CapturedWindow,
+    languages: &[Language],
+) -> Result<WindowText, ContinuousCaptureError> {
+    let current = window.image.to_luma8();
+    let previous = cache.get(&window.app_name, &window.window_name);
+    let plan = previous.map_or(RoiPlan::Full, |previous| {
+        plan_roi_ocr(
+            &previous.image,
+            &current,
+            &previous.text_json,
+            &cache.config,
+        )
+    });
+
+    let merged = match (plan, previous) {
+        (RoiPlan::Unchanged, Some(previous)) => {
+            debug!("window {} unchanged, reusing its ocr", window.window_name);
+            Some((
+                previous.text.clone(),
+                previous.text_json.clone(),
+                previous.confidence,
+                None,
+            ))
+        }
+        (RoiPlan::Regions(regions), Some(previous)) => {
+            debug!(
+                "ocr of {} changed regions of window {}",
+                regions.len(),
+                window.window_name
+            );
+            let mut region_blocks = Vec::with_capacity(regions.len());
+            let mut fallback_engine = None;
+            for region in regions {
+                let crop = window
+                    .image
+                    .crop_imm(region.x, region.y, region.width, region.height);
+                let (_, blocks, _, fallback) =
+                    perform_full_ocr(ocr_engine, &crop, languages).await?;
+                fallback_engine = fallback_engine.or(fallback);
+                region_blocks.push((region, blocks));
+            }
+            merge_blocks(&previous.text_json, region_blocks).map(|blocks| {
+                let (text, confidence) = (blocks_text(&blocks), blocks_confidence(&blocks));
+                (text, blocks, confidence, fallback_engine)
+            })
+        }
+        _ => None,
+    };
+    let (text, text_json, confidence, fallback_engine) = match merged {
+        Some(result) => result,
+        None => perform_full_ocr(ocr_engine, &window.image, languages).await?,
+    };
+
+    if fallback_engine.is_none() {
+        cache.insert(
+            &window.app_name,
+            &window.window_name,
+            CachedWindow {
+                image: current,
+                text: text.clone(),
+                text_json: text_json.clone(),
+                confidence,
+            },
+        );
+    }
+    Ok((text, text_json, confidence, fallback_engine))
+}
+
 async fn get_browser_url_if_needed(
     app_name: &str,
     is_focused: bool,
@@ -436,31 +635,6 @@ async fn get_browser_url_if_needed(
     }
 }
 
-async fn perform_ocr_with_engine(
-    ocr_engine: &OcrEngine,
-    image: &DynamicImage,
-    languages: Vec<Language>,
-) -> Result<(String, String, Option<f64>), ContinuousCaptureError> {
-    match ocr_engine {
-        OcrEngine::Unstructured => perform_ocr_cloud(image, languages)
-            .await
-            .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string())),
-        OcrEngine::Tesseract => Ok(perform_ocr_tesseract(image, languages)),
-        #[cfg(target_os = "windows")]
-        OcrEngine::WindowsNative => perform_ocr_windows(image)
-            .await
-            .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string())),
-        #[cfg(target_os = "macos")]
-        OcrEngine::AppleNative => Ok(perform_ocr_apple(image, &languages)),
-        OcrEngine::Custom(config) => perform_ocr_custom(image, languages, config)
-            .await
-            .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string())),
-        _ => Err(ContinuousCaptureError::ErrorProcessingOcr(
-            "Unsupported OCR engine".to_string(),
-        )),
-    }
-}
-
 async fn send_ocr_result(
     result_tx: &Sender<CaptureResult>,
     capture_result: CaptureResult,
@@ -506,16 +680,6 @@ fn log_ocr_performance(
     );
 }
 
-fn parse_json_output(json_output: &str) -> Vec<HashMap<String, String>> {
-    let parsed_output: Vec<HashMap<String, String>> = serde_json::from_str(json_output)
-        .unwrap_or_else(|e| {
-            error!("Failed to parse JSON output: {}", e);
-            Vec::new()
-        });
-
-    parsed_output
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RealtimeVisionEvent {
     Ocr(WindowOcr),
@@ -1,14 +1,30 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use base64::{engine::general_purpose, Engine as _};
 use image::DynamicImage;
+use once_cell::sync::Lazy;
 use screenpipe_core::Language;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, OnceCell};
+use tracing::{debug, warn};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CustomOcrConfig {
     pub api_url: String,
     pub api_key: String,
     pub timeout_ms: u64,
+    /// Images sent in one request at most, see [`perform_ocr_custom_batch`]
+    pub batch_size: usize,
+    /// How long the first image of a batch waits for others
+    pub batch_timeout_ms: u64,
+    /// Failed requests in a row after which the service isn't called for `circuit_open_ms`
+    pub failure_threshold: u32,
+    pub circuit_open_ms: u64,
+    /// Local engine used while the service fails, by name, like `tesseract`
+    pub fallback_engine: Option<String>,
 }
 
 impl Default for CustomOcrConfig {
@@ -17,6 +33,11 @@ impl Default for CustomOcrConfig {
             api_url: "http://localhost:8000/ocr".to_string(),
             api_key: "".to_string(),
             timeout_ms: 5000,
+            batch_size: 1,
+            batch_timeout_ms: 50,
+            failure_threshold: 5,
+            circuit_open_ms: 30000,
+            fallback_engine: None,
         }
     }
 }
@@ -27,6 +48,7 @@ impl From<screenpipe_db::CustomOcrConfig> for CustomOcrConfig {
             api_url: config.api_url,
             api_key: config.api_key,
             timeout_ms: config.timeout_ms,
+            ..Default::default()
         }
     }
 }
@@ -41,49 +63,99 @@ impl From<CustomOcrConfig> for screenpipe_db::CustomOcrConfig {
     }
 }
 
+type OcrResult = (String, String, Option<f64>);
+
 pub async fn perform_ocr_custom(
     image: &DynamicImage,
     languages: Vec<Language>,
     config: &CustomOcrConfig,
 ) -> Result<(String, String, Option<f64>)> {
+    let base64_image = encode_image(image)?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .build()?;
+    let mut results = post_images(&client, &[base64_image], &languages, config).await?;
+    results
+        .pop()
+        .ok_or_else(|| anyhow!("custom ocr returned no result"))
+}
+
+/// OCRs several images in one request.
+///
+/// A single image is posted as `{"image", "languages"}` and answered with
+/// `{"text", "structured_data", "confidence"}`. Several are posted as `{"images", "languages"}`
+/// and answered with `{"results"}`, one of the former per image in the same order.
+pub async fn perform_ocr_custom_batch(
+    images: &[DynamicImage],
+    languages: &[Language],
+    config: &CustomOcrConfig,
+) -> Result<Vec<(String, String, Option<f64>)>> {
+    let base64_images = images
+        .iter()
+        .map(encode_image)
+        .collect::<Result<Vec<_>>>()?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .build()?;
+    post_images(&client, &base64_images, languages, config).await
+}
+
+/// Base64 jpeg of the image
+fn encode_image(image: &DynamicImage) -> Result<String> {
     // Convert image to RGB before encoding to JPEG
     let rgb_image = image.to_rgb8();
 
-    // Convert image to base64 JPEG
     let mut buffer = Vec::new();
     rgb_image.write_to(
         &mut std::io::Cursor::new(&mut buffer),
         image::ImageFormat::Jpeg,
     )?;
-    let base64_image = general_purpose::STANDARD.encode(buffer);
-
-    // Prepare the request payload
-    let payload = serde_json::json!({
-        "image": base64_image,
-        "languages": languages.iter().map(|l| l.to_string()).collect::<Vec<_>>(),
-    });
+    Ok(general_purpose::STANDARD.encode(buffer))
+}
 
-    // Create client with timeout
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_millis(config.timeout_ms))
-        .build()?;
+async fn post_images(
+    client: &reqwest::Client,
+    base64_images: &[String],
+    languages: &[Language],
+    config: &CustomOcrConfig,
+) -> Result<Vec<OcrResult>> {
+    let languages = languages.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+    let payload = match base64_images {
+        [image] => serde_json::json!({ "image": image, "languages": languages }),
+        images => serde_json::json!({ "images": images, "languages": languages }),
+    };
 
-    // Make the API request
     let response = client
         .post(&config.api_url)
         .header("Authorization", format!("Bearer {}", config.api_key))
         .json(&payload)
         .send()
-        .await?;
+        .await?
+        .error_for_status()?;
 
-    // Handle the response
-    let ocr_result: OcrResponse = response.json().await?;
+    let responses = if base64_images.len() == 1 {
+        vec![response.json::<OcrResponse>().await?]
+    } else {
+        response.json::<OcrBatchResponse>().await?.results
+    };
+    if responses.len() != base64_images.len() {
+        return Err(anyhow!(
+            "custom ocr returned {} results for {} images",
+            responses.len(),
+            base64_images.len()
+        ));
+    }
 
-    Ok((
-        ocr_result.text,
-        ocr_result.structured_data.to_string(),
-        Some(ocr_result.confidence),
-    ))
+    Ok(responses
+        .into_iter()
+        .map(|ocr_result| {
+            (
+                ocr_result.text,
+                ocr_result.structured_data.to_string(),
+                Some(ocr_result.confidence),
+            )
+        })
+        .collect())
 }
 
 #[derive(Debug, Deserialize)]
@@ -92,3 +164,302 @@ struct OcrResponse {
     structured_data: serde_json::Value,
     confidence: f64,
 }
+
+#[derive(Debug, Deserialize)]
+struct OcrBatchResponse {
+    results: Vec<OcrResponse>,
+}
+
+/// Counters of the custom ocr service since startup
+#[derive(Default)]
+pub struct CustomOcrMetrics {
+    requests: AtomicU64,
+    images: AtomicU64,
+    failures: AtomicU64,
+    retries: AtomicU64,
+    circuit_opens: AtomicU64,
+    rejected: AtomicU64,
+    fallbacks: AtomicU64,
+    circuit_open: AtomicBool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CustomOcrMetricsSnapshot {
+    pub requests: u64,
+    /// Images OCR'd by the service
+    pub images: u64,
+    pub failures: u64,
+    /// Images put back in the ocr queue to be sent again later
+    pub retries: u64,
+    /// Times the service was found failing and stopped being called for a while
+    pub circuit_opens: u64,
+    /// Images not sent because the service was failing
+    pub rejected: u64,
+    /// Images OCR'd by the fallback engine instead
+    pub fallbacks: u64,
+    pub circuit_open: bool,
+}
+
+impl CustomOcrMetrics {
+    pub fn snapshot(&self) -> CustomOcrMetricsSnapshot {
+        CustomOcrMetricsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            images: self.images.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            circuit_opens: self.circuit_opens.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            fallbacks: self.fallbacks.load(Ordering::Relaxed),
+            circuit_open: self.circuit_open.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn record_fallback(&self) {
+        self.fallbacks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Metrics of every custom ocr client of the process
+pub static CUSTOM_OCR_METRICS: Lazy<CustomOcrMetrics> = Lazy::new(CustomOcrMetrics::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests are sent
+    Closed,
+    /// The service failed, requests are refused until it has had time to recover
+    Open,
+    /// A single request is let through to check whether the service recovered
+    HalfOpen,
+}
+
+/// Stops calling a service after `failure_threshold` failures in a row, for `open_for`
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_for: Duration,
+    failures: u32,
+    opened_at: Option<Instant>,
+    probing: bool,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_for: Duration) -> Self {
+        CircuitBreaker {
+            failure_threshold: failure_threshold.max(1),
+            open_for,
+            failures: 0,
+            opened_at: None,
+            probing: false,
+        }
+    }
+
+    pub fn state(&self, now: Instant) -> CircuitState {
+        match self.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if now.duration_since(opened_at) < self.open_for => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Whether a request can be sent now
+    pub fn allow(&mut self, now: Instant) -> bool {
+        match self.state(now) {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if self.probing => false,
+            CircuitState::HalfOpen => {
+                self.probing = true;
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.opened_at = None;
+        self.probing = false;
+    }
+
+    /// Returns whether the failure opened the circuit
+    pub fn record_failure(&mut self, now: Instant) -> bool {
+        self.failures += 1;
+        let open =
+            self.probing || (self.opened_at.is_none() && self.failures >= self.failure_threshold);
+        self.probing = false;
+        if open {
+            self.opened_at = Some(now);
+        }
+        open
+    }
+}
+
+struct BatchItem {
+    image: String,
+    languages: Vec<Language>,
+    reply: oneshot::Sender<Result<OcrResult, String>>,
+}
+
+/// Client of a custom ocr service. Images OCR'd at the same time are sent in batches and a
+/// service failing again and again isn't called for a while, see [`CircuitBreaker`].
+///
+/// Failed requests aren't retried here, the capture loop would wait on the retries. The windows
+/// are retried by the ocr queue instead, like those captured while the circuit is open, with a
+/// delay doubled on each retry up to 10 minutes.
+pub struct CustomOcrClient {
+    config: Arc<CustomOcrConfig>,
+    breaker: Arc<Mutex<CircuitBreaker>>,
+    batches: OnceCell<mpsc::Sender<BatchItem>>,
+}
+
+impl CustomOcrClient {
+    pub fn new(config: CustomOcrConfig) -> Self {
+        let breaker = CircuitBreaker::new(
+            config.failure_threshold,
+            Duration::from_millis(config.circuit_open_ms),
+        );
+        CustomOcrClient {
+            config: Arc::new(config),
+            breaker: Arc::new(Mutex::new(breaker)),
+            batches: OnceCell::new(),
+        }
+    }
+
+    /// Whether the service is called, false while the circuit is open
+    pub fn is_available(&self) -> bool {
+        self.breaker.lock().unwrap().state(Instant::now()) != CircuitState::Open
+    }
+
+    pub async fn recognize(
+        &self,
+        image: &DynamicImage,
+        languages: &[Language],
+    ) -> Result<(String, String, Option<f64>)> {
+        if !self.breaker.lock().unwrap().allow(Instant::now()) {
+            CUSTOM_OCR_METRICS.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(anyhow!(
+                "custom ocr service is failing, not calling it for now"
+            ));
+        }
+
+        let (reply, result) = oneshot::channel();
+        let item = BatchItem {
+            image: encode_image(image)?,
+            languages: languages.to_vec(),
+            reply,
+        };
+        let batches = self
+            .batches
+            .get_or_try_init(|| async { self.spawn_batcher() })
+            .await?;
+        batches
+            .send(item)
+            .await
+            .map_err(|_| anyhow!("custom ocr batcher stopped"))?;
+        result
+            .await
+            .map_err(|_| anyhow!("custom ocr request dropped"))?
+            .map_err(|e| anyhow!(e))
+    }
+
+    /// Collects images into batches and sends each batch on its own task
+    fn spawn_batcher(&self) -> Result<mpsc::Sender<BatchItem>> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_millis(self.config.timeout_ms))
+            .build()?;
+        let (tx, mut rx) = mpsc::channel::<BatchItem>(64);
+        let (config, breaker) = (self.config.clone(), self.breaker.clone());
+
+        tokio::spawn(async move {
+            let batch_size = config.batch_size.max(1);
+            while let Some(first) = rx.recv().await {
+                let mut batch = vec![first];
+                let deadline = tokio::time::sleep(Duration::from_millis(config.batch_timeout_ms));
+                tokio::pin!(deadline);
+                while batch.len() < batch_size {
+                    tokio::select! {
+                        item = rx.recv() => match item {
+                            Some(item) => batch.push(item),
+                            None => break,
+                        },
+                        _ = &mut deadline => break,
+                    }
+                }
+
+                // images of a request share its languages
+                while !batch.is_empty() {
+                    let languages = batch[0].languages.clone();
+                    let (same, rest): (Vec<_>, Vec<_>) = batch
+                        .into_iter()
+                        .partition(|item| item.languages == languages);
+                    batch = rest;
+                    tokio::spawn(send_batch(
+                        http.clone(),
+                        config.clone(),
+                        breaker.clone(),
+                        same,
+                    ));
+                }
+            }
+        });
+        Ok(tx)
+    }
+}
+
+async fn send_batch(
+    http: reqwest::Client,
+    config: Arc<CustomOcrConfig>,
+    breaker: Arc<Mutex<CircuitBreaker>>,
+    batch: Vec<BatchItem>,
+) {
+    let languages = batch
+        .first()
+        .map(|item| item.languages.clone())
+        .unwrap_or_default();
+    let (images, replies): (Vec<_>, Vec<_>) = batch
+        .into_iter()
+        .map(|item| (item.image, item.reply))
+        .unzip();
+    CUSTOM_OCR_METRICS.requests.fetch_add(1, Ordering::Relaxed);
+    let result = post_images(&http, &images, &languages, &config).await;
+
+    {
+        let mut breaker = breaker.lock().unwrap();
+        if result.is_ok() {
+            breaker.record_success();
+        } else if breaker.record_failure(Instant::now()) {
+            warn!(
+                "custom ocr service failed {} times in a row, not calling it for {}ms",
+                config.failure_threshold, config.circuit_open_ms
+            );
+            CUSTOM_OCR_METRICS
+                .circuit_opens
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        CUSTOM_OCR_METRICS.circuit_open.store(
+            breaker.state(Instant::now()) != CircuitState::Closed,
+            Ordering::Relaxed,
+        );
+    }
+
+    match result {
+        Ok(results) => {
+            CUSTOM_OCR_METRICS
+                .images
+                .fetch_add(results.len() as u64, Ordering::Relaxed);
+            for (reply, result) in replies.into_iter().zip(results) {
+                let _ = reply.send(Ok(result));
+            }
+        }
+        Err(e) => {
+            CUSTOM_OCR_METRICS.failures.fetch_add(1, Ordering::Relaxed);
+            debug!("custom ocr of {} images failed: {}", images.len(), e);
+            for reply in replies {
+                let _ = reply.send(Err(e.to_string()));
+            }
+        }
+    }
+}
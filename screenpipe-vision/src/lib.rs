@@ -5,6 +5,10 @@ pub mod custom_ocr;
 #[cfg(target_os = "windows")]
 pub mod microsoft;
 pub mod monitor;
+pub mod ocr_backend;
+pub mod onnx_ocr;
+pub mod redaction;
+pub mod roi;
 #[cfg(target_os = "macos")]
 pub mod run_ui_monitoring_macos;
 pub mod tesseract;
@@ -13,6 +17,7 @@ pub mod utils;
 pub use apple::perform_ocr_apple;
 pub use core::{continuous_capture, process_ocr_task, CaptureResult, RealtimeVisionEvent, UIFrame};
 // pub use types::CaptureResult;
+pub use ocr_backend::{OcrBackend, OcrBackendRegistry};
 pub use utils::OcrEngine;
 pub mod capture_screenshot_by_window;
 pub use custom_ocr::perform_ocr_custom;
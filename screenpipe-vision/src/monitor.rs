@@ -12,6 +12,8 @@ pub struct SafeMonitor {
 
 #[derive(Clone)]
 pub struct MonitorData {
+    pub x: i32,
+    pub y: i32,
     pub width: u32,
     pub height: u32,
     pub name: String,
@@ -22,6 +24,8 @@ impl SafeMonitor {
     pub fn new(monitor: Monitor) -> Self {
         let monitor_id = monitor.id().unwrap();
         let monitor_data = Arc::new(MonitorData {
+            x: monitor.x().unwrap(),
+            y: monitor.y().unwrap(),
             width: monitor.width().unwrap(),
             height: monitor.height().unwrap(),
             name: monitor.name().unwrap().to_string(),
@@ -67,6 +71,11 @@ impl SafeMonitor {
         (self.monitor_data.width, self.monitor_data.height)
     }
 
+    /// Top left corner of the monitor in the global desktop coordinates
+    pub fn position(&self) -> (i32, i32) {
+        (self.monitor_data.x, self.monitor_data.y)
+    }
+
     pub fn name(&self) -> &str {
         &self.monitor_data.name
     }
@@ -0,0 +1,526 @@
+//! OCR engines behind a common trait, so new ones can be added as their own module.
+//!
+//! An engine implements [`OcrBackend`] and is registered by name in [`OcrBackendRegistry`], which
+//! `--ocr-engine` looks engines up in. Every engine reports lines in the layout of
+//! [`screenpipe_db::OcrTextBlock`], with boxes in pixels from the top left corner of the image and
+//! confidences between 0 and 1, so search highlights, redaction and region OCR don't depend on
+//! the engine that found the text.
+
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use image::{DynamicImage, GenericImageView};
+use screenpipe_core::Language;
+use screenpipe_integrations::unstructured_ocr::perform_ocr_cloud;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+#[cfg(target_os = "macos")]
+use crate::apple::perform_ocr_apple;
+use crate::custom_ocr::{CustomOcrClient, CustomOcrConfig, CUSTOM_OCR_METRICS};
+#[cfg(target_os = "windows")]
+use crate::microsoft::perform_ocr_windows;
+use crate::onnx_ocr::OnnxOcrBackend;
+use crate::tesseract::perform_ocr_tesseract;
+use crate::utils::OcrEngine;
+
+/// A line of text found in an image
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrLine {
+    pub text: String,
+    /// Box in pixels of the image, from its top left corner
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+    /// Between 0 and 1, 1 for engines that don't report it
+    pub confidence: f64,
+}
+
+impl OcrLine {
+    /// A line covering the whole image, for engines that don't locate their text
+    pub fn covering(image: &DynamicImage, text: String, confidence: f64) -> Self {
+        let (width, height) = image.dimensions();
+        OcrLine {
+            text,
+            left: 0.0,
+            top: 0.0,
+            width: width as f64,
+            height: height as f64,
+            confidence,
+        }
+    }
+
+    /// Entry of `text_json`, in the layout of [`screenpipe_db::OcrTextBlock`]
+    pub fn to_block(&self, line_num: usize) -> HashMap<String, String> {
+        HashMap::from([
+            ("level".to_string(), "0".to_string()),
+            ("page_num".to_string(), "0".to_string()),
+            ("block_num".to_string(), "0".to_string()),
+            ("par_num".to_string(), "0".to_string()),
+            ("line_num".to_string(), line_num.to_string()),
+            ("word_num".to_string(), "0".to_string()),
+            ("left".to_string(), format!("{:.0}", self.left)),
+            ("top".to_string(), format!("{:.0}", self.top)),
+            ("width".to_string(), format!("{:.0}", self.width)),
+            ("height".to_string(), format!("{:.0}", self.height)),
+            ("conf".to_string(), format!("{:.3}", self.confidence)),
+            ("text".to_string(), self.text.clone()),
+        ])
+    }
+}
+
+/// Text found in an image by an engine
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OcrOutput {
+    pub text: String,
+    pub lines: Vec<OcrLine>,
+    /// Between 0 and 1, `None` when nothing was found
+    pub confidence: Option<f64>,
+    /// Engine that found the text when the backend was unavailable and fell back to another one
+    pub fallback_engine: Option<screenpipe_db::OcrEngine>,
+}
+
+impl OcrOutput {
+    /// Output made of the lines, their text joined by spaces and their mean confidence
+    pub fn from_lines(lines: Vec<OcrLine>) -> Self {
+        let text = lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let confidence = (!lines.is_empty())
+            .then(|| lines.iter().map(|line| line.confidence).sum::<f64>() / lines.len() as f64);
+        OcrOutput {
+            text,
+            lines,
+            confidence,
+            fallback_engine: None,
+        }
+    }
+
+    pub fn text_json(&self) -> Vec<HashMap<String, String>> {
+        self.lines
+            .iter()
+            .enumerate()
+            .map(|(line_num, line)| line.to_block(line_num))
+            .collect()
+    }
+}
+
+/// An OCR engine.
+///
+/// The built-in engines are registered in [`OcrBackendRegistry::default`], others can be added
+/// there or to a registry of their own.
+pub trait OcrBackend: Send + Sync {
+    /// Name the engine is selected with, like `tesseract`
+    fn name(&self) -> &str;
+
+    /// Engine recorded with the text in the database
+    fn engine(&self) -> screenpipe_db::OcrEngine {
+        screenpipe_db::OcrEngine::Other(self.name().to_string())
+    }
+
+    /// False while the engine is known to be down, like a custom ocr service that failed again
+    /// and again. Images failing meanwhile aren't at fault.
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// Finds the text of an image
+    fn recognize<'a>(
+        &'a self,
+        image: &'a DynamicImage,
+        languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<OcrOutput>>;
+}
+
+pub struct TesseractBackend;
+
+impl OcrBackend for TesseractBackend {
+    fn name(&self) -> &str {
+        "tesseract"
+    }
+
+    fn engine(&self) -> screenpipe_db::OcrEngine {
+        screenpipe_db::OcrEngine::Tesseract
+    }
+
+    fn recognize<'a>(
+        &'a self,
+        image: &'a DynamicImage,
+        languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<OcrOutput>> {
+        Box::pin(async move {
+            let (text, json_output, confidence) = perform_ocr_tesseract(image, languages.to_vec());
+            // tesseract reports lines in pixels with confidences in percent
+            let lines = lines_from_json(&json_output, image, BoxLayout::Pixels);
+            Ok(OcrOutput {
+                text,
+                lines,
+                confidence: confidence.map(|c| c / 100.0),
+                fallback_engine: None,
+            })
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub struct AppleNativeBackend;
+
+#[cfg(target_os = "macos")]
+impl OcrBackend for AppleNativeBackend {
+    fn name(&self) -> &str {
+        "apple-native"
+    }
+
+    fn engine(&self) -> screenpipe_db::OcrEngine {
+        screenpipe_db::OcrEngine::AppleNative
+    }
+
+    fn recognize<'a>(
+        &'a self,
+        image: &'a DynamicImage,
+        languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<OcrOutput>> {
+        Box::pin(async move {
+            let (_, json_output, _) = perform_ocr_apple(image, languages);
+            Ok(OcrOutput::from_lines(lines_from_json(
+                &json_output,
+                image,
+                BoxLayout::NormalizedFromBottom,
+            )))
+        })
+    }
+}
+
+/// Windows OCR only reports the text here, as a line covering the image
+#[cfg(target_os = "windows")]
+pub struct WindowsNativeBackend;
+
+#[cfg(target_os = "windows")]
+impl OcrBackend for WindowsNativeBackend {
+    fn name(&self) -> &str {
+        "windows-native"
+    }
+
+    fn engine(&self) -> screenpipe_db::OcrEngine {
+        screenpipe_db::OcrEngine::WindowsNative
+    }
+
+    fn recognize<'a>(
+        &'a self,
+        image: &'a DynamicImage,
+        _languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<OcrOutput>> {
+        Box::pin(async move {
+            let (text, _, _) = perform_ocr_windows(image).await?;
+            if text.is_empty() {
+                return Ok(OcrOutput::default());
+            }
+            let line = OcrLine::covering(image, text, 1.0);
+            Ok(OcrOutput::from_lines(vec![line]))
+        })
+    }
+}
+
+/// Unstructured's cloud api
+pub struct UnstructuredBackend;
+
+impl OcrBackend for UnstructuredBackend {
+    fn name(&self) -> &str {
+        "unstructured"
+    }
+
+    fn engine(&self) -> screenpipe_db::OcrEngine {
+        screenpipe_db::OcrEngine::Unstructured
+    }
+
+    fn recognize<'a>(
+        &'a self,
+        image: &'a DynamicImage,
+        languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<OcrOutput>> {
+        Box::pin(async move {
+            let (text, json_output, _) = perform_ocr_cloud(image, languages.to_vec()).await?;
+            let elements: Vec<Value> = serde_json::from_str(&json_output).unwrap_or_default();
+            // elements are located by the corners of a polygon
+            let lines = elements
+                .iter()
+                .filter_map(|element| {
+                    let text = element.get("text")?.as_str()?.to_string();
+                    let points = element
+                        .pointer("/metadata/coordinates/points")?
+                        .as_array()?;
+                    let (xs, ys): (Vec<f64>, Vec<f64>) = points
+                        .iter()
+                        .filter_map(|p| Some((p.get(0)?.as_f64()?, p.get(1)?.as_f64()?)))
+                        .unzip();
+                    let (left, top) = (
+                        xs.iter().copied().reduce(f64::min)?,
+                        ys.iter().copied().reduce(f64::min)?,
+                    );
+                    Some(OcrLine {
+                        text,
+                        left,
+                        top,
+                        width: xs.iter().copied().reduce(f64::max)? - left,
+                        height: ys.iter().copied().reduce(f64::max)? - top,
+                        confidence: 1.0,
+                    })
+                })
+                .collect::<Vec<_>>();
+            Ok(with_text(text, lines, image))
+        })
+    }
+}
+
+/// An http ocr service, see [`CustomOcrClient`]. While the service fails, images are OCR'd by
+/// the `fallback_engine` of the config when it has one.
+pub struct CustomBackend {
+    config: CustomOcrConfig,
+    client: CustomOcrClient,
+    fallback: Option<Arc<dyn OcrBackend>>,
+}
+
+impl CustomBackend {
+    pub fn new(config: CustomOcrConfig) -> Self {
+        let fallback = config.fallback_engine.as_deref().and_then(|name| {
+            let fallback = match name {
+                "custom" => Err(anyhow!("the custom engine can't be its own fallback")),
+                name => OcrBackendRegistry::default().create(name),
+            };
+            fallback
+                .map_err(|e| warn!("no fallback for custom ocr: {}", e))
+                .ok()
+        });
+        Self {
+            client: CustomOcrClient::new(config.clone()),
+            config,
+            fallback,
+        }
+    }
+
+    /// Falls back to `fallback` while the service fails, instead of the engine of the config
+    pub fn with_fallback(mut self, fallback: Arc<dyn OcrBackend>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Reads the config from `SCREENPIPE_CUSTOM_OCR_CONFIG`, the default one when unset or invalid
+    pub fn from_env() -> Self {
+        let config = match std::env::var("SCREENPIPE_CUSTOM_OCR_CONFIG") {
+            Ok(config_str) => serde_json::from_str(&config_str).unwrap_or_else(|e| {
+                warn!("failed to parse custom ocr config from env: {}", e);
+                CustomOcrConfig::default()
+            }),
+            Err(_) => CustomOcrConfig::default(),
+        };
+        Self::new(config)
+    }
+}
+
+impl OcrBackend for CustomBackend {
+    fn name(&self) -> &str {
+        "custom"
+    }
+
+    fn engine(&self) -> screenpipe_db::OcrEngine {
+        screenpipe_db::OcrEngine::Custom(self.config.clone().into())
+    }
+
+    fn is_available(&self) -> bool {
+        self.client.is_available()
+    }
+
+    fn recognize<'a>(
+        &'a self,
+        image: &'a DynamicImage,
+        languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<OcrOutput>> {
+        Box::pin(async move {
+            let (text, json_output, _) = match self.client.recognize(image, languages).await {
+                Ok(result) => result,
+                Err(e) => {
+                    let Some(fallback) = &self.fallback else {
+                        return Err(e);
+                    };
+                    debug!("custom ocr failed, using {}: {}", fallback.name(), e);
+                    CUSTOM_OCR_METRICS.record_fallback();
+                    let output = fallback.recognize(image, languages).await?;
+                    return Ok(OcrOutput {
+                        fallback_engine: Some(fallback.engine()),
+                        ..output
+                    });
+                }
+            };
+            let lines = lines_from_json(&json_output, image, BoxLayout::Detect);
+            Ok(with_text(text, lines, image))
+        })
+    }
+}
+
+impl From<OcrEngine> for Arc<dyn OcrBackend> {
+    fn from(engine: OcrEngine) -> Self {
+        match engine {
+            OcrEngine::Unstructured => Arc::new(UnstructuredBackend),
+            #[cfg(target_os = "windows")]
+            OcrEngine::WindowsNative => Arc::new(WindowsNativeBackend),
+            #[cfg(target_os = "macos")]
+            OcrEngine::AppleNative => Arc::new(AppleNativeBackend),
+            OcrEngine::Custom(config) => Arc::new(CustomBackend::new(config)),
+            _ => Arc::new(TesseractBackend),
+        }
+    }
+}
+
+type OcrBackendFactory = Arc<dyn Fn() -> Result<Arc<dyn OcrBackend>> + Send + Sync>;
+
+/// OCR engines by name, created when selected
+#[derive(Clone)]
+pub struct OcrBackendRegistry {
+    factories: BTreeMap<String, OcrBackendFactory>,
+}
+
+impl OcrBackendRegistry {
+    pub fn empty() -> Self {
+        OcrBackendRegistry {
+            factories: BTreeMap::new(),
+        }
+    }
+
+    /// Adds an engine, replacing the one registered under the same name
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn() -> Result<Arc<dyn OcrBackend>> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_string(), Arc::new(factory));
+    }
+
+    pub fn create(&self, name: &str) -> Result<Arc<dyn OcrBackend>> {
+        let factory = self.factories.get(name).ok_or_else(|| {
+            anyhow!(
+                "unknown ocr engine {}, available: {}",
+                name,
+                self.names().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        factory()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+}
+
+/// The engines built in for this platform
+impl Default for OcrBackendRegistry {
+    fn default() -> Self {
+        let mut registry = OcrBackendRegistry::empty();
+        registry.register("tesseract", || Ok(Arc::new(TesseractBackend)));
+        #[cfg(target_os = "macos")]
+        registry.register("apple-native", || Ok(Arc::new(AppleNativeBackend)));
+        #[cfg(target_os = "windows")]
+        registry.register("windows-native", || Ok(Arc::new(WindowsNativeBackend)));
+        registry.register("unstructured", || Ok(Arc::new(UnstructuredBackend)));
+        registry.register("custom", || Ok(Arc::new(CustomBackend::from_env())));
+        registry.register("onnx", || {
+            let backend = OnnxOcrBackend::from_env();
+            backend.check_models()?;
+            Ok(Arc::new(backend))
+        });
+        registry
+    }
+}
+
+/// Name of the engine used when none is selected
+pub fn default_ocr_backend_name() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "apple-native"
+    } else if cfg!(target_os = "windows") {
+        "windows-native"
+    } else {
+        "tesseract"
+    }
+}
+
+#[derive(Clone, Copy)]
+enum BoxLayout {
+    Pixels,
+    /// Fractions of the image with the origin at the bottom left, like apple vision
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    NormalizedFromBottom,
+    /// Pixels, or fractions of the image from the top left when every value is at most 1
+    Detect,
+}
+
+/// Lines of an engine reporting them as a json array of objects with `text`, a box in `left`,
+/// `top`, `width` and `height`, and a confidence in `conf` or `confidence`. Values can be numbers
+/// or strings, confidences above 1 are percents. Lines without a box are left out.
+fn lines_from_json(json_output: &str, image: &DynamicImage, layout: BoxLayout) -> Vec<OcrLine> {
+    let entries: Vec<Value> = serde_json::from_str(json_output).unwrap_or_default();
+    let (image_width, image_height) = image.dimensions();
+    let (image_width, image_height) = (image_width as f64, image_height as f64);
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let number = |key: &str| -> Option<f64> {
+                match entry.get(key)? {
+                    Value::Number(n) => n.as_f64(),
+                    Value::String(s) => s.trim().parse().ok(),
+                    _ => None,
+                }
+            };
+            let text = entry.get("text")?.as_str()?.to_string();
+            let (left, top, width, height) = (
+                number("left")?,
+                number("top")?,
+                number("width")?,
+                number("height")?,
+            );
+            let normalized = match layout {
+                BoxLayout::Pixels => false,
+                BoxLayout::NormalizedFromBottom => true,
+                BoxLayout::Detect => [left, top, width, height].iter().all(|v| *v <= 1.0),
+            };
+            let (left, top, width, height) = if normalized {
+                let top = match layout {
+                    BoxLayout::NormalizedFromBottom => 1.0 - top - height,
+                    _ => top,
+                };
+                (
+                    left * image_width,
+                    top * image_height,
+                    width * image_width,
+                    height * image_height,
+                )
+            } else {
+                (left, top, width, height)
+            };
+            let confidence = number("conf")
+                .or_else(|| number("confidence"))
+                .map_or(1.0, |c| if c > 1.0 { c / 100.0 } else { c });
+
+            Some(OcrLine {
+                text,
+                left,
+                top,
+                width,
+                height,
+                confidence: confidence.clamp(0.0, 1.0),
+            })
+        })
+        .collect()
+}
+
+/// Output keeping the text of the engine, with a line covering the image when it located none
+fn with_text(text: String, mut lines: Vec<OcrLine>, image: &DynamicImage) -> OcrOutput {
+    if lines.is_empty() && !text.is_empty() {
+        lines.push(OcrLine::covering(image, text.clone(), 1.0));
+    }
+    let output = OcrOutput::from_lines(lines);
+    OcrOutput { text, ..output }
+}
@@ -0,0 +1,421 @@
+//! Local OCR engine running paddle ocr models with onnx runtime on the cpu, without tesseract.
+//!
+//! A detection model scores every pixel of the image as text or not, connected areas of text
+//! become line boxes, and a recognition model reads each line as a sequence of characters
+//! decoded with ctc. The columns the characters were read at split lines into word boxes.
+
+use anyhow::{anyhow, Context, Result};
+use futures::future::BoxFuture;
+use image::imageops::FilterType;
+use image::{DynamicImage, RgbImage};
+use ndarray::Array4;
+use ort::{GraphOptimizationLevel, Session};
+use screenpipe_core::Language;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::{debug, error};
+
+use crate::ocr_backend::{OcrBackend, OcrLine, OcrOutput};
+
+const DETECTION_MODEL: &str = "ppocr-det.onnx";
+const RECOGNITION_MODEL: &str = "ppocr-rec-en.onnx";
+const DICTIONARY: &str = "ppocr-dict-en.txt";
+
+/// Height lines are read at by the recognition model
+const RECOGNITION_HEIGHT: u32 = 48;
+const MAX_RECOGNITION_WIDTH: u32 = 1600;
+
+/// Thresholds of the detection step, defaults of paddle ocr
+#[derive(Debug, Clone)]
+pub struct DetectionConfig {
+    /// Score above which a pixel is text
+    pub threshold: f32,
+    /// Mean score below which a box is dropped
+    pub box_threshold: f32,
+    /// How much boxes are grown back, the model shrinks text areas when training
+    pub unclip_ratio: f32,
+    /// Boxes with a side shorter than this, in pixels of the score map, are dropped
+    pub min_size: usize,
+    /// Longest side images are scaled down to before detection
+    pub max_side: u32,
+}
+
+impl Default for DetectionConfig {
+    fn default() -> Self {
+        DetectionConfig {
+            threshold: 0.3,
+            box_threshold: 0.6,
+            unclip_ratio: 1.5,
+            min_size: 3,
+            max_side: 960,
+        }
+    }
+}
+
+/// Box of a text line, in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextBox {
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+    /// Mean detection score of the pixels of the box
+    pub score: f32,
+}
+
+/// A character read by the recognition model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecodedChar {
+    /// Index in the model's classes, 0 is the ctc blank
+    pub class: usize,
+    /// Time step, or column of the line, it was read at
+    pub step: usize,
+    pub confidence: f32,
+}
+
+/// Boxes of the connected areas of `probs`, a `width` by `height` score map, in its pixels
+pub fn detect_boxes(
+    probs: &[f32],
+    width: usize,
+    height: usize,
+    config: &DetectionConfig,
+) -> Vec<TextBox> {
+    let mut visited = vec![false; probs.len()];
+    let mut boxes = Vec::new();
+    let mut stack = Vec::new();
+
+    for start in 0..probs.len().min(width * height) {
+        if visited[start] || probs[start] < config.threshold {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            (min_x, min_y, max_x, max_y) = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then(|| i + width),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if !visited[n] && probs[n] >= config.threshold {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+
+        let (box_width, box_height) = (max_x - min_x + 1, max_y - min_y + 1);
+        if box_width.min(box_height) < config.min_size {
+            continue;
+        }
+        let score = (min_y..=max_y)
+            .flat_map(|y| (min_x..=max_x).map(move |x| y * width + x))
+            .map(|i| probs[i])
+            .sum::<f32>()
+            / (box_width * box_height) as f32;
+        if score < config.box_threshold {
+            continue;
+        }
+
+        // grow the box back by the offset paddle ocr unclips polygons with
+        let (w, h) = (box_width as f64, box_height as f64);
+        let offset = w * h * config.unclip_ratio as f64 / (2.0 * (w + h));
+        let left = (min_x as f64 - offset).max(0.0);
+        let top = (min_y as f64 - offset).max(0.0);
+        boxes.push(TextBox {
+            left,
+            top,
+            width: ((max_x + 1) as f64 + offset).min(width as f64) - left,
+            height: ((max_y + 1) as f64 + offset).min(height as f64) - top,
+            score,
+        });
+    }
+
+    // reading order: top to bottom, then left to right within a line
+    boxes.sort_by(|a, b| {
+        let same_line = (a.top - b.top).abs() < a.height.min(b.height) / 2.0;
+        if same_line {
+            a.left.total_cmp(&b.left)
+        } else {
+            a.top.total_cmp(&b.top)
+        }
+    });
+    boxes
+}
+
+/// Greedy ctc decoding of `probs`, one row of `num_classes` scores per time step
+pub fn ctc_decode(probs: &[f32], num_classes: usize) -> Vec<DecodedChar> {
+    let mut chars = Vec::new();
+    let mut previous = 0;
+    for (step, row) in probs.chunks_exact(num_classes.max(1)).enumerate() {
+        let (class, confidence) = row
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((0, 0.0));
+        if class != 0 && class != previous {
+            chars.push(DecodedChar {
+                class,
+                step,
+                confidence,
+            });
+        }
+        previous = class;
+    }
+    chars
+}
+
+/// Words of a line read in `line`, placed by the steps their characters were read at out of
+/// `steps`. Class `i` is `dictionary[i - 1]`, the classes past the dictionary are spaces.
+pub fn words_from_chars(
+    chars: &[DecodedChar],
+    dictionary: &[String],
+    steps: usize,
+    line: &TextBox,
+) -> Vec<OcrLine> {
+    let step_width = line.width / steps.max(1) as f64;
+    let mut words = Vec::new();
+    let mut word: Vec<(&str, &DecodedChar)> = Vec::new();
+
+    let mut flush = |word: &mut Vec<(&str, &DecodedChar)>| {
+        let (Some((_, first)), Some((_, last))) = (word.first(), word.last()) else {
+            return;
+        };
+        let left = line.left + first.step as f64 * step_width;
+        words.push(OcrLine {
+            text: word.iter().map(|(text, _)| *text).collect(),
+            left,
+            top: line.top,
+            width: line.left + (last.step + 1) as f64 * step_width - left,
+            height: line.height,
+            confidence: word.iter().map(|(_, c)| c.confidence as f64).sum::<f64>()
+                / word.len() as f64,
+        });
+        word.clear();
+    };
+
+    for c in chars {
+        match c.class.checked_sub(1).and_then(|i| dictionary.get(i)) {
+            Some(text) if !text.trim().is_empty() => word.push((text.as_str(), c)),
+            _ => flush(&mut word),
+        }
+    }
+    flush(&mut word);
+    words
+}
+
+/// Characters of a paddle ocr dictionary, one per line
+pub fn parse_dictionary(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim_end_matches('\r').to_string())
+        .collect()
+}
+
+struct OnnxOcrModels {
+    detection: Session,
+    recognition: Session,
+    dictionary: Vec<String>,
+}
+
+/// Paddle ocr models run locally with onnx runtime
+pub struct OnnxOcrBackend {
+    models_dir: Option<PathBuf>,
+    config: DetectionConfig,
+    /// The error is kept as text when loading failed
+    models: OnceCell<std::result::Result<Arc<OnnxOcrModels>, String>>,
+}
+
+impl OnnxOcrBackend {
+    /// Models are read from `models_dir`, or from [`default_models_dir`] when `None`
+    pub fn new(models_dir: Option<PathBuf>, config: DetectionConfig) -> Self {
+        OnnxOcrBackend {
+            models_dir,
+            config,
+            models: OnceCell::new(),
+        }
+    }
+
+    /// Reads the models dir from `SCREENPIPE_ONNX_OCR_MODELS`, which lets other languages be
+    /// used with their recognition model and dictionary under the same file names
+    pub fn from_env() -> Self {
+        let models_dir = std::env::var_os("SCREENPIPE_ONNX_OCR_MODELS").map(PathBuf::from);
+        Self::new(models_dir, DetectionConfig::default())
+    }
+
+    /// Loaded on first use. A failure is kept, windows then fail right away rather than each
+    /// loading the models again.
+    async fn models(&self) -> Result<Arc<OnnxOcrModels>> {
+        self.models
+            .get_or_init(|| async {
+                self.load_models().await.map(Arc::new).map_err(|e| {
+                    error!("failed to load onnx ocr models: {:#}", e);
+                    format!("{:#}", e)
+                })
+            })
+            .await
+            .clone()
+            .map_err(|e| anyhow!("onnx ocr models are unavailable: {}", e))
+    }
+
+    /// Dir of the models, failing with where to put them when one is missing. Checked when the
+    /// engine is selected so a missing model stops screenpipe at startup.
+    pub fn check_models(&self) -> Result<PathBuf> {
+        let dir = match &self.models_dir {
+            Some(dir) => dir.clone(),
+            None => default_models_dir().ok_or_else(|| anyhow!("failed to get cache dir"))?,
+        };
+        for filename in [DETECTION_MODEL, RECOGNITION_MODEL, DICTIONARY] {
+            if !dir.join(filename).exists() {
+                return Err(anyhow!(
+                    "{} is missing from {:?}, put the paddle ocr models there or set SCREENPIPE_ONNX_OCR_MODELS",
+                    filename,
+                    dir
+                ));
+            }
+        }
+        Ok(dir)
+    }
+
+    async fn load_models(&self) -> Result<OnnxOcrModels> {
+        let dir = self.check_models()?;
+
+        debug!("loading onnx ocr models from {:?}", dir);
+        let dictionary = tokio::fs::read_to_string(dir.join(DICTIONARY))
+            .await
+            .with_context(|| format!("failed to read {:?}", dir.join(DICTIONARY)))?;
+        Ok(OnnxOcrModels {
+            detection: create_session(dir.join(DETECTION_MODEL))?,
+            recognition: create_session(dir.join(RECOGNITION_MODEL))?,
+            dictionary: parse_dictionary(&dictionary),
+        })
+    }
+}
+
+impl OcrBackend for OnnxOcrBackend {
+    fn name(&self) -> &str {
+        "onnx"
+    }
+
+    fn recognize<'a>(
+        &'a self,
+        image: &'a DynamicImage,
+        _languages: &'a [Language],
+    ) -> BoxFuture<'a, Result<OcrOutput>> {
+        Box::pin(async move {
+            let models = self.models().await?;
+            let image = image.to_rgb8();
+            let config = self.config.clone();
+            // inference is cpu bound, keep it off the runtime threads
+            tokio::task::spawn_blocking(move || run_ocr(&models, &image, &config))
+                .await
+                .map_err(|e| anyhow!("onnx ocr task failed: {}", e))?
+        })
+    }
+}
+
+fn create_session(path: impl AsRef<Path>) -> Result<Session> {
+    let session = Session::builder()?
+        .with_optimization_level(GraphOptimizationLevel::Level3)?
+        .with_intra_threads(1)?
+        .with_inter_threads(1)?
+        .commit_from_file(path.as_ref())?;
+    Ok(session)
+}
+
+fn run_ocr(
+    models: &OnnxOcrModels,
+    image: &RgbImage,
+    config: &DetectionConfig,
+) -> Result<OcrOutput> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Ok(OcrOutput::default());
+    }
+
+    // the detection model takes sides that are multiples of 32
+    let scale = (config.max_side as f64 / width.max(height) as f64).min(1.0);
+    let round = |side: u32| (((side as f64 * scale) / 32.0).round() as u32).max(1) * 32;
+    let (map_width, map_height) = (round(width), round(height));
+    let resized = image::imageops::resize(image, map_width, map_height, FilterType::Triangle);
+    let input = to_tensor(&resized, [0.485, 0.456, 0.406], [0.229, 0.224, 0.225]);
+
+    let outputs = models.detection.run(ort::inputs![input.view()]?)?;
+    let probs: Vec<f32> = outputs[0]
+        .try_extract_tensor::<f32>()?
+        .iter()
+        .copied()
+        .collect();
+    let (scale_x, scale_y) = (
+        width as f64 / map_width as f64,
+        height as f64 / map_height as f64,
+    );
+
+    let mut words = Vec::new();
+    for map_box in detect_boxes(&probs, map_width as usize, map_height as usize, config) {
+        let line = TextBox {
+            left: map_box.left * scale_x,
+            top: map_box.top * scale_y,
+            width: map_box.width * scale_x,
+            height: map_box.height * scale_y,
+            ..map_box
+        };
+        let crop = image::imageops::crop_imm(
+            image,
+            line.left as u32,
+            line.top as u32,
+            (line.width as u32).max(1),
+            (line.height as u32).max(1),
+        )
+        .to_image();
+        let crop_width = (crop.width() as f64 * RECOGNITION_HEIGHT as f64 / crop.height() as f64)
+            .ceil()
+            .clamp(
+                RECOGNITION_HEIGHT as f64 / 3.0,
+                MAX_RECOGNITION_WIDTH as f64,
+            );
+        let crop = image::imageops::resize(
+            &crop,
+            crop_width as u32,
+            RECOGNITION_HEIGHT,
+            FilterType::Triangle,
+        );
+        let input = to_tensor(&crop, [0.5; 3], [0.5; 3]);
+
+        let outputs = models.recognition.run(ort::inputs![input.view()]?)?;
+        let output = outputs[0].try_extract_tensor::<f32>()?;
+        let (steps, num_classes) = match output.shape() {
+            [_, steps, classes] => (*steps, *classes),
+            shape => return Err(anyhow!("unexpected recognition output shape {:?}", shape)),
+        };
+        let probs: Vec<f32> = output.iter().copied().collect();
+        let chars = ctc_decode(&probs, num_classes);
+        words.extend(words_from_chars(&chars, &models.dictionary, steps, &line));
+    }
+
+    Ok(OcrOutput::from_lines(words))
+}
+
+/// Normalized `[1, 3, height, width]` tensor of the image, paddle ocr models take bgr images
+fn to_tensor(image: &RgbImage, mean: [f32; 3], std: [f32; 3]) -> Array4<f32> {
+    let (width, height) = image.dimensions();
+    let mut tensor = Array4::zeros((1, 3, height as usize, width as usize));
+    for (x, y, pixel) in image.enumerate_pixels() {
+        for (channel, value) in pixel.0.iter().rev().enumerate() {
+            tensor[[0, channel, y as usize, x as usize]] =
+                (*value as f32 / 255.0 - mean[channel]) / std[channel];
+        }
+    }
+    tensor
+}
+
+/// Dir the models are read from when `SCREENPIPE_ONNX_OCR_MODELS` isn't set,
+/// `screenpipe/models/ppocr` in the cache dir
+pub fn default_models_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("screenpipe").join("models").join("ppocr"))
+}
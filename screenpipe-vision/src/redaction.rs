@@ -0,0 +1,205 @@
+//! Hides pii in the pixels of a frame before it is written to the video chunk.
+//!
+//! `remove_pii` only cleans the text stored in `ocr_text`, this uses the boxes in the ocr
+//! `text_json` to blur or black out the same text in the frame itself.
+
+use crate::core::{CaptureResult, WindowOcrResult};
+use clap::ValueEnum;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImage, GenericImageView, Rgba};
+use screenpipe_core::pii_removal::{detect_pii, pii_applies_to_app};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Extra pixels around a text box, ocr boxes are tight and anti-aliasing bleeds out of them
+const PADDING: f64 = 4.0;
+
+/// (left, top, width, height)
+type TextBox = (f64, f64, f64, f64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum RedactionMode {
+    /// Pixelate the region so the text can't be read
+    Blur,
+    /// Fill the region with black
+    BlackBox,
+}
+
+impl RedactionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RedactionMode::Blur => "blur",
+            RedactionMode::BlackBox => "black-box",
+        }
+    }
+}
+
+/// A redacted rectangle, in pixels of the full frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RedactedRegion {
+    pub pii_type: &'static str,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Redacts the pii found by ocr in the frame image and records the regions on each window
+/// result. Returns how many regions were redacted.
+pub fn redact_frame(result: &mut CaptureResult, mode: RedactionMode) -> usize {
+    let (frame_width, frame_height) = result.image.dimensions();
+    let mut redacted = 0;
+    for window in &mut result.window_ocr_results {
+        window.redacted_regions = pii_regions(window, frame_width, frame_height);
+        for region in &window.redacted_regions {
+            redact_region(&mut result.image, region, mode);
+        }
+        redacted += window.redacted_regions.len();
+    }
+    redacted
+}
+
+/// Regions of the frame showing pii in this window.
+///
+/// Lines are redacted as a whole. When the window text has pii but no line box matches, e.g.
+/// with engines that don't report boxes, the whole window is redacted, and the whole frame
+/// when the platform didn't report where the window is. Windows recorded without text because
+/// their OCR failed can't be searched for pii, they are redacted whole as `UNKNOWN`.
+pub fn pii_regions(
+    window: &WindowOcrResult,
+    frame_width: u32,
+    frame_height: u32,
+) -> Vec<RedactedRegion> {
+    if !pii_applies_to_app(&window.app_name) {
+        return Vec::new();
+    }
+    let window_pii = if window.ocr_deferred && window.text.is_empty() {
+        "UNKNOWN"
+    } else {
+        match detect_pii(&window.text) {
+            Some(pii_type) => pii_type,
+            None => return Vec::new(),
+        }
+    };
+    let Some((offset_x, offset_y)) = window.position else {
+        warn!(
+            "pii found in window {} but its position is unknown, redacting the whole frame",
+            window.window_name
+        );
+        return vec![RedactedRegion {
+            pii_type: window_pii,
+            x: 0,
+            y: 0,
+            width: frame_width,
+            height: frame_height,
+        }];
+    };
+
+    let (window_width, window_height) = window.image.dimensions();
+    let mut boxes: Vec<(&'static str, TextBox)> = window
+        .text_json
+        .iter()
+        .filter_map(|entry| {
+            let pii_type = detect_pii(entry.get("text")?)?;
+            let text_box = text_box(entry, window_width, window_height)?;
+            Some((pii_type, pad(text_box)))
+        })
+        .collect();
+    if boxes.is_empty() {
+        boxes.push((
+            window_pii,
+            (0.0, 0.0, window_width as f64, window_height as f64),
+        ));
+    }
+
+    boxes
+        .into_iter()
+        .filter_map(|(pii_type, (left, top, width, height))| {
+            // clip to the frame, windows can be partly off screen or on another monitor
+            let x0 = (offset_x as f64 + left).floor().max(0.0);
+            let y0 = (offset_y as f64 + top).floor().max(0.0);
+            let x1 = (offset_x as f64 + left + width)
+                .ceil()
+                .min(frame_width as f64);
+            let y1 = (offset_y as f64 + top + height)
+                .ceil()
+                .min(frame_height as f64);
+            if x1 <= x0 || y1 <= y0 {
+                return None;
+            }
+            Some(RedactedRegion {
+                pii_type,
+                x: x0 as u32,
+                y: y0 as u32,
+                width: (x1 - x0) as u32,
+                height: (y1 - y0) as u32,
+            })
+        })
+        .collect()
+}
+
+/// Box of an ocr line as (left, top, width, height) in pixels of the window image.
+///
+/// Tesseract reports pixels, apple vision reports values normalized to the image with the
+/// origin at the bottom left.
+fn text_box(
+    entry: &HashMap<String, String>,
+    window_width: u32,
+    window_height: u32,
+) -> Option<TextBox> {
+    let value = |key: &str| -> Option<f64> { entry.get(key)?.parse().ok() };
+    let (left, top, width, height) = (
+        value("left")?,
+        value("top")?,
+        value("width")?,
+        value("height")?,
+    );
+    if width <= 0.0 || height <= 0.0 {
+        return None;
+    }
+    if width <= 1.0 && height <= 1.0 {
+        let (window_width, window_height) = (window_width as f64, window_height as f64);
+        return Some((
+            left * window_width,
+            (1.0 - top - height) * window_height,
+            width * window_width,
+            height * window_height,
+        ));
+    }
+    Some((left, top, width, height))
+}
+
+fn pad((left, top, width, height): TextBox) -> TextBox {
+    (
+        left - PADDING,
+        top - PADDING,
+        width + 2.0 * PADDING,
+        height + 2.0 * PADDING,
+    )
+}
+
+pub fn redact_region(image: &mut DynamicImage, region: &RedactedRegion, mode: RedactionMode) {
+    match mode {
+        RedactionMode::BlackBox => {
+            for y in region.y..region.y + region.height {
+                for x in region.x..region.x + region.width {
+                    image.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+                }
+            }
+        }
+        RedactionMode::Blur => {
+            // blocks of half a line of text leave nothing readable, downscaling keeps this
+            // cheap even when a whole window is redacted
+            let block = (region.height / 2).clamp(8, 32);
+            let pixelated = image
+                .crop_imm(region.x, region.y, region.width, region.height)
+                .resize_exact(
+                    (region.width / block).max(1),
+                    (region.height / block).max(1),
+                    FilterType::Triangle,
+                )
+                .resize_exact(region.width, region.height, FilterType::Triangle);
+            imageops::replace(image, &pixelated, region.x as i64, region.y as i64);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! OCRs only the parts of a window that changed since the previous frame.
+//!
+//! Each window is split in tiles compared with its previous image. Changed tiles are grouped in
+//! regions, grown to the text lines of the previous result they cut through, and OCR'd on their
+//! own. Lines outside them are kept from the previous result, so a ticking clock doesn't OCR a
+//! whole screen again. Engines that don't locate their lines report one covering the window,
+//! which is then always OCR'd in full.
+
+use image::GrayImage;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoiConfig {
+    /// Side of the tiles compared between frames, in pixels
+    pub tile_size: u32,
+    /// Luma difference above which a pixel changed, so compression noise isn't a change
+    pub pixel_threshold: u8,
+    /// Share of the window above which changed regions are OCR'd as a whole window instead
+    pub max_changed_ratio: f64,
+}
+
+impl Default for RoiConfig {
+    fn default() -> Self {
+        RoiConfig {
+            tile_size: 64,
+            pixel_threshold: 24,
+            max_changed_ratio: 0.5,
+        }
+    }
+}
+
+/// A rectangle of a window image, in pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    fn intersects(&self, other: &Region) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    fn union(&self, other: &Region) -> Region {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        Region {
+            x,
+            y,
+            width: self.right().max(other.right()) - x,
+            height: self.bottom().max(other.bottom()) - y,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RoiPlan {
+    /// Nothing changed, the previous result still holds
+    Unchanged,
+    /// Only these regions need OCR
+    Regions(Vec<Region>),
+    /// The window has to be OCR'd as a whole
+    Full,
+}
+
+/// Decides what of the current image of a window needs OCR given its previous image and the
+/// lines found in it
+pub fn plan_roi_ocr(
+    previous: &GrayImage,
+    current: &GrayImage,
+    previous_blocks: &[HashMap<String, String>],
+    config: &RoiConfig,
+) -> RoiPlan {
+    if previous.dimensions() != current.dimensions() || config.tile_size == 0 {
+        return RoiPlan::Full;
+    }
+    let Some(line_boxes) = previous_blocks
+        .iter()
+        .map(pixel_box)
+        .collect::<Option<Vec<_>>>()
+    else {
+        return RoiPlan::Full;
+    };
+
+    let (width, height) = current.dimensions();
+    let tiles = changed_tiles(previous, current, config);
+    if tiles.is_empty() {
+        return RoiPlan::Unchanged;
+    }
+    let mut regions: Vec<Region> = tiles
+        .into_iter()
+        .map(|(column, row)| {
+            // one tile of margin for text crossing the tile border
+            let x = column.saturating_sub(1) * config.tile_size;
+            let y = row.saturating_sub(1) * config.tile_size;
+            Region {
+                x,
+                y,
+                width: ((column + 2) * config.tile_size).min(width) - x,
+                height: ((row + 2) * config.tile_size).min(height) - y,
+            }
+        })
+        .collect();
+
+    // grow regions to the lines they cut through and merge those that overlap, until stable
+    loop {
+        let mut grown = false;
+        for region in regions.iter_mut() {
+            for line in &line_boxes {
+                let line = clip(line, width, height);
+                if line.intersects(region) && region.union(&line) != *region {
+                    *region = region.union(&line);
+                    grown = true;
+                }
+            }
+        }
+        let mut merged: Vec<Region> = Vec::with_capacity(regions.len());
+        for region in regions {
+            match merged.iter_mut().find(|m| m.intersects(&region)) {
+                Some(existing) => {
+                    *existing = existing.union(&region);
+                    grown = true;
+                }
+                None => merged.push(region),
+            }
+        }
+        regions = merged;
+        if !grown {
+            break;
+        }
+    }
+
+    let changed: u64 = regions.iter().map(Region::area).sum();
+    if changed as f64 > config.max_changed_ratio * (width as u64 * height as u64) as f64 {
+        return RoiPlan::Full;
+    }
+    RoiPlan::Regions(regions)
+}
+
+/// Lines of the previous result outside the regions with the lines found in them, in reading
+/// order. `None` when a region result has lines without pixel boxes.
+pub fn merge_blocks(
+    previous_blocks: &[HashMap<String, String>],
+    region_blocks: Vec<(Region, Vec<HashMap<String, String>>)>,
+) -> Option<Vec<HashMap<String, String>>> {
+    let regions: Vec<Region> = region_blocks.iter().map(|(region, _)| *region).collect();
+    let mut blocks: Vec<HashMap<String, String>> = previous_blocks
+        .iter()
+        .filter(|block| {
+            pixel_box(block).is_some_and(|line| !regions.iter().any(|r| r.intersects(&line)))
+        })
+        .cloned()
+        .collect();
+
+    for (region, found) in region_blocks {
+        for mut block in found {
+            let line = pixel_box(&block)?;
+            block.insert("left".to_string(), (line.x + region.x).to_string());
+            block.insert("top".to_string(), (line.y + region.y).to_string());
+            blocks.push(block);
+        }
+    }
+
+    blocks.sort_by_key(|block| pixel_box(block).map(|line| (line.y, line.x)));
+    Some(blocks)
+}
+
+/// Text of merged lines, joined like Tesseract joins words
+pub fn blocks_text(blocks: &[HashMap<String, String>]) -> String {
+    blocks
+        .iter()
+        .filter_map(|block| block.get("text"))
+        .filter(|text| !text.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Mean confidence of merged lines
+pub fn blocks_confidence(blocks: &[HashMap<String, String>]) -> Option<f64> {
+    let confidences: Vec<f64> = blocks
+        .iter()
+        .filter_map(|block| block.get("conf")?.parse().ok())
+        .collect();
+    (!confidences.is_empty()).then(|| confidences.iter().sum::<f64>() / confidences.len() as f64)
+}
+
+/// Tiles with a pixel changed by more than the threshold, as (column, row)
+fn changed_tiles(previous: &GrayImage, current: &GrayImage, config: &RoiConfig) -> Vec<(u32, u32)> {
+    let (width, height) = current.dimensions();
+    let tile = config.tile_size;
+    let mut tiles = Vec::new();
+    for row in 0..height.div_ceil(tile) {
+        for column in 0..width.div_ceil(tile) {
+            let changed = (row * tile..((row + 1) * tile).min(height)).any(|y| {
+                (column * tile..((column + 1) * tile).min(width)).any(|x| {
+                    previous.get_pixel(x, y)[0].abs_diff(current.get_pixel(x, y)[0])
+                        > config.pixel_threshold
+                })
+            });
+            if changed {
+                tiles.push((column, row));
+            }
+        }
+    }
+    tiles
+}
+
+/// Box of a line in pixels of the window, `None` without box or with a box normalized to the
+/// window
+fn pixel_box(block: &HashMap<String, String>) -> Option<Region> {
+    let value = |key: &str| -> Option<f64> { block.get(key)?.parse().ok() };
+    let (left, top, width, height) = (
+        value("left")?,
+        value("top")?,
+        value("width")?,
+        value("height")?,
+    );
+    if width <= 1.0 && height <= 1.0 {
+        return None;
+    }
+    Some(Region {
+        x: left.max(0.0) as u32,
+        y: top.max(0.0) as u32,
+        width: width.max(0.0) as u32,
+        height: height.max(0.0) as u32,
+    })
+}
+
+fn clip(region: &Region, width: u32, height: u32) -> Region {
+    let (x, y) = (region.x.min(width), region.y.min(height));
+    Region {
+        x,
+        y,
+        width: region.right().min(width) - x,
+        height: region.bottom().min(height) - y,
+    }
+}
+
+/// Last OCR result of a window
+pub struct CachedWindow {
+    pub image: GrayImage,
+    pub text: String,
+    pub text_json: Vec<HashMap<String, String>>,
+    pub confidence: Option<f64>,
+}
+
+/// Last OCR result of each window of a monitor, by app and window name
+#[derive(Default)]
+pub struct RoiOcrCache {
+    pub config: RoiConfig,
+    windows: HashMap<(String, String), CachedWindow>,
+}
+
+impl RoiOcrCache {
+    pub fn new(config: RoiConfig) -> Self {
+        RoiOcrCache {
+            config,
+            windows: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, app_name: &str, window_name: &str) -> Option<&CachedWindow> {
+        self.windows
+            .get(&(app_name.to_string(), window_name.to_string()))
+    }
+
+    pub fn insert(&mut self, app_name: &str, window_name: &str, window: CachedWindow) {
+        self.windows
+            .insert((app_name.to_string(), window_name.to_string()), window);
+    }
+
+    /// Forgets windows that are no longer on screen
+    pub fn retain(&mut self, windows: &[(String, String)]) {
+        self.windows.retain(|key, _| windows.contains(key));
+    }
+}
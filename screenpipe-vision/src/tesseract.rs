@@ -63,6 +63,8 @@ fn data_output_to_json(data_output: &DataOutput) -> String {
     let mut current_conf = 0.0;
     let mut word_count = 0;
     let mut last_word_num = 0;
+    // (left, top, right, bottom) of the words in the current line
+    let mut line_bounds: Option<(i32, i32, i32, i32)> = None;
 
     for record in &data_output.data {
         if record.word_num == 0 && !current_line.is_empty() {
@@ -81,6 +83,7 @@ fn data_output_to_json(data_output: &DataOutput) -> String {
                     record.line_num
                 ),
             );
+            insert_bounds(&mut line_data, line_bounds.take());
             lines.push(line_data);
             current_line.clear();
             current_conf = 0.0;
@@ -93,6 +96,16 @@ fn data_output_to_json(data_output: &DataOutput) -> String {
             current_line.push_str(&record.text);
             current_conf += record.conf;
             word_count += 1;
+            let (right, bottom) = (record.left + record.width, record.top + record.height);
+            line_bounds = Some(match line_bounds {
+                Some((l, t, r, b)) => (
+                    l.min(record.left),
+                    t.min(record.top),
+                    r.max(right),
+                    b.max(bottom),
+                ),
+                None => (record.left, record.top, right, bottom),
+            });
         }
         last_word_num = record.word_num;
     }
@@ -101,12 +114,23 @@ fn data_output_to_json(data_output: &DataOutput) -> String {
         let mut line_data = HashMap::new();
         line_data.insert("text".to_string(), current_line);
         line_data.insert("confidence".to_string(), format!("{:.2}", avg_conf));
+        insert_bounds(&mut line_data, line_bounds);
         lines.push(line_data);
     }
 
     serde_json::to_string_pretty(&lines).unwrap()
 }
 
+/// Adds the pixel box of a line, used to redact it in the frame
+fn insert_bounds(line_data: &mut HashMap<String, String>, bounds: Option<(i32, i32, i32, i32)>) {
+    if let Some((left, top, right, bottom)) = bounds {
+        line_data.insert("left".to_string(), left.to_string());
+        line_data.insert("top".to_string(), top.to_string());
+        line_data.insert("width".to_string(), (right - left).to_string());
+        line_data.insert("height".to_string(), (bottom - top).to_string());
+    }
+}
+
 fn calculate_overall_confidence(data_output: &DataOutput) -> f64 {
     let total_conf: f32 = data_output.data.iter().map(|record| record.conf).sum();
     let count = data_output.data.len();
@@ -43,6 +43,8 @@ impl From<screenpipe_db::OcrEngine> for OcrEngine {
             screenpipe_db::OcrEngine::WindowsNative => OcrEngine::WindowsNative,
             screenpipe_db::OcrEngine::AppleNative => OcrEngine::AppleNative,
             screenpipe_db::OcrEngine::Custom(config) => OcrEngine::Custom(config.into()),
+            // engines plugged in as backends have no built-in equivalent
+            screenpipe_db::OcrEngine::Other(_) => OcrEngine::default(),
         }
     }
 }
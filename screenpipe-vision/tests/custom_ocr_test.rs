@@ -62,11 +62,253 @@ rm -rf venv app.py
 
 #[cfg(test)]
 mod tests {
-    use image::GenericImageView;
+    use anyhow::Result;
+    use futures::future::BoxFuture;
+    use image::{DynamicImage, GenericImageView};
     use screenpipe_core::Language;
-    use screenpipe_vision::custom_ocr::{perform_ocr_custom, CustomOcrConfig};
+    use screenpipe_vision::capture_screenshot_by_window::CapturedWindow;
+    use screenpipe_vision::core::OcrTaskData;
+    use screenpipe_vision::custom_ocr::{
+        perform_ocr_custom, CircuitBreaker, CircuitState, CustomOcrConfig,
+    };
+    use screenpipe_vision::ocr_backend::{CustomBackend, OcrOutput};
     use screenpipe_vision::utils::OcrEngine;
+    use screenpipe_vision::{process_ocr_task, OcrBackend};
+    use serde_json::{json, Value};
     use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    type Requests = Arc<Mutex<Vec<Value>>>;
+
+    /// Local ocr service answering the n-th request with `respond(n, payload)`, a status and a
+    /// body. Returns its url and the payloads it received.
+    async fn mock_service(respond: fn(usize, &Value) -> (u16, Value)) -> (String, Requests) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/ocr", listener.local_addr().unwrap());
+        let requests: Requests = Arc::default();
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 8192];
+                let payload = loop {
+                    let n = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break serde_json::from_str::<Value>(body).unwrap_or_default();
+                    }
+                };
+                let (status, body) = {
+                    let mut requests = received.lock().unwrap();
+                    requests.push(payload.clone());
+                    respond(requests.len() - 1, &payload)
+                };
+                let body = body.to_string();
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, requests)
+    }
+
+    fn ocr_response(text: &str) -> Value {
+        json!({
+            "text": text,
+            "structured_data": [
+                {"text": text, "left": 10, "top": 20, "width": 100, "height": 18, "conf": 0.9}
+            ],
+            "confidence": 0.9,
+        })
+    }
+
+    fn config(api_url: String) -> CustomOcrConfig {
+        CustomOcrConfig {
+            api_url,
+            ..Default::default()
+        }
+    }
+
+    struct FixedBackend;
+
+    impl OcrBackend for FixedBackend {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn recognize<'a>(
+            &'a self,
+            _image: &'a DynamicImage,
+            _languages: &'a [Language],
+        ) -> BoxFuture<'a, Result<OcrOutput>> {
+            Box::pin(async { Ok(OcrOutput::default()) })
+        }
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+
+        assert!(!breaker.record_failure(start));
+        assert!(breaker.allow(start));
+        assert!(breaker.record_failure(start));
+        assert_eq!(breaker.state(start), CircuitState::Open);
+        assert!(!breaker.allow(start + Duration::from_secs(10)));
+
+        // a single request checks whether the service is back, and reopens on failure
+        let later = start + Duration::from_secs(31);
+        assert!(breaker.allow(later));
+        assert!(!breaker.allow(later));
+        assert!(breaker.record_failure(later));
+        assert!(!breaker.allow(later + Duration::from_secs(10)));
+
+        let recovered = later + Duration::from_secs(31);
+        assert!(breaker.allow(recovered));
+        breaker.record_success();
+        assert_eq!(breaker.state(recovered), CircuitState::Closed);
+        assert!(breaker.allow(recovered));
+    }
+
+    #[tokio::test]
+    async fn test_custom_ocr_does_not_retry_failed_requests() {
+        let (url, requests) = mock_service(|n, _| match n {
+            0 => (503, json!({"error": "overloaded"})),
+            _ => (200, ocr_response("hello")),
+        })
+        .await;
+        let backend = CustomBackend::new(config(url));
+        let image = DynamicImage::new_rgb8(64, 32);
+
+        assert!(backend
+            .recognize(&image, &[Language::English])
+            .await
+            .is_err());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        let output = backend
+            .recognize(&image, &[Language::English])
+            .await
+            .unwrap();
+        assert_eq!(output.text, "hello");
+        assert_eq!(output.lines[0].left, 10.0);
+        assert!(output.fallback_engine.is_none());
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_windows_are_deferred_while_service_fails() {
+        let (url, _) = mock_service(|_, _| (500, json!({"error": "down"}))).await;
+        let backend = CustomBackend::new(config(url));
+        let (result_tx, mut result_rx) = mpsc::channel(1);
+        let window = |window_name: &str| CapturedWindow {
+            image: DynamicImage::new_rgb8(64, 32),
+            app_name: "editor".to_string(),
+            window_name: window_name.to_string(),
+            process_id: 0,
+            is_focused: false,
+            position: None,
+        };
+
+        process_ocr_task(
+            OcrTaskData {
+                image: DynamicImage::new_rgb8(128, 64),
+                window_images: vec![window("a"), window("b")],
+                frame_number: 1,
+                timestamp: Instant::now(),
+                result_tx,
+            },
+            &backend,
+            vec![],
+            None,
+        )
+        .await
+        .unwrap();
+
+        // the frame is kept, its windows are OCR'd again later
+        let result = result_rx.recv().await.unwrap();
+        assert_eq!(result.window_ocr_results.len(), 2);
+        for window in result.window_ocr_results {
+            assert!(window.text.is_empty());
+            assert!(window.ocr_deferred);
+            assert!(window.fallback_engine.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_ocr_batches_concurrent_images() {
+        let (url, requests) = mock_service(|_, payload| {
+            let results = payload["images"]
+                .as_array()
+                .map(|images| (0..images.len()).map(|i| ocr_response(&i.to_string())))
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+            (200, json!({ "results": results }))
+        })
+        .await;
+        let backend = CustomBackend::new(CustomOcrConfig {
+            batch_size: 3,
+            batch_timeout_ms: 500,
+            ..config(url)
+        });
+
+        let image = DynamicImage::new_rgb8(64, 32);
+        let (a, b, c) = tokio::join!(
+            backend.recognize(&image, &[]),
+            backend.recognize(&image, &[]),
+            backend.recognize(&image, &[]),
+        );
+        let mut texts = [a, b, c].map(|output| output.unwrap().text);
+        texts.sort();
+        assert_eq!(texts, ["0", "1", "2"]);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0]["images"].as_array().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_custom_ocr_falls_back_while_service_fails() {
+        let (url, requests) = mock_service(|_, _| (500, json!({"error": "down"}))).await;
+        let backend = CustomBackend::new(CustomOcrConfig {
+            failure_threshold: 1,
+            circuit_open_ms: 60_000,
+            ..config(url)
+        })
+        .with_fallback(Arc::new(FixedBackend));
+
+        let image = DynamicImage::new_rgb8(64, 32);
+        for _ in 0..3 {
+            let output = backend.recognize(&image, &[]).await.unwrap();
+            assert!(matches!(
+                output.fallback_engine,
+                Some(screenpipe_db::OcrEngine::Other(name)) if name == "fixed"
+            ));
+        }
+        // the service isn't called again once it failed
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
 
     #[tokio::test]
     #[ignore]
@@ -93,6 +335,7 @@ mod tests {
             api_url: "http://localhost:8000/ocr".to_string(),
             api_key: "".to_string(),
             timeout_ms: 5000,
+            ..Default::default()
         };
         let ocr_engine = OcrEngine::Custom(config);
 
@@ -139,6 +382,7 @@ mod tests {
             api_url: "http://localhost:8000/ocr".to_string(),
             api_key: "".to_string(),
             timeout_ms: 30000000,
+            ..Default::default()
         };
         let ocr_engine = OcrEngine::Custom(config);
 
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use futures::future::BoxFuture;
+    use image::DynamicImage;
+    use screenpipe_core::Language;
+    use screenpipe_db::{OcrEngine, OcrTextBlock};
+    use screenpipe_vision::ocr_backend::{OcrLine, OcrOutput};
+    use screenpipe_vision::{OcrBackend, OcrBackendRegistry};
+    use std::sync::Arc;
+
+    struct FixedBackend;
+
+    impl OcrBackend for FixedBackend {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn recognize<'a>(
+            &'a self,
+            _image: &'a DynamicImage,
+            _languages: &'a [Language],
+        ) -> BoxFuture<'a, Result<OcrOutput>> {
+            Box::pin(async move {
+                Ok(OcrOutput::from_lines(vec![
+                    OcrLine {
+                        text: "hello world".to_string(),
+                        left: 10.0,
+                        top: 20.0,
+                        width: 120.0,
+                        height: 18.0,
+                        confidence: 0.9,
+                    },
+                    OcrLine {
+                        text: "screenpipe".to_string(),
+                        left: 10.0,
+                        top: 50.0,
+                        width: 90.0,
+                        height: 18.0,
+                        confidence: 0.7,
+                    },
+                ]))
+            })
+        }
+    }
+
+    #[test]
+    fn test_registry_lists_builtin_engines() {
+        let registry = OcrBackendRegistry::default();
+        let names: Vec<&str> = registry.names().collect();
+        assert!(names.contains(&"tesseract"));
+        assert!(names.contains(&"custom"));
+        assert!(names.contains(&"onnx"));
+
+        let error = registry.create("paddle-onnx").err().unwrap();
+        assert!(error.to_string().contains("tesseract"));
+    }
+
+    #[tokio::test]
+    async fn test_registered_backend_output() {
+        let mut registry = OcrBackendRegistry::default();
+        registry.register("fixed", || Ok(Arc::new(FixedBackend)));
+
+        let backend = registry.create("fixed").unwrap();
+        assert!(matches!(backend.engine(), OcrEngine::Other(name) if name == "fixed"));
+        assert_eq!(backend.engine().to_string(), "fixed");
+
+        let output = backend
+            .recognize(&DynamicImage::new_rgb8(200, 100), &[])
+            .await
+            .unwrap();
+        assert_eq!(output.text, "hello world screenpipe");
+        assert!((output.confidence.unwrap() - 0.8).abs() < 1e-9);
+
+        // search highlights read text_json as ocr text blocks
+        let json = serde_json::to_string(&output.text_json()).unwrap();
+        let blocks: Vec<OcrTextBlock> = serde_json::from_str(&json).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].text, "screenpipe");
+        assert_eq!(blocks[1].line_num, "1");
+        assert_eq!(
+            (blocks[1].left.as_str(), blocks[1].top.as_str()),
+            ("10", "50")
+        );
+        assert_eq!(blocks[1].conf, "0.700");
+    }
+}
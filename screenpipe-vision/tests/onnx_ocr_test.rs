@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use image::DynamicImage;
+    use screenpipe_vision::onnx_ocr::{
+        ctc_decode, detect_boxes, parse_dictionary, words_from_chars, DetectionConfig,
+        OnnxOcrBackend, TextBox,
+    };
+    use screenpipe_vision::OcrBackend;
+
+    #[test]
+    fn test_detect_boxes() {
+        let (width, height) = (64, 32);
+        let mut probs = vec![0.0; width * height];
+        // a line on the right, above a line on the left, and a speck of noise
+        for (x0, y0, w, h) in [(40, 2, 20, 6), (4, 20, 30, 6), (60, 28, 2, 2)] {
+            for y in y0..y0 + h {
+                for x in x0..x0 + w {
+                    probs[y * width + x] = 0.9;
+                }
+            }
+        }
+
+        let boxes = detect_boxes(&probs, width, height, &DetectionConfig::default());
+        assert_eq!(boxes.len(), 2);
+        assert!(boxes[0].left > boxes[1].left);
+
+        // boxes are grown back around the scored pixels
+        let line = boxes[1];
+        assert!(line.left < 4.0 && line.top < 20.0);
+        assert!(line.left + line.width > 34.0 && line.top + line.height > 26.0);
+        assert!((line.score - 0.9).abs() < 1e-6);
+
+        // faint areas are not text
+        let faint = vec![0.4; width * height];
+        assert!(detect_boxes(&faint, width, height, &DetectionConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_ctc_decode_into_words() {
+        let dictionary = parse_dictionary("h\ni\nm\ne\n");
+        let num_classes = dictionary.len() + 2;
+        // blank, h, h, i, blank, space, m, e, blank, blank
+        let steps = [0, 1, 1, 2, 0, 5, 3, 4, 0, 0];
+        let probs: Vec<f32> = steps
+            .iter()
+            .flat_map(|class| (0..num_classes).map(move |c| if c == *class { 0.8 } else { 0.05 }))
+            .collect();
+
+        let chars = ctc_decode(&probs, num_classes);
+        assert_eq!(
+            chars.iter().map(|c| c.class).collect::<Vec<_>>(),
+            vec![1, 2, 5, 3, 4]
+        );
+
+        let line = TextBox {
+            left: 100.0,
+            top: 20.0,
+            width: 200.0,
+            height: 16.0,
+            score: 0.9,
+        };
+        let words = words_from_chars(&chars, &dictionary, steps.len(), &line);
+        assert_eq!(words.len(), 2);
+        assert_eq!(
+            (words[0].text.as_str(), words[1].text.as_str()),
+            ("hi", "me")
+        );
+        // each step is 20 pixels of the line
+        assert_eq!((words[0].left, words[0].width), (120.0, 60.0));
+        assert_eq!((words[1].left, words[1].width), (220.0, 40.0));
+        assert_eq!((words[1].top, words[1].height), (20.0, 16.0));
+        assert!((words[0].confidence - 0.8).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_missing_models_are_not_loaded_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend =
+            OnnxOcrBackend::new(Some(dir.path().to_path_buf()), DetectionConfig::default());
+        let image = DynamicImage::new_rgb8(32, 32);
+
+        let error = backend.check_models().unwrap_err();
+        assert!(error.to_string().contains("ppocr-det.onnx is missing"));
+        let error = backend.recognize(&image, &[]).await.unwrap_err();
+        assert!(error.to_string().contains("ppocr-det.onnx is missing"));
+
+        // models added later are only picked up on the next start
+        for filename in ["ppocr-det.onnx", "ppocr-rec-en.onnx", "ppocr-dict-en.txt"] {
+            std::fs::write(dir.path().join(filename), b"").unwrap();
+        }
+        assert_eq!(backend.check_models().unwrap(), dir.path());
+        let error = backend.recognize(&image, &[]).await.unwrap_err();
+        assert!(error.to_string().contains("ppocr-det.onnx is missing"));
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+    use screenpipe_vision::core::WindowOcrResult;
+    use screenpipe_vision::redaction::{redact_frame, RedactionMode};
+    use screenpipe_vision::CaptureResult;
+    use std::collections::HashMap;
+    use std::time::Instant;
+
+    fn line(
+        text: &str,
+        left: &str,
+        top: &str,
+        width: &str,
+        height: &str,
+    ) -> HashMap<String, String> {
+        HashMap::from([
+            ("text".to_string(), text.to_string()),
+            ("left".to_string(), left.to_string()),
+            ("top".to_string(), top.to_string()),
+            ("width".to_string(), width.to_string()),
+            ("height".to_string(), height.to_string()),
+        ])
+    }
+
+    fn window(
+        text_json: Vec<HashMap<String, String>>,
+        position: Option<(i32, i32)>,
+    ) -> WindowOcrResult {
+        let text = text_json
+            .iter()
+            .map(|line| line["text"].clone())
+            .collect::<Vec<_>>()
+            .join("\n");
+        WindowOcrResult {
+            image: DynamicImage::ImageRgba8(RgbaImage::new(400, 200)),
+            window_name: "checkout".to_string(),
+            app_name: "browser".to_string(),
+            text,
+            text_json,
+            focused: true,
+            confidence: 1.0,
+            browser_url: None,
+            position,
+            redacted_regions: Vec::new(),
+            fallback_engine: None,
+            ocr_deferred: false,
+        }
+    }
+
+    fn frame(windows: Vec<WindowOcrResult>) -> CaptureResult {
+        CaptureResult {
+            image: DynamicImage::ImageRgba8(RgbaImage::from_pixel(800, 600, Rgba([255; 4]))),
+            frame_number: 0,
+            timestamp: Instant::now(),
+            window_ocr_results: windows,
+        }
+    }
+
+    #[test]
+    fn test_pixel_boxes_are_redacted_in_frame_coordinates() {
+        let mut result = frame(vec![window(
+            vec![
+                line("Order summary", "10", "10", "120", "20"),
+                line("Card 4111 1111 1111 1111", "10", "50", "200", "20"),
+            ],
+            Some((100, 100)),
+        )]);
+
+        assert_eq!(redact_frame(&mut result, RedactionMode::BlackBox), 1);
+        let region = &result.window_ocr_results[0].redacted_regions[0];
+        assert_eq!(region.pii_type, "CREDIT_CARD");
+        assert_eq!((region.x, region.y), (106, 146));
+        assert_eq!((region.width, region.height), (208, 28));
+
+        assert_eq!(result.image.get_pixel(200, 160), Rgba([0, 0, 0, 255]));
+        // the line without pii is untouched
+        assert_eq!(result.image.get_pixel(150, 120), Rgba([255; 4]));
+    }
+
+    #[test]
+    fn test_normalized_boxes_use_a_bottom_left_origin() {
+        let mut result = frame(vec![window(
+            vec![line("test@example.com", "0.25", "0.75", "0.5", "0.1")],
+            Some((0, 0)),
+        )]);
+
+        redact_frame(&mut result, RedactionMode::Blur);
+        let region = &result.window_ocr_results[0].redacted_regions[0];
+        assert_eq!(region.pii_type, "EMAIL");
+        assert_eq!((region.x, region.y), (96, 26));
+        assert_eq!((region.width, region.height), (208, 28));
+    }
+
+    #[test]
+    fn test_whole_window_is_redacted_without_boxes() {
+        let mut text_json = vec![line("SSN 123-45-6789", "0", "0", "0", "0")];
+        text_json[0].retain(|key, _| key == "text");
+        let mut result = frame(vec![
+            window(text_json, Some((700, 500))),
+            window(vec![line("no pii", "1", "1", "10", "10")], Some((0, 0))),
+        ]);
+
+        assert_eq!(redact_frame(&mut result, RedactionMode::BlackBox), 1);
+        let region = &result.window_ocr_results[0].redacted_regions[0];
+        // clipped to the frame
+        assert_eq!(
+            (region.x, region.y, region.width, region.height),
+            (700, 500, 100, 100)
+        );
+        assert!(result.window_ocr_results[1].redacted_regions.is_empty());
+    }
+
+    #[test]
+    fn test_whole_frame_is_redacted_without_window_position() {
+        let mut result = frame(vec![window(
+            vec![line("Card 4111 1111 1111 1111", "10", "50", "200", "20")],
+            None,
+        )]);
+
+        assert_eq!(redact_frame(&mut result, RedactionMode::BlackBox), 1);
+        let region = &result.window_ocr_results[0].redacted_regions[0];
+        assert_eq!(region.pii_type, "CREDIT_CARD");
+        assert_eq!(
+            (region.x, region.y, region.width, region.height),
+            (0, 0, 800, 600)
+        );
+        assert_eq!(result.image.get_pixel(799, 0), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_windows_without_ocr_are_redacted_whole() {
+        let mut deferred = window(vec![], Some((100, 100)));
+        deferred.ocr_deferred = true;
+        let mut result = frame(vec![deferred]);
+
+        assert_eq!(redact_frame(&mut result, RedactionMode::BlackBox), 1);
+        let region = &result.window_ocr_results[0].redacted_regions[0];
+        assert_eq!(region.pii_type, "UNKNOWN");
+        assert_eq!(
+            (region.x, region.y, region.width, region.height),
+            (100, 100, 400, 200)
+        );
+    }
+}
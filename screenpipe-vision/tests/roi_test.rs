@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+    use screenpipe_vision::roi::{
+        blocks_confidence, blocks_text, merge_blocks, plan_roi_ocr, Region, RoiConfig, RoiPlan,
+    };
+    use std::collections::HashMap;
+
+    fn line(text: &str, left: u32, top: u32, width: u32, height: u32) -> HashMap<String, String> {
+        HashMap::from([
+            ("text".to_string(), text.to_string()),
+            ("left".to_string(), left.to_string()),
+            ("top".to_string(), top.to_string()),
+            ("width".to_string(), width.to_string()),
+            ("height".to_string(), height.to_string()),
+            ("conf".to_string(), "0.9".to_string()),
+        ])
+    }
+
+    fn fill(image: &mut GrayImage, x: u32, y: u32, width: u32, height: u32, value: u8) {
+        for py in y..y + height {
+            for px in x..x + width {
+                image.put_pixel(px, py, Luma([value]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_roi_ocr() {
+        let config = RoiConfig::default();
+        let previous = GrayImage::from_pixel(512, 512, Luma([255]));
+        let blocks = vec![
+            line("inbox", 10, 10, 200, 20),
+            line("12:00", 400, 480, 100, 20),
+        ];
+
+        // compression noise is not a change
+        let mut current = previous.clone();
+        fill(&mut current, 0, 0, 512, 512, 240);
+        assert_eq!(
+            plan_roi_ocr(&previous, &current, &blocks, &config),
+            RoiPlan::Unchanged
+        );
+
+        // the clock changed: its tile with a tile of margin, grown to the whole clock line
+        let mut current = previous.clone();
+        fill(&mut current, 470, 485, 20, 10, 0);
+        assert_eq!(
+            plan_roi_ocr(&previous, &current, &blocks, &config),
+            RoiPlan::Regions(vec![Region {
+                x: 384,
+                y: 384,
+                width: 128,
+                height: 128,
+            }])
+        );
+
+        // most of the window changed
+        let mut current = previous.clone();
+        fill(&mut current, 0, 0, 512, 300, 0);
+        assert_eq!(
+            plan_roi_ocr(&previous, &current, &blocks, &config),
+            RoiPlan::Full
+        );
+
+        // another size or lines without pixel boxes
+        let resized = GrayImage::from_pixel(256, 512, Luma([255]));
+        assert_eq!(
+            plan_roi_ocr(&previous, &resized, &blocks, &config),
+            RoiPlan::Full
+        );
+        let normalized = vec![HashMap::from([
+            ("text".to_string(), "inbox".to_string()),
+            ("left".to_string(), "0.1".to_string()),
+            ("top".to_string(), "0.1".to_string()),
+            ("width".to_string(), "0.5".to_string()),
+            ("height".to_string(), "0.05".to_string()),
+        ])];
+        let mut current = previous.clone();
+        fill(&mut current, 470, 485, 20, 10, 0);
+        assert_eq!(
+            plan_roi_ocr(&previous, &current, &normalized, &config),
+            RoiPlan::Full
+        );
+    }
+
+    #[test]
+    fn test_merge_blocks() {
+        let previous = vec![
+            line("inbox", 10, 10, 200, 20),
+            line("12:00", 400, 480, 100, 20),
+        ];
+        let region = Region {
+            x: 384,
+            y: 384,
+            width: 128,
+            height: 128,
+        };
+        let found = vec![line("12:01", 16, 96, 100, 20)];
+
+        let merged = merge_blocks(&previous, vec![(region, found)]).unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[1]["text"], "12:01");
+        assert_eq!(merged[1]["left"], "400");
+        assert_eq!(merged[1]["top"], "480");
+        assert_eq!(blocks_text(&merged), "inbox 12:01");
+        assert_eq!(blocks_confidence(&merged), Some(0.9));
+
+        // region results must have pixel boxes to be placed
+        let unplaced = vec![HashMap::from([("text".to_string(), "12:01".to_string())])];
+        assert!(merge_blocks(&previous, vec![(region, unplaced)]).is_none());
+    }
+}
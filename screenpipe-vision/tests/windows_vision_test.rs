@@ -4,7 +4,7 @@ mod tests {
     use screenpipe_vision::capture_screenshot_by_window::{CapturedWindow, WindowFilters};
     use screenpipe_vision::core::OcrTaskData;
     use screenpipe_vision::monitor::get_default_monitor;
-    use screenpipe_vision::{process_ocr_task, OcrEngine};
+    use screenpipe_vision::{process_ocr_task, OcrBackend, OcrEngine};
     use std::sync::Arc;
     use std::{path::PathBuf, time::Instant};
     use tokio::sync::mpsc;
@@ -27,7 +27,7 @@ mod tests {
         let frame_number = 1;
         let timestamp = Instant::now();
         let (tx, _rx) = mpsc::channel(1);
-        let ocr_engine = OcrEngine::WindowsNative;
+        let ocr_engine: Arc<dyn OcrBackend> = OcrEngine::WindowsNative.into();
 
         let window_images = vec![CapturedWindow {
             app_name: "test_app".to_string(),
@@ -35,6 +35,7 @@ mod tests {
             image,
             is_focused: true,
             process_id: 1234,
+            position: Some((0, 0)),
         }];
 
         let result = process_ocr_task(
@@ -45,8 +46,9 @@ mod tests {
                 timestamp,
                 result_tx: tx,
             },
-            &ocr_engine,
+            ocr_engine.as_ref(),
             vec![],
+            None,
         )
         .await;
 
@@ -66,18 +68,19 @@ mod tests {
         // Set up test parameters
         let interval = Duration::from_millis(1000);
         let save_text_files_flag = false;
-        let ocr_engine = OcrEngine::WindowsNative;
+        let ocr_engine: Arc<dyn OcrBackend> = OcrEngine::WindowsNative.into();
         let window_filters = Arc::new(WindowFilters::new(&[], &[]));
 
         // Spawn the continuous_capture function with corrected parameter order
         let capture_handle = tokio::spawn(continuous_capture(
             result_tx,
             interval,
-            ocr_engine,
+            Some(ocr_engine),
             monitor,
             window_filters, // window filters as empty vec
             vec![],         // languages as empty vec
             save_text_files_flag,
+            None,
         ));
 
         // Wait for a short duration to allow some captures to occur